@@ -76,7 +76,7 @@ fn build_graphs_if_not_exist(warc_path: &str, graph_path: &str) -> anyhow::Resul
 
     for (i, record) in warc.records().flatten().enumerate() {
         let webpage = match stract::webpage::Html::parse_without_text(
-            &record.response.body,
+            &record.response.text(),
             &record.request.url,
         ) {
             Ok(webpage) => webpage,
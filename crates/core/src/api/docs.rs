@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::{autosuggest, explore, hosts, search, summarize, webgraph};
+use super::{autosuggest, explore, hosts, regions, search, summarize, webgraph};
 use axum::Router;
 use utoipa::{Modify, OpenApi};
 use utoipa_swagger_ui::SwaggerUi;
@@ -36,10 +36,13 @@ use utoipa_swagger_ui::SwaggerUi;
             summarize::summarize_route,
             hosts::hosts_export_optic,
             explore::explore_export_optic,
+            explore::explore_clusters,
+            regions::regions,
         ),
         components(
             schemas(
                 crate::webpage::region::Region,
+                regions::DisplayedRegion,
                 optics::HostRankings,
                 search::ApiSearchQuery,
                 search::ApiSearchResult,
@@ -90,6 +93,9 @@ use utoipa_swagger_ui::SwaggerUi;
 
                 hosts::HostsExportOpticParams,
                 explore::ExploreExportOpticParams,
+                explore::ExploreClustersParams,
+                explore::ExploreCluster,
+                crate::webgraph::clusters::ClusterKind,
 
                 crate::webgraph::Node,
                 crate::webgraph::FullEdge,
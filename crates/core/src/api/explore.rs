@@ -14,11 +14,19 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use axum::extract;
+use std::sync::Arc;
+
+use axum::{extract, response::IntoResponse, Json};
 use http::StatusCode;
 use optics::{HostRankings, Optic};
 use utoipa::ToSchema;
 
+use crate::{similar_hosts::HostCluster, webgraph::clusters::ClusterKind};
+
+use super::State;
+
+const MAX_CLUSTER_MEMBERS: usize = 100;
+
 #[derive(serde::Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ExploreExportOpticParams {
@@ -44,7 +52,7 @@ pub async fn explore_export_optic(
         .into_iter()
         .chain(chosen_hosts.clone().into_iter())
         .map(|site| {
-            vec![optics::Matching {
+            vec![optics::Matching::Pattern {
                 pattern: vec![
                     optics::PatternPart::Anchor,
                     optics::PatternPart::Raw(site),
@@ -56,7 +64,7 @@ pub async fn explore_export_optic(
         .collect();
     let rule = optics::Rule {
         matches,
-        action: optics::Action::Boost(0),
+        action: optics::Action::Boost(0.0),
     };
 
     let optic = Optic {
@@ -70,3 +78,114 @@ pub async fn explore_export_optic(
 
     Ok(optic.to_string())
 }
+
+#[derive(serde::Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExploreClustersParams {
+    pub hosts: Vec<String>,
+    #[serde(default = "default_cluster_kind")]
+    pub kind: ClusterKind,
+    #[serde(default = "default_cluster_members")]
+    pub members: usize,
+}
+
+fn default_cluster_kind() -> ClusterKind {
+    ClusterKind::Community
+}
+
+fn default_cluster_members() -> usize {
+    20
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExploreCluster {
+    pub host: String,
+    pub kind: ClusterKind,
+    pub size: u64,
+    pub members: Vec<String>,
+}
+
+impl From<HostCluster> for ExploreCluster {
+    fn from(cluster: HostCluster) -> Self {
+        Self {
+            host: cluster.host.as_str().to_string(),
+            kind: cluster.kind,
+            size: cluster.size,
+            members: cluster
+                .members
+                .into_iter()
+                .map(|member| member.as_str().to_string())
+                .collect(),
+        }
+    }
+}
+
+#[utoipa::path(post,
+    path = "/beta/api/explore/clusters",
+    request_body(content = ExploreClustersParams),
+    responses(
+        (status = 200, description = "The clusters of hosts the given hosts belong to", body = Vec<ExploreCluster>),
+    )
+)]
+pub async fn explore_clusters(
+    extract::State(state): extract::State<Arc<State>>,
+    extract::Json(params): extract::Json<ExploreClustersParams>,
+) -> std::result::Result<impl IntoResponse, StatusCode> {
+    state.counters.explore_counter.inc();
+
+    let hosts: Vec<_> = params.hosts.into_iter().take(8).collect();
+
+    Ok(Json(
+        state
+            .similar_hosts
+            .clusters(&hosts, params.kind, params.members.min(MAX_CLUSTER_MEMBERS))
+            .await
+            .into_iter()
+            .map(ExploreCluster::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::webgraph::Node;
+
+    use super::*;
+
+    #[test]
+    fn clusters_params() {
+        let params: ExploreClustersParams =
+            serde_json::from_str(r#"{"hosts": ["a.com"]}"#).unwrap();
+        assert_eq!(params.hosts, vec!["a.com".to_string()]);
+        assert_eq!(params.kind, ClusterKind::Community);
+        assert_eq!(params.members, 20);
+
+        let params: ExploreClustersParams = serde_json::from_str(
+            r#"{"hosts": ["a.com"], "kind": "stronglyConnected", "members": 5}"#,
+        )
+        .unwrap();
+        assert_eq!(params.kind, ClusterKind::StronglyConnected);
+        assert_eq!(params.members, 5);
+    }
+
+    #[test]
+    fn cluster_response() {
+        let cluster = ExploreCluster::from(HostCluster {
+            host: Node::from("a.com"),
+            kind: ClusterKind::WeaklyConnected,
+            size: 3,
+            members: vec![Node::from("b.com"), Node::from("c.com")],
+        });
+
+        assert_eq!(
+            serde_json::to_value(cluster).unwrap(),
+            serde_json::json!({
+                "host": "a.com",
+                "kind": "weaklyConnected",
+                "size": 3,
+                "members": ["b.com", "c.com"],
+            })
+        );
+    }
+}
@@ -60,6 +60,7 @@ mod explore;
 mod hosts;
 pub mod improvement;
 mod metrics;
+mod regions;
 pub mod search;
 mod summarize;
 pub mod user_count;
@@ -144,7 +145,9 @@ fn build_router(state: Arc<State>) -> Router {
                 )
                 .route("/api/hosts/export", post(hosts::hosts_export_optic))
                 .route("/api/explore/export", post(explore::explore_export_optic))
+                .route("/api/explore/clusters", post(explore::explore_clusters))
                 .route("/api/entity_image", get(search::entity_image))
+                .route("/api/regions", get(regions::regions))
                 .layer(cors_layer()),
         )
         .with_state(state)
@@ -0,0 +1,48 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+use axum::Json;
+use utoipa::ToSchema;
+
+use crate::webpage::region::Region;
+
+#[derive(serde::Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplayedRegion {
+    pub region: Region,
+    pub name: String,
+    pub gl: String,
+}
+
+#[allow(clippy::unused_async)]
+#[utoipa::path(get,
+    path = "/beta/api/regions",
+    responses(
+        (status = 200, description = "The regions that can be selected when searching", body = Vec<DisplayedRegion>),
+    )
+)]
+pub async fn regions() -> Json<Vec<DisplayedRegion>> {
+    Json(
+        Region::all()
+            .into_iter()
+            .map(|region| DisplayedRegion {
+                region,
+                name: region.name(),
+                gl: region.gl(),
+            })
+            .collect(),
+    )
+}
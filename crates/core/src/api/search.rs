@@ -17,8 +17,10 @@
 use crate::{
     config::defaults,
     enum_map::EnumMap,
+    query,
     ranking::{SignalCoefficient, SignalEnum, SignalEnumDiscriminants},
 };
+use chrono::Utc;
 use http::StatusCode;
 use optics::{HostRankings, Optic};
 use std::{collections::HashMap, sync::Arc};
@@ -30,7 +32,7 @@ use axum_macros::debug_handler;
 use crate::{
     bangs::BangHit,
     searcher::{self, SearchQuery, SearchResult, WebsitesResult},
-    webpage::region::Region,
+    webpage::{lang::Lang, region::Region},
 };
 
 use super::State;
@@ -67,6 +69,16 @@ pub struct ApiSearchQuery {
     pub host_rankings: Option<HostRankings>,
     pub safe_search: Option<bool>,
 
+    /// Only return results last updated on or after this date (`YYYY-MM-DD`).
+    pub after: Option<String>,
+    /// Only return results last updated before this date (`YYYY-MM-DD`).
+    pub before: Option<String>,
+    /// Only return results updated within this period, e.g. `7d`, `2w` or `1y`.
+    pub updated: Option<String>,
+    /// Only return results in one of these languages (ISO 639-1 codes).
+    #[serde(default)]
+    pub languages: Vec<Lang>,
+
     pub signal_coefficients: Option<HashMap<SignalEnumDiscriminants, f64>>,
 
     #[serde(default = "defaults::SearchQuery::return_ranking_signals")]
@@ -104,6 +116,23 @@ impl TryFrom<ApiSearchQuery> for SearchQuery {
                     .into()
             });
 
+        let parse_date = |date: &str| {
+            query::parser::parse_date(date)
+                .map(query::parser::start_of_day_timestamp)
+                .ok_or_else(|| anyhow::anyhow!("invalid date: {date}"))
+        };
+
+        let mut updated_after = api.after.as_deref().map(parse_date).transpose()?;
+        let updated_before = api.before.as_deref().map(parse_date).transpose()?;
+
+        if let Some(updated) = api.updated.as_deref() {
+            let freshness = query::parser::parse_freshness(updated)
+                .ok_or_else(|| anyhow::anyhow!("invalid freshness: {updated}"))?;
+            let since = (Utc::now() - freshness).timestamp().max(0) as u64;
+
+            updated_after = Some(updated_after.map_or(since, |after| after.max(since)));
+        }
+
         let default = SearchQuery::default();
 
         Ok(SearchQuery {
@@ -122,6 +151,9 @@ impl TryFrom<ApiSearchQuery> for SearchQuery {
             #[cfg(not(feature = "return_body"))]
             return_body: None,
             return_structured_data: api.return_structured_data,
+            updated_after,
+            updated_before,
+            languages: api.languages,
         })
     }
 }
@@ -78,6 +78,7 @@ pub mod host {
                     host: node.node.as_str().to_string(),
                     score: node.score,
                     description: None,
+                    cluster: node.cluster.map(|id| id.as_u64().to_string()),
                 })
                 .collect::<Vec<_>>(),
         ))
@@ -23,6 +23,9 @@ use url::Url;
 
 use crate::{webpage::url_ext::UrlExt, Result};
 
+/// The maximum number of canonical urls and redirects that are followed when looking up a url.
+const MAX_HOPS: usize = 10;
+
 #[derive(bincode::Decode, bincode::Encode)]
 struct StoredUrl(#[bincode(with_serde)] Url);
 
@@ -48,13 +51,44 @@ impl CanonicalIndex {
         self.inner.insert(original, canonical)
     }
 
-    pub fn get(&self, url: &Url) -> Result<Option<Url>> {
+    /// A permanent redirect means the original url has been replaced, so unlike
+    /// canonical links it is trusted across domains (e.g. `example.com` -> `example.org`).
+    pub fn insert_permanent_redirect(&mut self, original: Url, target: Url) -> Result<()> {
+        if original == target {
+            return Ok(());
+        }
+
+        self.inner.insert(StoredUrl(original), StoredUrl(target))
+    }
+
+    fn get_direct(&self, url: &Url) -> Result<Option<Url>> {
         Ok(self
             .inner
             .get(&StoredUrl(url.clone()))?
             .map(|stored_url| stored_url.0))
     }
 
+    /// The canonical url for the url. Chains of redirects and canonical links
+    /// are followed, and loops are stopped at the last url before the loop.
+    pub fn get(&self, url: &Url) -> Result<Option<Url>> {
+        let mut seen = vec![url.clone()];
+        let mut res = None;
+
+        while seen.len() <= MAX_HOPS {
+            let current = res.as_ref().unwrap_or(url);
+
+            match self.get_direct(current)? {
+                Some(next) if !seen.contains(&next) => {
+                    seen.push(next.clone());
+                    res = Some(next);
+                }
+                _ => break,
+            }
+        }
+
+        Ok(res)
+    }
+
     pub fn commit(&mut self) -> Result<()> {
         self.inner.commit()
     }
@@ -68,3 +102,59 @@ impl CanonicalIndex {
         self.optimize_read()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirect_chains() {
+        let mut index = CanonicalIndex::open(crate::gen_temp_path()).unwrap();
+
+        let a = Url::parse("http://a.com/").unwrap();
+        let b = Url::parse("https://a.com/").unwrap();
+        let c = Url::parse("https://b.com/").unwrap();
+
+        index
+            .insert_permanent_redirect(a.clone(), b.clone())
+            .unwrap();
+        index
+            .insert_permanent_redirect(b.clone(), c.clone())
+            .unwrap();
+        index.commit().unwrap();
+
+        assert_eq!(index.get(&a).unwrap(), Some(c.clone()));
+        assert_eq!(index.get(&b).unwrap(), Some(c.clone()));
+        assert_eq!(index.get(&c).unwrap(), None);
+    }
+
+    #[test]
+    fn loops() {
+        let mut index = CanonicalIndex::open(crate::gen_temp_path()).unwrap();
+
+        let a = Url::parse("https://a.com/").unwrap();
+        let b = Url::parse("https://a.com/b").unwrap();
+
+        index
+            .insert_permanent_redirect(a.clone(), b.clone())
+            .unwrap();
+        index.insert(b.clone(), a.clone()).unwrap();
+        index.commit().unwrap();
+
+        assert_eq!(index.get(&a).unwrap(), Some(b.clone()));
+        assert_eq!(index.get(&b).unwrap(), Some(a.clone()));
+    }
+
+    #[test]
+    fn canonical_links_stay_on_domain() {
+        let mut index = CanonicalIndex::open(crate::gen_temp_path()).unwrap();
+
+        let a = Url::parse("https://a.com/").unwrap();
+        let b = Url::parse("https://b.com/").unwrap();
+
+        index.insert(a.clone(), b.clone()).unwrap();
+        index.commit().unwrap();
+
+        assert_eq!(index.get(&a).unwrap(), None);
+    }
+}
@@ -109,6 +109,10 @@ impl Crawler {
         60_000
     }
 
+    pub fn max_robots_crawl_delay_ms() -> u64 {
+        5 * 60_000
+    }
+
     pub fn max_politeness_factor() -> f32 {
         2048.0
     }
@@ -124,6 +128,58 @@ impl Crawler {
     pub fn dry_run() -> bool {
         false
     }
+
+    pub fn min_recrawl_interval_sec() -> u64 {
+        24 * 60 * 60
+    }
+
+    pub fn max_recrawl_interval_sec() -> u64 {
+        90 * 24 * 60 * 60
+    }
+
+    pub fn warc_max_bytes() -> usize {
+        1_000_000_000
+    }
+
+    pub fn warc_max_age_sec() -> u64 {
+        60 * 60
+    }
+}
+
+pub struct Frontier;
+
+impl Frontier {
+    pub fn expected_urls() -> u64 {
+        100_000_000
+    }
+
+    pub fn urls_per_job() -> usize {
+        128
+    }
+
+    pub fn wander_urls_per_job() -> u64 {
+        32
+    }
+
+    pub fn max_urls_per_domain() -> usize {
+        10_000
+    }
+
+    pub fn min_domain_budget() -> u64 {
+        16
+    }
+
+    pub fn max_domain_budget() -> u64 {
+        100_000
+    }
+
+    pub fn budget_period_sec() -> u64 {
+        24 * 60 * 60
+    }
+
+    pub fn job_timeout_sec() -> u64 {
+        2 * 60 * 60
+    }
 }
 
 pub struct SearchQuery;
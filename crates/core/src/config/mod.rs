@@ -20,6 +20,7 @@ use super::Result;
 use crate::ampc::dht;
 use crate::distributed::member::ShardId;
 use crate::feed::scheduler::SplitId;
+use crate::webgraph::LinkFilter;
 
 use std::fs::File;
 use std::io::{self, BufRead};
@@ -38,6 +39,7 @@ pub struct IndexingLocalConfig {
     pub page_centrality_store_path: Option<String>,
     pub safety_classifier_path: Option<String>,
     pub minimum_clean_words: Option<usize>,
+    pub regions_path: Option<String>,
 
     #[serde(default = "defaults::Indexing::batch_size")]
     pub batch_size: usize,
@@ -241,6 +243,7 @@ pub struct ApiConfig {
     pub cluster_id: String,
     pub gossip_seed_nodes: Option<Vec<SocketAddr>>,
     pub gossip_addr: SocketAddr,
+    pub regions_path: Option<String>,
 
     #[serde(default = "defaults::Api::max_similar_hosts")]
     pub max_similar_hosts: usize,
@@ -312,6 +315,7 @@ pub struct SearchServerConfig {
     pub lambda_model_path: Option<String>,
     pub dual_encoder_model_path: Option<String>,
     pub host: SocketAddr,
+    pub regions_path: Option<String>,
 
     #[serde(default)]
     pub collector: CollectorConfig,
@@ -333,6 +337,50 @@ pub struct EntitySearchServerConfig {
 pub struct CrawlCoordinatorConfig {
     pub job_queue: String,
     pub host: SocketAddr,
+
+    /// Hand out jobs from a persistent frontier that is fed with the urls discovered
+    /// during the crawl. The job queue is only used to seed the frontier if this is set.
+    #[serde(default)]
+    pub frontier: Option<FrontierConfig>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct FrontierConfig {
+    pub path: String,
+
+    /// Host centrality used to prioritise domains and size their budgets.
+    /// All domains get the minimum budget if not set.
+    #[serde(default)]
+    pub host_centrality_path: Option<String>,
+
+    /// Number of urls we expect to see, used to size the bloom filter that dedupes urls.
+    #[serde(default = "defaults::Frontier::expected_urls")]
+    pub expected_urls: u64,
+
+    #[serde(default = "defaults::Frontier::urls_per_job")]
+    pub urls_per_job: usize,
+
+    #[serde(default = "defaults::Frontier::wander_urls_per_job")]
+    pub wander_urls_per_job: u64,
+
+    /// Maximum number of urls waiting to be crawled for a single domain.
+    #[serde(default = "defaults::Frontier::max_urls_per_domain")]
+    pub max_urls_per_domain: usize,
+
+    /// Number of urls a domain with no centrality may have crawled per budget period.
+    #[serde(default = "defaults::Frontier::min_domain_budget")]
+    pub min_domain_budget: u64,
+
+    /// Number of urls the most central domains may have crawled per budget period.
+    #[serde(default = "defaults::Frontier::max_domain_budget")]
+    pub max_domain_budget: u64,
+
+    #[serde(default = "defaults::Frontier::budget_period_sec")]
+    pub budget_period_sec: u64,
+
+    /// A domain is handed out again if the worker hasn't reported back within this time.
+    #[serde(default = "defaults::Frontier::job_timeout_sec")]
+    pub job_timeout_sec: u64,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
@@ -358,6 +406,10 @@ pub struct CrawlerConfig {
     #[serde(default = "defaults::Crawler::max_crawl_delay_ms")]
     pub max_crawl_delay_ms: u64,
 
+    /// Upper bound on the `Crawl-delay` and `Request-rate` from robots.txt that we respect.
+    #[serde(default = "defaults::Crawler::max_robots_crawl_delay_ms")]
+    pub max_robots_crawl_delay_ms: u64,
+
     #[serde(default = "defaults::Crawler::max_politeness_factor")]
     pub max_politeness_factor: f32,
 
@@ -370,11 +422,67 @@ pub struct CrawlerConfig {
     #[serde(default = "defaults::Crawler::dry_run")]
     pub dry_run: bool,
 
+    /// Where to store the validators and change history of visited urls.
+    /// Recrawl scheduling is disabled if not set.
+    #[serde(default)]
+    pub recrawl_db_path: Option<String>,
+
+    #[serde(default = "defaults::Crawler::min_recrawl_interval_sec")]
+    pub min_recrawl_interval_sec: u64,
+
+    #[serde(default = "defaults::Crawler::max_recrawl_interval_sec")]
+    pub max_recrawl_interval_sec: u64,
+
+    /// Finished WARC files are rotated when they reach this size.
+    #[serde(default = "defaults::Crawler::warc_max_bytes")]
+    pub warc_max_bytes: usize,
+
+    /// Finished WARC files are rotated when their first record is this old,
+    /// so a slow crawl doesn't keep its results in memory for too long.
+    #[serde(default = "defaults::Crawler::warc_max_age_sec")]
+    pub warc_max_age_sec: u64,
+
     pub timeout_seconds: u64,
-    pub s3: S3Config,
+
+    /// Configs from before the sink was configurable have an `s3` table without a `type`.
+    #[serde(alias = "s3", deserialize_with = "WarcSinkConfig::deserialize_legacy")]
+    pub warc_sink: WarcSinkConfig,
     pub router_hosts: Vec<String>,
 }
 
+/// Where the crawler stores the WARC files it writes.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum WarcSinkConfig {
+    S3(S3Config),
+    Local(LocalSinkConfig),
+}
+
+impl WarcSinkConfig {
+    /// Deserialize the sink, accepting an untagged [`S3Config`] as well.
+    fn deserialize_legacy<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Sink {
+            Tagged(WarcSinkConfig),
+            Legacy(S3Config),
+        }
+
+        match <Sink as serde::Deserialize>::deserialize(deserializer)? {
+            Sink::Tagged(sink) => Ok(sink),
+            Sink::Legacy(s3) => Ok(Self::S3(s3)),
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct LocalSinkConfig {
+    pub folder: String,
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
 pub struct CrawlRouterConfig {
     pub host: SocketAddr,
@@ -413,6 +521,8 @@ pub struct WebgraphServerConfig {
     pub shard: ShardId,
     pub graph_path: String,
     pub granularity: WebgraphGranularity,
+    /// Clusters built with `webgraph clusters` for the host graph.
+    pub clusters_path: Option<String>,
 
     pub cluster_id: String,
     pub gossip_seed_nodes: Option<Vec<SocketAddr>>,
@@ -445,6 +555,8 @@ pub struct CrawlPlannerConfig {
     pub host_graph_path: String,
     pub output_path: String,
 
+    /// Jobs are assigned to the queues by domain. Queue `i` must be served by the
+    /// `i`th coordinator in the `coordinator_addrs` of the crawl router.
     pub num_job_queues: usize,
 
     pub crawl_budget: usize,
@@ -452,6 +564,11 @@ pub struct CrawlPlannerConfig {
     pub wander_fraction: f64,
     pub top_n_hosts_surplus: usize,
 
+    /// Recrawl database written by the crawlers. Urls that are due for a recrawl are
+    /// added to the jobs and urls that are not due are skipped.
+    #[serde(default)]
+    pub recrawl_db_path: Option<String>,
+
     pub num_threads: Option<usize>,
 }
 
@@ -470,6 +587,8 @@ pub struct LiveIndexConfig {
     pub min_crawl_delay_ms: u64,
     #[serde(default = "defaults::Crawler::max_crawl_delay_ms")]
     pub max_crawl_delay_ms: u64,
+    #[serde(default = "defaults::Crawler::max_robots_crawl_delay_ms")]
+    pub max_robots_crawl_delay_ms: u64,
     #[serde(default = "defaults::Crawler::max_politeness_factor")]
     pub max_politeness_factor: f32,
     #[serde(default = "defaults::Crawler::max_url_slowdown_retry")]
@@ -485,6 +604,7 @@ pub struct LiveIndexConfig {
     pub safety_classifier_path: Option<String>,
     pub host_centrality_threshold: Option<f64>,
     pub minimum_clean_words: Option<usize>,
+    pub regions_path: Option<String>,
 
     // search
     pub cluster_id: String,
@@ -582,6 +702,27 @@ pub struct HarmonicWorkerConfig {
     pub shard: ShardId,
     pub graph_path: String,
     pub host: SocketAddr,
+    /// Links in the graph to ignore.
+    #[serde(default)]
+    pub link_filter: LinkFilter,
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct PageRankCoordinatorConfig {
+    pub gossip: GossipConfig,
+    pub host: SocketAddr,
+    pub output_path: String,
+}
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct PageRankWorkerConfig {
+    pub gossip: GossipConfig,
+    pub shard: ShardId,
+    pub graph_path: String,
+    pub host: SocketAddr,
+    /// Links in the graph to ignore.
+    #[serde(default)]
+    pub link_filter: LinkFilter,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
@@ -591,3 +732,48 @@ pub struct CanonicalIndexConfig {
     pub limit_warc_files: Option<usize>,
     pub skip_warc_files: Option<usize>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_s3_warc_sink() {
+        let config: CrawlerConfig = toml::from_str(
+            r#"
+            num_worker_threads = 1
+            user_agent = { full = "test", token = "test" }
+            timeout_seconds = 30
+            router_hosts = []
+
+            [s3]
+            access_key = "access"
+            bucket = "crawl"
+            endpoint = "http://localhost"
+            folder = "test"
+            secret_key = "secret"
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(config.warc_sink, WarcSinkConfig::S3(s3) if s3.bucket == "crawl"));
+
+        let config: CrawlerConfig = toml::from_str(
+            r#"
+            num_worker_threads = 1
+            user_agent = { full = "test", token = "test" }
+            timeout_seconds = 30
+            router_hosts = []
+
+            [warc_sink]
+            type = "Local"
+            folder = "warcs"
+            "#,
+        )
+        .unwrap();
+
+        assert!(
+            matches!(config.warc_sink, WarcSinkConfig::Local(local) if local.folder == "warcs")
+        );
+    }
+}
@@ -14,21 +14,109 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::{file_queue::FileQueue, Job, Result};
+use super::{
+    file_queue::FileQueue, frontier::Frontier, DiscoveredUrls, DomainCrawled, Job, JobResponse,
+    Result,
+};
+use crate::config::FrontierConfig;
 use std::{path::Path, sync::Mutex};
 
+enum Jobs {
+    /// Jobs planned ahead of the crawl by the [`CrawlPlanner`](super::planner::CrawlPlanner).
+    Queue(Mutex<FileQueue<Job>>),
+    /// Jobs from the urls discovered during the crawl.
+    Frontier(Mutex<Frontier>),
+}
+
 pub struct CrawlCoordinator {
-    jobs: Mutex<FileQueue<Job>>,
+    jobs: Jobs,
+    /// Commits are serialized so an older snapshot of the seen urls
+    /// is never written after a newer one.
+    committing: Mutex<()>,
 }
 
 impl CrawlCoordinator {
     pub fn new<P: AsRef<Path>>(jobs_queue: P) -> Result<Self> {
         Ok(Self {
-            jobs: Mutex::new(FileQueue::open(jobs_queue)?),
+            jobs: Jobs::Queue(Mutex::new(FileQueue::open(jobs_queue)?)),
+            committing: Mutex::new(()),
         })
     }
 
-    pub fn sample_job(&self) -> Result<Option<Job>> {
-        self.jobs.lock().unwrap_or_else(|e| e.into_inner()).pop()
+    /// A coordinator that crawls continuously from a frontier. The jobs that are left in
+    /// `jobs_queue` are moved to the frontier, so it can be seeded with a crawl plan.
+    pub fn with_frontier<P: AsRef<Path>>(jobs_queue: P, config: FrontierConfig) -> Result<Self> {
+        let mut frontier = Frontier::open(config)?;
+
+        if jobs_queue.as_ref().exists() {
+            let mut queue: FileQueue<Job> = FileQueue::open(jobs_queue)?;
+            let mut num_jobs = 0;
+
+            while let Some(job) = queue.pop()? {
+                frontier.add_job(job);
+                num_jobs += 1;
+            }
+
+            if num_jobs > 0 {
+                tracing::info!("seeded frontier with {} jobs", num_jobs);
+            }
+        }
+
+        frontier.commit()?.write()?;
+
+        Ok(Self {
+            jobs: Jobs::Frontier(Mutex::new(frontier)),
+            committing: Mutex::new(()),
+        })
+    }
+
+    pub fn sample_job(&self) -> Result<JobResponse> {
+        match &self.jobs {
+            Jobs::Queue(queue) => Ok(queue
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .pop()?
+                .map_or(JobResponse::Done, JobResponse::Job)),
+            Jobs::Frontier(frontier) => Ok(frontier
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .sample_job()
+                .map_or(JobResponse::Wait, JobResponse::Job)),
+        }
+    }
+
+    /// Add urls discovered by the workers. They are ignored unless the coordinator has a frontier.
+    pub fn add_discovered(&self, discovered: DiscoveredUrls) {
+        if let Jobs::Frontier(frontier) = &self.jobs {
+            frontier
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .add_discovered(discovered);
+        }
+    }
+
+    pub fn domain_crawled(&self, crawled: DomainCrawled) {
+        if let Jobs::Frontier(frontier) = &self.jobs {
+            frontier
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .domain_crawled(crawled);
+        }
+    }
+
+    /// Persist the state of the frontier. The seen urls are written after the frontier
+    /// is unlocked, so the workers aren't blocked while a large filter is written.
+    pub fn commit(&self) -> Result<()> {
+        if let Jobs::Frontier(frontier) = &self.jobs {
+            let _committing = self.committing.lock().unwrap_or_else(|e| e.into_inner());
+
+            let seen = frontier
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .commit()?;
+            seen.write()?;
+        }
+
+        Ok(())
     }
 }
@@ -0,0 +1,323 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Encoding sniffing for fetched documents, following the steps of the
+//! [HTML encoding sniffing algorithm](https://html.spec.whatwg.org/multipage/parsing.html#encoding-sniffing-algorithm):
+//! byte order mark, transport layer charset, `<meta>` prescan and finally frequency analysis.
+
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+
+/// Number of bytes the `<meta>` prescan looks at.
+const PRESCAN_LEN: usize = 1024;
+
+/// Number of bytes fed to the detector when no encoding is declared.
+const DETECT_LEN: usize = 64 * 1024;
+
+/// Find the encoding of `bytes`.
+///
+/// `charset` is the `charset` parameter from the `Content-Type` header and `tld` is the top level
+/// domain of the url, which is used as a hint when the encoding has to be guessed.
+/// The `<meta>` prescan is only done for html documents.
+pub fn sniff(
+    bytes: &[u8],
+    charset: Option<&str>,
+    is_html: bool,
+    tld: Option<&str>,
+) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+
+    if let Some(encoding) = charset.and_then(|label| Encoding::for_label(label.trim().as_bytes())) {
+        return encoding;
+    }
+
+    if is_html {
+        if let Some(encoding) = prescan(bytes) {
+            return encoding;
+        }
+    }
+
+    detect(bytes, tld)
+}
+
+/// Decode `bytes` with the sniffed encoding. A byte order mark is removed.
+pub fn decode(bytes: &[u8], charset: Option<&str>, is_html: bool, tld: Option<&str>) -> String {
+    let encoding = sniff(bytes, charset, is_html, tld);
+    let (text, _, _) = encoding.decode(bytes);
+
+    text.into_owned()
+}
+
+fn detect(bytes: &[u8], tld: Option<&str>) -> &'static Encoding {
+    let mut detector = chardetng::EncodingDetector::new();
+    let end = bytes.len().min(DETECT_LEN);
+    detector.feed(&bytes[..end], end == bytes.len());
+
+    detector.guess(tld.map(|tld| tld.as_bytes()), true)
+}
+
+/// The encoding declared by a `<meta charset>` or `<meta http-equiv="content-type">`
+/// tag in the beginning of the document.
+fn prescan(bytes: &[u8]) -> Option<&'static Encoding> {
+    let bytes = &bytes[..bytes.len().min(PRESCAN_LEN)];
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let rest = &bytes[pos..];
+
+        if rest.starts_with(b"<!--") {
+            pos += find(&rest[4..], b"-->").map_or(rest.len(), |end| end + 4 + 3);
+        } else if starts_with_ignore_case(rest, b"<meta")
+            && rest.get(5).is_some_and(|b| is_space(*b) || *b == b'/')
+        {
+            let (attributes, len) = attributes(&rest[5..]);
+            pos += 5 + len;
+
+            if let Some(encoding) = meta_encoding(&attributes) {
+                return Some(encoding);
+            }
+        } else if rest.starts_with(b"<")
+            && rest
+                .get(1)
+                .is_some_and(|b| b.is_ascii_alphabetic() || *b == b'/')
+        {
+            // other tags are skipped along with their attributes
+            let name_len = rest[1..]
+                .iter()
+                .position(|b| is_space(*b) || *b == b'>')
+                .unwrap_or(rest.len() - 1);
+            let (_, len) = attributes(&rest[1 + name_len..]);
+            pos += 1 + name_len + len;
+        } else if rest.starts_with(b"<!") || rest.starts_with(b"</") || rest.starts_with(b"<?") {
+            pos += find(rest, b">").map_or(rest.len(), |end| end + 1);
+        } else {
+            pos += 1;
+        }
+    }
+
+    None
+}
+
+fn meta_encoding(attributes: &[(String, String)]) -> Option<&'static Encoding> {
+    let get = |name: &str| {
+        attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let label = match get("charset") {
+        Some(charset) => charset,
+        None => {
+            let http_equiv = get("http-equiv")?;
+
+            if !http_equiv.eq_ignore_ascii_case("content-type") {
+                return None;
+            }
+
+            charset_from_content(get("content")?)?
+        }
+    };
+
+    let encoding = Encoding::for_label(label.trim().as_bytes())?;
+
+    // a document that could be read to find the meta tag is not utf-16,
+    // and x-user-defined is treated as windows-1252 by browsers
+    if encoding == encoding_rs::UTF_16BE || encoding == encoding_rs::UTF_16LE {
+        Some(UTF_8)
+    } else if encoding == encoding_rs::X_USER_DEFINED {
+        Some(WINDOWS_1252)
+    } else {
+        Some(encoding)
+    }
+}
+
+/// The charset in the content attribute of a meta tag, e.g. `text/html; charset=shift_jis`.
+fn charset_from_content(content: &str) -> Option<&str> {
+    let lowercase = content.to_ascii_lowercase();
+    let start = lowercase.find("charset")? + "charset".len();
+    let rest = content[start..]
+        .trim_start()
+        .strip_prefix('=')?
+        .trim_start();
+
+    let value = match rest.chars().next()? {
+        quote @ ('"' | '\'') => rest[1..].split(quote).next()?,
+        _ => rest
+            .split(|c: char| c.is_ascii_whitespace() || c == ';')
+            .next()?,
+    };
+
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Parse the attributes of a tag until the closing `>`.
+/// Returns the attributes with lowercase names and the number of bytes consumed.
+fn attributes(bytes: &[u8]) -> (Vec<(String, String)>, usize) {
+    let mut res = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        while pos < bytes.len() && (is_space(bytes[pos]) || bytes[pos] == b'/') {
+            pos += 1;
+        }
+
+        if pos >= bytes.len() {
+            return (res, pos);
+        }
+
+        if bytes[pos] == b'>' {
+            return (res, pos + 1);
+        }
+
+        let name_start = pos;
+        while pos < bytes.len()
+            && !is_space(bytes[pos])
+            && !matches!(bytes[pos], b'=' | b'>' | b'/')
+        {
+            pos += 1;
+        }
+        let name = String::from_utf8_lossy(&bytes[name_start..pos]).to_ascii_lowercase();
+
+        while pos < bytes.len() && is_space(bytes[pos]) {
+            pos += 1;
+        }
+
+        if bytes.get(pos) != Some(&b'=') {
+            res.push((name, String::new()));
+            continue;
+        }
+        pos += 1;
+
+        while pos < bytes.len() && is_space(bytes[pos]) {
+            pos += 1;
+        }
+
+        let value = match bytes.get(pos) {
+            Some(quote @ (b'"' | b'\'')) => {
+                let start = pos + 1;
+                let end = bytes[start..]
+                    .iter()
+                    .position(|b| b == quote)
+                    .map_or(bytes.len(), |end| start + end);
+                pos = (end + 1).min(bytes.len());
+                &bytes[start..end]
+            }
+            _ => {
+                let start = pos;
+                while pos < bytes.len() && !is_space(bytes[pos]) && bytes[pos] != b'>' {
+                    pos += 1;
+                }
+                &bytes[start..pos]
+            }
+        };
+
+        res.push((name, String::from_utf8_lossy(value).into_owned()));
+    }
+}
+
+fn is_space(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r' | b'\x0c')
+}
+
+fn starts_with_ignore_case(bytes: &[u8], prefix: &[u8]) -> bool {
+    bytes.len() >= prefix.len() && bytes[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::{SHIFT_JIS, UTF_16LE, WINDOWS_1251};
+
+    use super::*;
+
+    #[test]
+    fn bom() {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(
+            "<html>hej</html>"
+                .encode_utf16()
+                .flat_map(|c| c.to_le_bytes()),
+        );
+
+        assert_eq!(sniff(&bytes, Some("windows-1252"), true, None), UTF_16LE);
+        assert_eq!(decode(&bytes, None, true, None), "<html>hej</html>");
+    }
+
+    #[test]
+    fn header_takes_precedence_over_meta() {
+        let html = br#"<html><head><meta charset="windows-1251"></head></html>"#;
+
+        assert_eq!(sniff(html, Some("shift_jis"), true, None), SHIFT_JIS);
+        assert_eq!(sniff(html, None, true, None), WINDOWS_1251);
+    }
+
+    #[test]
+    fn meta_charset() {
+        let html = br#"<!DOCTYPE html><html><head><META CharSet=Shift_JIS><title>test</title>"#;
+        assert_eq!(prescan(html), Some(SHIFT_JIS));
+
+        let html = br#"<html><head><meta http-equiv="Content-Type" content="text/html; charset='windows-1251'">"#;
+        assert_eq!(prescan(html), Some(WINDOWS_1251));
+
+        let html = br#"<html><head><meta name="description" content="charset=windows-1251">"#;
+        assert_eq!(prescan(html), None);
+
+        let html = br#"<html><!-- <meta charset="windows-1251"> --><head><meta charset="utf-16">"#;
+        assert_eq!(prescan(html), Some(UTF_8));
+
+        let html = br#"<html><head><title data-x=">">test</title><meta charset=shift_jis />"#;
+        assert_eq!(prescan(html), Some(SHIFT_JIS));
+    }
+
+    #[test]
+    fn meta_charset_is_only_in_prescan() {
+        let mut html = b"<html><head>".to_vec();
+        html.extend(std::iter::repeat(b' ').take(PRESCAN_LEN));
+        html.extend(br#"<meta charset="windows-1251">"#);
+
+        assert_eq!(prescan(&html), None);
+    }
+
+    #[test]
+    fn detection() {
+        let text = "Съешь же ещё этих мягких французских булок, да выпей чаю. \
+                    Широкая электрификация южных губерний даст мощный толчок подъёму сельского хозяйства.";
+        let (html, _, _) = WINDOWS_1251.encode(&format!("<html><body>{text}</body></html>"));
+
+        assert_eq!(sniff(&html, None, true, Some("ru")), WINDOWS_1251);
+        assert!(decode(&html, None, true, Some("ru")).contains("французских"));
+
+        let (bytes, _, _) = SHIFT_JIS.encode("<html><body>いろはにほへとちりぬるをわかよたれそつねならむうゐのおくやまけふこえてあさきゆめみしゑひもせす</body></html>");
+        assert_eq!(sniff(&bytes, None, true, Some("jp")), SHIFT_JIS);
+    }
+
+    #[test]
+    fn utf8_without_declaration() {
+        let html = "<html><body>æøå</body></html>".as_bytes();
+        assert_eq!(sniff(html, None, true, None), UTF_8);
+    }
+}
@@ -25,6 +25,17 @@ use std::{
 
 const POINTER_KEY: &str = "pointer";
 const DATA_KEY: &str = "data";
+const VERSION_KEY: &str = "version";
+
+/// An item that can be stored in a [`FileQueue`]. The version of the item format is
+/// stored with the queue, so queues written with an older format can still be read.
+/// Queues written before versions were introduced have version 0.
+pub trait QueueItem: bincode::Encode + bincode::Decode + Sized {
+    const VERSION: u32;
+
+    /// Decode an item that was written with an older version of the format.
+    fn decode_version(version: u32, bytes: &[u8]) -> Result<Self>;
+}
 
 #[derive(Debug, Clone, Copy, MaxSize, serde::Serialize, serde::Deserialize)]
 struct Header {
@@ -39,7 +50,7 @@ pub struct FileQueueWriter<T> {
 
 impl<T> FileQueueWriter<T>
 where
-    T: bincode::Encode + bincode::Decode,
+    T: QueueItem,
 {
     pub fn new<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
         if !path.as_ref().exists() {
@@ -53,6 +64,8 @@ where
             .read(true)
             .open(path.as_ref().join(DATA_KEY))?;
 
+        std::fs::write(path.as_ref().join(VERSION_KEY), T::VERSION.to_le_bytes())?;
+
         Ok(Self {
             path: path.as_ref().to_path_buf(),
             writer: BufWriter::new(file),
@@ -90,8 +103,9 @@ where
         let file = self.writer.into_inner()?;
 
         Ok(FileQueue {
-            pointer: FilePointer::open(self.path)?,
+            pointer: FilePointer::open(&self.path)?,
             file: unsafe { Mmap::map(&file)? },
+            version: T::VERSION,
             _marker: std::marker::PhantomData,
         })
     }
@@ -140,12 +154,14 @@ impl FilePointer {
 pub struct FileQueue<T> {
     pointer: FilePointer,
     file: Mmap,
+    /// The version of the format the items were written with.
+    version: u32,
     _marker: std::marker::PhantomData<T>,
 }
 
 impl<T> FileQueue<T>
 where
-    T: bincode::Encode + bincode::Decode,
+    T: QueueItem,
 {
     pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
         if !path.as_ref().exists() {
@@ -155,9 +171,27 @@ where
         let file = File::open(path.as_ref().join(DATA_KEY))?;
         let file = unsafe { Mmap::map(&file)? };
 
+        let version = match std::fs::read(path.as_ref().join(VERSION_KEY)) {
+            Ok(bytes) => u32::from_le_bytes(
+                bytes
+                    .try_into()
+                    .map_err(|_| anyhow::anyhow!("invalid file queue version"))?,
+            ),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(err) => return Err(err.into()),
+        };
+
+        if version > T::VERSION {
+            anyhow::bail!(
+                "file queue has version {version}, but only versions up to {} are supported",
+                T::VERSION
+            );
+        }
+
         Ok(Self {
             pointer: FilePointer::open(path)?,
             file,
+            version,
             _marker: std::marker::PhantomData,
         })
     }
@@ -177,7 +211,11 @@ where
 
         let body =
             &self.file[cur_pointer + header_size..cur_pointer + header_size + header.body_size];
-        let (item, _) = bincode::decode_from_slice(body, bincode::config::standard())?;
+        let item = if self.version == T::VERSION {
+            bincode::decode_from_slice(body, bincode::config::standard())?.0
+        } else {
+            T::decode_version(self.version, body)?
+        };
 
         self.pointer
             .set(cur_pointer + header_size + header.body_size)?;
@@ -191,6 +229,57 @@ mod tests {
     use super::*;
     use proptest::prelude::*;
 
+    impl QueueItem for String {
+        const VERSION: u32 = 0;
+
+        fn decode_version(version: u32, _: &[u8]) -> Result<Self> {
+            anyhow::bail!("unsupported version {version}")
+        }
+    }
+
+    #[derive(Debug, PartialEq, bincode::Encode, bincode::Decode)]
+    struct Item {
+        text: String,
+        count: u64,
+    }
+
+    impl QueueItem for Item {
+        const VERSION: u32 = 1;
+
+        fn decode_version(version: u32, bytes: &[u8]) -> Result<Self> {
+            match version {
+                0 => {
+                    let (text, _) = bincode::decode_from_slice(bytes, bincode::config::standard())?;
+                    Ok(Item { text, count: 0 })
+                }
+                _ => anyhow::bail!("unsupported version {version}"),
+            }
+        }
+    }
+
+    #[test]
+    fn old_versions() {
+        let path = crate::gen_temp_path();
+        let mut writer = FileQueueWriter::new(&path).unwrap();
+
+        writer.push("Hello".to_string()).unwrap();
+        writer.finalize().unwrap();
+
+        // queues written before versions were introduced don't have a version file
+        std::fs::remove_file(path.join(VERSION_KEY)).unwrap();
+
+        let mut queue: FileQueue<Item> = FileQueue::open(&path).unwrap();
+
+        assert_eq!(
+            queue.pop().unwrap(),
+            Some(Item {
+                text: "Hello".to_string(),
+                count: 0
+            })
+        );
+        assert_eq!(queue.pop().unwrap(), None);
+    }
+
     #[test]
     fn simple() {
         let mut writer = FileQueueWriter::new(crate::gen_temp_path()).unwrap();
@@ -0,0 +1,925 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The frontier holds the urls that are waiting to be crawled. Urls discovered
+//! by the workers are fed back into it, so the crawl can run continuously
+//! instead of stopping when a precomputed plan has been crawled.
+//!
+//! Domains are handed out in order of host centrality. Each domain has a budget of urls
+//! it may have crawled per budget period, which grows with its centrality.
+
+use std::{
+    cmp::Reverse,
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use bloom::U64BloomFilter;
+use url::Url;
+
+use crate::{
+    config::FrontierConfig,
+    webgraph::{Node, NodeID},
+    SortableFloat,
+};
+
+use super::{
+    DiscoveredUrls, Domain, DomainCrawled, HashMap, Job, Result, UrlToInsert, WeightedUrl,
+    MAX_DOMAIN_DISCOVERY_FACTOR, MAX_URLS_FOR_DOMAIN_PER_INSERT,
+};
+
+const DOMAINS_FOLDER: &str = "domains";
+const COMPACTING_FOLDER: &str = "domains.compacting";
+const OLD_DOMAINS_FOLDER: &str = "domains.old";
+const SEEN_FILE: &str = "seen.bloom";
+const SEEN_FALSE_POSITIVE_RATE: f64 = 0.001;
+
+/// Every commit writes a new version of the changed domains, so the store is
+/// compacted after this many commits to get rid of the old versions.
+const COMMITS_BETWEEN_COMPACTION: usize = 64;
+
+#[derive(Debug, Clone, Default, bincode::Encode, bincode::Decode)]
+struct DomainState {
+    urls: Vec<UrlToInsert>,
+    centrality: f64,
+    budget_used: f64,
+    /// Unix timestamp (seconds) of the start of the current budget period.
+    period_start: i64,
+    /// The urls of the job that has been handed out and not yet reported back.
+    /// The leases only live in memory, so the urls are queued again when the
+    /// frontier is reopened.
+    leased: Vec<UrlToInsert>,
+}
+
+/// What is needed to schedule a domain. The urls of the domain stay in
+/// the store until they are changed or handed out.
+#[derive(Debug, Clone, Default)]
+struct DomainInfo {
+    centrality: f64,
+    budget_used: f64,
+    period_start: i64,
+    num_urls: usize,
+}
+
+impl From<&DomainState> for DomainInfo {
+    fn from(state: &DomainState) -> Self {
+        Self {
+            centrality: state.centrality,
+            budget_used: state.budget_used,
+            period_start: state.period_start,
+            num_urls: state.urls.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct DomainUrls {
+    urls: Vec<UrlToInsert>,
+    leased: Vec<UrlToInsert>,
+}
+
+/// A job that has been handed out and not yet reported back.
+struct Lease {
+    started: Instant,
+    num_urls: f64,
+}
+
+/// The urls seen by the frontier when it was committed. The filter can be large,
+/// so it is written after the frontier has been released.
+pub struct SeenSnapshot {
+    path: PathBuf,
+    seen: U64BloomFilter,
+}
+
+impl SeenSnapshot {
+    pub fn write(self) -> Result<()> {
+        let bytes = bincode::encode_to_vec(&self.seen, bincode::config::standard())?;
+        write_atomic(&self.path, &bytes)
+    }
+}
+
+pub struct Frontier {
+    path: PathBuf,
+    config: FrontierConfig,
+    db: speedy_kv::Db<Domain, DomainState>,
+    host_centrality: Option<speedy_kv::Db<NodeID, f64>>,
+    seen: U64BloomFilter,
+    domains: HashMap<Domain, DomainInfo>,
+    /// The urls of the domains that have changed since the last commit.
+    /// All other domains have their urls in `db`.
+    changed: HashMap<Domain, DomainUrls>,
+    /// Domains with urls and budget left, ordered by centrality.
+    queued: BTreeSet<(Reverse<SortableFloat>, Domain)>,
+    /// Domains with urls that have used their budget, ordered by the end of their budget period.
+    exhausted: BTreeSet<(i64, Domain)>,
+    leases: HashMap<Domain, Lease>,
+    dirty: BTreeSet<Domain>,
+    commits_since_compaction: usize,
+}
+
+impl Frontier {
+    pub fn open(config: FrontierConfig) -> Result<Self> {
+        let path = PathBuf::from(&config.path);
+        std::fs::create_dir_all(&path)?;
+
+        recover_compaction(&path)?;
+
+        let db: speedy_kv::Db<Domain, DomainState> =
+            speedy_kv::Db::open_or_create(path.join(DOMAINS_FOLDER))?;
+
+        // segments are iterated from oldest to newest, so the latest state of each domain wins
+        let mut domains = HashMap::default();
+        let mut leased = BTreeSet::new();
+        for (domain, state) in db.iter() {
+            if state.leased.is_empty() {
+                leased.remove(&domain);
+            } else {
+                leased.insert(domain.clone());
+            }
+
+            domains.insert(domain, DomainInfo::from(&state));
+        }
+
+        let seen = match std::fs::read(path.join(SEEN_FILE)) {
+            Ok(bytes) => bincode::decode_from_slice(&bytes, bincode::config::standard())?.0,
+            Err(_) => U64BloomFilter::new(config.expected_urls, SEEN_FALSE_POSITIVE_RATE),
+        };
+
+        let host_centrality = config
+            .host_centrality_path
+            .as_ref()
+            .map(speedy_kv::Db::open_or_create)
+            .transpose()?;
+
+        let mut frontier = Self {
+            path,
+            config,
+            db,
+            host_centrality,
+            seen,
+            domains,
+            changed: HashMap::default(),
+            queued: BTreeSet::new(),
+            exhausted: BTreeSet::new(),
+            leases: HashMap::default(),
+            dirty: BTreeSet::new(),
+            commits_since_compaction: 0,
+        };
+
+        // the urls were marked as seen when they were handed out, so they would never be
+        // crawled if the job was lost with the previous process
+        for domain in &leased {
+            frontier.requeue_leased(domain);
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let domains: Vec<_> = frontier.domains.keys().cloned().collect();
+        for domain in domains {
+            frontier.schedule(&domain, now);
+        }
+
+        Ok(frontier)
+    }
+
+    pub fn num_domains(&self) -> usize {
+        self.domains.len()
+    }
+
+    pub fn num_urls(&self) -> usize {
+        self.domains.values().map(|info| info.num_urls).sum()
+    }
+
+    fn budget(&self, info: &DomainInfo) -> f64 {
+        let min = self.config.min_domain_budget as f64;
+        let max = (self.config.max_domain_budget as f64).max(min);
+
+        (min + (max - min) * info.centrality.clamp(0.0, 1.0)).round()
+    }
+
+    fn period_end(&self, info: &DomainInfo) -> i64 {
+        info.period_start + self.config.budget_period_sec as i64
+    }
+
+    fn host_centrality(&self, url: &Url) -> f64 {
+        self.host_centrality
+            .as_ref()
+            .and_then(|db| db.get(&Node::from(url).into_host().id()).ok().flatten())
+            .unwrap_or_default()
+    }
+
+    fn url_hash(url: &UrlToInsert) -> u64 {
+        bloom::fast_stable_hash_64(url.url.0.as_bytes())
+    }
+
+    fn stored_urls(db: &speedy_kv::Db<Domain, DomainState>, domain: &Domain) -> Result<DomainUrls> {
+        Ok(db
+            .get(domain)?
+            .map(|state| DomainUrls {
+                urls: state.urls,
+                leased: state.leased,
+            })
+            .unwrap_or_default())
+    }
+
+    /// The urls of the domain, read from the store the first time
+    /// the domain is changed after a commit.
+    fn urls_mut(&mut self, domain: &Domain) -> &mut DomainUrls {
+        let db = &self.db;
+
+        self.changed.entry(domain.clone()).or_insert_with(|| {
+            Self::stored_urls(db, domain).unwrap_or_else(|err| {
+                tracing::error!("failed to read the urls of {:?}: {:?}", domain, err);
+                DomainUrls::default()
+            })
+        })
+    }
+
+    fn state(&self, domain: &Domain, info: &DomainInfo) -> Result<DomainState> {
+        let urls = match self.changed.get(domain) {
+            Some(urls) => urls.clone(),
+            None => Self::stored_urls(&self.db, domain)?,
+        };
+
+        Ok(DomainState {
+            urls: urls.urls,
+            centrality: info.centrality,
+            budget_used: info.budget_used,
+            period_start: info.period_start,
+            leased: urls.leased,
+        })
+    }
+
+    /// Remove the domain from the queues. Must be called before
+    /// changing anything the position of the domain depends on.
+    fn unschedule(&mut self, domain: &Domain) {
+        if let Some(info) = self.domains.get(domain) {
+            let period_end = self.period_end(info);
+
+            self.queued.remove(&(
+                Reverse(SortableFloat::from(info.centrality)),
+                domain.clone(),
+            ));
+            self.exhausted.remove(&(period_end, domain.clone()));
+        }
+    }
+
+    /// Put the domain in the right queue, unless it has been handed out or has nothing to crawl.
+    fn schedule(&mut self, domain: &Domain, now: i64) {
+        if self.leases.contains_key(domain) {
+            return;
+        }
+
+        let period = self.config.budget_period_sec as i64;
+
+        let Some(info) = self.domains.get_mut(domain) else {
+            return;
+        };
+
+        if info.num_urls == 0 {
+            return;
+        }
+
+        if now >= info.period_start + period {
+            info.period_start = now;
+            info.budget_used = 0.0;
+            self.dirty.insert(domain.clone());
+        }
+
+        let info = &self.domains[domain];
+        let has_budget = info.budget_used < self.budget(info);
+        let period_end = self.period_end(info);
+        let centrality = info.centrality;
+
+        if has_budget {
+            self.queued
+                .insert((Reverse(SortableFloat::from(centrality)), domain.clone()));
+        } else {
+            self.exhausted.insert((period_end, domain.clone()));
+        }
+    }
+
+    /// Add the urls of a job, e.g. from the crawl plan.
+    pub fn add_job(&mut self, job: Job) {
+        let urls = job
+            .urls
+            .into_iter()
+            .map(|url| UrlToInsert {
+                url: (&url.url).into(),
+                weight: url.weight,
+            })
+            .collect();
+
+        self.insert(job.domain, urls, chrono::Utc::now().timestamp());
+    }
+
+    /// Add the urls discovered by a worker. Urls that have been seen before are ignored,
+    /// and at most [`MAX_DOMAIN_DISCOVERY_FACTOR`] new domains are added, so a single
+    /// link farm can't flood the frontier with domains.
+    pub fn add_discovered(&mut self, discovered: DiscoveredUrls) {
+        self.add_discovered_at(discovered, chrono::Utc::now().timestamp())
+    }
+
+    fn add_discovered_at(&mut self, discovered: DiscoveredUrls, now: i64) {
+        let mut new_domains = 0;
+
+        let mut discovered: Vec<_> = discovered.urls.into_iter().collect();
+        // prefer the domains with the most promising urls when new domains are limited
+        discovered.sort_by(|(_, a), (_, b)| {
+            let a = a.iter().map(|url| url.weight).sum::<f64>();
+            let b = b.iter().map(|url| url.weight).sum::<f64>();
+            b.total_cmp(&a)
+        });
+
+        for (domain, mut urls) in discovered {
+            if domain.as_str().is_empty() {
+                continue;
+            }
+
+            if !self.domains.contains_key(&domain) {
+                if new_domains >= MAX_DOMAIN_DISCOVERY_FACTOR {
+                    continue;
+                }
+
+                new_domains += 1;
+            }
+
+            urls.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+            urls.truncate(MAX_URLS_FOR_DOMAIN_PER_INSERT);
+
+            self.insert(domain, urls, now);
+        }
+    }
+
+    fn insert(&mut self, domain: Domain, urls: Vec<UrlToInsert>, now: i64) {
+        let urls: Vec<_> = urls
+            .into_iter()
+            .filter(|url| url.weight.is_finite())
+            .filter(|url| !self.seen.contains(Self::url_hash(url)))
+            .collect();
+
+        if urls.is_empty() {
+            return;
+        }
+
+        let centrality = urls
+            .iter()
+            .filter_map(|url| Url::try_from(&url.url).ok())
+            .map(|url| self.host_centrality(&url))
+            .fold(0.0, f64::max);
+
+        for url in &urls {
+            self.seen.insert(Self::url_hash(url));
+        }
+
+        self.unschedule(&domain);
+
+        let info = self
+            .domains
+            .entry(domain.clone())
+            .or_insert_with(|| DomainInfo {
+                period_start: now,
+                ..Default::default()
+            });
+        info.centrality = info.centrality.max(centrality);
+
+        let max_urls = self.config.max_urls_per_domain;
+        let domain_urls = self.urls_mut(&domain);
+        domain_urls.urls.extend(urls);
+
+        if domain_urls.urls.len() > max_urls {
+            domain_urls
+                .urls
+                .sort_by(|a, b| b.weight.total_cmp(&a.weight));
+            domain_urls.urls.truncate(max_urls);
+        }
+
+        let num_urls = domain_urls.urls.len();
+        if let Some(info) = self.domains.get_mut(&domain) {
+            info.num_urls = num_urls;
+        }
+
+        self.dirty.insert(domain.clone());
+        self.schedule(&domain, now);
+    }
+
+    /// The next job to crawl, if any domain has urls and budget left.
+    pub fn sample_job(&mut self) -> Option<Job> {
+        self.sample_job_at(chrono::Utc::now().timestamp())
+    }
+
+    fn sample_job_at(&mut self, now: i64) -> Option<Job> {
+        self.refresh(now);
+
+        let (_, domain) = self.queued.pop_first()?;
+        let info = self.domains.get(&domain)?;
+
+        let remaining = (self.budget(info) - info.budget_used).max(1.0) as usize;
+        let num_urls = self.config.urls_per_job.min(remaining).max(1);
+
+        let domain_urls = self.urls_mut(&domain);
+        domain_urls
+            .urls
+            .sort_by(|a, b| b.weight.total_cmp(&a.weight));
+        let num_urls = num_urls.min(domain_urls.urls.len());
+        let urls: Vec<_> = domain_urls.urls.drain(..num_urls).collect();
+        domain_urls.leased.clone_from(&urls);
+        let num_left = domain_urls.urls.len();
+
+        let info = self.domains.get_mut(&domain)?;
+        info.budget_used += num_urls as f64;
+        info.num_urls = num_left;
+
+        let wandering_urls = self
+            .config
+            .wander_urls_per_job
+            .min((remaining - num_urls) as u64);
+
+        self.leases.insert(
+            domain.clone(),
+            Lease {
+                started: Instant::now(),
+                num_urls: num_urls as f64,
+            },
+        );
+        self.dirty.insert(domain.clone());
+
+        Some(Job {
+            domain,
+            urls: urls
+                .iter()
+                .filter_map(|url| {
+                    Url::try_from(&url.url)
+                        .ok()
+                        .map(|u| WeightedUrl::new(u, url.weight))
+                })
+                .collect(),
+            wandering_urls,
+        })
+    }
+
+    /// Move domains whose budget period has ended back in the queue and
+    /// queue the urls of the jobs whose workers never reported back again.
+    fn refresh(&mut self, now: i64) {
+        while let Some((period_end, _)) = self.exhausted.first() {
+            if *period_end > now {
+                break;
+            }
+
+            let (_, domain) = self.exhausted.pop_first().unwrap();
+            self.schedule(&domain, now);
+        }
+
+        let timeout = Duration::from_secs(self.config.job_timeout_sec);
+        let expired: Vec<_> = self
+            .leases
+            .iter()
+            .filter(|(_, lease)| lease.started.elapsed() > timeout)
+            .map(|(domain, _)| domain.clone())
+            .collect();
+
+        for domain in expired {
+            tracing::warn!("job for {:?} timed out", domain);
+            self.leases.remove(&domain);
+            self.requeue_leased(&domain);
+            self.schedule(&domain, now);
+        }
+    }
+
+    /// Queue the leased urls of the domain again and give back the budget they used.
+    fn requeue_leased(&mut self, domain: &Domain) {
+        if !self.domains.contains_key(domain) {
+            return;
+        }
+
+        let domain_urls = self.urls_mut(domain);
+        let num_leased = domain_urls.leased.len();
+        let mut leased = std::mem::take(&mut domain_urls.leased);
+        domain_urls.urls.append(&mut leased);
+        let num_urls = domain_urls.urls.len();
+
+        if let Some(info) = self.domains.get_mut(domain) {
+            info.budget_used = (info.budget_used - num_leased as f64).max(0.0);
+            info.num_urls = num_urls;
+        }
+
+        self.dirty.insert(domain.clone());
+    }
+
+    /// The worker has finished the job for the domain. The budget used is replaced by what
+    /// the worker actually crawled, which includes the urls it wandered to.
+    pub fn domain_crawled(&mut self, crawled: DomainCrawled) {
+        self.domain_crawled_at(crawled, chrono::Utc::now().timestamp())
+    }
+
+    fn domain_crawled_at(&mut self, crawled: DomainCrawled, now: i64) {
+        let Some(lease) = self.leases.remove(&crawled.domain) else {
+            return;
+        };
+
+        if self.domains.contains_key(&crawled.domain) {
+            self.urls_mut(&crawled.domain).leased.clear();
+
+            if let Some(info) = self.domains.get_mut(&crawled.domain) {
+                info.budget_used =
+                    (info.budget_used - lease.num_urls + crawled.budget_used).max(0.0);
+            }
+
+            self.dirty.insert(crawled.domain.clone());
+        }
+
+        self.schedule(&crawled.domain, now);
+    }
+
+    /// Write the changed domains to the store. The returned snapshot of the seen urls
+    /// must be written as well, but that can be done after the frontier is released.
+    pub fn commit(&mut self) -> Result<SeenSnapshot> {
+        for domain in &self.dirty {
+            if let Some(info) = self.domains.get(domain) {
+                let state = self.state(domain, info)?;
+                self.db.insert(domain.clone(), state)?;
+            }
+        }
+
+        self.db.commit()?;
+        self.dirty.clear();
+        self.changed.clear();
+        self.commits_since_compaction += 1;
+
+        if self.commits_since_compaction >= COMMITS_BETWEEN_COMPACTION {
+            self.compact()?;
+        }
+
+        Ok(SeenSnapshot {
+            path: self.path.join(SEEN_FILE),
+            seen: self.seen.clone(),
+        })
+    }
+
+    /// Write the current state of all domains to a new store next to the current one.
+    fn write_compacted(&self) -> Result<()> {
+        let compacting = self.path.join(COMPACTING_FOLDER);
+        if compacting.exists() {
+            std::fs::remove_dir_all(&compacting)?;
+        }
+
+        let mut db = speedy_kv::Db::open_or_create(&compacting)?;
+
+        for (domain, info) in &self.domains {
+            db.insert(domain.clone(), self.state(domain, info)?)?;
+        }
+
+        db.commit()?;
+
+        Ok(())
+    }
+
+    /// Replace the store with a compacted one. The old store is only deleted once the
+    /// compacted one has taken its place, and [`recover_compaction`] finishes the swap
+    /// if we crash in between.
+    fn compact(&mut self) -> Result<()> {
+        self.write_compacted()?;
+
+        let domains = self.path.join(DOMAINS_FOLDER);
+        let old = self.path.join(OLD_DOMAINS_FOLDER);
+
+        std::fs::rename(&domains, &old)?;
+        std::fs::rename(self.path.join(COMPACTING_FOLDER), &domains)?;
+        self.db = speedy_kv::Db::open_or_create(&domains)?;
+        std::fs::remove_dir_all(old)?;
+
+        self.commits_since_compaction = 0;
+
+        Ok(())
+    }
+}
+
+/// Finish or roll back a compaction that was interrupted by a crash.
+fn recover_compaction(path: &Path) -> Result<()> {
+    let domains = path.join(DOMAINS_FOLDER);
+    let compacting = path.join(COMPACTING_FOLDER);
+    let old = path.join(OLD_DOMAINS_FOLDER);
+
+    if old.exists() {
+        if !domains.exists() {
+            // the old store is only moved aside once the compacted store is complete
+            if compacting.exists() {
+                std::fs::rename(&compacting, &domains)?;
+            } else {
+                std::fs::rename(&old, &domains)?;
+                return Ok(());
+            }
+        }
+
+        std::fs::remove_dir_all(&old)?;
+    }
+
+    // the compaction didn't get to the swap, so the store is untouched
+    if compacting.exists() {
+        std::fs::remove_dir_all(&compacting)?;
+    }
+
+    Ok(())
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(tmp, path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> FrontierConfig {
+        FrontierConfig {
+            path: crate::gen_temp_path().to_str().unwrap().to_string(),
+            host_centrality_path: None,
+            expected_urls: 1_000,
+            urls_per_job: 2,
+            wander_urls_per_job: 0,
+            max_urls_per_domain: 100,
+            min_domain_budget: 3,
+            max_domain_budget: 100,
+            budget_period_sec: 60,
+            job_timeout_sec: 60 * 60,
+        }
+    }
+
+    fn discovered(urls: &[(&str, f64)]) -> DiscoveredUrls {
+        let mut res: HashMap<Domain, Vec<UrlToInsert>> = HashMap::default();
+
+        for (url, weight) in urls {
+            let url = Url::parse(url).unwrap();
+            res.entry(Domain::from(&url))
+                .or_default()
+                .push(UrlToInsert {
+                    url: url.into(),
+                    weight: *weight,
+                });
+        }
+
+        DiscoveredUrls { urls: res }
+    }
+
+    fn crawled(domain: &str, budget_used: f64) -> DomainCrawled {
+        DomainCrawled {
+            domain: Domain::from(domain.to_string()),
+            budget_used,
+        }
+    }
+
+    fn urls(job: &Job) -> Vec<&str> {
+        job.urls.iter().map(|url| url.url.as_str()).collect()
+    }
+
+    #[test]
+    fn dedupe() {
+        let mut frontier = Frontier::open(config()).unwrap();
+
+        frontier.add_discovered_at(discovered(&[("https://a.com/", 1.0)]), 0);
+        frontier.add_discovered_at(
+            discovered(&[("https://a.com/", 1.0), ("https://a.com/b", 0.5)]),
+            0,
+        );
+        assert_eq!(frontier.num_urls(), 2);
+
+        let job = frontier.sample_job_at(0).unwrap();
+        assert_eq!(urls(&job), vec!["https://a.com/", "https://a.com/b"]);
+
+        // urls that have been handed out are not added again
+        frontier.add_discovered_at(discovered(&[("https://a.com/", 1.0)]), 0);
+        assert_eq!(frontier.num_urls(), 0);
+    }
+
+    #[test]
+    fn one_job_per_domain() {
+        let mut frontier = Frontier::open(config()).unwrap();
+
+        frontier.add_discovered_at(
+            discovered(&[
+                ("https://a.com/1", 1.0),
+                ("https://a.com/2", 1.0),
+                ("https://a.com/3", 1.0),
+            ]),
+            0,
+        );
+
+        let job = frontier.sample_job_at(0).unwrap();
+        assert_eq!(job.urls.len(), 2);
+        assert!(frontier.sample_job_at(0).is_none());
+
+        frontier.domain_crawled_at(crawled("a.com", 2.0), 0);
+        let job = frontier.sample_job_at(0).unwrap();
+        assert_eq!(urls(&job), vec!["https://a.com/3"]);
+    }
+
+    #[test]
+    fn budget() {
+        let mut frontier = Frontier::open(config()).unwrap();
+
+        frontier.add_discovered_at(
+            discovered(&[
+                ("https://a.com/1", 4.0),
+                ("https://a.com/2", 3.0),
+                ("https://a.com/3", 2.0),
+                ("https://a.com/4", 1.0),
+            ]),
+            0,
+        );
+
+        frontier.sample_job_at(0).unwrap();
+        // the worker wandered to more urls than it was given
+        frontier.domain_crawled_at(crawled("a.com", 3.0), 0);
+        assert!(frontier.sample_job_at(10).is_none());
+
+        // the budget is reset when the period ends
+        let job = frontier.sample_job_at(60).unwrap();
+        assert_eq!(urls(&job), vec!["https://a.com/3", "https://a.com/4"]);
+    }
+
+    #[test]
+    fn limit_new_domains() {
+        let mut frontier = Frontier::open(config()).unwrap();
+
+        let urls: Vec<_> = (0..2 * MAX_DOMAIN_DISCOVERY_FACTOR)
+            .map(|i| (format!("https://{i}.com/"), i as f64))
+            .collect();
+        let urls: Vec<_> = urls.iter().map(|(url, w)| (url.as_str(), *w)).collect();
+
+        frontier.add_discovered_at(discovered(&urls), 0);
+        assert_eq!(frontier.num_domains(), MAX_DOMAIN_DISCOVERY_FACTOR);
+
+        // the domains with the highest weights are kept
+        let top = Domain::from(format!("{}.com", 2 * MAX_DOMAIN_DISCOVERY_FACTOR - 1));
+        assert!(frontier.domains.contains_key(&top));
+        assert!(!frontier
+            .domains
+            .contains_key(&Domain::from("0.com".to_string())));
+    }
+
+    #[test]
+    fn priority() {
+        let host_centrality_path = crate::gen_temp_path();
+        let mut host_centrality: speedy_kv::Db<NodeID, f64> =
+            speedy_kv::Db::open_or_create(&host_centrality_path).unwrap();
+        host_centrality
+            .insert(Node::from("b.com").into_host().id(), 0.5)
+            .unwrap();
+        host_centrality.commit().unwrap();
+        drop(host_centrality);
+
+        let mut config = config();
+        config.host_centrality_path = Some(host_centrality_path.to_str().unwrap().to_string());
+        let mut frontier = Frontier::open(config).unwrap();
+
+        frontier.add_discovered_at(
+            discovered(&[("https://a.com/", 1.0), ("https://b.com/", 1.0)]),
+            0,
+        );
+
+        assert_eq!(
+            frontier.sample_job_at(0).unwrap().domain,
+            Domain::from("b.com".to_string())
+        );
+        assert_eq!(
+            frontier.sample_job_at(0).unwrap().domain,
+            Domain::from("a.com".to_string())
+        );
+    }
+
+    #[test]
+    fn persistence() {
+        let config = config();
+
+        let mut frontier = Frontier::open(config.clone()).unwrap();
+        frontier.add_discovered_at(
+            discovered(&[
+                ("https://a.com/1", 1.0),
+                ("https://a.com/2", 0.5),
+                ("https://b.com/", 1.0),
+            ]),
+            0,
+        );
+        frontier.commit().unwrap().write().unwrap();
+
+        let job = frontier.sample_job_at(0).unwrap();
+        frontier.domain_crawled_at(crawled(job.domain.as_str(), job.urls.len() as f64), 0);
+        frontier.commit().unwrap().write().unwrap();
+        frontier.compact().unwrap();
+        drop(frontier);
+
+        let mut frontier = Frontier::open(config).unwrap();
+        assert_eq!(frontier.num_domains(), 2);
+        assert_eq!(frontier.num_urls(), 3 - job.urls.len());
+
+        frontier.add_discovered_at(discovered(&[("https://b.com/", 1.0)]), 0);
+        assert_eq!(frontier.num_urls(), 3 - job.urls.len());
+    }
+
+    #[test]
+    fn lost_leases() {
+        let config = config();
+
+        let mut frontier = Frontier::open(config.clone()).unwrap();
+        frontier.add_discovered_at(
+            discovered(&[("https://a.com/1", 1.0), ("https://a.com/2", 0.5)]),
+            0,
+        );
+
+        let job = frontier.sample_job_at(0).unwrap();
+        frontier.commit().unwrap().write().unwrap();
+        drop(frontier);
+
+        // the job was never reported back, so its urls are handed out again
+        let mut frontier = Frontier::open(config).unwrap();
+        assert_eq!(frontier.num_urls(), 2);
+        assert_eq!(urls(&frontier.sample_job_at(0).unwrap()), urls(&job));
+    }
+
+    #[test]
+    fn expired_leases() {
+        let mut config = config();
+        config.job_timeout_sec = 0;
+
+        let mut frontier = Frontier::open(config).unwrap();
+        frontier.add_discovered_at(
+            discovered(&[
+                ("https://a.com/1", 1.0),
+                ("https://a.com/2", 0.5),
+                ("https://a.com/3", 0.1),
+            ]),
+            0,
+        );
+
+        let job = frontier.sample_job_at(0).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+
+        // the worker never reported back, so its urls and budget are given back
+        let retry = frontier.sample_job_at(0).unwrap();
+        assert_eq!(urls(&retry), urls(&job));
+        assert_eq!(frontier.num_urls(), 1);
+        assert_eq!(frontier.domains[&job.domain].budget_used, 2.0);
+    }
+
+    #[test]
+    fn interrupted_compaction() {
+        let config = config();
+        let path = PathBuf::from(&config.path);
+
+        let mut frontier = Frontier::open(config.clone()).unwrap();
+        frontier.add_discovered_at(
+            discovered(&[("https://a.com/", 1.0), ("https://b.com/", 1.0)]),
+            0,
+        );
+        frontier.commit().unwrap().write().unwrap();
+        frontier.write_compacted().unwrap();
+        drop(frontier);
+
+        // crashed after the old store was moved aside
+        std::fs::rename(path.join(DOMAINS_FOLDER), path.join(OLD_DOMAINS_FOLDER)).unwrap();
+
+        let mut frontier = Frontier::open(config.clone()).unwrap();
+        assert_eq!(frontier.num_urls(), 2);
+        assert!(!path.join(OLD_DOMAINS_FOLDER).exists());
+        assert!(!path.join(COMPACTING_FOLDER).exists());
+
+        // crashed after the compacted store was moved in
+        frontier.write_compacted().unwrap();
+        drop(frontier);
+        std::fs::rename(path.join(DOMAINS_FOLDER), path.join(OLD_DOMAINS_FOLDER)).unwrap();
+        std::fs::rename(path.join(COMPACTING_FOLDER), path.join(DOMAINS_FOLDER)).unwrap();
+
+        let frontier = Frontier::open(config.clone()).unwrap();
+        assert_eq!(frontier.num_urls(), 2);
+        assert!(!path.join(OLD_DOMAINS_FOLDER).exists());
+        drop(frontier);
+
+        // crashed while writing the compacted store
+        std::fs::create_dir_all(path.join(COMPACTING_FOLDER)).unwrap();
+
+        let mut frontier = Frontier::open(config).unwrap();
+        assert_eq!(frontier.num_urls(), 2);
+        assert!(!path.join(COMPACTING_FOLDER).exists());
+        assert_eq!(
+            frontier.sample_job_at(0).unwrap().domain,
+            Domain::from("a.com".to_string())
+        );
+    }
+}
@@ -22,16 +22,28 @@ use url::Url;
 
 use crate::{config::CrawlerConfig, warc, webpage::url_ext::UrlExt};
 
-use self::{warc_writer::WarcWriter, worker::WorkerThread};
-pub use worker::JobExecutor;
+use self::{
+    file_queue::QueueItem, recrawl::RecrawlScheduler, warc_writer::WarcWriter, worker::WorkerThread,
+};
+pub use recrawl::{Validators, Visit};
+pub use redirect::{Redirect, RedirectChain};
+pub use warc_sink::{CallbackSink, LocalSink, S3Sink, WarcSink};
+pub use warc_writer::Rotation;
+pub use worker::{JobExecutor, JobOutcome};
 
 pub mod coordinator;
+mod encoding;
 mod robots_txt;
 pub mod router;
+mod sitemap;
 pub use router::Router;
 mod file_queue;
+pub mod frontier;
 pub mod planner;
+pub mod recrawl;
+pub mod redirect;
 mod wander_prirotiser;
+pub mod warc_sink;
 mod warc_writer;
 mod worker;
 
@@ -60,6 +72,12 @@ pub enum Error {
 
     #[error("invalid redirect")]
     InvalidRedirect,
+
+    #[error("redirect loop")]
+    RedirectLoop,
+
+    #[error("too many redirects")]
+    TooManyRedirects,
 }
 
 type Result<T, E = anyhow::Error> = std::result::Result<T, E>;
@@ -85,6 +103,8 @@ struct Site(String);
     PartialEq,
     Eq,
     Hash,
+    PartialOrd,
+    Ord,
     serde::Serialize,
     serde::Deserialize,
     bincode::Encode,
@@ -114,6 +134,13 @@ impl Domain {
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// The index of the coordinator that owns the domain. The planner writes the jobs
+    /// for the domain to the job queue with the same index, so queue `i` must be served
+    /// by the `i`th coordinator of the router.
+    pub fn owner(&self, num_coordinators: usize) -> usize {
+        (bloom::fast_stable_hash_64(self.as_str().as_bytes()) % num_coordinators as u64) as usize
+    }
 }
 
 #[derive(serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode, Debug, Clone)]
@@ -121,6 +148,19 @@ pub struct WeightedUrl {
     #[bincode(with_serde)]
     pub url: Url,
     pub weight: f64,
+    /// Validators from the previous visit of the url, if any.
+    #[serde(default)]
+    pub validators: Validators,
+}
+
+impl WeightedUrl {
+    pub fn new(url: Url, weight: f64) -> Self {
+        Self {
+            url,
+            weight,
+            validators: Validators::default(),
+        }
+    }
 }
 
 impl PartialEq for WeightedUrl {
@@ -146,6 +186,60 @@ pub struct Job {
     pub wandering_urls: u64,
 }
 
+/// A url in a job planned before urls had validators.
+#[derive(bincode::Encode, bincode::Decode)]
+struct WeightedUrlV0 {
+    #[bincode(with_serde)]
+    url: Url,
+    weight: f64,
+}
+
+/// A job planned before urls had validators.
+#[derive(bincode::Encode, bincode::Decode)]
+struct JobV0 {
+    domain: Domain,
+    urls: VecDeque<WeightedUrlV0>,
+    wandering_urls: u64,
+}
+
+impl From<JobV0> for Job {
+    fn from(job: JobV0) -> Self {
+        Self {
+            domain: job.domain,
+            urls: job
+                .urls
+                .into_iter()
+                .map(|url| WeightedUrl::new(url.url, url.weight))
+                .collect(),
+            wandering_urls: job.wandering_urls,
+        }
+    }
+}
+
+impl QueueItem for Job {
+    const VERSION: u32 = 1;
+
+    fn decode_version(version: u32, bytes: &[u8]) -> Result<Self> {
+        match version {
+            0 => {
+                let (job, _): (JobV0, _) =
+                    bincode::decode_from_slice(bytes, bincode::config::standard())?;
+                Ok(job.into())
+            }
+            _ => Err(anyhow::anyhow!("unsupported job version {version}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode)]
+pub enum JobResponse {
+    Job(Job),
+    /// There is no job right now, but there might be later when more urls have been discovered.
+    Wait,
+    /// All jobs have been handed out.
+    Done,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode)]
 pub enum UrlResponse {
     Success {
@@ -162,6 +256,11 @@ pub enum UrlResponse {
         url: Url,
         #[bincode(with_serde)]
         new_url: Url,
+        chain: RedirectChain,
+    },
+    NotModified {
+        #[bincode(with_serde)]
+        url: Url,
     },
 }
 
@@ -257,18 +356,45 @@ pub struct CrawlDatum {
     pub url: Url,
     pub status_code: u16,
     pub payload_type: warc::PayloadType,
-    pub body: String,
+    /// The payload of the response. Text payloads are decoded to utf-8 when they are fetched.
+    pub body: Vec<u8>,
     pub fetch_time_ms: u64,
+    pub validators: Validators,
+    pub request_headers: warc::HttpHeaders,
+    pub response_headers: warc::HttpHeaders,
+    /// The redirects that were followed from the requested url to `url`.
+    pub redirects: RedirectChain,
+    /// When the page was last downloaded. Set for `304 Not Modified` responses, which
+    /// refer to the payload of that capture.
+    pub previous_fetch: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+/// How often the recrawl scheduler of a worker is persisted.
+const RECRAWL_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
 pub struct Crawler {
     writer: Arc<WarcWriter>,
+    recrawl: Option<Arc<RecrawlScheduler>>,
     handles: Vec<tokio::task::JoinHandle<()>>,
+    /// Periodically saves the recrawl scheduler, as workers that crawl from a
+    /// frontier never finish.
+    save_handle: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl Crawler {
     pub async fn new(config: CrawlerConfig) -> Result<Self> {
-        let writer = Arc::new(WarcWriter::new(config.s3.clone()));
+        let writer = WarcWriter::from_config(&config);
+        Self::with_writer(config, writer).await
+    }
+
+    /// Create a crawler that stores its WARC files in `sink` instead of the sink from the config.
+    pub async fn with_sink<S: WarcSink>(config: CrawlerConfig, sink: S) -> Result<Self> {
+        let writer = WarcWriter::new(sink, Rotation::from(&config));
+        Self::with_writer(config, writer).await
+    }
+
+    async fn with_writer(config: CrawlerConfig, writer: WarcWriter) -> Result<Self> {
+        let writer = Arc::new(writer);
         let mut handles = Vec::new();
         let mut router_hosts = Vec::new();
 
@@ -276,16 +402,53 @@ impl Crawler {
             router_hosts.push(host.parse()?);
         }
 
+        let recrawl = match &config.recrawl_db_path {
+            Some(path) => Some(Arc::new(RecrawlScheduler::open(path)?.with_intervals(
+                Duration::from_secs(config.min_recrawl_interval_sec),
+                Duration::from_secs(config.max_recrawl_interval_sec),
+            ))),
+            None => None,
+        };
+
         for _ in 0..config.num_worker_threads {
-            let worker =
-                WorkerThread::new(Arc::clone(&writer), config.clone(), router_hosts.clone())?;
+            let worker = WorkerThread::new(
+                Arc::clone(&writer),
+                config.clone(),
+                router_hosts.clone(),
+                recrawl.clone(),
+            )?;
 
             handles.push(tokio::spawn(async move {
                 worker.run().await;
             }));
         }
 
-        Ok(Self { writer, handles })
+        let save_handle = recrawl.clone().map(|recrawl| {
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(RECRAWL_SAVE_INTERVAL);
+                interval.tick().await;
+
+                loop {
+                    interval.tick().await;
+
+                    let recrawl = Arc::clone(&recrawl);
+                    match tokio::task::spawn_blocking(move || recrawl.save()).await {
+                        Ok(Err(err)) => {
+                            tracing::error!("failed to save recrawl scheduler: {:?}", err)
+                        }
+                        Err(err) => tracing::error!("failed to save recrawl scheduler: {:?}", err),
+                        Ok(Ok(())) => {}
+                    }
+                }
+            })
+        });
+
+        Ok(Self {
+            writer,
+            recrawl,
+            handles,
+            save_handle,
+        })
     }
 
     pub async fn run(self) {
@@ -293,7 +456,15 @@ impl Crawler {
             handle.await.ok();
         }
 
+        if let Some(handle) = self.save_handle {
+            handle.abort();
+        }
+
         self.writer.finish().await.unwrap();
+
+        if let Some(recrawl) = self.recrawl {
+            recrawl.save().unwrap();
+        }
     }
 }
 
@@ -302,9 +473,8 @@ pub trait DatumStream: Send + Sync {
     fn finish(&self) -> impl Future<Output = Result<()>> + Send;
 }
 
-pub fn reqwest_client(config: &CrawlerConfig) -> Result<reqwest::Client> {
-    let timeout = Duration::from_secs(config.timeout_seconds);
-
+/// Headers that are sent with every request from the crawler.
+fn default_headers() -> reqwest::header::HeaderMap {
     let mut headers = reqwest::header::HeaderMap::default();
     headers.insert(
         reqwest::header::ACCEPT,
@@ -315,12 +485,49 @@ pub fn reqwest_client(config: &CrawlerConfig) -> Result<reqwest::Client> {
         reqwest::header::HeaderValue::from_static("en-US,en;q=0.9,*;q=0.8"),
     );
 
+    headers
+}
+
+pub fn reqwest_client(config: &CrawlerConfig) -> Result<reqwest::Client> {
+    let timeout = Duration::from_secs(config.timeout_seconds);
+
     Ok(reqwest::Client::builder()
         .timeout(timeout)
         .connect_timeout(timeout)
         .http2_keep_alive_interval(None)
-        .default_headers(headers)
-        .redirect(reqwest::redirect::Policy::limited(config.max_redirects))
+        .default_headers(default_headers())
+        // redirects are followed by the crawler so the chains can be recorded
+        .redirect(reqwest::redirect::Policy::none())
         .user_agent(&config.user_agent.full)
         .build()?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_job_v0() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let job = JobV0 {
+            domain: Domain::from("example.com".to_string()),
+            urls: VecDeque::from([WeightedUrlV0 {
+                url: url.clone(),
+                weight: 2.0,
+            }]),
+            wandering_urls: 3,
+        };
+        let bytes = bincode::encode_to_vec(&job, bincode::config::standard()).unwrap();
+
+        let job = Job::decode_version(0, &bytes).unwrap();
+
+        assert_eq!(job.domain, Domain::from("example.com".to_string()));
+        assert_eq!(job.urls.len(), 1);
+        assert_eq!(job.urls[0].url, url);
+        assert_eq!(job.urls[0].weight, 2.0);
+        assert!(job.urls[0].validators.is_empty());
+        assert_eq!(job.wandering_urls, 3);
+
+        assert!(Job::decode_version(Job::VERSION + 1, &bytes).is_err());
+    }
+}
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
 use hashbrown::HashSet;
 use indicatif::ParallelProgressIterator;
 use indicatif::ProgressIterator;
@@ -23,13 +24,10 @@ use rayon::ThreadPoolBuilder;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::iter;
-use std::{
-    path::Path,
-    sync::{atomic::AtomicUsize, Mutex},
-};
+use std::{path::Path, sync::Mutex};
 use url::Url;
 
-use crate::crawler::WeightedUrl;
+use crate::crawler::{recrawl::RecrawlScheduler, WeightedUrl};
 use crate::webgraph::centrality::{top_nodes, TopNodes};
 use crate::SortableFloat;
 use crate::{
@@ -47,6 +45,7 @@ pub struct CrawlPlanner {
     page_centrality: speedy_kv::Db<NodeID, f64>,
     host_graph: Webgraph,
     page_graph: Webgraph,
+    recrawl: Option<RecrawlScheduler>,
     config: CrawlPlannerConfig,
 }
 
@@ -65,10 +64,36 @@ impl CrawlPlanner {
             page_centrality,
             host_graph,
             page_graph,
+            recrawl: None,
             config,
         })
     }
 
+    /// Skip urls that are not due for a recrawl and add the ones that are.
+    pub fn with_recrawl_scheduler(mut self, recrawl: RecrawlScheduler) -> Self {
+        self.recrawl = Some(recrawl);
+        self
+    }
+
+    fn is_due(&self, url: &Url, now: DateTime<Utc>) -> bool {
+        match &self.recrawl {
+            Some(recrawl) => recrawl.is_due(url, now),
+            None => true,
+        }
+    }
+
+    fn with_validators(&self, mut url: WeightedUrl) -> WeightedUrl {
+        if let Some(validators) = self
+            .recrawl
+            .as_ref()
+            .and_then(|recrawl| recrawl.validators(&url.url))
+        {
+            url.validators = validators;
+        }
+
+        url
+    }
+
     fn all_pages(&self, host: NodeID) -> Vec<(NodeID, f64)> {
         self.page_graph
             .pages_by_host(&host)
@@ -138,12 +163,14 @@ impl CrawlPlanner {
         domain: Domain,
         hosts: &[NodeID],
         host_budgets: &BTreeMap<NodeID, u64>,
+        recrawl_urls: Vec<WeightedUrl>,
+        now: DateTime<Utc>,
     ) -> (Job, DomainStats) {
         let mut total_wander_budget = 0;
         let mut total_schedule_budget = 0;
-        let mut total_scheduled_urls = 0;
+        let mut total_scheduled_urls = recrawl_urls.len() as u64;
         let mut total_known_urls = 0;
-        let mut urls = HashSet::new();
+        let mut urls: HashSet<WeightedUrl> = recrawl_urls.into_iter().collect();
 
         for host in hosts {
             let mut pages = self.all_pages(*host);
@@ -181,14 +208,15 @@ impl CrawlPlanner {
                     .filter_map(|(n, score)| {
                         Url::parse(&format!("http://{n}")).ok().map(|u| (u, score))
                     })
-                    .map(|(url, score)| WeightedUrl { url, weight: score })
+                    .filter(|(url, _)| self.is_due(url, now))
+                    .map(|(url, score)| self.with_validators(WeightedUrl::new(url, score)))
                     .take(schedule_budget as usize)
                     .chain(
                         iter::once_with(|| {
-                            Some(WeightedUrl {
-                                url: format!("http://{host_name}").parse().ok()?,
-                                weight: 1.0,
-                            })
+                            Some(WeightedUrl::new(
+                                format!("http://{host_name}").parse().ok()?,
+                                1.0,
+                            ))
                         })
                         .flatten(),
                     ),
@@ -359,8 +387,6 @@ impl CrawlPlanner {
 
         let stats = Mutex::new(Vec::new());
 
-        let next_queue = AtomicUsize::new(0);
-
         let num_threads = self
             .config
             .num_threads
@@ -372,6 +398,24 @@ impl CrawlPlanner {
             .thread_name(move |num| format!("crawl-planner-{num}"))
             .build()?;
 
+        let now = Utc::now();
+        let recrawl_urls = Mutex::new(
+            self.recrawl
+                .as_ref()
+                .map(|recrawl| recrawl.due(now))
+                .unwrap_or_default(),
+        );
+
+        let push_job = |job: Job| {
+            // the router sends the discovered urls and crawl reports of a domain to the
+            // coordinator that owns it, so the coordinator must also have its planned job
+            job_queues[job.domain.owner(job_queues.len())]
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(job)
+                .unwrap();
+        };
+
         pool.install(|| {
             let host_budgets = self.assign_host_budgets(&hosts);
 
@@ -383,23 +427,49 @@ impl CrawlPlanner {
                         return;
                     }
 
-                    let (job, domain_stats) = self.prepare_job(domain, &hosts, &host_budgets);
+                    let domain_recrawl_urls = recrawl_urls
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .remove(&domain)
+                        .unwrap_or_default();
+
+                    let (job, domain_stats) =
+                        self.prepare_job(domain, &hosts, &host_budgets, domain_recrawl_urls, now);
 
                     stats
                         .lock()
                         .unwrap_or_else(|e| e.into_inner())
                         .push(domain_stats);
 
-                    // `fetch_add` wraps around on overflow
-                    let queue_index = next_queue.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-                    job_queues[queue_index % job_queues.len()]
-                        .lock()
-                        .unwrap_or_else(|e| e.into_inner())
-                        .push(job)
-                        .unwrap();
+                    push_job(job);
                 })
         });
 
+        // domains that are due for a recrawl but are not among the top hosts
+        for (domain, urls) in recrawl_urls.into_inner().unwrap_or_else(|e| e.into_inner()) {
+            if domain.as_str().is_empty() {
+                continue;
+            }
+
+            stats
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(DomainStats {
+                    domain: domain.clone(),
+                    known_urls: urls.len(),
+                    num_hosts: 0,
+                    schedule_budget: 0,
+                    scheduled_urls: urls.len() as u64,
+                    wander_budget: 0,
+                });
+
+            push_job(Job {
+                domain,
+                urls: urls.into_iter().collect(),
+                wandering_urls: 0,
+            });
+        }
+
         for queue in job_queues {
             queue
                 .into_inner()
@@ -583,6 +653,7 @@ mod tests {
                 page_graph_path: String::new(),
                 host_graph_path: String::new(),
                 output_path: String::new(),
+                recrawl_db_path: None,
                 num_threads: Some(1),
             },
         )
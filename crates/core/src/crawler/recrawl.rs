@@ -0,0 +1,652 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Keeps track of when urls should be visited again.
+//!
+//! For every visited url we store the validators (`ETag` and `Last-Modified`) so the
+//! next visit can be a conditional request, and a short history of the visits that is
+//! used to estimate how often the page changes.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use url::Url;
+
+use super::{Domain, HashMap, Result, UrlString, WeightedUrl};
+
+const DEFAULT_MIN_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const DEFAULT_MAX_INTERVAL: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+
+/// Saved schedulers start with this magic followed by the version of the format.
+/// Files without it were written before the format was versioned.
+const MAGIC: &[u8] = b"stract-recrawl";
+const VERSION: u32 = 1;
+
+/// The cache validators of a response.
+#[derive(
+    Debug,
+    Clone,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    bincode::Encode,
+    bincode::Decode,
+)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// The validators of a new response for the same url. A `304 Not Modified` response
+    /// is allowed to leave out the validators, in which case the old ones are kept.
+    pub fn updated(&self, headers: &HeaderMap) -> Self {
+        let new = Self::from_headers(headers);
+
+        Self {
+            etag: new.etag.or_else(|| self.etag.clone()),
+            last_modified: new.last_modified.or_else(|| self.last_modified.clone()),
+        }
+    }
+
+    /// Make the request conditional on the page having changed since the validators were received.
+    pub fn apply(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+
+        request
+    }
+}
+
+/// The outcome of visiting a url.
+#[derive(Debug, Clone)]
+pub enum Visit {
+    /// The page was downloaded.
+    Fetched {
+        validators: Validators,
+        content_hash: [u8; 16],
+    },
+    /// The server responded with `304 Not Modified`.
+    NotModified { validators: Validators },
+    /// The page could not be downloaded. This does not tell us anything about whether it changed.
+    Failed,
+}
+
+impl Visit {
+    pub fn fetched(validators: Validators, body: impl AsRef<[u8]>) -> Self {
+        Self::Fetched {
+            validators,
+            content_hash: md5::compute(body).0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, bincode::Encode, bincode::Decode)]
+struct UrlState {
+    validators: Validators,
+    content_hash: Option<[u8; 16]>,
+    /// Last time the page was successfully visited (downloaded or not modified).
+    #[bincode(with_serde)]
+    last_visit: DateTime<Utc>,
+    /// Last time the page was downloaded.
+    #[bincode(with_serde)]
+    last_fetch: Option<DateTime<Utc>>,
+    #[bincode(with_serde)]
+    next_visit: DateTime<Utc>,
+    /// Number of intervals between successful visits.
+    num_intervals: u64,
+    /// Number of intervals where the page changed.
+    num_changes: u64,
+    /// Total length of the intervals in seconds.
+    total_interval_secs: f64,
+}
+
+/// The state of a url before the time of the last download was stored.
+#[derive(bincode::Encode, bincode::Decode)]
+struct UrlStateV0 {
+    validators: Validators,
+    content_hash: Option<[u8; 16]>,
+    #[bincode(with_serde)]
+    last_visit: DateTime<Utc>,
+    #[bincode(with_serde)]
+    next_visit: DateTime<Utc>,
+    num_intervals: u64,
+    num_changes: u64,
+    total_interval_secs: f64,
+}
+
+impl From<UrlStateV0> for UrlState {
+    fn from(state: UrlStateV0) -> Self {
+        Self {
+            validators: state.validators,
+            content_hash: state.content_hash,
+            last_visit: state.last_visit,
+            last_fetch: None,
+            next_visit: state.next_visit,
+            num_intervals: state.num_intervals,
+            num_changes: state.num_changes,
+            total_interval_secs: state.total_interval_secs,
+        }
+    }
+}
+
+impl UrlState {
+    fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            validators: Validators::default(),
+            content_hash: None,
+            last_visit: now,
+            last_fetch: None,
+            next_visit: now,
+            num_intervals: 0,
+            num_changes: 0,
+            total_interval_secs: 0.0,
+        }
+    }
+
+    /// Estimated number of changes per second.
+    ///
+    /// As we only visit the page at discrete points in time, we can at most detect one change
+    /// per interval. The estimator from Cho and Garcia-Molina, "Estimating frequency of change" (2003)
+    /// corrects for this.
+    fn change_rate(&self) -> Option<f64> {
+        if self.num_intervals == 0 || self.total_interval_secs <= 0.0 {
+            return None;
+        }
+
+        let n = self.num_intervals as f64;
+        let x = self.num_changes.min(self.num_intervals) as f64;
+        let avg_interval = self.total_interval_secs / n;
+
+        Some(-((n - x + 0.5) / (n + 0.5)).ln() / avg_interval)
+    }
+
+    fn interval(&self, min_interval: Duration, max_interval: Duration) -> Duration {
+        match self.change_rate() {
+            None => min_interval,
+            Some(rate) if rate <= 0.0 => max_interval,
+            Some(rate) => {
+                let secs = (1.0 / rate).min(max_interval.as_secs_f64());
+                Duration::from_secs_f64(secs).clamp(min_interval, max_interval)
+            }
+        }
+    }
+
+    fn record_success(&mut self, changed: bool, now: DateTime<Utc>) {
+        let interval = (now - self.last_visit).num_seconds();
+
+        if interval > 0 {
+            self.num_intervals += 1;
+            self.total_interval_secs += interval as f64;
+
+            if changed {
+                self.num_changes += 1;
+            }
+        }
+
+        self.last_visit = now;
+    }
+}
+
+/// Decides when urls should be visited again based on how often they have changed
+/// in the past.
+pub struct RecrawlScheduler {
+    path: PathBuf,
+    urls: Mutex<BTreeMap<UrlString, UrlState>>,
+    /// Held while saving, so an older snapshot never replaces a newer one.
+    saving: Mutex<()>,
+    min_interval: Duration,
+    max_interval: Duration,
+}
+
+impl RecrawlScheduler {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let urls = if path.exists() {
+            Self::decode(&std::fs::read(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            path,
+            urls: Mutex::new(urls),
+            saving: Mutex::new(()),
+            min_interval: DEFAULT_MIN_INTERVAL,
+            max_interval: DEFAULT_MAX_INTERVAL,
+        })
+    }
+
+    /// The bounds for the time between two visits of the same url.
+    pub fn with_intervals(mut self, min_interval: Duration, max_interval: Duration) -> Self {
+        self.min_interval = min_interval;
+        self.max_interval = max_interval.max(min_interval);
+        self
+    }
+
+    fn urls(&self) -> std::sync::MutexGuard<'_, BTreeMap<UrlString, UrlState>> {
+        self.urls.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn len(&self) -> usize {
+        self.urls().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urls().is_empty()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<BTreeMap<UrlString, UrlState>> {
+        let config = bincode::config::standard();
+
+        let Some(bytes) = bytes.strip_prefix(MAGIC) else {
+            let (urls, _): (BTreeMap<UrlString, UrlStateV0>, _) =
+                bincode::decode_from_slice(bytes, config)?;
+
+            return Ok(urls
+                .into_iter()
+                .map(|(url, state)| (url, state.into()))
+                .collect());
+        };
+
+        let (version, bytes) = bytes
+            .split_first_chunk::<4>()
+            .ok_or_else(|| anyhow::anyhow!("missing recrawl scheduler version"))?;
+
+        match u32::from_le_bytes(*version) {
+            VERSION => Ok(bincode::decode_from_slice(bytes, config)?.0),
+            version => Err(anyhow::anyhow!(
+                "unsupported recrawl scheduler version {version}"
+            )),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let _saving = self.saving.lock().unwrap_or_else(|e| e.into_inner());
+
+        // the urls are only locked while they are copied, so visits can be
+        // recorded while the snapshot is encoded and written
+        let urls = self.urls().clone();
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend(bincode::encode_to_vec(&urls, bincode::config::standard())?);
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // write to a temporary file first so a crash doesn't leave a corrupt file behind
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, bytes)?;
+        std::fs::rename(tmp, &self.path)?;
+
+        Ok(())
+    }
+
+    pub fn record(&self, url: &Url, visit: Visit, now: DateTime<Utc>) {
+        let mut urls = self.urls();
+        let state = urls
+            .entry(UrlString::from(url))
+            .or_insert_with(|| UrlState::new(now));
+
+        match visit {
+            Visit::Fetched {
+                validators,
+                content_hash,
+            } => {
+                // servers that ignore conditional requests always respond with the full page,
+                // so we compare the content to see if the page actually changed.
+                let changed = state.content_hash.is_some_and(|hash| hash != content_hash);
+
+                if state.content_hash.is_some() {
+                    state.record_success(changed, now);
+                }
+
+                state.validators = validators;
+                state.content_hash = Some(content_hash);
+                state.last_fetch = Some(now);
+            }
+            Visit::NotModified { validators } => {
+                state.record_success(false, now);
+                state.validators = validators;
+            }
+            Visit::Failed => {}
+        }
+
+        let interval = state.interval(self.min_interval, self.max_interval);
+        state.next_visit = now
+            + chrono::Duration::from_std(interval).unwrap_or_else(|_| chrono::Duration::days(1));
+    }
+
+    /// The validators to use for a conditional request to the url.
+    pub fn validators(&self, url: &Url) -> Option<Validators> {
+        self.urls()
+            .get(&UrlString::from(url))
+            .map(|state| state.validators.clone())
+            .filter(|validators| !validators.is_empty())
+    }
+
+    /// Whether the url should be visited. Urls we have never seen are always due.
+    pub fn is_due(&self, url: &Url, now: DateTime<Utc>) -> bool {
+        match self.urls().get(&UrlString::from(url)) {
+            Some(state) => state.next_visit <= now,
+            None => true,
+        }
+    }
+
+    /// The last time the url was downloaded, i.e. the time of the capture that a
+    /// `304 Not Modified` response refers to.
+    pub fn last_fetch(&self, url: &Url) -> Option<DateTime<Utc>> {
+        self.urls()
+            .get(&UrlString::from(url))
+            .and_then(|state| state.last_fetch)
+    }
+
+    pub fn next_visit(&self, url: &Url) -> Option<DateTime<Utc>> {
+        self.urls()
+            .get(&UrlString::from(url))
+            .map(|state| state.next_visit)
+    }
+
+    /// All the urls that are due for a visit, grouped by domain.
+    ///
+    /// The weight of a url is higher the longer it is overdue, relative to its
+    /// expected interval between changes.
+    pub fn due(&self, now: DateTime<Utc>) -> HashMap<Domain, Vec<WeightedUrl>> {
+        let mut res: HashMap<Domain, Vec<WeightedUrl>> = HashMap::default();
+
+        for (url, state) in self.urls().iter() {
+            if state.next_visit > now {
+                continue;
+            }
+
+            let Ok(url) = Url::try_from(url) else {
+                continue;
+            };
+
+            let interval = state
+                .interval(self.min_interval, self.max_interval)
+                .as_secs_f64()
+                .max(1.0);
+            let overdue = (now - state.next_visit).num_seconds().max(0) as f64;
+
+            res.entry(Domain::from(&url))
+                .or_default()
+                .push(WeightedUrl {
+                    url,
+                    weight: 1.0 + overdue / interval,
+                    validators: state.validators.clone(),
+                });
+        }
+
+        for urls in res.values_mut() {
+            urls.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen_temp_path;
+
+    fn scheduler() -> RecrawlScheduler {
+        RecrawlScheduler::open(gen_temp_path().join("recrawl.bin"))
+            .unwrap()
+            .with_intervals(
+                Duration::from_secs(60 * 60),
+                Duration::from_secs(30 * 24 * 60 * 60),
+            )
+    }
+
+    fn validators(etag: &str) -> Validators {
+        Validators {
+            etag: Some(etag.to_string()),
+            last_modified: None,
+        }
+    }
+
+    #[test]
+    fn conditional_request() {
+        let validators = Validators {
+            etag: Some("\"abc\"".to_string()),
+            last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+        };
+
+        let request = validators
+            .apply(reqwest::Client::new().get("https://example.com/"))
+            .build()
+            .unwrap();
+
+        assert_eq!(request.headers().get(IF_NONE_MATCH).unwrap(), "\"abc\"");
+        assert_eq!(
+            request.headers().get(IF_MODIFIED_SINCE).unwrap(),
+            "Wed, 21 Oct 2015 07:28:00 GMT"
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, "\"def\"".parse().unwrap());
+        let updated = validators.updated(&headers);
+
+        assert_eq!(updated.etag.as_deref(), Some("\"def\""));
+        assert_eq!(updated.last_modified, validators.last_modified);
+    }
+
+    #[test]
+    fn change_rate() {
+        let mut state = UrlState::new(Utc::now());
+        assert_eq!(state.change_rate(), None);
+
+        state.num_intervals = 10;
+        state.total_interval_secs = 10.0 * 3600.0;
+        assert_eq!(state.change_rate(), Some(0.0));
+
+        state.num_changes = 5;
+        let half = state.change_rate().unwrap();
+        state.num_changes = 10;
+        let all = state.change_rate().unwrap();
+
+        assert!(half > 0.0);
+        // a page that changed between every visit might change more than once per interval
+        assert!(all > 1.0 / 3600.0);
+        assert!(all > half);
+    }
+
+    #[test]
+    fn changing_pages_are_visited_more_often() {
+        let scheduler = scheduler();
+        let stable = Url::parse("https://example.com/about").unwrap();
+        let news = Url::parse("https://example.com/news").unwrap();
+
+        let mut now = Utc::now();
+
+        for i in 0..10 {
+            scheduler.record(
+                &stable,
+                Visit::NotModified {
+                    validators: validators("\"stable\""),
+                },
+                now,
+            );
+            scheduler.record(&news, Visit::fetched(validators(""), &format!("{i}")), now);
+
+            now += chrono::Duration::hours(6);
+        }
+
+        assert!(scheduler.next_visit(&news).unwrap() < scheduler.next_visit(&stable).unwrap());
+        assert_eq!(
+            scheduler.validators(&stable),
+            Some(validators("\"stable\""))
+        );
+
+        assert!(scheduler.is_due(&news, now));
+        assert!(!scheduler.is_due(&stable, now));
+        assert!(scheduler.is_due(&Url::parse("https://example.com/new").unwrap(), now));
+
+        let due = scheduler.due(now);
+        assert_eq!(due.len(), 1);
+        let urls = &due[&Domain::from(&news)];
+        assert_eq!(urls.len(), 1);
+        assert_eq!(urls[0].url, news);
+    }
+
+    #[test]
+    fn unchanged_content_is_not_a_change() {
+        let scheduler = scheduler();
+        let url = Url::parse("https://example.com/").unwrap();
+        let mut now = Utc::now();
+
+        for _ in 0..10 {
+            scheduler.record(&url, Visit::fetched(Validators::default(), "same"), now);
+            now += chrono::Duration::days(1);
+        }
+
+        assert_eq!(scheduler.validators(&url), None);
+        assert_eq!(
+            scheduler.next_visit(&url).unwrap(),
+            now - chrono::Duration::days(1) + chrono::Duration::days(30)
+        );
+    }
+
+    #[test]
+    fn save_and_open() {
+        let scheduler = scheduler();
+        let url = Url::parse("https://example.com/").unwrap();
+        scheduler.record(
+            &url,
+            Visit::fetched(validators("\"abc\""), "body"),
+            Utc::now(),
+        );
+        scheduler.save().unwrap();
+
+        let reopened = RecrawlScheduler::open(&scheduler.path).unwrap();
+        assert_eq!(reopened.len(), 1);
+        assert_eq!(reopened.validators(&url), Some(validators("\"abc\"")));
+        assert_eq!(reopened.last_fetch(&url), scheduler.last_fetch(&url));
+    }
+
+    #[test]
+    fn record_while_saving() {
+        let scheduler = scheduler();
+        let now = Utc::now();
+
+        std::thread::scope(|s| {
+            for host in 0..4 {
+                let scheduler = &scheduler;
+                s.spawn(move || {
+                    for page in 0..100 {
+                        let url = Url::parse(&format!("https://{host}.com/{page}")).unwrap();
+                        scheduler.record(&url, Visit::Failed, now);
+
+                        if page % 10 == 0 {
+                            scheduler.save().unwrap();
+                        }
+                    }
+                });
+            }
+        });
+
+        scheduler.save().unwrap();
+
+        let reopened = RecrawlScheduler::open(&scheduler.path).unwrap();
+        assert_eq!(reopened.len(), 400);
+    }
+
+    #[test]
+    fn open_unversioned() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let now = Utc::now();
+        let urls: BTreeMap<UrlString, UrlStateV0> = [(
+            UrlString::from(&url),
+            UrlStateV0 {
+                validators: validators("\"abc\""),
+                content_hash: None,
+                last_visit: now,
+                next_visit: now,
+                num_intervals: 0,
+                num_changes: 0,
+                total_interval_secs: 0.0,
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let path = gen_temp_path().join("recrawl.bin");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &path,
+            bincode::encode_to_vec(&urls, bincode::config::standard()).unwrap(),
+        )
+        .unwrap();
+
+        let scheduler = RecrawlScheduler::open(&path).unwrap();
+        assert_eq!(scheduler.validators(&url), Some(validators("\"abc\"")));
+        assert_eq!(scheduler.last_fetch(&url), None);
+    }
+
+    #[test]
+    fn last_fetch() {
+        let scheduler = scheduler();
+        let url = Url::parse("https://example.com/").unwrap();
+        let fetched = Utc::now();
+
+        scheduler.record(&url, Visit::fetched(validators("\"abc\""), "body"), fetched);
+        scheduler.record(
+            &url,
+            Visit::NotModified {
+                validators: validators("\"abc\""),
+            },
+            fetched + chrono::Duration::hours(2),
+        );
+
+        assert_eq!(scheduler.last_fetch(&url), Some(fetched));
+    }
+}
@@ -0,0 +1,264 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Redirects are followed by the crawler instead of the http client,
+//! so every hop of a redirect chain can be recorded.
+
+use url::Url;
+
+use super::{Error, Result};
+
+/// Whether the status code is a redirect we follow.
+pub fn is_redirect(status_code: u16) -> bool {
+    matches!(status_code, 301 | 302 | 303 | 307 | 308)
+}
+
+/// `301 Moved Permanently` and `308 Permanent Redirect` mean that the old url has been
+/// replaced by the new one. The other redirects are temporary.
+pub fn is_permanent(status_code: u16) -> bool {
+    matches!(status_code, 301 | 308)
+}
+
+/// A single hop in a redirect chain.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    bincode::Encode,
+    bincode::Decode,
+)]
+pub struct Redirect {
+    #[bincode(with_serde)]
+    pub from: Url,
+    #[bincode(with_serde)]
+    pub to: Url,
+    pub status_code: u16,
+}
+
+impl Redirect {
+    pub fn is_permanent(&self) -> bool {
+        is_permanent(self.status_code)
+    }
+}
+
+/// The redirects that were followed to get from the requested url to the final response.
+#[derive(
+    Debug,
+    Clone,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    bincode::Encode,
+    bincode::Decode,
+)]
+pub struct RedirectChain {
+    hops: Vec<Redirect>,
+}
+
+impl RedirectChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the next hop of the chain. Fails if the chain would be longer than `max_redirects`
+    /// or if the redirect leads back to a url that is already in the chain.
+    pub fn push(&mut self, redirect: Redirect, max_redirects: usize) -> Result<()> {
+        if self.hops.len() >= max_redirects {
+            return Err(Error::TooManyRedirects.into());
+        }
+
+        if redirect.to == redirect.from || self.urls().any(|url| *url == redirect.to) {
+            return Err(Error::RedirectLoop.into());
+        }
+
+        self.hops.push(redirect);
+
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hops.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.hops.len()
+    }
+
+    pub fn hops(&self) -> &[Redirect] {
+        &self.hops
+    }
+
+    /// All the urls in the chain, starting with the requested url.
+    pub fn urls(&self) -> impl Iterator<Item = &Url> {
+        self.hops
+            .first()
+            .map(|hop| &hop.from)
+            .into_iter()
+            .chain(self.hops.iter().map(|hop| &hop.to))
+    }
+
+    /// Whether every hop in the chain is permanent. Only then has the requested
+    /// url been replaced by the final url.
+    pub fn is_permanent(&self) -> bool {
+        !self.hops.is_empty() && self.hops.iter().all(Redirect::is_permanent)
+    }
+
+    /// The url the requested url has permanently moved to, i.e. the target of the
+    /// leading permanent hops of the chain.
+    pub fn permanent_target(&self) -> Option<&Url> {
+        self.hops
+            .iter()
+            .take_while(|hop| hop.is_permanent())
+            .last()
+            .map(|hop| &hop.to)
+    }
+}
+
+/// Send a get request and follow the redirects of the response. `prepare` is applied to the
+/// first request only, since e.g. the validators for a url don't apply to the redirect target.
+pub async fn get(
+    client: &reqwest::Client,
+    url: Url,
+    max_redirects: usize,
+    prepare: impl FnOnce(reqwest::RequestBuilder) -> reqwest::RequestBuilder,
+) -> Result<(reqwest::Response, RedirectChain)> {
+    let mut chain = RedirectChain::new();
+    let mut url = url;
+    let mut res = prepare(client.get(url.as_str())).send().await?;
+
+    while is_redirect(res.status().as_u16()) {
+        let location = res
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .ok_or(Error::InvalidRedirect)?;
+
+        let to = url.join(location).map_err(|_| Error::InvalidRedirect)?;
+
+        if !matches!(to.scheme(), "http" | "https") {
+            return Err(Error::InvalidRedirect.into());
+        }
+
+        chain.push(
+            Redirect {
+                from: url,
+                to: to.clone(),
+                status_code: res.status().as_u16(),
+            },
+            max_redirects,
+        )?;
+
+        url = to;
+        res = client.get(url.as_str()).send().await?;
+    }
+
+    Ok((res, chain))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redirect(from: &str, to: &str, status_code: u16) -> Redirect {
+        Redirect {
+            from: Url::parse(from).unwrap(),
+            to: Url::parse(to).unwrap(),
+            status_code,
+        }
+    }
+
+    #[test]
+    fn permanence() {
+        let mut chain = RedirectChain::new();
+        assert!(!chain.is_permanent());
+        assert_eq!(chain.permanent_target(), None);
+
+        chain
+            .push(redirect("http://a.com/", "https://a.com/", 301), 10)
+            .unwrap();
+        chain
+            .push(redirect("https://a.com/", "https://www.a.com/", 308), 10)
+            .unwrap();
+
+        assert!(chain.is_permanent());
+        assert_eq!(
+            chain.permanent_target(),
+            Some(&Url::parse("https://www.a.com/").unwrap())
+        );
+
+        chain
+            .push(
+                redirect("https://www.a.com/", "https://www.a.com/login", 307),
+                10,
+            )
+            .unwrap();
+
+        assert!(!chain.is_permanent());
+        assert_eq!(
+            chain.permanent_target(),
+            Some(&Url::parse("https://www.a.com/").unwrap())
+        );
+        assert_eq!(chain.urls().count(), 4);
+    }
+
+    #[test]
+    fn loops() {
+        let mut chain = RedirectChain::new();
+
+        assert!(chain
+            .push(redirect("https://a.com/", "https://a.com/", 302), 10)
+            .is_err());
+
+        chain
+            .push(redirect("https://a.com/", "https://a.com/b", 302), 10)
+            .unwrap();
+        chain
+            .push(redirect("https://a.com/b", "https://a.com/c", 303), 10)
+            .unwrap();
+
+        assert!(chain
+            .push(redirect("https://a.com/c", "https://a.com/", 302), 10)
+            .is_err());
+        assert_eq!(chain.len(), 2);
+    }
+
+    #[test]
+    fn too_many_redirects() {
+        let mut chain = RedirectChain::new();
+
+        for i in 0..3 {
+            chain
+                .push(
+                    redirect(
+                        &format!("https://a.com/{i}"),
+                        &format!("https://a.com/{}", i + 1),
+                        301,
+                    ),
+                    3,
+                )
+                .unwrap();
+        }
+
+        assert!(chain
+            .push(redirect("https://a.com/3", "https://a.com/4", 301), 3)
+            .is_err());
+    }
+}
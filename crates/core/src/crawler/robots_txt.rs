@@ -22,7 +22,10 @@ use robotstxt_with_cache::matcher::{
 
 use url::Url;
 
-use super::{Error, Result, Site};
+use super::{redirect, Error, Result, Site};
+
+/// RFC 9309 asks crawlers to follow at least five redirects when fetching robots.txt.
+const MAX_REDIRECTS: usize = 5;
 
 enum Lookup<T> {
     Found(T),
@@ -55,21 +58,21 @@ impl RobotsTxtManager {
         }
     }
 
+    async fn fetch(&self, url: &str) -> Result<reqwest::Response> {
+        let url = Url::parse(url)?;
+
+        redirect::get(&self.client, url, MAX_REDIRECTS, |req| {
+            req.timeout(Duration::from_secs(60))
+        })
+        .await
+        .map(|(res, _)| res)
+    }
+
     async fn fetch_robots_txt(&self, site: &Site) -> Result<RobotsTxt> {
-        let mut res = self
-            .client
-            .get(&format!("http://{}/robots.txt", site.0))
-            .timeout(Duration::from_secs(60))
-            .send()
-            .await;
+        let mut res = self.fetch(&format!("http://{}/robots.txt", site.0)).await;
 
         if res.is_err() {
-            res = self
-                .client
-                .get(&format!("https://{}/robots.txt", site.0))
-                .timeout(Duration::from_secs(60))
-                .send()
-                .await;
+            res = self.fetch(&format!("https://{}/robots.txt", site.0)).await;
         }
 
         let res = res?;
@@ -140,25 +143,128 @@ impl RobotsTxtManager {
         self.cache.get_mut(&site).unwrap()
     }
 
-    pub async fn sitemap(&mut self, url: &Url) -> Option<Url> {
+    pub async fn sitemaps(&mut self, url: &Url) -> Vec<Url> {
         match self.get_mut(url).await {
-            Lookup::Found(robotstxt) => robotstxt.sitemap.clone(),
+            Lookup::Found(robotstxt) => robotstxt.sitemaps.clone(),
+            Lookup::NotFound => Vec::new(),
+        }
+    }
+
+    /// The delay between requests that the site asks `user_agent` to respect, based on
+    /// the `Crawl-delay` and `Request-rate` directives.
+    pub async fn crawl_delay(&mut self, url: &Url, user_agent: &str) -> Option<Duration> {
+        match self.get_mut(url).await {
+            Lookup::Found(robotstxt) => robotstxt.crawl_delay(user_agent),
             Lookup::NotFound => None,
         }
     }
 }
 
+/// The politeness directives of a group of `User-agent` lines.
+#[derive(Debug, Default)]
+struct Group {
+    agents: Vec<String>,
+    crawl_delay: Option<Duration>,
+}
+
+impl Group {
+    fn set_delay(&mut self, delay: Duration) {
+        self.crawl_delay = Some(self.crawl_delay.map_or(delay, |d| d.max(delay)));
+    }
+}
+
+/// Parse a `Crawl-delay` value in seconds.
+fn parse_crawl_delay(value: &str) -> Option<Duration> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+}
+
+/// Parse a `Request-rate` value like `1/10` (1 request per 10 seconds) or `10/1m` into the
+/// delay between requests. The period can have a `s`, `m` or `h` suffix and defaults to seconds.
+fn parse_request_rate(value: &str) -> Option<Duration> {
+    let (requests, period) = value.split_once('/')?;
+    let requests = requests.trim().parse::<f64>().ok()?;
+
+    // some sites add the allowed time of day after the rate, e.g. `1/5 0900-1700`
+    let period = period.split_whitespace().next()?;
+    let (period, unit_secs) = match period.chars().last()? {
+        's' | 'S' => (&period[..period.len() - 1], 1.0),
+        'm' | 'M' => (&period[..period.len() - 1], 60.0),
+        'h' | 'H' => (&period[..period.len() - 1], 60.0 * 60.0),
+        _ => (period, 1.0),
+    };
+    let period = period.parse::<f64>().ok()? * unit_secs;
+
+    if requests <= 0.0 {
+        return None;
+    }
+
+    // values that don't fit in a duration (e.g. `1/1e30`) are ignored
+    Duration::try_from_secs_f64(period / requests).ok()
+}
+
+fn parse_groups(body: &str) -> Vec<Group> {
+    let mut groups: Vec<Group> = Vec::new();
+    let mut in_rules = false;
+
+    for line in body.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+
+        let Some((directive, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        let value = value.trim();
+
+        match directive.trim().to_ascii_lowercase().as_str() {
+            "user-agent" => {
+                // consecutive user-agent lines belong to the same group
+                if in_rules || groups.is_empty() {
+                    groups.push(Group::default());
+                    in_rules = false;
+                }
+
+                if let Some(group) = groups.last_mut() {
+                    group.agents.push(value.to_ascii_lowercase());
+                }
+            }
+            "crawl-delay" => {
+                in_rules = true;
+
+                if let (Some(group), Some(delay)) = (groups.last_mut(), parse_crawl_delay(value)) {
+                    group.set_delay(delay);
+                }
+            }
+            "request-rate" => {
+                in_rules = true;
+
+                if let (Some(group), Some(delay)) = (groups.last_mut(), parse_request_rate(value)) {
+                    group.set_delay(delay);
+                }
+            }
+            "sitemap" => {}
+            _ => in_rules = true,
+        }
+    }
+
+    groups
+}
+
 struct RobotsTxt {
     download_time: std::time::Instant,
     matcher: CachingRobotsMatcher<LongestMatchRobotsMatchStrategy>,
-    sitemap: Option<Url>,
+    sitemaps: Vec<Url>,
+    groups: Vec<Group>,
 }
 
 impl RobotsTxt {
     fn new(body: String) -> Self {
         let mut s = Self {
             matcher: CachingRobotsMatcher::new(RobotsMatcher::default()),
-            sitemap: None,
+            sitemaps: Vec::new(),
+            groups: Vec::new(),
             download_time: std::time::Instant::now(),
         };
 
@@ -174,15 +280,42 @@ impl RobotsTxt {
     fn update(&mut self, body: String) {
         self.matcher.parse(&body.to_lowercase());
 
-        self.sitemap = body
-            .to_ascii_lowercase()
+        // the directive is case-insensitive, but the url is not
+        self.sitemaps = body
             .lines()
-            .find(|line| line.starts_with("sitemap:"))
-            .map(|line| line.split(':').nth(1).unwrap().trim())
-            .and_then(|s| Url::parse(s).ok());
+            .filter_map(|line| line.trim().split_once(':'))
+            .filter(|(directive, _)| directive.trim().eq_ignore_ascii_case("sitemap"))
+            .filter_map(|(_, url)| Url::parse(url.trim()).ok())
+            .collect();
+
+        self.groups = parse_groups(&body);
 
         self.download_time = std::time::Instant::now();
     }
+
+    /// The crawl delay of the group for `user_agent`, or the `*` group if there is
+    /// no group specifically for the user agent.
+    fn crawl_delay(&self, user_agent: &str) -> Option<Duration> {
+        let user_agent = user_agent.to_ascii_lowercase();
+
+        let delay = |agent: &str| {
+            self.groups
+                .iter()
+                .filter(|group| group.agents.iter().any(|a| a == agent))
+                .filter_map(|group| group.crawl_delay)
+                .max()
+        };
+
+        if self
+            .groups
+            .iter()
+            .any(|group| group.agents.contains(&user_agent))
+        {
+            delay(&user_agent)
+        } else {
+            delay("*")
+        }
+    }
 }
 
 #[cfg(test)]
@@ -222,4 +355,90 @@ mod tests {
             .matcher
             .one_agent_allowed_by_robots(ua_token, "http://example.com/example"));
     }
+
+    #[test]
+    fn sitemaps() {
+        let robots_txt = RobotsTxt::new(
+            r#"User-agent: *
+            Disallow: /private
+            Sitemap: https://example.com/Sitemap.xml
+            sitemap:https://example.com/news/sitemap.xml.gz
+            SITEMAP: not a url
+            # Sitemap: https://example.com/commented.xml"#
+                .to_string(),
+        );
+
+        assert_eq!(
+            robots_txt.sitemaps,
+            vec![
+                Url::parse("https://example.com/Sitemap.xml").unwrap(),
+                Url::parse("https://example.com/news/sitemap.xml.gz").unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn crawl_delay() {
+        let robots_txt = RobotsTxt::new(
+            r#"User-agent: *
+            Crawl-delay: 2
+
+            User-agent: OtherBot
+            User-agent: StractBot
+            Disallow: /private
+            Crawl-delay: 10 # seconds
+
+            User-agent: SlowBot
+            Request-rate: 1/1m
+            Crawl-delay: 5"#
+                .to_string(),
+        );
+
+        assert_eq!(
+            robots_txt.crawl_delay("StractBot"),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            robots_txt.crawl_delay("otherbot"),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            robots_txt.crawl_delay("SlowBot"),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            robots_txt.crawl_delay("UnknownBot"),
+            Some(Duration::from_secs(2))
+        );
+
+        let robots_txt = RobotsTxt::new(
+            r#"User-agent: StractBot
+            Disallow: /private"#
+                .to_string(),
+        );
+        assert_eq!(robots_txt.crawl_delay("StractBot"), None);
+    }
+
+    #[test]
+    fn request_rate() {
+        assert_eq!(parse_request_rate("1/10"), Some(Duration::from_secs(10)));
+        assert_eq!(parse_request_rate("1/10s"), Some(Duration::from_secs(10)));
+        assert_eq!(parse_request_rate("6/1m"), Some(Duration::from_secs(10)));
+        assert_eq!(parse_request_rate("1/1h"), Some(Duration::from_secs(3600)));
+        assert_eq!(
+            parse_request_rate("1/5 0900-1700"),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(parse_request_rate("0/10"), None);
+        assert_eq!(parse_request_rate("fast"), None);
+
+        assert_eq!(parse_crawl_delay("0.5"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_crawl_delay("-1"), None);
+        assert_eq!(parse_crawl_delay("1e30"), None);
+        assert_eq!(parse_crawl_delay("inf"), None);
+        assert_eq!(parse_crawl_delay("NaN"), None);
+        assert_eq!(parse_request_rate("1/1e30"), None);
+        assert_eq!(parse_request_rate("1e-30/1"), None);
+        assert_eq!(parse_request_rate("1/-1"), None);
+    }
 }
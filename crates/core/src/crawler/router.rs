@@ -1,6 +1,6 @@
 use anyhow::Result;
-use rand::Rng;
-use std::{net::SocketAddr, time::Duration};
+use rand::seq::SliceRandom;
+use std::{collections::HashSet, net::SocketAddr, time::Duration};
 use tokio::sync::Mutex;
 
 use crate::{
@@ -8,7 +8,7 @@ use crate::{
     entrypoint::crawler::coordinator::{CoordinatorService, GetJob},
 };
 
-use super::Job;
+use super::{DiscoveredUrls, Domain, DomainCrawled, HashMap, JobResponse};
 
 struct RemoteCoordinator {
     addr: SocketAddr,
@@ -26,7 +26,7 @@ impl RemoteCoordinator {
         .await?)
     }
 
-    async fn sample_job(&self) -> Result<Option<Job>> {
+    async fn sample_job(&self) -> Result<JobResponse> {
         let mut conn = self.conn().await?;
 
         let response = conn
@@ -35,50 +35,118 @@ impl RemoteCoordinator {
 
         Ok(response)
     }
+
+    async fn add_discovered(&self, discovered: DiscoveredUrls) -> Result<()> {
+        let mut conn = self.conn().await?;
+        conn.send_with_timeout(discovered, Duration::from_secs(90))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn domain_crawled(&self, crawled: DomainCrawled) -> Result<()> {
+        let mut conn = self.conn().await?;
+        conn.send_with_timeout(crawled, Duration::from_secs(90))
+            .await?;
+
+        Ok(())
+    }
 }
 
-struct InnerRouter {
+pub struct Router {
     coordinators: Vec<RemoteCoordinator>,
+    /// Coordinators that have handed out all their jobs.
+    done: Mutex<HashSet<usize>>,
 }
 
-impl InnerRouter {
-    async fn new(coordinator_addrs: Vec<SocketAddr>) -> Result<Self> {
+impl Router {
+    pub async fn new(coordinator_addrs: Vec<SocketAddr>) -> Result<Self> {
         Ok(Self {
             coordinators: coordinator_addrs
                 .into_iter()
                 .map(|addr| RemoteCoordinator { addr })
                 .collect(),
+            done: Mutex::new(HashSet::new()),
         })
     }
 
-    async fn sample_job(&mut self) -> Result<Option<Job>> {
-        while !self.coordinators.is_empty() {
-            let idx = rand::thread_rng().gen_range(0..self.coordinators.len());
-            let res = self.coordinators[idx].sample_job().await?;
-
-            if res.is_some() {
-                return Ok(res);
+    pub async fn sample_job(&self) -> Result<JobResponse> {
+        let mut candidates: Vec<usize> = {
+            let done = self.done.lock().await;
+            (0..self.coordinators.len())
+                .filter(|idx| !done.contains(idx))
+                .collect()
+        };
+        candidates.shuffle(&mut rand::thread_rng());
+
+        let mut should_wait = false;
+
+        for idx in candidates {
+            match self.coordinators[idx].sample_job().await {
+                Ok(JobResponse::Job(job)) => return Ok(JobResponse::Job(job)),
+                Ok(JobResponse::Wait) => should_wait = true,
+                Ok(JobResponse::Done) => {
+                    self.done.lock().await.insert(idx);
+                }
+                // an unavailable coordinator should not keep the workers from the others
+                Err(err) => {
+                    tracing::error!(
+                        "failed to sample job from {}: {:?}",
+                        self.coordinators[idx].addr,
+                        err
+                    );
+                    should_wait = true;
+                }
             }
+        }
 
-            self.coordinators.remove(idx);
+        if should_wait {
+            Ok(JobResponse::Wait)
+        } else {
+            Ok(JobResponse::Done)
+        }
+    }
+
+    /// Each domain belongs to a single coordinator, so only one job
+    /// for the domain is handed out at a time.
+    fn owner(&self, domain: &Domain) -> Option<&RemoteCoordinator> {
+        if self.coordinators.is_empty() {
+            return None;
         }
 
-        Ok(None)
+        Some(&self.coordinators[domain.owner(self.coordinators.len())])
     }
-}
 
-pub struct Router {
-    inner: Mutex<InnerRouter>,
-}
+    pub async fn add_discovered(&self, discovered: DiscoveredUrls) -> Result<()> {
+        let mut by_coordinator: HashMap<SocketAddr, DiscoveredUrls> = HashMap::default();
+
+        for (domain, urls) in discovered.urls {
+            let Some(owner) = self.owner(&domain) else {
+                break;
+            };
+
+            by_coordinator
+                .entry(owner.addr)
+                .or_insert_with(|| DiscoveredUrls {
+                    urls: HashMap::default(),
+                })
+                .urls
+                .insert(domain, urls);
+        }
 
-impl Router {
-    pub async fn new(coordinator_addrs: Vec<SocketAddr>) -> Result<Self> {
-        Ok(Self {
-            inner: Mutex::new(InnerRouter::new(coordinator_addrs).await?),
-        })
+        for (addr, discovered) in by_coordinator {
+            RemoteCoordinator { addr }
+                .add_discovered(discovered)
+                .await?;
+        }
+
+        Ok(())
     }
 
-    pub async fn sample_job(&self) -> Result<Option<Job>> {
-        self.inner.lock().await.sample_job().await
+    pub async fn domain_crawled(&self, crawled: DomainCrawled) -> Result<()> {
+        match self.owner(&crawled.domain) {
+            Some(owner) => owner.domain_crawled(crawled).await,
+            None => Ok(()),
+        }
     }
 }
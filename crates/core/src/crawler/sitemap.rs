@@ -0,0 +1,379 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Parsing of sitemaps as described in the [sitemap protocol](https://www.sitemaps.org/protocol.html).
+
+use std::io::Read;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use quick_xml::events::Event;
+use url::Url;
+
+/// Priority of urls that doesn't specify one. This is the default from the protocol.
+const DEFAULT_PRIORITY: f64 = 0.5;
+
+/// Freshness of urls without a `<lastmod>`.
+const DEFAULT_FRESHNESS: f64 = 0.5;
+
+/// Number of days before the freshness of a url is halved.
+const FRESHNESS_HALF_LIFE_DAYS: f64 = 30.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapUrl {
+    pub url: Url,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub priority: Option<f64>,
+}
+
+impl SitemapUrl {
+    /// The weight of the url when prioritising which urls to crawl. Urls with a high priority
+    /// that were recently modified get the highest weight.
+    pub fn weight(&self, now: DateTime<Utc>) -> f64 {
+        let priority = self.priority.unwrap_or(DEFAULT_PRIORITY).clamp(0.0, 1.0);
+
+        let freshness = match self.last_modified {
+            Some(last_modified) => {
+                let age_days = (now - last_modified).num_hours().max(0) as f64 / 24.0;
+                1.0 / (1.0 + age_days / FRESHNESS_HALF_LIFE_DAYS)
+            }
+            None => DEFAULT_FRESHNESS,
+        };
+
+        priority * freshness
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SitemapEntry {
+    Url(SitemapUrl),
+    /// A sitemap referenced from a `<sitemapindex>`.
+    Sitemap(Url),
+}
+
+/// Decode the body of a sitemap. Sitemaps are allowed to be gzipped, in which case
+/// at most `max_len` bytes are decompressed.
+pub fn decode(body: &[u8], max_len: usize) -> Option<String> {
+    const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+    if body.starts_with(&GZIP_MAGIC) {
+        let mut decoded = Vec::new();
+
+        flate2::read::MultiGzDecoder::new(body)
+            .take(max_len as u64)
+            .read_to_end(&mut decoded)
+            .ok()?;
+
+        Some(String::from_utf8_lossy(&decoded).into_owned())
+    } else {
+        Some(String::from_utf8_lossy(body).into_owned())
+    }
+}
+
+/// Parse a `W3C Datetime` as used by `<lastmod>`. Both full timestamps and plain dates are supported.
+fn parse_lastmod(s: &str) -> Option<DateTime<Utc>> {
+    let s = s.trim();
+
+    DateTime::parse_from_rfc3339(s)
+        .or_else(|_| DateTime::parse_from_str(s, "%Y-%m-%dT%H:%M%:z"))
+        .map(|date| date.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)
+                .map(|date| date.and_utc())
+        })
+}
+
+#[derive(Default)]
+struct PartialEntry {
+    loc: Option<String>,
+    lastmod: Option<String>,
+    priority: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Loc,
+    Lastmod,
+    Priority,
+}
+
+pub fn parse(s: &str) -> Vec<SitemapEntry> {
+    let mut reader = quick_xml::Reader::from_str(s);
+
+    let mut res = vec![];
+
+    let mut in_sitemap = false;
+    let mut in_url = false;
+    let mut field = None;
+    let mut entry = PartialEntry::default();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) => match e.local_name().as_ref() {
+                b"sitemap" => {
+                    in_sitemap = true;
+                    entry = PartialEntry::default();
+                }
+                b"url" => {
+                    in_url = true;
+                    entry = PartialEntry::default();
+                }
+                b"loc" => field = Some(Field::Loc),
+                b"lastmod" => field = Some(Field::Lastmod),
+                b"priority" => field = Some(Field::Priority),
+                _ => {}
+            },
+            Ok(Event::End(ref e)) => match e.local_name().as_ref() {
+                b"sitemap" if in_sitemap => {
+                    in_sitemap = false;
+
+                    if let Some(url) = entry.loc.take().and_then(|loc| Url::parse(&loc).ok()) {
+                        res.push(SitemapEntry::Sitemap(url));
+                    }
+                }
+                b"url" if in_url => {
+                    in_url = false;
+
+                    if let Some(url) = entry.loc.take().and_then(|loc| Url::parse(&loc).ok()) {
+                        res.push(SitemapEntry::Url(SitemapUrl {
+                            url,
+                            last_modified: entry.lastmod.take().and_then(|s| parse_lastmod(&s)),
+                            priority: entry
+                                .priority
+                                .take()
+                                .and_then(|s| s.trim().parse::<f64>().ok())
+                                .filter(|p| p.is_finite()),
+                        }));
+                    }
+                }
+                b"loc" | b"lastmod" | b"priority" => field = None,
+                _ => {}
+            },
+            Ok(Event::Text(e)) => {
+                if !in_sitemap && !in_url {
+                    continue;
+                }
+
+                let Ok(text) = e.unescape() else {
+                    continue;
+                };
+
+                match field {
+                    Some(Field::Loc) => entry.loc = Some(text.to_string()),
+                    Some(Field::Lastmod) => entry.lastmod = Some(text.to_string()),
+                    Some(Field::Priority) => entry.priority = Some(text.to_string()),
+                    None => {}
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => {
+                tracing::debug!("failed to parse sitemap: {}", e);
+                break;
+            }
+            _ => (),
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use chrono::{TimeZone, Timelike};
+
+    use super::*;
+
+    fn url(url: &str) -> SitemapEntry {
+        SitemapEntry::Url(SitemapUrl {
+            url: url.parse().unwrap(),
+            last_modified: Some(Utc.with_ymd_and_hms(2023, 10, 18, 5, 40, 4).unwrap()),
+            priority: None,
+        })
+    }
+
+    #[test]
+    fn sitemap_index() {
+        let dr = r#"<sitemapindex>
+        <sitemap>
+        <loc>https://www.dr.dk/drtv/sitemap.xml</loc>
+        </sitemap>
+        <sitemap>
+        <loc>https://www.dr.dk/sitemap.tvguide.xml</loc>
+        <lastmod>2023-10-18</lastmod>
+        </sitemap>
+        <sitemap>
+        <loc>
+        https://www.dr.dk/sitemap.kommunalvalg.resultater.xml
+        </loc>
+        </sitemap>
+        <sitemap>
+        <loc>https://www.dr.dk/sitemap.folketingsvalg2022.xml</loc>
+        </sitemap>
+        </sitemapindex>"#;
+
+        let entries = parse(dr);
+        assert_eq!(
+            entries,
+            vec![
+                SitemapEntry::Sitemap("https://www.dr.dk/drtv/sitemap.xml".parse().unwrap()),
+                SitemapEntry::Sitemap("https://www.dr.dk/sitemap.tvguide.xml".parse().unwrap()),
+                SitemapEntry::Sitemap(
+                    "https://www.dr.dk/sitemap.kommunalvalg.resultater.xml"
+                        .parse()
+                        .unwrap()
+                ),
+                SitemapEntry::Sitemap(
+                    "https://www.dr.dk/sitemap.folketingsvalg2022.xml"
+                        .parse()
+                        .unwrap()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn urlset() {
+        let dr = r#"<urlset>
+        <url>
+        <lastmod>2023-10-18T05:40:04.7435930+00:00</lastmod>
+        <loc>https://www.dr.dk/drtv/serie/sleepover_6382</loc>
+        </url>
+        <url>
+        <lastmod>2023-10-18T05:40:04.7435930+00:00</lastmod>
+        <loc>https://www.dr.dk/drtv/saeson/sleepover_9673</loc>
+        </url>
+        <url>
+        <lastmod>2023-10-18T05:40:04.7435930+00:00</lastmod>
+        <loc>
+        https://www.dr.dk/drtv/episode/sleepover_-zoologisk-museum_52239
+        </loc>
+        </url>
+        <url>
+        <lastmod>2023-10-18T05:40:04.7435930+00:00</lastmod>
+        <loc>
+        https://www.dr.dk/drtv/episode/sleepover_-koebenhavns-raadhus_52252
+        </loc>
+        </url>
+        </urlset>"#;
+
+        let entries: Vec<_> = parse(dr)
+            .into_iter()
+            .map(|entry| match entry {
+                // ignore the fractional seconds
+                SitemapEntry::Url(mut url) => {
+                    url.last_modified = url
+                        .last_modified
+                        .map(|date| date.with_nanosecond(0).unwrap());
+                    SitemapEntry::Url(url)
+                }
+                entry => entry,
+            })
+            .collect();
+
+        assert_eq!(
+            entries,
+            vec![
+                url("https://www.dr.dk/drtv/serie/sleepover_6382"),
+                url("https://www.dr.dk/drtv/saeson/sleepover_9673"),
+                url("https://www.dr.dk/drtv/episode/sleepover_-zoologisk-museum_52239"),
+                url("https://www.dr.dk/drtv/episode/sleepover_-koebenhavns-raadhus_52252"),
+            ]
+        );
+    }
+
+    #[test]
+    fn lastmod_and_priority() {
+        let sitemap = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+            <url>
+                <loc>https://example.com/a</loc>
+                <lastmod>2024-01-02</lastmod>
+                <priority>0.8</priority>
+            </url>
+            <url>
+                <loc>https://example.com/b</loc>
+                <lastmod>2024-01-02T10:30+01:00</lastmod>
+                <priority>invalid</priority>
+            </url>
+            <url>
+                <loc>https://example.com/c</loc>
+            </url>
+        </urlset>"#;
+
+        let entries = parse(sitemap);
+
+        assert_eq!(
+            entries,
+            vec![
+                SitemapEntry::Url(SitemapUrl {
+                    url: "https://example.com/a".parse().unwrap(),
+                    last_modified: Some(Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap()),
+                    priority: Some(0.8),
+                }),
+                SitemapEntry::Url(SitemapUrl {
+                    url: "https://example.com/b".parse().unwrap(),
+                    last_modified: Some(Utc.with_ymd_and_hms(2024, 1, 2, 9, 30, 0).unwrap()),
+                    priority: None,
+                }),
+                SitemapEntry::Url(SitemapUrl {
+                    url: "https://example.com/c".parse().unwrap(),
+                    last_modified: None,
+                    priority: None,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn weights() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap();
+        let url = |last_modified, priority| SitemapUrl {
+            url: "https://example.com".parse().unwrap(),
+            last_modified,
+            priority,
+        };
+
+        let fresh = url(Some(now), Some(0.5));
+        let old = url(
+            Some(Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap()),
+            Some(0.5),
+        );
+        let important = url(Some(now), Some(1.0));
+        let unknown = url(None, None);
+
+        assert!(important.weight(now) > fresh.weight(now));
+        assert!(fresh.weight(now) > unknown.weight(now));
+        assert!(unknown.weight(now) > old.weight(now));
+        assert_eq!(fresh.weight(now), 0.5);
+    }
+
+    #[test]
+    fn gzipped() {
+        let sitemap = "<urlset><url><loc>https://example.com/a</loc></url></urlset>";
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(sitemap.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        assert_eq!(decode(&gzipped, 1024).unwrap(), sitemap);
+        assert_eq!(decode(sitemap.as_bytes(), 1024).unwrap(), sitemap);
+        assert_eq!(decode(&gzipped, 10).unwrap(), &sitemap[..10]);
+    }
+}
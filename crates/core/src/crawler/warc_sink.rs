@@ -0,0 +1,144 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Destinations for the WARC files finished by the [`WarcWriter`](super::WarcWriter).
+
+use std::{future::Future, path::PathBuf, sync::Arc, time::Duration};
+
+use crate::config::{LocalSinkConfig, S3Config};
+
+use super::Result;
+
+/// Stores finished WARC files.
+pub trait WarcSink: Send + Sync + 'static {
+    /// Store the gzipped WARC file `data` under `name`.
+    fn put(&self, name: &str, data: Vec<u8>) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Uploads the WARC files to a folder in an S3 bucket.
+pub struct S3Sink {
+    config: S3Config,
+}
+
+impl S3Sink {
+    pub fn new(config: S3Config) -> Self {
+        Self { config }
+    }
+}
+
+impl WarcSink for S3Sink {
+    async fn put(&self, name: &str, data: Vec<u8>) -> Result<()> {
+        let bucket = s3::Bucket::new(
+            &self.config.bucket,
+            s3::Region::Custom {
+                region: "".to_string(),
+                endpoint: self.config.endpoint.clone(),
+            },
+            s3::creds::Credentials {
+                access_key: Some(self.config.access_key.clone()),
+                secret_key: Some(self.config.secret_key.clone()),
+                security_token: None,
+                session_token: None,
+                expiration: None,
+            },
+        )?
+        .with_path_style()
+        .with_request_timeout(Duration::from_secs(30 * 60));
+
+        bucket
+            .put_object_with_content_type(
+                &format!("{}/{}", &self.config.folder, name),
+                &data,
+                "application/warc",
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Writes the WARC files to a local folder.
+pub struct LocalSink {
+    folder: PathBuf,
+}
+
+impl LocalSink {
+    pub fn new(config: LocalSinkConfig) -> Self {
+        Self {
+            folder: PathBuf::from(config.folder),
+        }
+    }
+}
+
+impl WarcSink for LocalSink {
+    async fn put(&self, name: &str, data: Vec<u8>) -> Result<()> {
+        tokio::fs::create_dir_all(&self.folder).await?;
+
+        // the file is renamed when it is complete, so readers never see a partial file
+        let tmp = self.folder.join(format!(".{name}.tmp"));
+        tokio::fs::write(&tmp, data).await?;
+        tokio::fs::rename(&tmp, self.folder.join(name)).await?;
+
+        Ok(())
+    }
+}
+
+/// Hands the WARC files to a callback in the same process, e.g. to inspect
+/// the results of a crawl in tests.
+#[derive(Clone)]
+pub struct CallbackSink {
+    callback: Arc<dyn Fn(String, Vec<u8>) + Send + Sync>,
+}
+
+impl CallbackSink {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(String, Vec<u8>) + Send + Sync + 'static,
+    {
+        Self {
+            callback: Arc::new(callback),
+        }
+    }
+}
+
+impl WarcSink for CallbackSink {
+    async fn put(&self, name: &str, data: Vec<u8>) -> Result<()> {
+        (self.callback)(name.to_string(), data);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn local_sink() {
+        let folder = crate::gen_temp_path();
+        let sink = LocalSink::new(LocalSinkConfig {
+            folder: folder.to_str().unwrap().to_string(),
+        });
+
+        sink.put("test.warc.gz", vec![1, 2, 3]).await.unwrap();
+
+        assert_eq!(
+            std::fs::read(folder.join("test.warc.gz")).unwrap(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(std::fs::read_dir(&folder).unwrap().count(), 1);
+    }
+}
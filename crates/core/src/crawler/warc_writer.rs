@@ -16,15 +16,20 @@
 
 use std::time::Duration;
 
+use tokio::time::Instant;
+
 use crate::{
-    config::{self, S3Config},
+    config::{CrawlerConfig, WarcSinkConfig},
     warc,
 };
 
-use super::{CrawlDatum, DatumStream, Result};
+use super::{
+    warc_sink::{LocalSink, S3Sink, WarcSink},
+    CrawlDatum, DatumStream, Redirect, Result,
+};
 
 /// The WarcWriter is responsible for storing the crawl datums
-/// as WARC files on S3.
+/// as WARC files in a [`WarcSink`].
 pub struct WarcWriter {
     tx: tokio::sync::mpsc::Sender<WarcWriterMessage>,
 }
@@ -50,7 +55,48 @@ pub enum WarcWriterMessage {
     Finish,
 }
 
-async fn commit(writer: warc::DeduplicatedWarcWriter, s3: config::S3Config) {
+/// Text payloads of crawl datums have been decoded to utf-8 when they were fetched,
+/// so the stored headers must describe the decoded payload.
+fn text_payload_headers(headers: &warc::HttpHeaders, payload_len: usize) -> warc::HttpHeaders {
+    let mut res = warc::HttpHeaders::new();
+
+    for (name, value) in headers.for_decoded_payload(payload_len).iter() {
+        if name.eq_ignore_ascii_case("content-type") {
+            let mime = value.split(';').next().unwrap_or_default().trim();
+            res.push(name, format!("{mime}; charset=utf-8"));
+        } else {
+            res.push(name, value);
+        }
+    }
+
+    res
+}
+
+fn redirect_record(hop: &Redirect, datum: &CrawlDatum) -> warc::WarcRecord {
+    let mut headers = warc::HttpHeaders::new();
+    headers.push("Location", hop.to.as_str());
+    headers.push("Content-Length", "0");
+
+    warc::WarcRecord {
+        request: warc::Request {
+            url: hop.from.to_string(),
+            headers: datum.request_headers.clone(),
+        },
+        response: warc::Response {
+            headers,
+            body: Vec::new(),
+            payload_type: None,
+            status_code: Some(hop.status_code),
+            refers_to_date: None,
+            date: None,
+        },
+        metadata: warc::Metadata {
+            fetch_time_ms: datum.fetch_time_ms,
+        },
+    }
+}
+
+async fn commit<S: WarcSink>(writer: warc::DeduplicatedWarcWriter, sink: &S) {
     let filename = format!(
         "{}_{}.warc.gz",
         chrono::Utc::now().to_rfc3339(),
@@ -58,44 +104,56 @@ async fn commit(writer: warc::DeduplicatedWarcWriter, s3: config::S3Config) {
     );
     let data = writer.finish().unwrap();
 
-    match s3::Bucket::new(
-        &s3.bucket,
-        s3::Region::Custom {
-            region: "".to_string(),
-            endpoint: s3.endpoint.clone(),
-        },
-        s3::creds::Credentials {
-            access_key: Some(s3.access_key.clone()),
-            secret_key: Some(s3.secret_key.clone()),
-            security_token: None,
-            session_token: None,
-            expiration: None,
-        },
-    ) {
-        Ok(bucket) => {
-            let bucket = bucket
-                .with_path_style()
-                .with_request_timeout(Duration::from_secs(30 * 60));
-
-            if let Err(err) = bucket
-                .put_object_with_content_type(
-                    &format!("{}/{}", &s3.folder, filename),
-                    &data,
-                    "application/warc",
-                )
-                .await
-            {
-                tracing::error!("failed to upload to bucket: {:?}", err);
-            }
+    if let Err(err) = sink.put(&filename, data).await {
+        tracing::error!("failed to store warc file: {:?}", err);
+    }
+}
+
+/// When the WARC file being written is finished and handed to the sink.
+#[derive(Debug, Clone, Copy)]
+pub struct Rotation {
+    pub max_bytes: usize,
+    pub max_age: Duration,
+}
+
+impl From<&CrawlerConfig> for Rotation {
+    fn from(config: &CrawlerConfig) -> Self {
+        Self {
+            max_bytes: config.warc_max_bytes,
+            max_age: Duration::from_secs(config.warc_max_age_sec),
         }
-        Err(err) => tracing::error!("failed to connect to bucket: {:?}", err),
     }
 }
 
-async fn writer_task(mut rx: tokio::sync::mpsc::Receiver<WarcWriterMessage>, s3: S3Config) {
+async fn writer_task<S: WarcSink>(
+    mut rx: tokio::sync::mpsc::Receiver<WarcWriterMessage>,
+    sink: S,
+    rotation: Rotation,
+) {
     let mut writer = warc::DeduplicatedWarcWriter::new();
+    let mut first_write: Option<Instant> = None;
+
+    loop {
+        // wake up when the current file gets too old, even if nothing is written
+        let deadline = first_write.map(|first| first + rotation.max_age);
+
+        let message = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(message) => message,
+                Err(_) => {
+                    commit(writer, &sink).await;
+                    writer = warc::DeduplicatedWarcWriter::new();
+                    first_write = None;
+                    continue;
+                }
+            },
+            None => rx.recv().await,
+        };
+
+        let Some(message) = message else {
+            break;
+        };
 
-    while let Some(message) = rx.recv().await {
         match message {
             WarcWriterMessage::Crawl(datum) => {
                 let w = &mut writer;
@@ -103,13 +161,43 @@ async fn writer_task(mut rx: tokio::sync::mpsc::Receiver<WarcWriterMessage>, s3:
 
                 rayon::scope(move |s| {
                     s.spawn(move |_| {
+                        // every hop of the redirect chain is stored as its own record
+                        // so the redirects can be used when building the canonical index
+                        for hop in datum.redirects.hops() {
+                            w.write(&redirect_record(hop, &datum)).unwrap();
+                        }
+
+                        let body = datum.body;
+                        let not_modified = datum.status_code == 304;
+
+                        // a not modified response has no payload, so its headers are stored as sent
+                        let (headers, payload_type) = if not_modified {
+                            (datum.response_headers, None)
+                        } else {
+                            let headers = match datum.payload_type {
+                                warc::PayloadType::Pdf => {
+                                    datum.response_headers.for_decoded_payload(body.len())
+                                }
+                                _ => text_payload_headers(&datum.response_headers, body.len()),
+                            };
+
+                            (headers, Some(datum.payload_type))
+                        };
+
                         let warc_record = warc::WarcRecord {
                             request: warc::Request {
                                 url: datum.url.to_string(),
+                                headers: datum.request_headers,
                             },
                             response: warc::Response {
-                                body: datum.body,
-                                payload_type: Some(datum.payload_type),
+                                headers,
+                                body,
+                                payload_type,
+                                status_code: Some(datum.status_code),
+                                refers_to_date: datum
+                                    .previous_fetch
+                                    .map(|time| time.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+                                date: None,
                             },
                             metadata: warc::Metadata {
                                 fetch_time_ms: datum.fetch_time_ms,
@@ -122,15 +210,17 @@ async fn writer_task(mut rx: tokio::sync::mpsc::Receiver<WarcWriterMessage>, s3:
                 });
 
                 recv.await.unwrap();
+                first_write.get_or_insert_with(Instant::now);
 
-                if writer.num_bytes() > 1_000_000_000 {
-                    commit(writer, s3.clone()).await;
+                if writer.num_bytes() > rotation.max_bytes {
+                    commit(writer, &sink).await;
                     writer = warc::DeduplicatedWarcWriter::new();
+                    first_write = None;
                 }
             }
             WarcWriterMessage::Finish => {
                 if writer.num_writes() > 0 {
-                    commit(writer, s3.clone()).await;
+                    commit(writer, &sink).await;
                 }
                 break;
             }
@@ -139,11 +229,150 @@ async fn writer_task(mut rx: tokio::sync::mpsc::Receiver<WarcWriterMessage>, s3:
 }
 
 impl WarcWriter {
-    pub fn new(s3: S3Config) -> Self {
+    pub fn new<S: WarcSink>(sink: S, rotation: Rotation) -> Self {
         let (tx, rx) = tokio::sync::mpsc::channel(1);
 
-        tokio::spawn(writer_task(rx, s3));
+        tokio::spawn(writer_task(rx, sink, rotation));
 
         Self { tx }
     }
+
+    pub fn from_config(config: &CrawlerConfig) -> Self {
+        let rotation = Rotation::from(config);
+
+        match &config.warc_sink {
+            WarcSinkConfig::S3(s3) => Self::new(S3Sink::new(s3.clone()), rotation),
+            WarcSinkConfig::Local(local) => Self::new(LocalSink::new(local.clone()), rotation),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use url::Url;
+
+    use crate::crawler::{CallbackSink, RedirectChain, Validators};
+
+    use super::*;
+
+    fn datum(url: &str) -> CrawlDatum {
+        CrawlDatum {
+            url: Url::parse(url).unwrap(),
+            status_code: 200,
+            payload_type: warc::PayloadType::Html,
+            body: b"<html><body>test</body></html>".to_vec(),
+            fetch_time_ms: 42,
+            validators: Validators::default(),
+            request_headers: warc::HttpHeaders::new(),
+            response_headers: warc::HttpHeaders::new(),
+            redirects: RedirectChain::new(),
+            previous_fetch: None,
+        }
+    }
+
+    fn callback_writer(rotation: Rotation) -> (WarcWriter, Arc<Mutex<Vec<Vec<u8>>>>) {
+        let files = Arc::new(Mutex::new(Vec::new()));
+        let sink = CallbackSink::new({
+            let files = Arc::clone(&files);
+            move |_, data| files.lock().unwrap().push(data)
+        });
+
+        (WarcWriter::new(sink, rotation), files)
+    }
+
+    fn num_records(files: &[Vec<u8>]) -> usize {
+        files
+            .iter()
+            .map(|data| {
+                warc::WarcFile::new(data.clone())
+                    .records()
+                    .flatten()
+                    .count()
+            })
+            .sum()
+    }
+
+    #[tokio::test]
+    async fn rotate_by_size() {
+        let (writer, files) = callback_writer(Rotation {
+            max_bytes: 0,
+            max_age: Duration::from_secs(60 * 60),
+        });
+
+        writer.write(datum("https://a.com/")).await.unwrap();
+        writer.write(datum("https://a.com/b")).await.unwrap();
+        writer.finish().await.unwrap();
+
+        let files = files.lock().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(num_records(&files), 2);
+    }
+
+    #[tokio::test]
+    async fn not_modified() {
+        let (writer, files) = callback_writer(Rotation {
+            max_bytes: usize::MAX,
+            max_age: Duration::from_secs(60 * 60),
+        });
+
+        let mut response_headers = warc::HttpHeaders::new();
+        response_headers.push("ETag", "\"abc\"");
+
+        writer
+            .write(CrawlDatum {
+                status_code: 304,
+                body: Vec::new(),
+                response_headers,
+                previous_fetch: Some("2024-01-01T00:00:00Z".parse().unwrap()),
+                ..datum("https://a.com/")
+            })
+            .await
+            .unwrap();
+        writer.finish().await.unwrap();
+
+        let files = files.lock().unwrap();
+        let record = warc::WarcFile::new(files[0].clone())
+            .records()
+            .next()
+            .unwrap()
+            .unwrap();
+
+        assert!(record.response.is_not_modified());
+        assert_eq!(record.response.payload_type, None);
+        assert_eq!(record.response.headers.get("etag"), Some("\"abc\""));
+        assert_eq!(
+            record.response.refers_to_date.as_deref(),
+            Some("2024-01-01T00:00:00Z")
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rotate_by_age() {
+        let max_age = Duration::from_secs(60);
+        let (writer, files) = callback_writer(Rotation {
+            max_bytes: usize::MAX,
+            max_age,
+        });
+
+        let start = Instant::now();
+        writer.write(datum("https://a.com/")).await.unwrap();
+
+        // the file is committed once it is too old, even without new writes.
+        // the record is written on another thread, so we advance the clock until it has been committed
+        while files.lock().unwrap().is_empty() {
+            tokio::time::advance(Duration::from_secs(1)).await;
+        }
+
+        assert!(start.elapsed() >= max_age);
+        assert_eq!(files.lock().unwrap().len(), 1);
+
+        writer.write(datum("https://a.com/b")).await.unwrap();
+        writer.finish().await.unwrap();
+
+        let files = files.lock().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(num_records(&files), 2);
+    }
 }
@@ -14,10 +14,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 use anyhow::anyhow;
-use encoding_rs::{Encoding, UTF_8};
-use hashbrown::HashSet;
+use chrono::Utc;
+use hashbrown::{HashMap, HashSet};
 use mime::Mime;
-use quick_xml::events::Event;
 use rand::seq::SliceRandom;
 use tokio_stream::StreamExt;
 
@@ -32,7 +31,7 @@ use url::Url;
 
 use crate::{
     config::CrawlerConfig,
-    crawler::MAX_URL_LEN_BYTES,
+    crawler::{MAX_OUTGOING_URLS_PER_PAGE, MAX_URLS_FOR_DOMAIN_PER_INSERT, MAX_URL_LEN_BYTES},
     distributed::{retry_strategy::ExponentialBackoff, sonic},
     entrypoint::crawler::router::{NewJob, RouterService},
     warc,
@@ -40,29 +39,52 @@ use crate::{
 };
 
 use super::{
-    reqwest_client, robots_txt::RobotsTxtManager, wander_prirotiser::WanderPrioritiser, CrawlDatum,
-    DatumStream, Domain, Error, Result, RetrieableUrl, Site, UrlResponse, WarcWriter, WeightedUrl,
-    WorkerJob,
+    default_headers, encoding,
+    recrawl::{RecrawlScheduler, Validators, Visit},
+    redirect::{self, RedirectChain},
+    reqwest_client,
+    robots_txt::RobotsTxtManager,
+    sitemap::{self, SitemapEntry, SitemapUrl},
+    wander_prirotiser::WanderPrioritiser,
+    CrawlDatum, DatumStream, DiscoveredUrls, Domain, DomainCrawled, Error, JobResponse, Result,
+    RetrieableUrl, Site, UrlResponse, UrlToInsert, WarcWriter, WeightedUrl, WorkerJob,
 };
 
 const MAX_CONTENT_LENGTH: usize = 32 * 1024 * 1024; // 32 MB
 
-const IGNORED_EXTENSIONS: [&str; 27] = [
-    ".pdf", ".jpg", ".zip", ".png", ".css", ".js", ".json", ".jsonp", ".woff2", ".woff", ".ttf",
-    ".svg", ".gif", ".jpeg", ".ico", ".mp4", ".mp3", ".avi", ".mov", ".mpeg", ".webm", ".wav",
-    ".flac", ".aac", ".ogg", ".m4a", ".m4v",
+/// How deep to follow nested sitemap indexes.
+const MAX_SITEMAP_DEPTH: usize = 5;
+
+/// Maximum number of sitemaps to fetch for a site.
+const MAX_SITEMAPS_PER_SITE: usize = 64;
+
+const IGNORED_EXTENSIONS: [&str; 26] = [
+    ".jpg", ".zip", ".png", ".css", ".js", ".json", ".jsonp", ".woff2", ".woff", ".ttf", ".svg",
+    ".gif", ".jpeg", ".ico", ".mp4", ".mp3", ".avi", ".mov", ".mpeg", ".webm", ".wav", ".flac",
+    ".aac", ".ogg", ".m4a", ".m4v",
 ];
 
+/// How long to wait before asking for a new job when the coordinators have none right now.
+const WAIT_FOR_JOB: Duration = Duration::from_secs(30);
+
 struct ProcessedUrl {
     new_urls: Vec<Url>,
     response: UrlResponse,
 }
 
+/// What happened while executing a job. Reported back to the coordinators
+/// so the discovered urls can be crawled later.
+pub struct JobOutcome {
+    pub crawled: DomainCrawled,
+    pub discovered: DiscoveredUrls,
+}
+
 pub struct WorkerThread {
     writer: Arc<WarcWriter>,
     client: reqwest::Client,
     config: Arc<CrawlerConfig>,
     router_hosts: Vec<SocketAddr>,
+    recrawl: Option<Arc<RecrawlScheduler>>,
 }
 
 impl WorkerThread {
@@ -70,6 +92,7 @@ impl WorkerThread {
         writer: Arc<WarcWriter>,
         config: CrawlerConfig,
         router_hosts: Vec<SocketAddr>,
+        recrawl: Option<Arc<RecrawlScheduler>>,
     ) -> Result<Self> {
         let client = reqwest_client(&config)?;
 
@@ -78,6 +101,7 @@ impl WorkerThread {
             client,
             config: Arc::new(config),
             router_hosts,
+            recrawl,
         })
     }
 
@@ -94,6 +118,21 @@ impl WorkerThread {
         .await?)
     }
 
+    /// Send the urls discovered during a job and the budget it used to the coordinators.
+    async fn report(&self, outcome: JobOutcome) -> Result<()> {
+        let mut conn = self.router_conn().await?;
+
+        if !outcome.discovered.urls.is_empty() {
+            conn.send_with_timeout(outcome.discovered, Duration::from_secs(90))
+                .await?;
+        }
+
+        conn.send_with_timeout(outcome.crawled, Duration::from_secs(90))
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn run(self) {
         loop {
             let mut conn = self.router_conn().await.unwrap();
@@ -102,16 +141,28 @@ impl WorkerThread {
                 .await;
 
             match res {
-                Ok(Some(job)) => {
-                    let executor = JobExecutor::new(
+                Ok(JobResponse::Job(job)) => {
+                    let mut executor = JobExecutor::new(
                         job.into(),
                         self.client.clone(),
                         self.config.clone(),
                         self.writer.clone(),
                     );
-                    executor.run().await;
+
+                    if let Some(recrawl) = &self.recrawl {
+                        executor = executor.with_recrawl_scheduler(recrawl.clone());
+                    }
+
+                    let outcome = executor.run().await;
+
+                    if let Err(err) = self.report(outcome).await {
+                        tracing::error!("failed to report job outcome: {:?}", err);
+                    }
                 }
-                Ok(None) => {
+                Ok(JobResponse::Wait) => {
+                    tokio::time::sleep(WAIT_FOR_JOB).await;
+                }
+                Ok(JobResponse::Done) => {
                     return;
                 }
                 _ => {
@@ -130,9 +181,13 @@ pub struct JobExecutor<S: DatumStream> {
     robotstxt: RobotsTxtManager,
     crawled_urls: HashSet<Url>,
     crawled_sitemaps: HashSet<Site>,
-    sitemap_urls: HashSet<Url>,
+    sitemap_urls: HashMap<Url, f64>,
     config: Arc<CrawlerConfig>,
     wander_prioritiser: WanderPrioritiser,
+    recrawl: Option<Arc<RecrawlScheduler>>,
+    /// Urls found during the job that were not crawled, grouped by domain.
+    discovered: HashMap<Domain, HashMap<Url, f64>>,
+    num_fetched: u64,
     job: WorkerJob,
 }
 
@@ -153,14 +208,24 @@ impl<S: DatumStream> JobExecutor<S> {
             client,
             crawled_urls: HashSet::new(),
             crawled_sitemaps: HashSet::new(),
-            sitemap_urls: HashSet::new(),
+            sitemap_urls: HashMap::new(),
             config,
             wander_prioritiser: WanderPrioritiser::new(),
+            recrawl: None,
+            discovered: HashMap::new(),
+            num_fetched: 0,
             job,
         }
     }
 
-    pub async fn run(mut self) {
+    /// Use conditional requests for urls we have visited before and record the
+    /// outcome of every visit, so the scheduler can decide when to visit them again.
+    pub fn with_recrawl_scheduler(mut self, recrawl: Arc<RecrawlScheduler>) -> Self {
+        self.recrawl = Some(recrawl);
+        self
+    }
+
+    pub async fn run(mut self) -> JobOutcome {
         tracing::info!("Processing job: {:?}", self.job.domain);
 
         self.scheduled_urls().await;
@@ -168,6 +233,61 @@ impl<S: DatumStream> JobExecutor<S> {
         if self.job.wandering_urls > 0 {
             self.wander().await;
         }
+
+        self.outcome()
+    }
+
+    fn outcome(self) -> JobOutcome {
+        let crawled_urls = self.crawled_urls;
+
+        let urls = self
+            .discovered
+            .into_iter()
+            .map(|(domain, urls)| {
+                let mut urls: Vec<_> = urls
+                    .into_iter()
+                    .filter(|(url, _)| !crawled_urls.contains(url))
+                    .collect();
+                urls.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+                let urls = urls
+                    .into_iter()
+                    .take(MAX_URLS_FOR_DOMAIN_PER_INSERT)
+                    .map(|(url, weight)| UrlToInsert {
+                        url: url.into(),
+                        weight,
+                    })
+                    .collect::<Vec<_>>();
+
+                (domain, urls)
+            })
+            .filter(|(_, urls)| !urls.is_empty())
+            .collect();
+
+        JobOutcome {
+            crawled: DomainCrawled {
+                domain: self.job.domain,
+                budget_used: self.num_fetched as f64,
+            },
+            discovered: DiscoveredUrls { urls },
+        }
+    }
+
+    /// Remember a url that was found but not crawled in this job.
+    fn discover(&mut self, url: Url, weight: f64) {
+        let domain = Domain::from(&url);
+
+        if domain.as_str().is_empty() || !weight.is_finite() {
+            return;
+        }
+
+        let urls = self.discovered.entry(domain).or_default();
+
+        if let Some(w) = urls.get_mut(&url) {
+            *w += weight;
+        } else if urls.len() < MAX_URLS_FOR_DOMAIN_PER_INSERT {
+            urls.insert(url, weight);
+        }
     }
 
     async fn scheduled_urls(&mut self) {
@@ -176,26 +296,37 @@ impl<S: DatumStream> JobExecutor<S> {
     }
 
     async fn wander(&mut self) {
-        let mut urls: Vec<(Url, f64)> = self
+        // urls that are both linked to and in a sitemap get the sum of their scores
+        let mut scores: HashMap<Url, f64> = HashMap::new();
+        for (url, score) in self
             .wander_prioritiser
             .top_and_clear(self.job.wandering_urls as usize)
             .into_iter()
-            .chain(self.sitemap_urls.drain().map(|url| (url.clone(), 0.0)))
+            .chain(self.sitemap_urls.drain())
+        {
+            *scores.entry(url).or_default() += score;
+        }
+
+        let mut urls: Vec<(Url, f64)> = scores
+            .into_iter()
             .filter(|(url, _)| !self.crawled_urls.contains(url))
             .filter(|(url, _)| self.job.domain == Domain::from(url))
             .filter(|(_, score)| score.is_finite())
+            .filter(|(url, _)| self.is_due(url))
             .collect();
 
         urls.sort_by(|(a, _), (b, _)| a.cmp(b));
-        urls.dedup_by(|(a, _), (b, _)| a == b);
-
         urls.sort_by(|(_, a), (_, b)| b.total_cmp(a));
 
+        let leftover = urls.split_off(urls.len().min(self.job.wandering_urls as usize));
+
+        for (url, weight) in leftover {
+            self.discover(url, weight);
+        }
+
         let urls = urls
             .into_iter()
-            .take(self.job.wandering_urls as usize)
-            .map(|(url, _)| url)
-            .map(|url| WeightedUrl { url, weight: 0.0 })
+            .map(|(url, weight)| WeightedUrl::new(url, weight))
             .map(RetrieableUrl::from)
             .collect();
 
@@ -231,6 +362,14 @@ impl<S: DatumStream> JobExecutor<S> {
                 continue;
             }
 
+            let robots_delay = if self.config.dry_run {
+                None
+            } else {
+                self.robotstxt
+                    .crawl_delay(retryable_url.url(), &self.config.user_agent.token)
+                    .await
+            };
+
             let site = Site(
                 retryable_url
                     .url()
@@ -241,29 +380,40 @@ impl<S: DatumStream> JobExecutor<S> {
             if !self.config.dry_run && fetch_sitemap && !self.crawled_sitemaps.contains(&site) {
                 self.crawled_sitemaps.insert(site.clone());
 
-                if let Some(sitemap) = self.robotstxt.sitemap(retryable_url.url()).await {
+                let sitemaps = self.robotstxt.sitemaps(retryable_url.url()).await;
+                let now = Utc::now();
+
+                for sitemap_url in self
+                    .urls_from_sitemaps(sitemaps, MAX_SITEMAP_DEPTH, robots_delay)
+                    .await
+                {
+                    let weight = sitemap_url.weight(now);
+
                     self.sitemap_urls
-                        .extend(self.urls_from_sitemap(sitemap, 5).await);
+                        .entry(sitemap_url.url)
+                        .and_modify(|w| *w = w.max(weight))
+                        .or_insert(weight);
                 }
             }
 
-            let res = self.process_url(retryable_url.url().clone()).await;
+            let validators = self.validators(&retryable_url.weighted_url);
+            let res = self
+                .process_url(retryable_url.url().clone(), &validators, robots_delay)
+                .await;
 
             match res.response {
                 UrlResponse::Success { url: _ } => {
-                    let weight = retryable_url.weighted_url.weight;
-
-                    for new_url in res.new_urls {
-                        if new_url.host_str().is_none() {
-                            continue;
-                        }
-
-                        if new_url.root_domain() != retryable_url.url().root_domain() {
-                            continue;
-                        }
-
-                        self.wander_prioritiser.inc(new_url, weight);
-                    }
+                    self.crawled_urls.insert(retryable_url.url().clone());
+                    self.prioritise_new_urls(&retryable_url, res.new_urls);
+                }
+                UrlResponse::Redirected {
+                    url: _,
+                    new_url: _,
+                    chain,
+                } => {
+                    // every url in the chain has been visited, so we don't need to fetch them again
+                    self.crawled_urls.extend(chain.urls().cloned());
+                    self.prioritise_new_urls(&retryable_url, res.new_urls);
                 }
                 UrlResponse::Failed {
                     url: _,
@@ -276,11 +426,60 @@ impl<S: DatumStream> JobExecutor<S> {
                         continue;
                     }
                 }
-                UrlResponse::Redirected { url: _, new_url: _ } => {}
+                UrlResponse::NotModified { url: _ } => {}
             }
         }
     }
 
+    fn prioritise_new_urls(&mut self, url: &RetrieableUrl, new_urls: Vec<Url>) {
+        let weight = url.weighted_url.weight;
+
+        let mut num_outgoing = 0;
+
+        for new_url in new_urls {
+            if new_url.host_str().is_none() {
+                continue;
+            }
+
+            if new_url.root_domain() != url.url().root_domain()
+                || Domain::from(&new_url) != self.job.domain
+            {
+                if num_outgoing < MAX_OUTGOING_URLS_PER_PAGE {
+                    num_outgoing += 1;
+                    self.discover(new_url, weight);
+                }
+
+                continue;
+            }
+
+            self.wander_prioritiser.inc(new_url, weight);
+        }
+    }
+
+    fn is_due(&self, url: &Url) -> bool {
+        match &self.recrawl {
+            Some(recrawl) => recrawl.is_due(url, Utc::now()),
+            None => true,
+        }
+    }
+
+    fn validators(&self, url: &WeightedUrl) -> Validators {
+        if !url.validators.is_empty() {
+            return url.validators.clone();
+        }
+
+        self.recrawl
+            .as_ref()
+            .and_then(|recrawl| recrawl.validators(&url.url))
+            .unwrap_or_default()
+    }
+
+    fn record_visit(&self, url: &Url, visit: Visit) {
+        if let Some(recrawl) = &self.recrawl {
+            recrawl.record(url, visit, Utc::now());
+        }
+    }
+
     fn increase_politeness(&mut self) {
         self.politeness_factor *= 2.0;
 
@@ -304,23 +503,54 @@ impl<S: DatumStream> JobExecutor<S> {
             .collect()
     }
 
-    async fn process_url(&mut self, url: Url) -> ProcessedUrl {
-        let fetch = self.crawl_url(url.clone()).await;
+    async fn process_url(
+        &mut self,
+        url: Url,
+        validators: &Validators,
+        robots_delay: Option<Duration>,
+    ) -> ProcessedUrl {
+        let fetch = self.crawl_url(url.clone(), validators, robots_delay).await;
+        self.num_fetched += 1;
+
+        // the capture a not modified response refers to, before the visit is recorded
+        let previous_fetch = self
+            .recrawl
+            .as_ref()
+            .and_then(|recrawl| recrawl.last_fetch(&url));
+
+        let visit = match &fetch {
+            Ok(datum) if datum.status_code == 200 => {
+                Visit::fetched(datum.validators.clone(), &datum.body)
+            }
+            Ok(datum) if datum.status_code == 304 => Visit::NotModified {
+                validators: datum.validators.clone(),
+            },
+            _ => Visit::Failed,
+        };
+        self.record_visit(&url, visit);
 
         match fetch {
             Ok(datum) => match datum.status_code {
+                // we index pdfs, but don't follow the links in them
+                200 if datum.payload_type == warc::PayloadType::Pdf => {
+                    self.save_datum(datum.clone()).await;
+
+                    ProcessedUrl {
+                        new_urls: Vec::new(),
+                        response: Self::success(url, datum),
+                    }
+                }
                 200 => {
                     self.save_datum(datum.clone()).await;
 
-                    match Html::parse(&datum.body, datum.url.as_str()) {
-                        Ok(html) => {
-                            let new_urls = Self::new_urls(&html);
-                            let url_res = UrlResponse::Success { url: datum.url };
-                            ProcessedUrl {
-                                new_urls,
-                                response: url_res,
-                            }
-                        }
+                    let html =
+                        Html::parse(&String::from_utf8_lossy(&datum.body), datum.url.as_str());
+
+                    match html {
+                        Ok(html) => ProcessedUrl {
+                            new_urls: Self::new_urls(&html),
+                            response: Self::success(url, datum),
+                        },
                         Err(_) => ProcessedUrl {
                             new_urls: Vec::new(),
                             response: UrlResponse::Failed {
@@ -330,15 +560,18 @@ impl<S: DatumStream> JobExecutor<S> {
                         },
                     }
                 }
-                301 | 302 => {
-                    let url_res = UrlResponse::Redirected {
-                        url,
-                        new_url: datum.url,
-                    };
+                // the page has not changed since the last visit, so there is nothing new to index.
+                // the response is still stored as a revisit of the earlier capture.
+                304 => {
+                    self.save_datum(CrawlDatum {
+                        previous_fetch,
+                        ..datum
+                    })
+                    .await;
 
                     ProcessedUrl {
                         new_urls: Vec::new(),
-                        response: url_res,
+                        response: UrlResponse::NotModified { url },
                     }
                 }
 
@@ -371,6 +604,19 @@ impl<S: DatumStream> JobExecutor<S> {
         }
     }
 
+    /// The response for a successfully fetched url, which might have been redirected.
+    fn success(url: Url, datum: CrawlDatum) -> UrlResponse {
+        if datum.redirects.is_empty() {
+            UrlResponse::Success { url: datum.url }
+        } else {
+            UrlResponse::Redirected {
+                url,
+                new_url: datum.url,
+                chain: datum.redirects,
+            }
+        }
+    }
+
     async fn save_datum(&self, datum: CrawlDatum) {
         if datum.status_code != 200 {
             return;
@@ -380,38 +626,58 @@ impl<S: DatumStream> JobExecutor<S> {
     }
 
     async fn fetch(&self, url: Url) -> Result<reqwest::Response> {
+        self.conditional_fetch(url, &Validators::default())
+            .await
+            .map(|(res, _)| res)
+    }
+
+    async fn conditional_fetch(
+        &self,
+        url: Url,
+        validators: &Validators,
+    ) -> Result<(reqwest::Response, RedirectChain)> {
         if self.config.dry_run {
             tracing::debug!("dry run: {}", url);
             return Err(Error::FetchFailed(reqwest::StatusCode::IM_A_TEAPOT).into());
         }
 
-        self.client
-            .get(url.to_string())
-            .send()
-            .await
-            .map_err(|e| e.into())
+        redirect::get(&self.client, url, self.config.max_redirects, |req| {
+            validators.apply(req)
+        })
+        .await
     }
 
-    async fn fetch_with_https_priority(&self, url: Url) -> Result<reqwest::Response> {
+    async fn fetch_with_https_priority(
+        &self,
+        url: Url,
+        validators: &Validators,
+    ) -> Result<(reqwest::Response, RedirectChain)> {
         if url.scheme() == "http" {
             let mut https = url.clone();
             https
                 .set_scheme("https")
                 .map_err(|_| anyhow!("set scheme on url failed"))?;
 
-            match self.fetch(https).await {
+            match self.conditional_fetch(https, validators).await {
                 Ok(res) => Ok(res),
                 Err(_) => {
                     tokio::time::sleep(Duration::from_millis(self.config.min_crawl_delay_ms)).await;
-                    self.fetch(url.clone()).await
+                    self.conditional_fetch(url.clone(), validators).await
                 }
             }
         } else {
-            self.fetch(url.clone()).await
+            self.conditional_fetch(url.clone(), validators).await
         }
     }
 
-    async fn politeness_delay(&self, fetch_time: Duration) {
+    /// The delay the site asks for in robots.txt, bounded by the maximum delay we are willing to respect.
+    fn bounded_robots_delay(&self, robots_delay: Option<Duration>) -> Duration {
+        robots_delay
+            .unwrap_or_default()
+            .min(Duration::from_millis(self.config.max_robots_crawl_delay_ms))
+    }
+
+    async fn politeness_delay(&self, fetch_time: Duration, robots_delay: Option<Duration>) {
         let mut delay = fetch_time;
 
         if delay < Duration::from_millis(self.config.min_crawl_delay_ms) {
@@ -424,6 +690,9 @@ impl<S: DatumStream> JobExecutor<S> {
             delay = Duration::from_millis(self.config.max_crawl_delay_ms);
         }
 
+        // the crawl delay from robots.txt is a lower bound, even if it exceeds `max_crawl_delay_ms`
+        delay = delay.max(self.bounded_robots_delay(robots_delay));
+
         tokio::time::sleep(delay).await;
     }
 
@@ -440,7 +709,7 @@ impl<S: DatumStream> JobExecutor<S> {
             }
         }
 
-        // check if content type is html
+        // check if content type is supported
         match res
             .headers()
             .get(reqwest::header::CONTENT_TYPE)
@@ -449,55 +718,65 @@ impl<S: DatumStream> JobExecutor<S> {
             Some(ct) if ct.contains("text/html") => Ok(warc::PayloadType::Html),
             Some(ct) if ct.contains("application/rss") => Ok(warc::PayloadType::Rss),
             Some(ct) if ct.contains("application/atom") => Ok(warc::PayloadType::Atom),
+            Some(ct) if ct.contains("application/pdf") => Ok(warc::PayloadType::Pdf),
             ct => Err(Error::InvalidContentType(format!("{ct:?}")).into()),
         }
     }
 
-    fn redirect_datum(
-        &self,
-        res: &reqwest::Response,
-        url: &Url,
-        payload_type: warc::PayloadType,
-        fetch_time: Duration,
-    ) -> Result<Option<CrawlDatum>> {
-        let status_code = res.status().as_u16();
-
-        if status_code == 301 || status_code == 302 {
-            let location = res
-                .headers()
-                .get("location")
-                .ok_or(Error::InvalidRedirect)?;
-
-            let location = location.to_str().map_err(|_| Error::InvalidRedirect)?;
+    /// The headers sent when requesting a url with the validators.
+    fn request_headers(&self, validators: &Validators) -> warc::HttpHeaders {
+        let mut headers = warc::HttpHeaders::from(&default_headers());
+        headers.push(
+            reqwest::header::USER_AGENT.as_str(),
+            self.config.user_agent.full.as_str(),
+        );
 
-            let url = Url::parse(location)
-                .or_else(|_| url.join(location))
-                .map_err(|_| Error::InvalidRedirect)?;
+        if let Some(etag) = &validators.etag {
+            headers.push(reqwest::header::IF_NONE_MATCH.as_str(), etag.as_str());
+        }
 
-            Ok(Some(CrawlDatum {
-                url,
-                status_code,
-                payload_type,
-                body: String::new(),
-                fetch_time_ms: fetch_time.as_millis() as u64,
-            }))
-        } else {
-            Ok(None)
+        if let Some(last_modified) = &validators.last_modified {
+            headers.push(
+                reqwest::header::IF_MODIFIED_SINCE.as_str(),
+                last_modified.as_str(),
+            );
         }
+
+        headers
     }
 
-    async fn encoded_body(&self, res: reqwest::Response) -> Result<String> {
+    async fn encoded_body(
+        &self,
+        res: reqwest::Response,
+        payload_type: warc::PayloadType,
+    ) -> Result<String> {
         let content_type = res
             .headers()
             .get(reqwest::header::CONTENT_TYPE)
             .and_then(|value| value.to_str().ok())
             .and_then(|value| value.parse::<Mime>().ok());
-        let encoding_name = content_type
+        let charset = content_type
             .as_ref()
-            .and_then(|mime| mime.get_param("charset").map(|charset| charset.as_str()))
-            .unwrap_or("utf-8");
-        let encoding = Encoding::for_label(encoding_name.as_bytes()).unwrap_or(UTF_8);
+            .and_then(|mime| mime.get_param("charset"))
+            .map(|charset| charset.as_str().to_string());
+        // the detector wants the rightmost label, e.g. `jp` for `co.jp`
+        let tld = res
+            .url()
+            .tld()
+            .and_then(|tld| tld.rsplit('.').next())
+            .map(|tld| tld.to_string());
+
+        let bytes = Self::raw_body(res).await?;
+
+        Ok(encoding::decode(
+            &bytes,
+            charset.as_deref(),
+            payload_type == warc::PayloadType::Html,
+            tld.as_deref(),
+        ))
+    }
 
+    async fn raw_body(res: reqwest::Response) -> Result<Vec<u8>> {
         let mut bytes = Vec::new();
 
         let mut stream = res.bytes_stream();
@@ -515,29 +794,53 @@ impl<S: DatumStream> JobExecutor<S> {
             }
         }
 
-        let (text, _, _) = encoding.decode(&bytes);
-        Ok(text.to_string())
+        Ok(bytes)
     }
 
-    async fn crawl_url(&self, url: Url) -> Result<CrawlDatum> {
+    async fn crawl_url(
+        &self,
+        url: Url,
+        validators: &Validators,
+        robots_delay: Option<Duration>,
+    ) -> Result<CrawlDatum> {
+        let request_headers = self.request_headers(validators);
         let start = Instant::now();
-        let res = self.fetch_with_https_priority(url.clone()).await;
+        let res = self
+            .fetch_with_https_priority(url.clone(), validators)
+            .await;
         let fetch_time = start.elapsed();
-        self.politeness_delay(fetch_time).await;
+        self.politeness_delay(fetch_time, robots_delay).await;
 
         // we want to delay before returning the error
-        let res = res?;
+        let (res, redirects) = res?;
 
-        let payload_type = self.check_headers(&res)?;
-
-        if let Some(datum) = self.redirect_datum(&res, &url, payload_type, fetch_time)? {
-            return Ok(datum);
+        // a not modified response has no body, so there are no content headers to check
+        if res.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(CrawlDatum {
+                url,
+                status_code: res.status().as_u16(),
+                payload_type: warc::PayloadType::Html,
+                body: Vec::new(),
+                fetch_time_ms: fetch_time.as_millis() as u64,
+                validators: validators.updated(res.headers()),
+                response_headers: warc::HttpHeaders::from(res.headers()),
+                request_headers,
+                redirects,
+                previous_fetch: None,
+            });
         }
 
+        let payload_type = self.check_headers(&res)?;
+
         let status_code = res.status().as_u16();
 
         let res_url = res.url().clone();
-        let body = self.encoded_body(res).await?;
+        let validators = Validators::from_headers(res.headers());
+        let response_headers = warc::HttpHeaders::from(res.headers());
+        let body = match payload_type {
+            warc::PayloadType::Pdf => Self::raw_body(res).await?,
+            _ => self.encoded_body(res, payload_type).await?.into_bytes(),
+        };
 
         Ok(CrawlDatum {
             url: res_url,
@@ -545,42 +848,60 @@ impl<S: DatumStream> JobExecutor<S> {
             body,
             payload_type,
             fetch_time_ms: fetch_time.as_millis() as u64,
+            validators,
+            request_headers,
+            response_headers,
+            redirects,
+            previous_fetch: None,
         })
     }
 
-    async fn urls_from_sitemap(&self, sitemap: Url, max_depth: usize) -> Vec<Url> {
-        let mut stack = vec![(sitemap, 0)];
+    /// Fetch the sitemaps and all the sitemaps they reference (up to `max_depth` levels deep)
+    /// and return the urls they contain.
+    async fn urls_from_sitemaps(
+        &self,
+        sitemaps: Vec<Url>,
+        max_depth: usize,
+        robots_delay: Option<Duration>,
+    ) -> Vec<SitemapUrl> {
+        let delay = Duration::from_millis(self.config.min_crawl_delay_ms)
+            .max(self.bounded_robots_delay(robots_delay));
+        let mut stack: Vec<_> = sitemaps.into_iter().rev().map(|url| (url, 0)).collect();
+        let mut fetched = HashSet::new();
         let mut urls = vec![];
 
         while let Some((url, depth)) = stack.pop() {
-            if depth >= max_depth {
+            if depth >= max_depth
+                || fetched.len() >= MAX_SITEMAPS_PER_SITE
+                || !fetched.insert(url.clone())
+            {
                 continue;
             }
 
             let res = self.fetch(url).await;
-            tokio::time::sleep(Duration::from_millis(self.config.min_crawl_delay_ms)).await;
+            tokio::time::sleep(delay).await;
 
-            if res.is_err() {
+            let Ok(res) = res else {
                 continue;
-            }
-
-            let res = res.unwrap();
+            };
 
             if res.status() != reqwest::StatusCode::OK {
                 continue;
             }
 
-            let body = res.text().await;
+            let Ok(body) = res.bytes().await else {
+                continue;
+            };
 
-            if body.is_err() {
+            if body.len() > MAX_CONTENT_LENGTH {
                 continue;
             }
 
-            let body = body.unwrap();
-
-            let entries = parse_sitemap(&body);
+            let Some(body) = sitemap::decode(&body, MAX_CONTENT_LENGTH) else {
+                continue;
+            };
 
-            for entry in entries {
+            for entry in sitemap::parse(&body) {
                 match entry {
                     SitemapEntry::Url(url) => {
                         urls.push(url);
@@ -595,155 +916,3 @@ impl<S: DatumStream> JobExecutor<S> {
         urls
     }
 }
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-enum SitemapEntry {
-    Url(Url),
-    Sitemap(Url),
-}
-
-fn parse_sitemap(s: &str) -> Vec<SitemapEntry> {
-    let mut reader = quick_xml::Reader::from_str(s);
-
-    let mut res = vec![];
-
-    let mut in_sitemap = false;
-    let mut in_url = false;
-    let mut in_loc = false;
-
-    loop {
-        match reader.read_event() {
-            Ok(Event::Start(ref e)) => {
-                if e.name().as_ref() == b"sitemap" {
-                    in_sitemap = true;
-                } else if e.name().as_ref() == b"url" {
-                    in_url = true;
-                } else if e.name().as_ref() == b"loc" {
-                    in_loc = true;
-                }
-            }
-            Ok(Event::End(ref e)) => {
-                if e.name().as_ref() == b"sitemap" {
-                    in_sitemap = false;
-                } else if e.name().as_ref() == b"url" {
-                    in_url = false;
-                } else if e.name().as_ref() == b"loc" {
-                    in_loc = false;
-                }
-            }
-            Ok(Event::Text(e)) => {
-                if in_sitemap && in_loc {
-                    if let Ok(url) = Url::parse(&e.unescape().unwrap()) {
-                        res.push(SitemapEntry::Sitemap(url));
-                    }
-                } else if in_url && in_loc {
-                    if let Ok(url) = Url::parse(&e.unescape().unwrap()) {
-                        res.push(SitemapEntry::Url(url));
-                    }
-                }
-            }
-            Ok(Event::Eof) => break,
-            Err(e) => {
-                tracing::debug!("failed to parse sitemap: {}", e);
-                break;
-            }
-            _ => (),
-        }
-    }
-
-    res
-}
-
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn parse_sitemap() {
-        let dr = r#"<sitemapindex>
-        <sitemap>
-        <loc>https://www.dr.dk/drtv/sitemap.xml</loc>
-        </sitemap>
-        <sitemap>
-        <loc>https://www.dr.dk/sitemap.tvguide.xml</loc>
-        </sitemap>
-        <sitemap>
-        <loc>
-        https://www.dr.dk/sitemap.kommunalvalg.resultater.xml
-        </loc>
-        </sitemap>
-        <sitemap>
-        <loc>https://www.dr.dk/sitemap.folketingsvalg2022.xml</loc>
-        </sitemap>
-        </sitemapindex>"#;
-
-        let entries = super::parse_sitemap(dr);
-        assert_eq!(
-            entries,
-            vec![
-                super::SitemapEntry::Sitemap("https://www.dr.dk/drtv/sitemap.xml".parse().unwrap()),
-                super::SitemapEntry::Sitemap(
-                    "https://www.dr.dk/sitemap.tvguide.xml".parse().unwrap()
-                ),
-                super::SitemapEntry::Sitemap(
-                    "https://www.dr.dk/sitemap.kommunalvalg.resultater.xml"
-                        .parse()
-                        .unwrap()
-                ),
-                super::SitemapEntry::Sitemap(
-                    "https://www.dr.dk/sitemap.folketingsvalg2022.xml"
-                        .parse()
-                        .unwrap()
-                ),
-            ]
-        );
-
-        let dr = r#"<urlset>
-        <url>
-        <lastmod>2023-10-18T05:40:04.7435930+00:00</lastmod>
-        <loc>https://www.dr.dk/drtv/serie/sleepover_6382</loc>
-        </url>
-        <url>
-        <lastmod>2023-10-18T05:40:04.7435930+00:00</lastmod>
-        <loc>https://www.dr.dk/drtv/saeson/sleepover_9673</loc>
-        </url>
-        <url>
-        <lastmod>2023-10-18T05:40:04.7435930+00:00</lastmod>
-        <loc>
-        https://www.dr.dk/drtv/episode/sleepover_-zoologisk-museum_52239
-        </loc>
-        </url>
-        <url>
-        <lastmod>2023-10-18T05:40:04.7435930+00:00</lastmod>
-        <loc>
-        https://www.dr.dk/drtv/episode/sleepover_-koebenhavns-raadhus_52252
-        </loc>
-        </url>
-        </urlset>"#;
-
-        let entries = super::parse_sitemap(dr);
-        assert_eq!(
-            entries,
-            vec![
-                super::SitemapEntry::Url(
-                    "https://www.dr.dk/drtv/serie/sleepover_6382"
-                        .parse()
-                        .unwrap()
-                ),
-                super::SitemapEntry::Url(
-                    "https://www.dr.dk/drtv/saeson/sleepover_9673"
-                        .parse()
-                        .unwrap()
-                ),
-                super::SitemapEntry::Url(
-                    "https://www.dr.dk/drtv/episode/sleepover_-zoologisk-museum_52239"
-                        .parse()
-                        .unwrap()
-                ),
-                super::SitemapEntry::Url(
-                    "https://www.dr.dk/drtv/episode/sleepover_-koebenhavns-raadhus_52252"
-                        .parse()
-                        .unwrap()
-                ),
-            ]
-        );
-    }
-}
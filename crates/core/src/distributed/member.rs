@@ -94,6 +94,13 @@ pub enum Service {
     HarmonicCoordinator {
         host: SocketAddr,
     },
+    PageRankWorker {
+        host: SocketAddr,
+        shard: ShardId,
+    },
+    PageRankCoordinator {
+        host: SocketAddr,
+    },
 }
 
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
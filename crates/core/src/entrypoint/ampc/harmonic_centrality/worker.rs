@@ -178,6 +178,7 @@ pub fn run(config: HarmonicWorkerConfig) -> Result<()> {
 
     let graph = Webgraph::builder(config.graph_path)
         .single_threaded()
+        .link_filter(config.link_filter)
         .open();
     let worker = CentralityWorker::new(config.shard, graph);
     let service = Service::HarmonicWorker {
@@ -16,3 +16,4 @@
 
 pub mod dht;
 pub mod harmonic_centrality;
+pub mod pagerank;
@@ -0,0 +1,186 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+use crate::{
+    ampc::{dht::ShardId, prelude::*, Coordinator, DhtConn},
+    config::PageRankCoordinatorConfig,
+    distributed::member::Member,
+    webgraph::centrality::{store_centrality, store_csv, top_nodes, TopNodes},
+    Result,
+};
+use std::{collections::BTreeMap, net::SocketAddr, path::Path};
+
+use crate::{
+    ampc::DefaultDhtTable,
+    distributed::{cluster::Cluster, member::Service},
+};
+
+use super::{Meta, PageRankJob, PageRankMapper, PageRankTables, RemotePageRankWorker};
+
+pub struct PageRankSetup {
+    dht: DhtConn<PageRankTables>,
+}
+
+impl PageRankSetup {
+    pub fn new_for_dht_members(dht_members: &[(ShardId, SocketAddr)]) -> Self {
+        let initial = PageRankTables {
+            meta: DefaultDhtTable::new(dht_members, "pagerank_meta"),
+            out_degree: DefaultDhtTable::new(dht_members, "pagerank_out_degree"),
+            contributions: DefaultDhtTable::new(dht_members, "pagerank_contributions"),
+            rank: DefaultDhtTable::new(dht_members, "pagerank_rank"),
+            mass: DefaultDhtTable::new(dht_members, "pagerank_mass"),
+            diff: DefaultDhtTable::new(dht_members, "pagerank_diff"),
+        };
+
+        let dht = DhtConn::new(initial);
+
+        Self { dht }
+    }
+}
+
+impl Setup for PageRankSetup {
+    type DhtTables = PageRankTables;
+
+    fn init_dht(&self) -> DhtConn<Self::DhtTables> {
+        self.dht.clone()
+    }
+
+    fn setup_round(&self, dht: &Self::DhtTables) {
+        let mut meta = dht.meta.get(()).unwrap();
+        meta.round += 1;
+
+        dht.meta.set((), meta);
+    }
+
+    fn setup_first_round(&self, dht: &Self::DhtTables) {
+        dht.meta.set((), Meta { round: 0 });
+    }
+}
+
+pub struct PageRankFinish;
+
+impl Finisher for PageRankFinish {
+    type Job = PageRankJob;
+
+    fn is_finished(&self, dht: &PageRankTables) -> bool {
+        dht.has_converged()
+    }
+}
+
+pub fn build(
+    dht: &[(ShardId, SocketAddr)],
+    workers: Vec<RemotePageRankWorker>,
+) -> Coordinator<PageRankJob> {
+    let setup = PageRankSetup::new_for_dht_members(dht);
+
+    Coordinator::new(setup, workers)
+        .with_mapper(PageRankMapper::OutDegrees)
+        .with_mapper(PageRankMapper::Reset)
+        .with_mapper(PageRankMapper::Contributions)
+        .with_mapper(PageRankMapper::Ranks)
+}
+
+struct ClusterInfo {
+    // dropping the handle will leave the cluster
+    _handle: Cluster,
+    dht: Vec<(ShardId, SocketAddr)>,
+    workers: Vec<RemotePageRankWorker>,
+}
+
+async fn setup_gossip(config: PageRankCoordinatorConfig) -> Result<ClusterInfo> {
+    let handle = Cluster::join(
+        Member {
+            id: config.gossip.cluster_id,
+            service: Service::PageRankCoordinator { host: config.host },
+        },
+        config.gossip.addr,
+        config.gossip.seed_nodes.unwrap_or_default(),
+    )
+    .await?;
+
+    tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+
+    let members = handle.members().await;
+
+    let dht = members
+        .iter()
+        .filter_map(|member| {
+            if let Service::Dht { host, shard } = member.service {
+                Some((shard, host))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let workers = members
+        .iter()
+        .filter_map(|member| {
+            if let Service::PageRankWorker { host, shard } = member.service {
+                Some(RemotePageRankWorker::new(shard, host))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    Ok(ClusterInfo {
+        _handle: handle,
+        dht,
+        workers,
+    })
+}
+
+pub fn run(config: PageRankCoordinatorConfig) -> Result<()> {
+    let tokio_conf = config.clone();
+    let cluster = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(setup_gossip(tokio_conf))?;
+
+    let jobs = cluster
+        .workers
+        .iter()
+        .map(|worker| PageRankJob {
+            shard: worker.shard(),
+        })
+        .collect();
+
+    let coordinator = build(&cluster.dht, cluster.workers.clone());
+    let res = coordinator.run(jobs, PageRankFinish)?;
+
+    let output_path = Path::new(&config.output_path);
+
+    let store = store_centrality(res.rank.iter(), output_path, "pagerank");
+
+    let top_nodes = top_nodes(&store, TopNodes::Top(1_000_000));
+
+    let ids = top_nodes.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+    let id2node: BTreeMap<_, _> = cluster
+        .workers
+        .iter()
+        .flat_map(|w| w.batch_id2node(ids.clone()))
+        .collect();
+
+    let top_nodes = top_nodes
+        .iter()
+        .filter_map(|(id, c)| id2node.get(id).map(|n| (n.clone(), *c)))
+        .collect::<Vec<_>>();
+
+    store_csv(top_nodes, output_path.join("pagerank.csv"));
+
+    Ok(())
+}
@@ -0,0 +1,249 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+use std::collections::BTreeMap;
+
+use crate::{
+    ampc::{
+        dht::{F64Add, U64Add},
+        prelude::*,
+        DhtConn,
+    },
+    webgraph::{self, centrality::pagerank::DAMPING, EdgeLimit},
+};
+
+use super::{PageRankJob, PageRankTables, PageRankWorker};
+
+const OPS_BATCH_PER_SHARD: u64 = 4096;
+
+#[derive(serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode, Debug, Clone)]
+pub enum PageRankMapper {
+    OutDegrees,
+    Reset,
+    Contributions,
+    Ranks,
+}
+
+impl PageRankMapper {
+    /// the out degree of a node is the sum of its outgoing edges on all workers.
+    /// nodes without outgoing edges are inserted with a degree of 0, so the number
+    /// of keys in the table is the number of nodes in the graph.
+    fn update_out_degrees(
+        worker: &PageRankWorker,
+        nodes: &[webgraph::NodeID],
+        dht: &DhtConn<PageRankTables>,
+    ) {
+        let degrees: Vec<_> = nodes
+            .iter()
+            .map(|node| {
+                let degree = worker
+                    .graph()
+                    .raw_outgoing_edges(node, EdgeLimit::Unlimited)
+                    .len() as u64;
+
+                (*node, degree)
+            })
+            .collect();
+
+        dht.next().out_degree.batch_upsert(U64Add, degrees);
+    }
+
+    fn reset(nodes: &[webgraph::NodeID], dht: &DhtConn<PageRankTables>) {
+        dht.next()
+            .contributions
+            .batch_set(nodes.iter().map(|node| (*node, 0.0)).collect());
+    }
+
+    /// send the rank from dht.prev of each node along its outgoing edges
+    /// and sum the contributions in dht.next
+    fn update_contributions(
+        worker: &PageRankWorker,
+        nodes: &[webgraph::NodeID],
+        num_nodes: u64,
+        dht: &DhtConn<PageRankTables>,
+    ) {
+        let ranks: BTreeMap<_, _> = dht
+            .prev()
+            .rank
+            .batch_get(nodes.to_vec())
+            .into_iter()
+            .collect();
+        let degrees: BTreeMap<_, _> = dht
+            .next()
+            .out_degree
+            .batch_get(nodes.to_vec())
+            .into_iter()
+            .collect();
+
+        let mut contributions: BTreeMap<webgraph::NodeID, f64> = BTreeMap::new();
+        let mut mass = 0.0;
+
+        for node in nodes {
+            let edges = worker
+                .graph()
+                .raw_outgoing_edges(node, EdgeLimit::Unlimited);
+
+            if edges.is_empty() {
+                continue;
+            }
+
+            // ranks are uniform before the first round
+            let rank = ranks.get(node).copied().unwrap_or(1.0 / num_nodes as f64);
+            let degree = degrees
+                .get(node)
+                .copied()
+                .unwrap_or(edges.len() as u64)
+                .max(1);
+
+            let share = DAMPING * rank / degree as f64;
+
+            for edge in edges {
+                *contributions.entry(edge.to).or_default() += share;
+                mass += share;
+            }
+        }
+
+        if contributions.is_empty() {
+            return;
+        }
+
+        dht.next()
+            .contributions
+            .batch_upsert(F64Add, contributions.into_iter().collect());
+        dht.next().mass.upsert(F64Add, (), mass);
+    }
+
+    /// the rank that was not sent along an edge is spread uniformly over all nodes
+    fn update_ranks(
+        nodes: &[webgraph::NodeID],
+        num_nodes: u64,
+        teleport: f64,
+        dht: &DhtConn<PageRankTables>,
+    ) {
+        let contributions: BTreeMap<_, _> = dht
+            .next()
+            .contributions
+            .batch_get(nodes.to_vec())
+            .into_iter()
+            .collect();
+        let old_ranks: BTreeMap<_, _> = dht
+            .prev()
+            .rank
+            .batch_get(nodes.to_vec())
+            .into_iter()
+            .collect();
+
+        let mut diff = 0.0;
+        let mut ranks = Vec::with_capacity(nodes.len());
+
+        for node in nodes {
+            let rank = contributions.get(node).copied().unwrap_or_default() + teleport;
+            let old_rank = old_ranks
+                .get(node)
+                .copied()
+                .unwrap_or(1.0 / num_nodes as f64);
+
+            diff += (rank - old_rank).abs();
+            ranks.push((*node, rank));
+        }
+
+        dht.next().rank.batch_set(ranks);
+
+        // nodes that are present on several workers are counted once per worker,
+        // which only makes the convergence check stricter
+        dht.next().diff.upsert(F64Add, (), diff);
+    }
+
+    fn for_each_batch<F>(worker: &PageRankWorker, dht: &DhtConn<PageRankTables>, f: F)
+    where
+        F: Fn(&[webgraph::NodeID]) + Sync,
+    {
+        // shards are the same for both prev and next
+        let num_shards = dht.prev().num_shards();
+        let batch_size = (num_shards * OPS_BATCH_PER_SHARD) as usize;
+
+        let pool = rayon::ThreadPoolBuilder::new().build().unwrap();
+
+        pool.scope(|s| {
+            let mut batch = Vec::with_capacity(batch_size);
+
+            for node in worker.graph().nodes() {
+                batch.push(node);
+
+                if batch.len() >= batch_size {
+                    let update_batch = batch.clone();
+                    let f = &f;
+                    s.spawn(move |_| f(&update_batch));
+
+                    batch.clear();
+                }
+            }
+
+            if !batch.is_empty() {
+                let f = &f;
+                s.spawn(move |_| f(&batch));
+            }
+        });
+    }
+
+    fn map_out_degrees(worker: &PageRankWorker, dht: &DhtConn<PageRankTables>) {
+        if dht.next().meta.get(()).unwrap().round != 1 {
+            return;
+        }
+
+        Self::for_each_batch(worker, dht, |nodes| {
+            Self::update_out_degrees(worker, nodes, dht)
+        });
+    }
+
+    fn map_reset(worker: &PageRankWorker, dht: &DhtConn<PageRankTables>) {
+        dht.next().mass.set((), 0.0);
+        dht.next().diff.set((), 0.0);
+
+        Self::for_each_batch(worker, dht, |nodes| Self::reset(nodes, dht));
+    }
+
+    fn map_contributions(worker: &PageRankWorker, dht: &DhtConn<PageRankTables>) {
+        let num_nodes = dht.next().num_nodes();
+
+        Self::for_each_batch(worker, dht, |nodes| {
+            Self::update_contributions(worker, nodes, num_nodes, dht)
+        });
+    }
+
+    fn map_ranks(worker: &PageRankWorker, dht: &DhtConn<PageRankTables>) {
+        let num_nodes = dht.next().num_nodes();
+        let mass = dht.next().mass.get(()).unwrap_or_default();
+        let teleport = (1.0 - mass) / num_nodes as f64;
+
+        Self::for_each_batch(worker, dht, |nodes| {
+            Self::update_ranks(nodes, num_nodes, teleport, dht)
+        });
+    }
+}
+
+impl Mapper for PageRankMapper {
+    type Job = PageRankJob;
+
+    fn map(&self, _: Self::Job, worker: &PageRankWorker, dht: &DhtConn<PageRankTables>) {
+        match self {
+            PageRankMapper::OutDegrees => Self::map_out_degrees(worker, dht),
+            PageRankMapper::Reset => Self::map_reset(worker, dht),
+            PageRankMapper::Contributions => Self::map_contributions(worker, dht),
+            PageRankMapper::Ranks => Self::map_ranks(worker, dht),
+        }
+    }
+}
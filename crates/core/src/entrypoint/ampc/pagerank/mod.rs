@@ -0,0 +1,193 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Distributed PageRank. Every round each worker sends the rank of its nodes along
+//! their outgoing edges and the contributions are summed in the DHT with [`F64Add`].
+//! The rank mass that is not sent along an edge (teleports and dangling nodes)
+//! is spread uniformly over all nodes. The computation stops when the L1 distance
+//! between the ranks of two consecutive rounds is below [`TOLERANCE`].
+//!
+//! [`F64Add`]: crate::ampc::dht::F64Add
+
+use crate::ampc::prelude::*;
+
+use crate::distributed::member::ShardId;
+use crate::webgraph::centrality::pagerank::{MAX_ITERATIONS, TOLERANCE};
+use crate::{ampc::DefaultDhtTable, webgraph};
+
+pub mod coordinator;
+mod mapper;
+pub mod worker;
+
+pub use coordinator::{PageRankFinish, PageRankSetup};
+pub use mapper::PageRankMapper;
+pub use worker::{PageRankWorker, RemotePageRankWorker};
+
+#[derive(serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode, Debug, Clone)]
+pub struct Meta {
+    round: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode, Debug, Clone)]
+pub struct PageRankTables {
+    meta: DefaultDhtTable<(), Meta>,
+    out_degree: DefaultDhtTable<webgraph::NodeID, u64>,
+    contributions: DefaultDhtTable<webgraph::NodeID, f64>,
+    rank: DefaultDhtTable<webgraph::NodeID, f64>,
+    /// total rank sent along edges in the round
+    mass: DefaultDhtTable<(), f64>,
+    /// L1 distance between the ranks of the previous and the current round
+    diff: DefaultDhtTable<(), f64>,
+}
+
+impl PageRankTables {
+    pub fn num_shards(&self) -> u64 {
+        self.rank.shards().len() as u64
+    }
+
+    pub fn num_nodes(&self) -> u64 {
+        self.out_degree.num_keys()
+    }
+
+    pub fn has_converged(&self) -> bool {
+        self.meta.get(()).unwrap().round >= MAX_ITERATIONS as u64
+            || self.diff.get(()).is_some_and(|diff| diff < TOLERANCE)
+    }
+}
+
+impl_dht_tables!(
+    PageRankTables,
+    [meta, out_degree, contributions, rank, mass, diff]
+);
+
+#[derive(serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode, Debug, Clone)]
+pub struct PageRankJob {
+    shard: ShardId,
+}
+
+impl Job for PageRankJob {
+    type DhtTables = PageRankTables;
+    type Worker = PageRankWorker;
+    type Mapper = PageRankMapper;
+
+    fn is_schedulable(&self, worker: &RemotePageRankWorker) -> bool {
+        self.shard == worker.shard()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use crate::{
+        executor::Executor,
+        free_socket_addr,
+        webgraph::{centrality::pagerank::PageRank, Compression, WebgraphWriter},
+    };
+
+    use super::*;
+
+    #[test]
+    #[traced_test]
+    fn test_simple_graph() {
+        let mut combined = WebgraphWriter::new(
+            crate::gen_temp_path(),
+            Executor::single_thread(),
+            Compression::default(),
+            None,
+        );
+        let mut a = WebgraphWriter::new(
+            crate::gen_temp_path(),
+            Executor::single_thread(),
+            Compression::default(),
+            None,
+        );
+        let mut b = WebgraphWriter::new(
+            crate::gen_temp_path(),
+            Executor::single_thread(),
+            Compression::default(),
+            None,
+        );
+
+        let edges = crate::webgraph::tests::test_edges();
+
+        for (i, (from, to, label)) in edges.into_iter().enumerate() {
+            combined.insert(from.clone(), to.clone(), label.clone());
+
+            if i % 2 == 0 {
+                a.insert(from, to, label);
+            } else {
+                b.insert(from, to, label);
+            }
+        }
+
+        combined.commit();
+        a.commit();
+        b.commit();
+
+        let combined = combined.finalize();
+        let a = a.finalize();
+        let b = b.finalize();
+
+        let expected = PageRank::build(&combined, crate::gen_temp_path());
+
+        let worker = PageRankWorker::new(1.into(), a);
+        let worker_addr = free_socket_addr();
+        std::thread::spawn(move || {
+            worker.run(worker_addr).unwrap();
+        });
+
+        std::thread::sleep(std::time::Duration::from_secs(2)); // Wait for worker to start
+        let a = RemotePageRankWorker::new(1.into(), worker_addr);
+
+        let worker = PageRankWorker::new(2.into(), b);
+        let worker_addr = free_socket_addr();
+        std::thread::spawn(move || {
+            worker.run(worker_addr).unwrap();
+        });
+
+        std::thread::sleep(std::time::Duration::from_secs(2)); // Wait for worker to start
+        let b = RemotePageRankWorker::new(2.into(), worker_addr);
+
+        let (dht_shard, dht_addr) = crate::entrypoint::ampc::dht::tests::setup();
+        let res = coordinator::build(&[(dht_shard, dht_addr)], vec![a, b])
+            .run(
+                vec![
+                    PageRankJob { shard: 1.into() },
+                    PageRankJob { shard: 2.into() },
+                ],
+                PageRankFinish,
+            )
+            .unwrap();
+
+        let mut actual = res.rank.iter().collect::<Vec<_>>();
+        let mut expected = expected.iter().collect::<Vec<_>>();
+
+        actual.sort_by(|a, b| a.0.cmp(&b.0));
+        expected.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(actual.len(), expected.len());
+
+        for ((expected_node, expected), (actual_node, actual)) in expected.iter().zip(actual.iter())
+        {
+            assert_eq!(expected_node, actual_node);
+            assert!((expected - actual).abs() < 0.0001);
+        }
+
+        let sum: f64 = actual.iter().map(|(_, rank)| rank).sum();
+        assert!((sum - 1.0).abs() < 0.0001);
+    }
+}
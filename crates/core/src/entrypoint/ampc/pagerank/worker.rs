@@ -0,0 +1,122 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+use crate::{
+    ampc::prelude::*,
+    config::PageRankWorkerConfig,
+    distributed::member::{Service, ShardId},
+    webgraph::{self, Webgraph},
+    Result,
+};
+use std::net::SocketAddr;
+
+use super::PageRankJob;
+
+pub struct PageRankWorker {
+    shard: ShardId,
+    graph: Webgraph,
+}
+
+impl PageRankWorker {
+    pub fn new(shard: ShardId, graph: Webgraph) -> Self {
+        Self { shard, graph }
+    }
+
+    pub fn shard(&self) -> ShardId {
+        self.shard
+    }
+
+    pub fn graph(&self) -> &Webgraph {
+        &self.graph
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode, Debug, Clone)]
+pub struct GetShard;
+
+impl Message<PageRankWorker> for GetShard {
+    type Response = ShardId;
+
+    fn handle(self, worker: &PageRankWorker) -> Self::Response {
+        worker.shard
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode, Debug, Clone)]
+pub struct BatchId2Node(Vec<webgraph::NodeID>);
+
+impl Message<PageRankWorker> for BatchId2Node {
+    type Response = Vec<(webgraph::NodeID, webgraph::Node)>;
+
+    fn handle(self, worker: &PageRankWorker) -> Self::Response {
+        self.0
+            .iter()
+            .filter_map(|id| worker.graph.id2node(id).map(|node| (*id, node)))
+            .collect()
+    }
+}
+
+impl_worker!(PageRankJob, RemotePageRankWorker => PageRankWorker, [GetShard, BatchId2Node]);
+
+#[derive(Clone)]
+pub struct RemotePageRankWorker {
+    shard: ShardId,
+    addr: SocketAddr,
+}
+
+impl RemotePageRankWorker {
+    pub fn new(shard: ShardId, addr: SocketAddr) -> Self {
+        Self { shard, addr }
+    }
+
+    pub fn shard(&self) -> ShardId {
+        self.shard
+    }
+
+    pub fn batch_id2node(
+        &self,
+        id: Vec<webgraph::NodeID>,
+    ) -> Vec<(webgraph::NodeID, webgraph::Node)> {
+        self.send(BatchId2Node(id))
+    }
+}
+
+impl RemoteWorker for RemotePageRankWorker {
+    type Job = PageRankJob;
+
+    fn remote_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+pub fn run(config: PageRankWorkerConfig) -> Result<()> {
+    let tokio_conf = config.clone();
+
+    let graph = Webgraph::builder(config.graph_path)
+        .single_threaded()
+        .link_filter(config.link_filter)
+        .open();
+    let worker = PageRankWorker::new(config.shard, graph);
+    let service = Service::PageRankWorker {
+        host: tokio_conf.host,
+        shard: tokio_conf.shard,
+    };
+    crate::start_gossip_cluster_thread(tokio_conf.gossip, Some(service));
+
+    worker.run(config.host)?;
+
+    Ok(())
+}
@@ -23,9 +23,12 @@ use crate::{
     api::{metrics_router, router, user_count, Counters},
     config,
     metrics::Label,
+    webpage::region::RegionTable,
 };
 
 pub async fn run(config: config::ApiConfig) -> Result<()> {
+    RegionTable::install_from_path(config.regions_path.as_deref())?;
+
     let search_counter_success = crate::metrics::Counter::default();
     let search_counter_fail = crate::metrics::Counter::default();
     let explore_counter = crate::metrics::Counter::default();
@@ -45,17 +45,37 @@ impl Worker {
         let warc_file = WarcFile::download(&job.warc_source, &job.warc_path)?;
 
         for record in warc_file.records().flatten() {
-            let webpage = match Html::parse_without_text(&record.response.body, &record.request.url)
-            {
-                Ok(webpage) => webpage,
-                Err(err) => {
-                    tracing::error!("error parsing webpage: {}", err);
-                    continue;
+            if record.response.is_redirect() {
+                let url = Url::parse(&record.request.url)?;
+
+                if let Some(target) = record.response.permanent_redirect(&url) {
+                    self.index.insert_permanent_redirect(url, target)?;
                 }
-            };
 
-            if let Some(canonical_url) = webpage.canonical_url() {
-                let url = Url::parse(&record.request.url)?;
+                continue;
+            }
+
+            // the page was indexed from the capture the response refers to
+            if record.response.is_not_modified() {
+                continue;
+            }
+
+            let webpage =
+                match Html::parse_without_text(&record.response.text(), &record.request.url) {
+                    Ok(webpage) => webpage,
+                    Err(err) => {
+                        tracing::error!("error parsing webpage: {}", err);
+                        continue;
+                    }
+                };
+
+            let url = Url::parse(&record.request.url)?;
+
+            // the canonical link element takes precedence over the http `Link` header
+            if let Some(canonical_url) = webpage
+                .canonical_url()
+                .or_else(|| record.response.headers.canonical_url(&url))
+            {
                 self.index.insert(url, canonical_url)?;
             }
         }
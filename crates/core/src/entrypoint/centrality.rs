@@ -21,10 +21,10 @@ use crate::{
     external_sort::ExternalSorter,
     webgraph::{
         centrality::{
-            approx_harmonic::ApproxHarmonic, harmonic::HarmonicCentrality, store_csv,
-            store_harmonic, TopNodes,
+            approx_harmonic::ApproxHarmonic, harmonic::HarmonicCentrality, pagerank::PageRank,
+            store_csv, store_harmonic, TopNodes,
         },
-        WebgraphBuilder,
+        LinkFilter, Node, NodeID, Webgraph, WebgraphBuilder,
     },
     SortableFloat,
 };
@@ -32,12 +32,19 @@ use crate::{
 pub struct Centrality;
 
 impl Centrality {
-    pub fn build_harmonic<P: AsRef<Path>>(webgraph_path: P, base_output: P) {
+    pub fn build_harmonic<P: AsRef<Path>>(
+        webgraph_path: P,
+        base_output: P,
+        link_filter: LinkFilter,
+    ) {
         tracing::info!(
             "Building harmonic centrality for {}",
             webgraph_path.as_ref().to_str().unwrap()
         );
-        let graph = WebgraphBuilder::new(webgraph_path).single_threaded().open();
+        let graph = WebgraphBuilder::new(webgraph_path)
+            .single_threaded()
+            .link_filter(link_filter)
+            .open();
         let harmonic_centrality = HarmonicCentrality::calculate(&graph);
         let store = store_harmonic(
             harmonic_centrality.iter().map(|(n, c)| (*n, c)),
@@ -53,49 +60,118 @@ impl Centrality {
         store_csv(top_harmonics, base_output.as_ref().join("harmonic.csv"));
     }
 
-    pub fn build_approx_harmonic<P: AsRef<Path>>(webgraph_path: P, base_output: P) -> Result<()> {
+    pub fn build_approx_harmonic<P: AsRef<Path>>(
+        webgraph_path: P,
+        base_output: P,
+        link_filter: LinkFilter,
+    ) -> Result<()> {
         tracing::info!(
             "Building approximated harmonic centrality for {}",
             webgraph_path.as_ref().to_str().unwrap()
         );
 
-        let graph = WebgraphBuilder::new(webgraph_path).single_threaded().open();
+        let graph = WebgraphBuilder::new(webgraph_path)
+            .single_threaded()
+            .link_filter(link_filter)
+            .open();
 
         let approx = ApproxHarmonic::build(&graph, base_output.as_ref().join("approx_harmonic"));
-        let mut approx_rank: speedy_kv::Db<crate::webgraph::NodeID, u64> =
-            speedy_kv::Db::open_or_create(base_output.as_ref().join("approx_harmonic_rank"))
-                .unwrap();
-
-        let mut top_nodes = Vec::new();
-
-        for (rank, node, centrality) in ExternalSorter::new()
-            .with_chunk_size(100_000_000)
-            .sort(
-                approx
-                    .iter()
-                    .map(|(node_id, centrality)| (Reverse(SortableFloat(centrality)), node_id)),
-            )?
-            .enumerate()
-            .map(|(rank, (Reverse(SortableFloat(centrality)), node_id))| {
-                (rank, node_id, centrality)
-            })
-        {
-            approx_rank.insert(node, rank as u64).unwrap();
-
-            if approx_rank.uncommitted_inserts() > 1_000_000 {
-                approx_rank.commit().unwrap();
+
+        store_rank(
+            &graph,
+            approx.iter(),
+            base_output.as_ref(),
+            "approx_harmonic",
+        )
+    }
+
+    /// Calculate PageRank for the graph. If `trusted_hosts` is given, the PageRank
+    /// is personalised to the hosts listed in the file (one host per line).
+    ///
+    /// The indexer reads the ranks of a host graph from `pagerank_rank` and
+    /// `personalized_pagerank_rank` in its host centrality folder.
+    pub fn build_pagerank<P: AsRef<Path>>(
+        webgraph_path: P,
+        base_output: P,
+        trusted_hosts: Option<P>,
+        link_filter: LinkFilter,
+    ) -> Result<()> {
+        tracing::info!(
+            "Building pagerank for {}",
+            webgraph_path.as_ref().to_str().unwrap()
+        );
+
+        let graph = WebgraphBuilder::new(webgraph_path)
+            .single_threaded()
+            .link_filter(link_filter)
+            .open();
+
+        match trusted_hosts {
+            Some(trusted_hosts) => {
+                let trusted_hosts: Vec<Node> = std::fs::read_to_string(trusted_hosts)?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(Node::from)
+                    .collect();
+
+                let pagerank = PageRank::build_personalized(
+                    &graph,
+                    &trusted_hosts,
+                    base_output.as_ref().join("personalized_pagerank"),
+                );
+
+                store_rank(
+                    &graph,
+                    pagerank.iter(),
+                    base_output.as_ref(),
+                    "personalized_pagerank",
+                )
             }
+            None => {
+                let pagerank = PageRank::build(&graph, base_output.as_ref().join("pagerank"));
 
-            if top_nodes.len() < 1_000_000 {
-                top_nodes.push((graph.id2node(&node).unwrap(), centrality));
+                store_rank(&graph, pagerank.iter(), base_output.as_ref(), "pagerank")
             }
         }
+    }
+}
 
-        approx_rank.commit().unwrap();
-        approx_rank.merge_all_segments().unwrap();
-
-        store_csv(top_nodes, base_output.as_ref().join("approx_harmonic.csv"));
+/// Store the rank of each node in `{name}_rank` and the top nodes in `{name}.csv`.
+fn store_rank(
+    graph: &Webgraph,
+    centralities: impl Iterator<Item = (NodeID, f64)>,
+    base_output: &Path,
+    name: &str,
+) -> Result<()> {
+    let mut rank_store: speedy_kv::Db<NodeID, u64> =
+        speedy_kv::Db::open_or_create(base_output.join(format!("{name}_rank"))).unwrap();
+
+    let mut top_nodes = Vec::new();
+
+    for (rank, node, centrality) in ExternalSorter::new()
+        .with_chunk_size(100_000_000)
+        .sort(
+            centralities.map(|(node_id, centrality)| (Reverse(SortableFloat(centrality)), node_id)),
+        )?
+        .enumerate()
+        .map(|(rank, (Reverse(SortableFloat(centrality)), node_id))| (rank, node_id, centrality))
+    {
+        rank_store.insert(node, rank as u64).unwrap();
+
+        if rank_store.uncommitted_inserts() > 1_000_000 {
+            rank_store.commit().unwrap();
+        }
 
-        Ok(())
+        if top_nodes.len() < 1_000_000 {
+            top_nodes.push((graph.id2node(&node).unwrap(), centrality));
+        }
     }
+
+    rank_store.commit().unwrap();
+    rank_store.merge_all_segments().unwrap();
+
+    store_csv(top_nodes, base_output.join(format!("{name}.csv")));
+
+    Ok(())
 }
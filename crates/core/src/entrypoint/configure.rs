@@ -25,11 +25,12 @@ use crate::config::{
 };
 use crate::entrypoint::indexer::JobSettings;
 use crate::entrypoint::{dmoz_parser, indexer};
+use crate::webgraph::LinkFilter;
 use crate::Result;
 use std::fs::{self};
 use std::path::Path;
 
-use super::{webgraph, Centrality, EntityIndexer};
+use super::{webgraph, Centrality, EntityIndexer, Webgraph};
 
 const DATA_PATH: &str = "data";
 const BUCKET_NAME: &str = "public";
@@ -142,14 +143,26 @@ fn calculate_centrality() {
     let out_path = Path::new(DATA_PATH).join("centrality");
 
     if !out_path.exists() {
-        Centrality::build_harmonic(&webgraph_path, &out_path);
+        Centrality::build_harmonic(&webgraph_path, &out_path, LinkFilter::default());
     }
 
     let webgraph_page = Path::new(DATA_PATH).join("webgraph_page");
     let out_path_page = Path::new(DATA_PATH).join("centrality_page");
 
     if !out_path_page.exists() {
-        Centrality::build_approx_harmonic(webgraph_page, out_path_page).unwrap();
+        Centrality::build_approx_harmonic(webgraph_page, out_path_page, LinkFilter::default())
+            .unwrap();
+    }
+}
+
+fn calculate_clusters() {
+    debug!("Calculating clusters");
+    let webgraph_path = Path::new(DATA_PATH).join("webgraph_host");
+    let out_path = Path::new(DATA_PATH).join("webgraph_host_clusters");
+    let centrality_path = Path::new(DATA_PATH).join("centrality");
+
+    if !out_path.exists() {
+        Webgraph::clusters(&webgraph_path, &out_path, Some(&centrality_path));
     }
 }
 
@@ -207,6 +220,7 @@ fn create_inverted_index() -> Result<()> {
         host_centrality_threshold: None,
         safety_classifier_path: None,
         minimum_clean_words: None,
+        regions_path: None,
         batch_size: defaults::Indexing::batch_size(),
         autocommit_after_num_inserts: defaults::Indexing::autocommit_after_num_inserts(),
         dual_encoder: Some(IndexingDualEncoderConfig {
@@ -248,6 +262,7 @@ fn parse_topics() -> Result<()> {
 fn index_files() -> Result<()> {
     create_webgraph()?;
     calculate_centrality();
+    calculate_clusters();
     parse_topics()?;
     create_inverted_index()?;
     create_entity_index()?;
@@ -14,11 +14,11 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use crate::{
     config,
-    crawler::{self, planner::CrawlPlanner, CrawlCoordinator, Crawler},
+    crawler::{self, planner::CrawlPlanner, recrawl::RecrawlScheduler, CrawlCoordinator, Crawler},
     distributed::sonic::service::{sonic_service, Message},
     webgraph::WebgraphBuilder,
     Result,
@@ -32,8 +32,33 @@ pub async fn worker(config: config::CrawlerConfig) -> Result<()> {
     Ok(())
 }
 
+/// How often the frontier of a coordinator is persisted.
+const FRONTIER_COMMIT_INTERVAL: Duration = Duration::from_secs(60);
+
 pub async fn coordinator(config: config::CrawlCoordinatorConfig) -> Result<()> {
-    let coordinator = Arc::new(CrawlCoordinator::new(config.job_queue)?);
+    let coordinator = Arc::new(match config.frontier {
+        Some(frontier) => CrawlCoordinator::with_frontier(config.job_queue, frontier)?,
+        None => CrawlCoordinator::new(config.job_queue)?,
+    });
+
+    tokio::spawn({
+        let coordinator = Arc::clone(&coordinator);
+
+        async move {
+            let mut interval = tokio::time::interval(FRONTIER_COMMIT_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                let coordinator = Arc::clone(&coordinator);
+                match tokio::task::spawn_blocking(move || coordinator.commit()).await {
+                    Ok(Err(err)) => tracing::error!("failed to commit frontier: {:?}", err),
+                    Err(err) => tracing::error!("failed to commit frontier: {:?}", err),
+                    Ok(Ok(())) => {}
+                }
+            }
+        }
+    });
 
     let addr: SocketAddr = config.host;
     let server = coordinator::CoordinatorService { coordinator }
@@ -71,7 +96,13 @@ pub fn planner(config: config::CrawlPlannerConfig) -> Result<()> {
     let host_graph = WebgraphBuilder::new(&config.host_graph_path).open();
     let output_path = config.output_path.clone();
 
-    let planner = CrawlPlanner::new(
+    let recrawl = config
+        .recrawl_db_path
+        .as_ref()
+        .map(RecrawlScheduler::open)
+        .transpose()?;
+
+    let mut planner = CrawlPlanner::new(
         host_centrality,
         page_centrality,
         host_graph,
@@ -79,20 +110,24 @@ pub fn planner(config: config::CrawlPlannerConfig) -> Result<()> {
         config,
     )?;
 
+    if let Some(recrawl) = recrawl {
+        planner = planner.with_recrawl_scheduler(recrawl);
+    }
+
     planner.build(output_path)?;
 
     Ok(())
 }
 
 pub mod router {
-    use crate::crawler::Job;
+    use crate::crawler::{DiscoveredUrls, DomainCrawled, JobResponse};
 
     use super::*;
     pub struct RouterService {
         pub router: crawler::Router,
     }
 
-    sonic_service!(RouterService, [NewJob]);
+    sonic_service!(RouterService, [NewJob, DiscoveredUrls, DomainCrawled]);
 
     #[derive(
         Debug, Clone, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode,
@@ -100,16 +135,43 @@ pub mod router {
     pub struct NewJob {}
 
     impl Message<RouterService> for NewJob {
-        type Response = Option<Job>;
+        type Response = JobResponse;
 
         async fn handle(self, server: &RouterService) -> Self::Response {
-            server.router.sample_job().await.ok().flatten()
+            match server.router.sample_job().await {
+                Ok(res) => res,
+                // the coordinators might be temporarily unavailable
+                Err(err) => {
+                    tracing::error!("failed to sample job: {:?}", err);
+                    JobResponse::Wait
+                }
+            }
+        }
+    }
+
+    impl Message<RouterService> for DiscoveredUrls {
+        type Response = ();
+
+        async fn handle(self, server: &RouterService) -> Self::Response {
+            if let Err(err) = server.router.add_discovered(self).await {
+                tracing::error!("failed to add discovered urls: {:?}", err);
+            }
+        }
+    }
+
+    impl Message<RouterService> for DomainCrawled {
+        type Response = ();
+
+        async fn handle(self, server: &RouterService) -> Self::Response {
+            if let Err(err) = server.router.domain_crawled(self).await {
+                tracing::error!("failed to report crawled domain: {:?}", err);
+            }
         }
     }
 }
 
 pub mod coordinator {
-    use crate::crawler::Job;
+    use crate::crawler::{DiscoveredUrls, DomainCrawled, JobResponse};
 
     use super::*;
 
@@ -117,7 +179,7 @@ pub mod coordinator {
         pub coordinator: Arc<CrawlCoordinator>,
     }
 
-    sonic_service!(CoordinatorService, [GetJob]);
+    sonic_service!(CoordinatorService, [GetJob, DiscoveredUrls, DomainCrawled]);
 
     #[derive(
         Debug, Clone, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode,
@@ -125,10 +187,34 @@ pub mod coordinator {
     pub struct GetJob {}
 
     impl Message<CoordinatorService> for GetJob {
-        type Response = Option<Job>;
+        type Response = JobResponse;
+
+        async fn handle(self, server: &CoordinatorService) -> Self::Response {
+            match server.coordinator.sample_job() {
+                Ok(res) => res,
+                // the error might be transient, e.g. a failed read from the job queue,
+                // so the workers should not give up on the coordinator
+                Err(err) => {
+                    tracing::error!("failed to sample job: {:?}", err);
+                    JobResponse::Wait
+                }
+            }
+        }
+    }
+
+    impl Message<CoordinatorService> for DiscoveredUrls {
+        type Response = ();
+
+        async fn handle(self, server: &CoordinatorService) -> Self::Response {
+            server.coordinator.add_discovered(self);
+        }
+    }
+
+    impl Message<CoordinatorService> for DomainCrawled {
+        type Response = ();
 
         async fn handle(self, server: &CoordinatorService) -> Self::Response {
-            server.coordinator.sample_job().ok().flatten()
+            server.coordinator.domain_crawled(self);
         }
     }
 }
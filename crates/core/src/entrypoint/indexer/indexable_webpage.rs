@@ -15,20 +15,41 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::crawler::CrawlDatum;
-use crate::warc::WarcRecord;
+use crate::warc::{HttpHeaders, PayloadType, WarcRecord};
+
+/// The content of a page that can be indexed.
+#[derive(Debug, Clone)]
+pub enum Body {
+    Html(String),
+    Pdf(Vec<u8>),
+}
 
 pub struct IndexableWebpage {
     pub url: String,
-    pub body: String,
+    pub body: Body,
     pub fetch_time_ms: u64,
+    pub request_headers: HttpHeaders,
+    pub response_headers: HttpHeaders,
+}
+
+impl IndexableWebpage {
+    /// The user agent the page was fetched with, if it was recorded.
+    pub fn user_agent(&self) -> Option<&str> {
+        self.request_headers.get("user-agent")
+    }
 }
 
 impl From<CrawlDatum> for IndexableWebpage {
     fn from(datum: CrawlDatum) -> Self {
         Self {
             url: datum.url.to_string(),
-            body: datum.body,
+            body: match datum.payload_type {
+                PayloadType::Pdf => Body::Pdf(datum.body),
+                _ => Body::Html(String::from_utf8_lossy(&datum.body).into_owned()),
+            },
             fetch_time_ms: datum.fetch_time_ms,
+            request_headers: datum.request_headers,
+            response_headers: datum.response_headers,
         }
     }
 }
@@ -37,8 +58,13 @@ impl From<WarcRecord> for IndexableWebpage {
     fn from(record: WarcRecord) -> Self {
         Self {
             url: record.request.url,
-            body: record.response.body,
+            body: match record.response.payload_type {
+                Some(PayloadType::Pdf) => Body::Pdf(record.response.payload().into_owned()),
+                _ => Body::Html(record.response.text()),
+            },
             fetch_time_ms: record.metadata.fetch_time_ms,
+            request_headers: record.request.headers,
+            response_headers: record.response.headers,
         }
     }
 }
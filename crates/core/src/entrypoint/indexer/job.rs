@@ -71,8 +71,12 @@ impl Job {
             for chunk in &file
                 .records()
                 .flatten()
+                .filter(|record| !record.response.is_redirect())
+                .filter(|record| !record.response.is_not_modified())
                 .filter(|record| match &record.response.payload_type {
-                    Some(payload_type) => matches!(payload_type, PayloadType::Html),
+                    Some(payload_type) => {
+                        matches!(payload_type, PayloadType::Html | PayloadType::Pdf)
+                    }
                     None => true,
                 })
                 .chunks(self.settings.batch_size)
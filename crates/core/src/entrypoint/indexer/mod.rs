@@ -23,12 +23,13 @@ use std::thread;
 
 use itertools::Itertools;
 
-pub use crate::entrypoint::indexer::indexable_webpage::IndexableWebpage;
+pub use crate::entrypoint::indexer::indexable_webpage::{Body, IndexableWebpage};
 pub use crate::entrypoint::indexer::job::{Job, JobSettings};
 pub use crate::entrypoint::indexer::worker::IndexingWorker;
 
 use crate::config::{self, WarcSource};
 use crate::index::Index;
+use crate::webpage::region::RegionTable;
 use crate::Result;
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode)]
@@ -41,6 +42,8 @@ impl From<String> for IndexPointer {
 }
 
 pub fn run(config: &config::IndexingLocalConfig) -> Result<()> {
+    RegionTable::install_from_path(config.regions_path.as_deref())?;
+
     let warc_paths = config.warc_source.paths()?;
 
     let job_config: WarcSource = config.warc_source.clone();
@@ -20,7 +20,7 @@ use std::path::Path;
 
 use tracing::debug;
 
-pub use super::indexable_webpage::IndexableWebpage;
+pub use super::indexable_webpage::{Body, IndexableWebpage};
 pub use super::job::{Job, JobSettings};
 use crate::config::{
     IndexingDualEncoderConfig, IndexingGraphConfig, IndexingLocalConfig, LiveIndexConfig,
@@ -35,7 +35,7 @@ use crate::index::Index;
 use crate::rake::RakeModel;
 use crate::ranking::SignalComputer;
 use crate::webgraph::{self, EdgeLimit, Node, NodeID};
-use crate::webpage::{safety_classifier, Html, Webpage};
+use crate::webpage::{pdf::Pdf, safety_classifier, Html, Webpage};
 
 pub struct Config {
     pub host_centrality_store_path: String,
@@ -109,7 +109,7 @@ impl Webgraph {
             .map(|edges| {
                 edges
                     .into_iter()
-                    .map(|edge| edge.label)
+                    .map(|edge| edge.label.text)
                     .filter(|label| !label.is_empty())
                     .filter(|label| {
                         let label = label.to_lowercase();
@@ -162,6 +162,8 @@ impl Webgraph {
 pub struct IndexingWorker {
     host_centrality_store: speedy_kv::Db<NodeID, f64>,
     host_centrality_rank_store: speedy_kv::Db<NodeID, u64>,
+    host_pagerank_rank_store: speedy_kv::Db<NodeID, u64>,
+    host_trustrank_rank_store: speedy_kv::Db<NodeID, u64>,
     page_centrality_store: Option<speedy_kv::Db<NodeID, f64>>,
     page_centrality_rank_store: Option<speedy_kv::Db<NodeID, u64>>,
     page_webgraph: Option<Webgraph>,
@@ -188,6 +190,14 @@ impl IndexingWorker {
                 Path::new(&config.host_centrality_store_path).join("harmonic_rank"),
             )
             .unwrap(),
+            host_pagerank_rank_store: speedy_kv::Db::open_or_create(
+                Path::new(&config.host_centrality_store_path).join("pagerank_rank"),
+            )
+            .unwrap(),
+            host_trustrank_rank_store: speedy_kv::Db::open_or_create(
+                Path::new(&config.host_centrality_store_path).join("personalized_pagerank_rank"),
+            )
+            .unwrap(),
             page_centrality_store: config.page_centrality_store_path.as_ref().map(|p| {
                 speedy_kv::Db::open_or_create(Path::new(&p).join("approx_harmonic")).unwrap()
             }),
@@ -236,13 +246,23 @@ impl IndexingWorker {
     }
 
     fn prepare(&self, page: &IndexableWebpage) -> Result<Webpage> {
-        let html = match Html::parse_without_text(&page.body, &page.url) {
-            Ok(html) => html,
-            Err(err) => {
-                return Err(anyhow::anyhow!("error parsing html: {:?}", err));
-            }
+        let mut html = match &page.body {
+            Body::Html(body) => match Html::parse_without_text(body, &page.url) {
+                Ok(html) => html,
+                Err(err) => {
+                    return Err(anyhow::anyhow!("error parsing html: {:?}", err));
+                }
+            },
+            Body::Pdf(body) => match Pdf::parse(body).and_then(|pdf| pdf.into_html(&page.url)) {
+                Ok(html) => html,
+                Err(err) => {
+                    return Err(anyhow::anyhow!("error parsing pdf: {:?}", err));
+                }
+            },
         };
 
+        html.apply_http_headers(&page.response_headers, page.user_agent());
+
         if html.is_no_index() {
             return Err(anyhow::anyhow!("noindex"));
         }
@@ -284,6 +304,17 @@ impl IndexingWorker {
         page.host_centrality = host_centrality;
         page.host_centrality_rank = host_centrality_rank;
 
+        page.host_pagerank_rank = self
+            .host_pagerank_rank_store
+            .get(&host_node_id)
+            .unwrap()
+            .unwrap_or(u64::MAX);
+        page.host_trustrank_rank = self
+            .host_trustrank_rank_store
+            .get(&host_node_id)
+            .unwrap()
+            .unwrap_or(u64::MAX);
+
         if !page.host_centrality.is_finite() {
             page.host_centrality = 0.0;
         }
@@ -463,6 +494,8 @@ impl IndexingWorker {
                 page_centrality_rank: prepared.page_centrality_rank,
                 host_centrality: prepared.host_centrality,
                 host_centrality_rank: prepared.host_centrality_rank,
+                host_pagerank_rank: prepared.host_pagerank_rank,
+                host_trustrank_rank: prepared.host_trustrank_rank,
                 fetch_time_ms: page.fetch_time_ms,
                 pre_computed_score: 0.0,
                 node_id: prepared.node_id,
@@ -491,6 +524,7 @@ impl IndexingWorker {
 #[cfg(test)]
 mod tests {
     use crate::config::WarcSource;
+    use crate::warc::HttpHeaders;
 
     use super::*;
 
@@ -514,6 +548,7 @@ mod tests {
             }),
             host_centrality_threshold: None,
             minimum_clean_words: None,
+            regions_path: None,
             batch_size: 10,
             autocommit_after_num_inserts:
                 crate::config::defaults::Indexing::autocommit_after_num_inserts(),
@@ -532,15 +567,21 @@ mod tests {
         let webpages = vec![
             IndexableWebpage {
                 url: "https://a.com".to_string(),
-                body: "<html><head><title>Homemade Heart Brownie Recipe</title></head><body>Example</body></html>"
-                    .to_string(),
+                body: Body::Html(
+                    "<html><head><title>Homemade Heart Brownie Recipe</title></head><body>Example</body></html>".to_string(),
+                ),
                 fetch_time_ms: 0,
+                request_headers: HttpHeaders::new(),
+                response_headers: HttpHeaders::new(),
             },
             IndexableWebpage {
                 url: "https://b.com".to_string(),
-                body: "<html><head><title>How To Use an iMac as a Monitor for a PC</title></head><body>Example</body></html>"
-                    .to_string(),
+                body: Body::Html(
+                    "<html><head><title>How To Use an iMac as a Monitor for a PC</title></head><body>Example</body></html>".to_string(),
+                ),
                 fetch_time_ms: 0,
+                request_headers: HttpHeaders::new(),
+                response_headers: HttpHeaders::new(),
             },
         ];
 
@@ -28,6 +28,7 @@ use crate::{
     live_index::{Index, IndexManager},
     searcher::{InitialWebsiteResult, LocalSearcher},
     webgraph::WebgraphBuilder,
+    webpage::region::RegionTable,
 };
 use anyhow::Result;
 use tracing::info;
@@ -88,6 +89,8 @@ impl sonic::service::Message<SearchService> for Search {
 }
 
 pub async fn serve(config: LiveIndexConfig) -> Result<()> {
+    RegionTable::install_from_path(config.regions_path.as_deref())?;
+
     let addr = config.host;
 
     let server = SearchService::new(config).await?.bind(&addr).await.unwrap();
@@ -28,6 +28,7 @@ mod entity;
 pub mod entity_search_server;
 pub mod feed_indexer;
 pub mod indexer;
+pub mod optic_test;
 pub mod safety_classifier;
 pub mod search_server;
 pub mod web_spell;
@@ -0,0 +1,80 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+
+use crate::{optic_test::OpticTest, Result};
+
+/// The test files to run. Directories are expanded to all the `.toml` files they contain.
+fn test_files(paths: &[String]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for path in paths {
+        let path = Path::new(path);
+
+        if path.is_dir() {
+            let mut dir_files = std::fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<Vec<_>>>()?;
+            dir_files.retain(|file| file.extension().is_some_and(|ext| ext == "toml"));
+            dir_files.sort();
+
+            files.extend(dir_files);
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    Ok(files)
+}
+
+/// Run the optic tests at `paths` and print a report. Returns an error if any of the tests fail.
+pub fn run(paths: &[String]) -> Result<()> {
+    let files = test_files(paths)?;
+    let mut failed = 0;
+
+    for file in &files {
+        let report = OpticTest::open(file)?.run()?;
+
+        if report.passed() {
+            println!(
+                "PASS {} ({} expectations)",
+                report.name, report.num_expectations
+            );
+            continue;
+        }
+
+        failed += 1;
+        println!("FAIL {}", report.name);
+
+        for failure in &report.failures {
+            println!("    expected {}: {}", failure.expectation, failure.reason);
+        }
+
+        println!("    results with the optic:");
+        for (i, url) in report.results.iter().enumerate() {
+            println!("        {}. {url}", i + 1);
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow!("{failed} of {} optic tests failed", files.len()));
+    }
+
+    Ok(())
+}
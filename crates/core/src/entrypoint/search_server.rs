@@ -32,6 +32,7 @@ use crate::{
     models::dual_encoder::DualEncoder,
     ranking::models::{lambdamart::LambdaMART, linear::LinearRegression},
     searcher::{InitialWebsiteResult, LocalSearcher, SearchQuery},
+    webpage::region::RegionTable,
     Result,
 };
 
@@ -153,6 +154,8 @@ impl sonic::service::Message<SearchService> for GetHomepageDescriptions {
 }
 
 pub async fn run(config: config::SearchServerConfig) -> Result<()> {
+    RegionTable::install_from_path(config.regions_path.as_deref())?;
+
     let addr = config.host;
     let server = SearchService::new(config).await?.bind(addr).await.unwrap();
 
@@ -68,7 +68,7 @@ impl SpellWorker {
 
         for file in warc_files.by_ref() {
             for record in file.records().flatten() {
-                let webpage = match Html::parse(&record.response.body, &record.request.url) {
+                let webpage = match Html::parse(&record.response.text(), &record.request.url) {
                     Ok(webpage) => webpage,
                     Err(err) => {
                         tracing::error!("error parsing webpage: {}", err);
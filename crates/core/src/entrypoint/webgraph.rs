@@ -17,7 +17,8 @@ use crate::{
     canon_index::CanonicalIndex,
     config::{self, WarcSource, WebgraphConstructConfig},
     entrypoint::download_all_warc_files,
-    webgraph::{self, Node, NodeID, WebgraphWriter},
+    warc::WarcRecord,
+    webgraph::{self, LinkLabel, Node, NodeID, WebgraphWriter},
     webpage::{url_ext::UrlExt, Html},
     Result,
 };
@@ -28,6 +29,16 @@ use std::{path::Path, sync::Arc};
 use tokio::pin;
 use tracing::{info, trace};
 
+/// Read the ids of the nodes in `path` (one node per line).
+fn read_node_ids<P: AsRef<Path>>(path: P) -> Result<Vec<NodeID>> {
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| Node::from(line).id())
+        .collect())
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode, Clone)]
 struct GraphPointer {
     path: String,
@@ -90,6 +101,19 @@ pub fn open_page_graph_writer<P: AsRef<Path>>(
     )
 }
 
+/// When the record was crawled, as a unix timestamp in seconds. This is the `WARC-Date`
+/// of the response, as the `Date` header is set by the server and might be cached or wrong.
+fn crawl_time(record: &WarcRecord) -> u64 {
+    record
+        .response
+        .date
+        .as_deref()
+        .and_then(|date| chrono::DateTime::parse_from_rfc3339(date).ok())
+        .map(|date| date.timestamp())
+        .unwrap_or_else(|| chrono::Utc::now().timestamp())
+        .max(0) as u64
+}
+
 fn canonical_or_self(index: &CanonicalIndex, url: Url) -> Url {
     if let Some(url) = index.get(&url).unwrap() {
         url
@@ -118,7 +142,7 @@ impl WebgraphWorker {
         for file in warc_files.by_ref() {
             for record in file.records().flatten() {
                 let webpage =
-                    match Html::parse_without_text(&record.response.body, &record.request.url) {
+                    match Html::parse_without_text(&record.response.text(), &record.request.url) {
                         Ok(webpage) => webpage,
                         Err(err) => {
                             tracing::error!("error parsing webpage: {}", err);
@@ -126,6 +150,8 @@ impl WebgraphWorker {
                         }
                     };
 
+                let seen = crawl_time(&record);
+
                 for mut link in webpage
                     .anchor_links()
                     .into_iter()
@@ -146,8 +172,16 @@ impl WebgraphWorker {
 
                     let mut destination = Node::from(destination);
 
+                    let label = LinkLabel {
+                        text: link.text,
+                        rel: link.rel,
+                        position: link.position,
+                        first_seen: seen,
+                        last_seen: seen,
+                    };
+
                     self.page_graph
-                        .insert(source.clone(), destination.clone(), link.text.clone());
+                        .insert(source.clone(), destination.clone(), label.clone());
 
                     source = source.into_host();
                     destination = destination.into_host();
@@ -158,7 +192,7 @@ impl WebgraphWorker {
                         && source_domain.is_some()
                         && dest_domain != source_domain
                     {
-                        self.host_graph.insert(source, destination, link.text);
+                        self.host_graph.insert(source, destination, label);
                     }
                 }
             }
@@ -174,6 +208,102 @@ impl WebgraphWorker {
 pub struct Webgraph {}
 
 impl Webgraph {
+    /// Calculate the clusters of the host graph at `webgraph_path` and store them in `output_path`.
+    /// The host centrality in `host_centrality_store_path` is used to pick the members that are
+    /// stored for each cluster.
+    pub fn clusters<P: AsRef<Path>>(
+        webgraph_path: P,
+        output_path: P,
+        host_centrality_store_path: Option<P>,
+    ) {
+        info!(
+            "Calculating clusters for {}",
+            webgraph_path.as_ref().to_str().unwrap()
+        );
+
+        let graph = webgraph::WebgraphBuilder::new(webgraph_path)
+            .single_threaded()
+            .open();
+
+        let host_centrality: Option<speedy_kv::Db<NodeID, f64>> = host_centrality_store_path
+            .map(|path| speedy_kv::Db::open_or_create(path.as_ref().join("harmonic")).unwrap());
+
+        webgraph::clusters::HostClusters::build(&graph, host_centrality.as_ref(), output_path);
+    }
+
+    /// Update the webgraph at `webgraph_path` with the graphs built from fresh crawls in `paths`.
+    /// The outgoing links of the recrawled pages are replaced by the links from the fresh graphs.
+    ///
+    /// `recrawled` and `deleted` are files with one node per line. The outgoing links of the
+    /// nodes in `recrawled` are removed even if the fresh graphs have no links from them,
+    /// and the nodes in `deleted` (e.g. pages that are gone) are removed from the graph.
+    pub fn update<P: AsRef<Path>, Q: AsRef<Path>>(
+        webgraph_path: P,
+        paths: &[Q],
+        recrawled: Option<P>,
+        deleted: Option<P>,
+    ) -> Result<()> {
+        let mut graph = webgraph::WebgraphBuilder::new(webgraph_path)
+            .single_threaded()
+            .open();
+
+        let mut recrawled = recrawled
+            .map(read_node_ids)
+            .transpose()?
+            .unwrap_or_default();
+
+        for path in paths {
+            info!("updating with {}", path.as_ref().to_str().unwrap());
+
+            let other = webgraph::WebgraphBuilder::new(path)
+                .single_threaded()
+                .open();
+            // the recrawled pages only need to be cleared once
+            graph.update(other, &std::mem::take(&mut recrawled))?;
+        }
+
+        if !recrawled.is_empty() {
+            for node in &recrawled {
+                graph.delete_outgoing_edges(node);
+            }
+
+            graph.commit();
+        }
+
+        if let Some(deleted) = deleted {
+            for node in read_node_ids(deleted)? {
+                graph.delete_node(&node);
+            }
+
+            graph.commit();
+        }
+
+        graph.optimize_read();
+
+        Ok(())
+    }
+
+    /// Remove the deleted edges from the webgraph at `webgraph_path` and merge its segments.
+    pub fn compact<P: AsRef<Path>>(
+        webgraph_path: P,
+        host_centrality_rank_store_path: Option<P>,
+    ) -> Result<()> {
+        let host_centrality_rank_store = if let Some(path) = host_centrality_rank_store_path {
+            Some(Arc::new(speedy_kv::Db::open_or_create(path)?))
+        } else {
+            None
+        };
+
+        let mut graph = webgraph::WebgraphBuilder::new(webgraph_path)
+            .single_threaded()
+            .open();
+
+        graph.compact(webgraph::Compression::Lz4, host_centrality_rank_store)?;
+        graph.optimize_read();
+
+        Ok(())
+    }
+
     pub fn run(config: &WebgraphConstructConfig) -> Result<()> {
         let warc_paths = config.warc_source.paths()?;
 
@@ -26,9 +26,11 @@ use crate::distributed::member::Member;
 use crate::distributed::member::Service;
 use crate::distributed::sonic::service::sonic_service;
 use crate::distributed::sonic::service::Message;
+use crate::webgraph::clusters::{Cluster as HostCluster, ClusterKind, HostClusters};
 use crate::webgraph::Edge;
 use crate::webgraph::EdgeLimit;
 use crate::webgraph::FullEdge;
+use crate::webgraph::LinkLabel;
 use crate::webgraph::Node;
 use crate::webgraph::NodeID;
 use crate::webgraph::Webgraph;
@@ -41,10 +43,13 @@ pub struct ScoredHost {
     pub host: String,
     pub score: f64,
     pub description: Option<String>,
+    /// Identifier of the community the host belongs to, if any.
+    pub cluster: Option<String>,
 }
 
 pub struct WebGraphService {
     graph: Arc<Webgraph>,
+    clusters: Option<Arc<HostClusters>>,
 }
 
 sonic_service!(
@@ -56,7 +61,8 @@ sonic_service!(
         RawIngoingEdges,
         RawOutgoingEdges,
         RawIngoingEdgesWithLabels,
-        RawOutgoingEdgesWithLabels
+        RawOutgoingEdgesWithLabels,
+        GetCluster,
     ]
 );
 
@@ -136,7 +142,7 @@ pub struct RawIngoingEdgesWithLabels {
 }
 
 impl Message<WebGraphService> for RawIngoingEdgesWithLabels {
-    type Response = Vec<Edge<String>>;
+    type Response = Vec<Edge<LinkLabel>>;
 
     async fn handle(self, server: &WebGraphService) -> Self::Response {
         server
@@ -152,7 +158,7 @@ pub struct RawOutgoingEdgesWithLabels {
 }
 
 impl Message<WebGraphService> for RawOutgoingEdgesWithLabels {
-    type Response = Vec<Edge<String>>;
+    type Response = Vec<Edge<LinkLabel>>;
 
     async fn handle(self, server: &WebGraphService) -> Self::Response {
         server
@@ -161,6 +167,32 @@ impl Message<WebGraphService> for RawOutgoingEdgesWithLabels {
     }
 }
 
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode)]
+pub struct GetCluster {
+    pub node: NodeID,
+    pub kind: ClusterKind,
+}
+
+/// Servers without clusters say so, so clients can stop asking them.
+#[derive(
+    Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode,
+)]
+pub enum ClusterReply {
+    Unavailable,
+    Cluster(Option<HostCluster>),
+}
+
+impl Message<WebGraphService> for GetCluster {
+    type Response = ClusterReply;
+
+    async fn handle(self, server: &WebGraphService) -> Self::Response {
+        match &server.clusters {
+            Some(clusters) => ClusterReply::Cluster(clusters.get(&self.node, self.kind)),
+            None => ClusterReply::Unavailable,
+        }
+    }
+}
+
 pub async fn run(config: config::WebgraphServerConfig) -> Result<()> {
     let addr: SocketAddr = config.host;
 
@@ -183,7 +215,14 @@ pub async fn run(config: config::WebgraphServerConfig) -> Result<()> {
 
     let graph = Arc::new(WebgraphBuilder::new(config.graph_path).open());
 
-    let server = WebGraphService { graph }.bind(addr).await.unwrap();
+    let clusters = config
+        .clusters_path
+        .map(|path| Arc::new(HostClusters::open(path)));
+
+    let server = WebGraphService { graph, clusters }
+        .bind(addr)
+        .await
+        .unwrap();
 
     info!("webgraph server is ready to accept requests on {}", addr);
 
@@ -193,3 +232,61 @@ pub async fn run(config: config::WebgraphServerConfig) -> Result<()> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        executor::Executor,
+        webgraph::{Compression, WebgraphWriter},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn get_cluster() {
+        let mut writer = WebgraphWriter::new(
+            crate::gen_temp_path(),
+            Executor::single_thread(),
+            Compression::default(),
+            None,
+        );
+        writer.insert(Node::from("a.com"), Node::from("b.com"), String::new());
+        writer.commit();
+        let graph = Arc::new(writer.finalize());
+
+        let a = Node::from("a.com").id();
+        let request = GetCluster {
+            node: a,
+            kind: ClusterKind::WeaklyConnected,
+        };
+
+        let server = WebGraphService {
+            graph: Arc::clone(&graph),
+            clusters: None,
+        };
+        assert_eq!(
+            request.clone().handle(&server).await,
+            ClusterReply::Unavailable
+        );
+
+        let path = crate::gen_temp_path();
+        let clusters = HostClusters::build(&graph, None, &path);
+        let server = WebGraphService {
+            graph,
+            clusters: Some(Arc::new(clusters)),
+        };
+
+        let ClusterReply::Cluster(Some(cluster)) = request.handle(&server).await else {
+            panic!("a.com should be in a cluster");
+        };
+        assert_eq!(cluster.size, 2);
+        assert!(cluster.members.contains(&a));
+        assert!(cluster.members.contains(&Node::from("b.com").id()));
+
+        let request = GetCluster {
+            node: Node::from("c.com").id(),
+            kind: ClusterKind::WeaklyConnected,
+        };
+        assert_eq!(request.handle(&server).await, ClusterReply::Cluster(None));
+    }
+}
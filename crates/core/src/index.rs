@@ -66,7 +66,7 @@ impl Index {
         self.inverted_index.tokenizers()
     }
 
-    #[cfg(test)]
+    /// Open an empty index in a new temporary directory.
     pub fn temporary() -> Result<Self> {
         let path = crate::gen_temp_path();
         let mut s = Self::open(path)?;
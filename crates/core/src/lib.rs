@@ -70,6 +70,7 @@ mod llm_utils;
 mod metrics;
 mod models;
 pub mod naive_bayes;
+pub mod optic_test;
 pub mod prehashed;
 mod query;
 mod rake;
@@ -124,6 +125,9 @@ pub enum Error {
     #[error("Unknown region")]
     UnknownRegion,
 
+    #[error("Unknown signal in optic: {0}")]
+    UnknownOpticSignal(String),
+
     #[error("Unknown CLI option")]
     UnknownCLIOption,
 
@@ -135,6 +139,9 @@ pub enum Error {
 
     #[error("Unknown webpage robots meta tag")]
     UnknownRobotsMetaTag,
+
+    #[error("Failed to parse PDF: {0}")]
+    PdfParse(String),
 }
 
 pub type Result<T, E = anyhow::Error> = std::result::Result<T, E>;
@@ -73,7 +73,7 @@ impl Crawler {
             urls: urls
                 .clone()
                 .into_iter()
-                .map(|url| RetrieableUrl::from(WeightedUrl { url, weight: 1.0 }))
+                .map(|url| RetrieableUrl::from(WeightedUrl::new(url, 1.0)))
                 .collect(),
             wandering_urls: 0,
         };
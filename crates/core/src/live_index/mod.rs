@@ -66,19 +66,21 @@ impl From<&LiveIndexConfig> for CrawlerConfig {
             politeness_factor: live.politeness_factor,
             min_crawl_delay_ms: live.min_crawl_delay_ms,
             max_crawl_delay_ms: live.max_crawl_delay_ms,
+            max_robots_crawl_delay_ms: live.max_robots_crawl_delay_ms,
             max_politeness_factor: live.max_politeness_factor,
             max_url_slowdown_retry: live.max_url_slowdown_retry,
             max_redirects: live.max_redirects,
             dry_run: false,
+            recrawl_db_path: None,
+            min_recrawl_interval_sec: crate::config::defaults::Crawler::min_recrawl_interval_sec(),
+            max_recrawl_interval_sec: crate::config::defaults::Crawler::max_recrawl_interval_sec(),
             timeout_seconds: live.timeout_seconds,
             // no impact
-            s3: crate::config::S3Config {
-                bucket: String::new(),
+            warc_max_bytes: crate::config::defaults::Crawler::warc_max_bytes(),
+            warc_max_age_sec: crate::config::defaults::Crawler::warc_max_age_sec(),
+            warc_sink: crate::config::WarcSinkConfig::Local(crate::config::LocalSinkConfig {
                 folder: String::new(),
-                access_key: String::new(),
-                secret_key: String::new(),
-                endpoint: String::new(),
-            },
+            }),
             router_hosts: Vec::new(),
         }
     }
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use serde::de::DeserializeOwned;
 use std::fs;
 use std::path::Path;
@@ -27,7 +27,7 @@ use stract::entrypoint::configure;
 use stract::entrypoint::{
     self, api, entity_search_server, safety_classifier, search_server, webgraph_server,
 };
-use stract::webgraph::WebgraphBuilder;
+use stract::webgraph::{LinkFilter, WebgraphBuilder};
 use tracing_subscriber::prelude::*;
 
 #[cfg(not(target_env = "msvc"))]
@@ -131,6 +131,22 @@ enum Commands {
         #[clap(subcommand)]
         options: AmpcOptions,
     },
+
+    /// Optic specific commands.
+    Optics {
+        #[clap(subcommand)]
+        options: OpticsOptions,
+    },
+}
+
+#[derive(Subcommand)]
+enum OpticsOptions {
+    /// Run optic tests. A test indexes a set of fixture pages and checks how the optic ranks them.
+    /// Directories are expanded to all the `.toml` test files they contain.
+    Test {
+        #[clap(required = true)]
+        paths: Vec<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -144,6 +160,13 @@ enum AmpcOptions {
     /// Start a coordinator to distribute the harmonic centrality computation.
     /// Workers needs to be started before the coordinator.
     HarmonicCoordinator { config_path: String },
+
+    /// Start a worker to compute the PageRank of a graph.
+    PageRankWorker { config_path: String },
+
+    /// Start a coordinator to distribute the PageRank computation.
+    /// Workers needs to be started before the coordinator.
+    PageRankCoordinator { config_path: String },
 }
 
 #[derive(Subcommand)]
@@ -192,12 +215,54 @@ enum CentralityMode {
     Host {
         webgraph_path: String,
         output_path: String,
+        #[clap(flatten)]
+        links: LinkFilterArgs,
     },
     /// Calculate metrics for the page webgraph.
     Page {
         webgraph_path: String,
         output_path: String,
+        #[clap(flatten)]
+        links: LinkFilterArgs,
     },
+    /// Calculate PageRank for a host or page webgraph.
+    PageRank {
+        webgraph_path: String,
+        output_path: String,
+        /// File with one trusted host per line. If given, the PageRank is personalised to these hosts.
+        #[clap(long)]
+        trusted_hosts: Option<String>,
+        #[clap(flatten)]
+        links: LinkFilterArgs,
+    },
+}
+
+/// Links to ignore when calculating the centrality.
+#[derive(Args)]
+struct LinkFilterArgs {
+    /// Ignore links with rel="nofollow".
+    #[clap(long)]
+    ignore_nofollow: bool,
+    /// Ignore links with rel="sponsored".
+    #[clap(long)]
+    ignore_sponsored: bool,
+    /// Ignore links with rel="ugc".
+    #[clap(long)]
+    ignore_ugc: bool,
+    /// Ignore links in the navigation, header, footer and sidebars of pages.
+    #[clap(long)]
+    ignore_navigation: bool,
+}
+
+impl From<LinkFilterArgs> for LinkFilter {
+    fn from(args: LinkFilterArgs) -> Self {
+        Self {
+            ignore_nofollow: args.ignore_nofollow,
+            ignore_sponsored: args.ignore_sponsored,
+            ignore_ugc: args.ignore_ugc,
+            ignore_navigation: args.ignore_navigation,
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -214,6 +279,37 @@ enum WebgraphOptions {
     /// Deploy the webgraph server. The webgraph server is responsible for serving the webgraph to the search servers.
     /// This is e.g. used to find similar sites etc.
     Server { config_path: String },
+
+    /// Calculate connected components and communities of hosts in the host webgraph.
+    Clusters {
+        webgraph_path: String,
+        output_path: String,
+        /// Host centrality used to pick the members that are stored for each cluster.
+        #[clap(long)]
+        host_centrality_store_path: Option<String>,
+    },
+
+    /// Update a webgraph with graphs built from fresh crawls.
+    /// The outgoing links of recrawled pages are replaced by their new links.
+    Update {
+        webgraph_path: String,
+        paths: Vec<String>,
+        /// File with the recrawled pages (one url per line). Their outgoing links
+        /// are removed even if they no longer have any links.
+        #[clap(long)]
+        recrawled: Option<String>,
+        /// File with the pages to remove from the graph (one url per line),
+        /// e.g. pages that no longer exist.
+        #[clap(long)]
+        deleted: Option<String>,
+    },
+
+    /// Remove deleted edges from a webgraph and merge its segments into one.
+    Compact {
+        webgraph_path: String,
+        #[clap(long)]
+        host_centrality_rank_store_path: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -302,13 +398,34 @@ fn main() -> Result<()> {
                 CentralityMode::Host {
                     webgraph_path,
                     output_path,
+                    links,
                 } => {
-                    entrypoint::Centrality::build_harmonic(&webgraph_path, &output_path);
+                    entrypoint::Centrality::build_harmonic(
+                        &webgraph_path,
+                        &output_path,
+                        links.into(),
+                    );
                 }
                 CentralityMode::Page {
                     webgraph_path,
                     output_path,
-                } => entrypoint::Centrality::build_approx_harmonic(webgraph_path, output_path)?,
+                    links,
+                } => entrypoint::Centrality::build_approx_harmonic(
+                    webgraph_path,
+                    output_path,
+                    links.into(),
+                )?,
+                CentralityMode::PageRank {
+                    webgraph_path,
+                    output_path,
+                    trusted_hosts,
+                    links,
+                } => entrypoint::Centrality::build_pagerank(
+                    webgraph_path,
+                    output_path,
+                    trusted_hosts,
+                    links.into(),
+                )?,
             }
             tracing::info!("Done");
         }
@@ -329,6 +446,33 @@ fn main() -> Result<()> {
 
                 webgraph.optimize_read();
             }
+            WebgraphOptions::Clusters {
+                webgraph_path,
+                output_path,
+                host_centrality_store_path,
+            } => entrypoint::Webgraph::clusters(
+                &webgraph_path,
+                &output_path,
+                host_centrality_store_path.as_ref(),
+            ),
+            WebgraphOptions::Update {
+                webgraph_path,
+                paths,
+                recrawled,
+                deleted,
+            } => entrypoint::Webgraph::update(
+                &webgraph_path,
+                &paths,
+                recrawled.as_ref(),
+                deleted.as_ref(),
+            )?,
+            WebgraphOptions::Compact {
+                webgraph_path,
+                host_centrality_rank_store_path,
+            } => entrypoint::Webgraph::compact(
+                &webgraph_path,
+                host_centrality_rank_store_path.as_ref(),
+            )?,
             WebgraphOptions::Server { config_path } => {
                 let config: config::WebgraphServerConfig = load_toml_config(config_path);
 
@@ -453,6 +597,17 @@ fn main() -> Result<()> {
                 let config: config::HarmonicCoordinatorConfig = load_toml_config(config_path);
                 entrypoint::ampc::harmonic_centrality::coordinator::run(config)?;
             }
+            AmpcOptions::PageRankWorker { config_path } => {
+                let config: config::PageRankWorkerConfig = load_toml_config(config_path);
+                entrypoint::ampc::pagerank::worker::run(config)?;
+            }
+            AmpcOptions::PageRankCoordinator { config_path } => {
+                let config: config::PageRankCoordinatorConfig = load_toml_config(config_path);
+                entrypoint::ampc::pagerank::coordinator::run(config)?;
+            }
+        },
+        Commands::Optics { options } => match options {
+            OpticsOptions::Test { paths } => entrypoint::optic_test::run(&paths)?,
         },
     }
 
@@ -0,0 +1,451 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Regression tests for optics.
+//!
+//! An optic test is a toml file with a set of fixture pages, a query and a list of
+//! expectations about how the optic should affect the results. The fixture pages are
+//! indexed in a temporary index and the query is searched both with and without the optic.
+//!
+//! ```toml
+//! optic = "blogs.optic" # relative to the test file. Use `optic_source` to inline the optic.
+//! query = "recipe"
+//!
+//! [[pages]]
+//! url = "https://blog.example.com/recipe"
+//! title = "My favourite recipe"
+//! body = "..."
+//!
+//! [[expect]]
+//! kind = "ranks_above"
+//! url = "https://blog.example.com/recipe"
+//! other = "https://contentfarm.com/recipe"
+//! ```
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use optics::{DirectoryLoader, Optic};
+
+use crate::{
+    index::Index,
+    searcher::{LocalSearcher, SearchQuery},
+    webpage::{pdf::escape, Html, Webpage},
+    Result,
+};
+
+/// A page that is indexed before the expectations are checked.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct FixturePage {
+    pub url: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub body: String,
+    /// The raw html of the page. Takes precedence over `title` and `body`.
+    pub html: Option<String>,
+    #[serde(default)]
+    pub host_centrality: f64,
+    #[serde(default)]
+    pub page_centrality: f64,
+}
+
+impl FixturePage {
+    fn webpage(&self) -> Result<Webpage> {
+        let html = match &self.html {
+            Some(html) => html.clone(),
+            None => format!(
+                "<html><head><title>{}</title></head><body>{}</body></html>",
+                escape(&self.title),
+                escape(&self.body)
+            ),
+        };
+
+        let mut webpage = Webpage::from(Html::parse(&html, &self.url)?);
+        webpage.host_centrality = self.host_centrality;
+        webpage.page_centrality = self.page_centrality;
+
+        Ok(webpage)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Expectation {
+    /// The page is in the results when the optic is applied.
+    Present { url: String },
+    /// The page matches the query, but is not in the results when the optic is applied.
+    Discarded { url: String },
+    /// The page ranks above `other` when the optic is applied.
+    RanksAbove { url: String, other: String },
+    /// The page is the top result when the optic is applied.
+    RanksFirst { url: String },
+}
+
+impl Display for Expectation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expectation::Present { url } => write!(f, "{url} is present"),
+            Expectation::Discarded { url } => write!(f, "{url} is discarded"),
+            Expectation::RanksAbove { url, other } => write!(f, "{url} ranks above {other}"),
+            Expectation::RanksFirst { url } => write!(f, "{url} ranks first"),
+        }
+    }
+}
+
+impl Expectation {
+    /// Check the expectation against the results of the query without the optic (`baseline`)
+    /// and with the optic (`results`). Returns the reason if the expectation is not met.
+    fn check(&self, baseline: &[String], results: &[String]) -> std::result::Result<(), String> {
+        let position = |url: &str| {
+            let url = normalize(url);
+            results.iter().position(|res| *res == url)
+        };
+
+        match self {
+            Expectation::Present { url } => match position(url) {
+                Some(_) => Ok(()),
+                None => Err(format!("{url} is not in the results")),
+            },
+            Expectation::Discarded { url } => {
+                if !baseline.contains(&normalize(url)) {
+                    Err(format!(
+                        "{url} does not match the query, even without the optic"
+                    ))
+                } else if let Some(pos) = position(url) {
+                    Err(format!("{url} is at position {}", pos + 1))
+                } else {
+                    Ok(())
+                }
+            }
+            Expectation::RanksAbove { url, other } => match (position(url), position(other)) {
+                (None, _) => Err(format!("{url} is not in the results")),
+                (Some(_), None) => Ok(()),
+                (Some(a), Some(b)) if a < b => Ok(()),
+                (Some(a), Some(b)) => Err(format!(
+                    "{url} is at position {} and {other} is at position {}",
+                    a + 1,
+                    b + 1
+                )),
+            },
+            Expectation::RanksFirst { url } => match position(url) {
+                Some(0) => Ok(()),
+                Some(pos) => Err(format!("{url} is at position {}", pos + 1)),
+                None => Err(format!("{url} is not in the results")),
+            },
+        }
+    }
+}
+
+/// Urls are compared in the normalized form used in the search results.
+fn normalize(url: &str) -> String {
+    url::Url::parse(url)
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| url.to_string())
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct RawOpticTest {
+    optic: Option<String>,
+    optic_source: Option<String>,
+    query: String,
+    #[serde(default)]
+    pages: Vec<FixturePage>,
+    #[serde(default, rename = "expect")]
+    expectations: Vec<Expectation>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OpticTest {
+    pub name: String,
+    pub optic: Optic,
+    pub query: String,
+    pub pages: Vec<FixturePage>,
+    pub expectations: Vec<Expectation>,
+}
+
+impl OpticTest {
+    /// Parse a test. Paths to optics and imports are resolved relative to `base`.
+    pub fn parse<P: AsRef<Path>>(name: &str, source: &str, base: P) -> Result<Self> {
+        let raw: RawOpticTest = toml::from_str(source)?;
+        let base = base.as_ref();
+
+        let optic_source = match (raw.optic, raw.optic_source) {
+            (Some(path), None) => std::fs::read_to_string(base.join(&path))
+                .with_context(|| format!("Failed to read optic: '{path}'"))?,
+            (None, Some(source)) => source,
+            _ => {
+                return Err(anyhow!(
+                    "an optic test must have exactly one of `optic` and `optic_source`"
+                ))
+            }
+        };
+
+        let optic = Optic::parse_with_loader(&optic_source, &DirectoryLoader::new(base))?;
+
+        Ok(Self {
+            name: name.to_string(),
+            optic,
+            query: raw.query,
+            pages: raw.pages,
+            expectations: raw.expectations,
+        })
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read optic test: '{}'", path.display()))?;
+
+        Self::parse(
+            &path.display().to_string(),
+            &source,
+            path.parent().unwrap_or(Path::new(".")),
+        )
+    }
+
+    /// Index the fixture pages in a temporary index and check all the expectations.
+    pub fn run(&self) -> Result<TestReport> {
+        self.run_at(crate::gen_temp_path())
+    }
+
+    fn run_at(&self, path: PathBuf) -> Result<TestReport> {
+        // declared before the index so it is removed after the index is closed
+        let dir = TempIndexDir(path);
+
+        let mut index = Index::open(&dir.0)?;
+        index.prepare_writer()?;
+
+        for page in &self.pages {
+            index.insert(&page.webpage()?)?;
+        }
+
+        index.commit()?;
+
+        let searcher = LocalSearcher::from(index);
+
+        let query = SearchQuery {
+            query: self.query.clone(),
+            num_results: self.pages.len().max(1),
+            ..Default::default()
+        };
+
+        let urls = |query: &SearchQuery| -> Result<Vec<String>> {
+            Ok(searcher
+                .search(query)?
+                .webpages
+                .into_iter()
+                .map(|webpage| webpage.url)
+                .collect())
+        };
+
+        let baseline = urls(&query)?;
+        let results = urls(&SearchQuery {
+            optic: Some(self.optic.clone()),
+            ..query
+        })?;
+
+        drop(searcher);
+
+        let failures = self
+            .expectations
+            .iter()
+            .filter_map(|expectation| {
+                expectation
+                    .check(&baseline, &results)
+                    .err()
+                    .map(|reason| Failure {
+                        expectation: expectation.clone(),
+                        reason,
+                    })
+            })
+            .collect();
+
+        Ok(TestReport {
+            name: self.name.clone(),
+            num_expectations: self.expectations.len(),
+            results,
+            failures,
+        })
+    }
+}
+
+/// Removes the temporary index of a test, also when the test returns early with an error.
+struct TempIndexDir(PathBuf);
+
+impl Drop for TempIndexDir {
+    fn drop(&mut self) {
+        if self.0.exists() {
+            if let Err(err) = std::fs::remove_dir_all(&self.0) {
+                tracing::warn!(
+                    "failed to remove temporary index {}: {err}",
+                    self.0.display()
+                );
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub expectation: Expectation,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct TestReport {
+    pub name: String,
+    pub num_expectations: usize,
+    /// The urls of the results with the optic applied.
+    pub results: Vec<String>,
+    pub failures: Vec<Failure>,
+}
+
+impl TestReport {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST: &str = r#"
+optic_source = '''
+Rule {
+    Matches {
+        Domain("contentfarm.com")
+    },
+    Action(Discard)
+};
+Rule {
+    Matches {
+        Domain("blog.com")
+    },
+    Action(Boost(100))
+}
+'''
+query = "recipe"
+
+[[pages]]
+url = "https://www.blog.com/recipe"
+title = "My favourite recipe"
+body = "this is the best recipe for pancakes you will ever find"
+
+[[pages]]
+url = "https://www.contentfarm.com/recipe"
+title = "Recipe"
+body = "recipe recipe recipe recipe recipe recipe"
+
+[[pages]]
+url = "https://www.news.com/recipe"
+title = "Recipe news"
+body = "there is a new recipe for pancakes"
+
+[[expect]]
+kind = "discarded"
+url = "https://www.contentfarm.com/recipe"
+
+[[expect]]
+kind = "ranks_first"
+url = "https://www.blog.com/recipe"
+
+[[expect]]
+kind = "ranks_above"
+url = "https://www.blog.com/recipe"
+other = "https://www.news.com/recipe"
+"#;
+
+    #[test]
+    fn passing_test() {
+        let test = OpticTest::parse("recipes", TEST, ".").unwrap();
+        assert_eq!(test.pages.len(), 3);
+        assert_eq!(test.expectations.len(), 3);
+
+        let report = test.run().unwrap();
+        assert!(report.passed(), "{:?}", report.failures);
+        assert!(!report
+            .results
+            .contains(&"https://www.contentfarm.com/recipe".to_string()));
+    }
+
+    #[test]
+    fn failing_expectations() {
+        let mut test = OpticTest::parse("recipes", TEST, ".").unwrap();
+        test.expectations = vec![
+            Expectation::Present {
+                url: "https://www.contentfarm.com/recipe".to_string(),
+            },
+            Expectation::Discarded {
+                url: "https://www.unknown.com/".to_string(),
+            },
+            Expectation::RanksAbove {
+                url: "https://www.news.com/recipe".to_string(),
+                other: "https://www.blog.com/recipe".to_string(),
+            },
+        ];
+
+        let report = test.run().unwrap();
+
+        assert_eq!(report.failures.len(), 3);
+        assert!(report.failures[1]
+            .reason
+            .contains("does not match the query"));
+    }
+
+    #[test]
+    fn escaped_fixture() {
+        let page = FixturePage {
+            url: "https://www.example.com/".to_string(),
+            title: "Fish & </title><b>chips</b>".to_string(),
+            body: "<script>recipe".to_string(),
+            html: None,
+            host_centrality: 0.0,
+            page_centrality: 0.0,
+        };
+
+        let webpage = page.webpage().unwrap();
+        assert_eq!(webpage.html.title().unwrap(), "Fish & </title><b>chips</b>");
+        // the body would be a script if it wasn't escaped
+        assert!(!webpage.html.empty_all_text());
+    }
+
+    #[test]
+    fn index_removed_on_error() {
+        let mut test = OpticTest::parse("recipes", TEST, ".").unwrap();
+        test.pages.push(FixturePage {
+            url: "not a url".to_string(),
+            title: String::new(),
+            body: String::new(),
+            html: None,
+            host_centrality: 0.0,
+            page_centrality: 0.0,
+        });
+
+        let path = crate::gen_temp_path();
+        assert!(test.run_at(path.clone()).is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn missing_optic() {
+        assert!(OpticTest::parse("empty", r#"query = "test""#, ".").is_err());
+    }
+}
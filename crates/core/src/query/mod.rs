@@ -17,7 +17,7 @@
 use crate::{
     inverted_index::InvertedIndex,
     query::parser::TermCompound,
-    ranking::SignalCoefficient,
+    ranking::{SignalCoefficient, SignalEnum},
     schema::text_field,
     search_ctx::Ctx,
     searcher::SearchQuery,
@@ -95,7 +95,7 @@ impl Query {
 
         let simple_terms_text: Vec<String> = parsed_terms
             .iter()
-            .filter_map(|term| term.as_simple_text().map(|s| s.to_string()))
+            .flat_map(|term| term.simple_texts())
             .flat_map(|term| {
                 // term might be a phrase, so we split it into words
                 term.split_ascii_whitespace()
@@ -117,6 +117,27 @@ impl Query {
             ))));
         }
 
+        if let Some(languages) = query
+            .languages
+            .iter()
+            .map(|lang| plan::Node::Range(plan::FastFieldRange::language(*lang)))
+            .reduce(|left, right| left.or(right))
+        {
+            plan = plan.and(languages);
+        }
+
+        if let Some(timestamp) = query.updated_after {
+            plan = plan.and(plan::Node::Range(plan::FastFieldRange::updated_after(
+                timestamp,
+            )));
+        }
+
+        if let Some(timestamp) = query.updated_before {
+            plan = plan.and(plan::Node::Range(plan::FastFieldRange::updated_before(
+                timestamp,
+            )));
+        }
+
         let mut tantivy_query = plan
             .into_query()
             .as_tantivy(lang.as_ref(), &schema)
@@ -132,6 +153,14 @@ impl Query {
         }
 
         for optic in &optics {
+            for rule in &optic.rules {
+                if let optics::Action::SetSignal { signal, .. } = &rule.action {
+                    if SignalEnum::from_optic_name(signal).is_none() {
+                        return Err(Error::UnknownOpticSignal(signal.clone()).into());
+                    }
+                }
+            }
+
             let mut subqueries = vec![(Occur::Must, tantivy_query.box_clone())];
             subqueries.append(&mut optic.as_multiple_tantivy(&schema, &ctx.fastfield_reader));
             tantivy_query = Box::new(BooleanQuery::new(subqueries));
@@ -206,7 +235,12 @@ impl tantivy::query::Query for Query {
 
 #[cfg(test)]
 mod tests {
-    use crate::{index::Index, rand_words, searcher::LocalSearcher, webpage::Webpage};
+    use crate::{
+        index::Index,
+        rand_words,
+        searcher::LocalSearcher,
+        webpage::{lang::Lang, Webpage},
+    };
     use proptest::prelude::*;
 
     use super::*;
@@ -389,6 +423,294 @@ mod tests {
         assert_eq!(result.webpages[0].url, "https://www.second.com/");
     }
 
+    #[test]
+    fn or_query() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for (title, url) in [
+            ("Async rust", "https://www.first.com"),
+            ("Async golang", "https://www.second.com"),
+            ("Async python", "https://www.third.com"),
+            ("Sync rust", "https://www.example.com"),
+        ] {
+            index
+                .insert(
+                    &Webpage::test_parse(
+                        &format!(
+                            r#"
+                            <html>
+                                <head>
+                                    <title>{title}</title>
+                                </head>
+                                <body>
+                                    {title} {}
+                                </body>
+                            </html>
+                        "#,
+                            rand_words(100)
+                        ),
+                        url,
+                    )
+                    .unwrap(),
+                )
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().expect("failed to commit index");
+        let searcher = LocalSearcher::from(index);
+
+        let query = SearchQuery {
+            query: "(rust OR golang) intitle:async".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        let mut urls: Vec<_> = result.webpages.iter().map(|w| w.url.clone()).collect();
+        urls.sort();
+        assert_eq!(
+            urls,
+            vec![
+                "https://www.first.com/".to_string(),
+                "https://www.second.com/".to_string()
+            ]
+        );
+
+        let query = SearchQuery {
+            query: "rust | python -site:example.com".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        let mut urls: Vec<_> = result.webpages.iter().map(|w| w.url.clone()).collect();
+        urls.sort();
+        assert_eq!(
+            urls,
+            vec![
+                "https://www.first.com/".to_string(),
+                "https://www.third.com/".to_string()
+            ]
+        );
+
+        let query = SearchQuery {
+            query: "async -(rust OR python)".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.second.com/");
+    }
+
+    #[test]
+    fn or_negated_query() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for (title, url) in [
+            ("Async rust", "https://www.first.com"),
+            ("Async golang", "https://www.second.com"),
+            ("Sync python", "https://www.third.com"),
+        ] {
+            index
+                .insert(
+                    &Webpage::test_parse(
+                        &format!(
+                            r#"
+                            <html>
+                                <head>
+                                    <title>{title}</title>
+                                </head>
+                                <body>
+                                    {title} {}
+                                </body>
+                            </html>
+                        "#,
+                            rand_words(100)
+                        ),
+                        url,
+                    )
+                    .unwrap(),
+                )
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().expect("failed to commit index");
+        let searcher = LocalSearcher::from(index);
+
+        let query = SearchQuery {
+            query: "rust OR -async".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        let mut urls: Vec<_> = result.webpages.iter().map(|w| w.url.clone()).collect();
+        urls.sort();
+        assert_eq!(
+            urls,
+            vec![
+                "https://www.first.com/".to_string(),
+                "https://www.third.com/".to_string()
+            ]
+        );
+
+        let query = SearchQuery {
+            query: "async (golang OR -rust)".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.second.com/");
+    }
+
+    #[test]
+    fn updated_range_query() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for (updated_time, url) in [
+            (Some("1999-06-22T19:37:34+00:00"), "https://www.first.com"),
+            (Some("2023-06-22T19:37:34+00:00"), "https://www.second.com"),
+            (None, "https://www.third.com"),
+        ] {
+            let meta = updated_time
+                .map(|time| format!(r#"<meta property="og:updated_time" content="{time}" />"#))
+                .unwrap_or_default();
+
+            index
+                .insert(
+                    &Webpage::test_parse(
+                        &format!(
+                            r#"
+                            <html>
+                                <head>
+                                    <title>Changelog</title>
+                                    {meta}
+                                </head>
+                                <body>
+                                    Changelog {}
+                                </body>
+                            </html>
+                        "#,
+                            rand_words(100)
+                        ),
+                        url,
+                    )
+                    .unwrap(),
+                )
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().expect("failed to commit index");
+        let searcher = LocalSearcher::from(index);
+
+        let query = SearchQuery {
+            query: "changelog after:2020-01-01".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.second.com/");
+
+        let query = SearchQuery {
+            query: "changelog before:2020".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.first.com/");
+
+        let query = SearchQuery {
+            query: "changelog updated:7d".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert!(result.webpages.is_empty());
+
+        let query = SearchQuery {
+            query: "changelog".to_string(),
+            updated_after: Some(parser::start_of_day_timestamp(
+                chrono::NaiveDate::from_ymd_opt(1990, 1, 1).unwrap(),
+            )),
+            updated_before: Some(parser::start_of_day_timestamp(
+                chrono::NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            )),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.first.com/");
+    }
+
+    #[test]
+    fn lang_and_filetype_query() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for (body, url) in [
+            (
+                "This is the manual for the washing machine. Read it carefully before you start the machine for the first time.",
+                "https://www.first.com/manual.pdf",
+            ),
+            (
+                "Dies ist das Handbuch für die Waschmaschine. Lesen Sie es sorgfältig durch, bevor Sie die Maschine zum ersten Mal starten.",
+                "https://www.second.com/manual.pdf",
+            ),
+            (
+                "Dies ist das Handbuch für die Waschmaschine. Lesen Sie es sorgfältig durch, bevor Sie die Maschine zum ersten Mal starten.",
+                "https://www.third.com/manual.html",
+            ),
+        ] {
+            index
+                .insert(
+                    &Webpage::test_parse(
+                        &format!(
+                            r#"
+                            <html>
+                                <head>
+                                    <title>Manual</title>
+                                </head>
+                                <body>
+                                    {body}
+                                </body>
+                            </html>
+                        "#
+                        ),
+                        url,
+                    )
+                    .unwrap(),
+                )
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().expect("failed to commit index");
+        let searcher = LocalSearcher::from(index);
+
+        let query = SearchQuery {
+            query: "manual lang:de".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        let mut urls: Vec<_> = result.webpages.iter().map(|w| w.url.clone()).collect();
+        urls.sort();
+        assert_eq!(
+            urls,
+            vec![
+                "https://www.second.com/manual.pdf".to_string(),
+                "https://www.third.com/manual.html".to_string()
+            ]
+        );
+
+        let query = SearchQuery {
+            query: "manual lang:de filetype:pdf".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.second.com/manual.pdf");
+
+        let query = SearchQuery {
+            query: "manual".to_string(),
+            languages: vec![Lang::from_code("en").unwrap()],
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.first.com/manual.pdf");
+    }
+
     #[test]
     fn site_query() {
         let mut index = Index::temporary().expect("Unable to open index");
@@ -15,14 +15,24 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use itertools::Itertools;
-use optics::{Action, MatchLocation, Matching, Optic, PatternPart, Rule};
-use std::iter;
+use optics::{
+    Action, Comparison, FastFieldPredicate, MatchLocation, Matching, Optic, PatternPart, Rule,
+};
+use std::{iter, ops::Bound};
 use tantivy::{
-    query::{BooleanQuery, Occur, QueryClone},
+    query::{BooleanQuery, Occur, QueryClone, RangeQuery},
     schema::Schema,
 };
 
-use crate::{fastfield_reader::FastFieldReader, schema::text_field, webpage::schema_org};
+use crate::{
+    fastfield_reader::FastFieldReader,
+    ranking::SignalEnum,
+    schema::{
+        fast_field::{self, FastField},
+        text_field, FastFieldEnum, FLOAT_SCALING,
+    },
+    webpage::schema_org,
+};
 
 use super::{const_query::ConstQuery, pattern_query::PatternQuery, union::UnionQuery};
 
@@ -83,9 +93,20 @@ impl AsMultipleTantivyQuery for Optic {
     }
 }
 
+/// How a rule changes the ranking of the documents it matches.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RuleEffect {
+    /// Added to the optic boost. Negative values downrank the document.
+    Boost(f64),
+    /// Multiplied onto the optic boost.
+    Multiply(f64),
+    /// Overrides the coefficient of the signal.
+    SetCoefficient(SignalEnum, f64),
+}
+
 pub struct SearchableRule {
     pub query: Box<dyn tantivy::query::Query>,
-    pub boost: f64,
+    pub effect: RuleEffect,
 }
 
 pub trait AsSearchableRule {
@@ -142,21 +163,41 @@ impl AsSearchableRule for Rule {
                 Occur::Should,
                 SearchableRule {
                     query: Box::new(ConstQuery::new(subquery, 1.0)),
-                    boost: *boost as f64,
+                    effect: RuleEffect::Boost(*boost),
                 },
             )),
             Action::Downrank(boost) => Some((
                 Occur::Should,
                 SearchableRule {
                     query: Box::new(ConstQuery::new(subquery, 1.0)),
-                    boost: *boost as f64 * -1.0,
+                    effect: RuleEffect::Boost(*boost * -1.0),
+                },
+            )),
+            Action::Multiply(factor) => Some((
+                Occur::Should,
+                SearchableRule {
+                    query: Box::new(ConstQuery::new(subquery, 1.0)),
+                    effect: RuleEffect::Multiply(*factor),
+                },
+            )),
+            Action::SetSignal {
+                signal,
+                coefficient,
+            } => Some((
+                Occur::Should,
+                SearchableRule {
+                    query: Box::new(ConstQuery::new(subquery, 1.0)),
+                    effect: RuleEffect::SetCoefficient(
+                        SignalEnum::from_optic_name(signal)?,
+                        *coefficient,
+                    ),
                 },
             )),
             Action::Discard => Some((
                 Occur::MustNot,
                 SearchableRule {
                     query: subquery,
-                    boost: 0.0,
+                    effect: RuleEffect::Boost(0.0),
                 },
             )),
         }
@@ -169,10 +210,17 @@ impl AsTantivyQuery for Matching {
         schema: &Schema,
         fastfield_reader: &FastFieldReader,
     ) -> Box<dyn tantivy::query::Query> {
-        match &self.location {
+        let (pattern, location) = match self {
+            Matching::Pattern { pattern, location } => (pattern, location),
+            Matching::FastField(predicate) => {
+                return predicate.as_tantivy(schema, fastfield_reader)
+            }
+        };
+
+        match location {
             MatchLocation::Site => ConstQuery::new(
                 PatternQuery::new(
-                    self.pattern.clone(),
+                    pattern.clone(),
                     text_field::UrlForSiteOperator.into(),
                     schema,
                     fastfield_reader.clone(),
@@ -183,7 +231,7 @@ impl AsTantivyQuery for Matching {
             .box_clone(),
             MatchLocation::Url => Box::new(ConstQuery::new(
                 Box::new(PatternQuery::new(
-                    self.pattern.clone(),
+                    pattern.clone(),
                     text_field::Url.into(),
                     schema,
                     fastfield_reader.clone(),
@@ -192,7 +240,7 @@ impl AsTantivyQuery for Matching {
             )),
             MatchLocation::Domain => Box::new(ConstQuery::new(
                 Box::new(PatternQuery::new(
-                    self.pattern.clone(),
+                    pattern.clone(),
                     text_field::Domain.into(),
                     schema,
                     fastfield_reader.clone(),
@@ -201,7 +249,7 @@ impl AsTantivyQuery for Matching {
             )),
             MatchLocation::Title => Box::new(ConstQuery::new(
                 Box::new(PatternQuery::new(
-                    self.pattern.clone(),
+                    pattern.clone(),
                     text_field::Title.into(),
                     schema,
                     fastfield_reader.clone(),
@@ -211,7 +259,7 @@ impl AsTantivyQuery for Matching {
             MatchLocation::Description => UnionQuery::from(vec![
                 Box::new(ConstQuery::new(
                     Box::new(PatternQuery::new(
-                        self.pattern.clone(),
+                        pattern.clone(),
                         text_field::Description.into(),
                         schema,
                         fastfield_reader.clone(),
@@ -220,7 +268,7 @@ impl AsTantivyQuery for Matching {
                 )) as Box<dyn tantivy::query::Query>,
                 Box::new(ConstQuery::new(
                     Box::new(PatternQuery::new(
-                        self.pattern.clone(),
+                        pattern.clone(),
                         text_field::DmozDescription.into(),
                         schema,
                         fastfield_reader.clone(),
@@ -231,7 +279,7 @@ impl AsTantivyQuery for Matching {
             .box_clone(),
             MatchLocation::Content => Box::new(ConstQuery::new(
                 Box::new(PatternQuery::new(
-                    self.pattern.clone(),
+                    pattern.clone(),
                     text_field::CleanBody.into(),
                     schema,
                     fastfield_reader.clone(),
@@ -240,7 +288,7 @@ impl AsTantivyQuery for Matching {
             )),
             MatchLocation::MicroformatTag => Box::new(ConstQuery::new(
                 Box::new(PatternQuery::new(
-                    self.pattern.clone(),
+                    pattern.clone(),
                     text_field::MicroformatTags.into(),
                     schema,
                     fastfield_reader.clone(),
@@ -248,7 +296,7 @@ impl AsTantivyQuery for Matching {
                 1.0,
             )),
             MatchLocation::Schema => {
-                let mut pattern = self.pattern.clone();
+                let mut pattern = pattern.clone();
                 // add TYPE_PREFIX to first term in pattern to ensure
                 // we match from the beginning of the path
                 if let Some(PatternPart::Raw(first_term)) = pattern
@@ -272,6 +320,76 @@ impl AsTantivyQuery for Matching {
     }
 }
 
+/// The bounds of the values `v` where `v <cmp> value` holds.
+fn comparison_bounds(cmp: Comparison, value: u64) -> (Bound<u64>, Bound<u64>) {
+    match cmp {
+        Comparison::Less => (Bound::Unbounded, Bound::Excluded(value)),
+        Comparison::LessOrEqual => (Bound::Unbounded, Bound::Included(value)),
+        Comparison::Greater => (Bound::Excluded(value), Bound::Unbounded),
+        Comparison::GreaterOrEqual => (Bound::Included(value), Bound::Unbounded),
+        Comparison::Equal => (Bound::Included(value), Bound::Included(value)),
+    }
+}
+
+/// The bounds of the timestamps `t` where the day of `t` compares to the
+/// day starting at `day` (a midnight timestamp) as `cmp`.
+fn date_bounds(cmp: Comparison, day: u64) -> (Bound<u64>, Bound<u64>) {
+    const DAY: u64 = 24 * 60 * 60;
+    let next_day = day.saturating_add(DAY);
+
+    match cmp {
+        Comparison::Less => (Bound::Unbounded, Bound::Excluded(day)),
+        Comparison::LessOrEqual => (Bound::Unbounded, Bound::Excluded(next_day)),
+        Comparison::Greater => (Bound::Included(next_day), Bound::Unbounded),
+        Comparison::GreaterOrEqual => (Bound::Included(day), Bound::Unbounded),
+        Comparison::Equal => (Bound::Included(day), Bound::Excluded(next_day)),
+    }
+}
+
+impl AsTantivyQuery for FastFieldPredicate {
+    fn as_tantivy(&self, _: &Schema, _: &FastFieldReader) -> Box<dyn tantivy::query::Query> {
+        let (field, (lower, upper)): (FastFieldEnum, _) = match self {
+            FastFieldPredicate::LastUpdated(cmp, timestamp) => {
+                let (lower, upper) = date_bounds(*cmp, *timestamp);
+
+                // pages where the update time is unknown are stored as 0
+                // and should never match
+                let lower = match lower {
+                    Bound::Unbounded => Bound::Included(1),
+                    lower => lower,
+                };
+
+                (fast_field::LastUpdated.into(), (lower, upper))
+            }
+            FastFieldPredicate::LikelyHasAds(value) => (
+                fast_field::LikelyHasAds.into(),
+                comparison_bounds(Comparison::Equal, *value as u64),
+            ),
+            FastFieldPredicate::LikelyHasPaywall(value) => (
+                fast_field::LikelyHasPaywall.into(),
+                comparison_bounds(Comparison::Equal, *value as u64),
+            ),
+            FastFieldPredicate::TrackerScore(cmp, score) => (
+                fast_field::TrackerScore.into(),
+                comparison_bounds(*cmp, *score),
+            ),
+            FastFieldPredicate::HostCentrality(cmp, centrality) => (
+                fast_field::HostCentrality.into(),
+                comparison_bounds(*cmp, (centrality.max(0.0) * FLOAT_SCALING as f64) as u64),
+            ),
+        };
+
+        Box::new(ConstQuery::new(
+            Box::new(RangeQuery::new_u64_bounds(
+                field.name().to_string(),
+                lower,
+                upper,
+            )),
+            1.0,
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use optics::{HostRankings, Optic};
@@ -1936,4 +2054,236 @@ mod tests {
         assert_eq!(res.len(), 1);
         assert_eq!(res[0].url, "https://a-third-example.com/");
     }
+
+    #[test]
+    fn fast_field_predicates() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        index
+            .insert(&Webpage {
+                html: Html::parse(
+                    &format!(
+                        r#"
+                    <html>
+                        <head>
+                            <title>Website A</title>
+                            <meta property="article:modified_time" content="2023-06-01T12:00:00+00:00" />
+                        </head>
+                        <body>
+                            {CONTENT} {}
+                        </body>
+                    </html>
+                "#,
+                        crate::rand_words(100)
+                    ),
+                    "https://www.a.com",
+                )
+                .unwrap(),
+                host_centrality: 0.5,
+                fetch_time_ms: 500,
+                ..Default::default()
+            })
+            .expect("failed to insert webpage");
+        index
+            .insert(&Webpage {
+                html: Html::parse(
+                    &format!(
+                        r#"
+                    <html>
+                        <head>
+                            <title>Website B</title>
+                            <script src="https://tracker.example.org/track.js"></script>
+                            <script src="https://doubleclick.net/ads.js"></script>
+                        </head>
+                        <body>
+                            {CONTENT} {}
+                        </body>
+                    </html>
+                "#,
+                        crate::rand_words(100)
+                    ),
+                    "https://www.b.com",
+                )
+                .unwrap(),
+                host_centrality: 0.01,
+                fetch_time_ms: 500,
+                ..Default::default()
+            })
+            .expect("failed to insert webpage");
+
+        index.commit().expect("failed to commit index");
+        let searcher = LocalSearcher::from(index);
+
+        let search = |optic: &str| {
+            searcher
+                .search(&SearchQuery {
+                    query: "website".to_string(),
+                    optic: Some(Optic::parse(optic).unwrap()),
+                    ..Default::default()
+                })
+                .unwrap()
+                .webpages
+                .into_iter()
+                .map(|page| page.url)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            search("DiscardNonMatching; Rule { Matches { HostCentrality(> 0.1) } }"),
+            vec!["https://www.a.com/".to_string()]
+        );
+        assert_eq!(
+            search("Rule { Matches { TrackerScore(> 0) }, Action(Discard) }"),
+            vec!["https://www.a.com/".to_string()]
+        );
+        assert_eq!(
+            search("DiscardNonMatching; Rule { Matches { LastUpdated(> \"2023-01-01\") } }"),
+            vec!["https://www.a.com/".to_string()]
+        );
+        assert_eq!(
+            search("DiscardNonMatching; Rule { Matches { LastUpdated(< \"2023-01-01\") } }"),
+            Vec::<String>::new()
+        );
+
+        // a.com was updated at noon, so comparisons against its day must cover the whole day
+        for (cmp, date, expected) in [
+            ("=", "2023-06-01", true),
+            ("=", "2023-05-31", false),
+            ("=", "2023-06-02", false),
+            ("<=", "2023-06-01", true),
+            ("<=", "2023-05-31", false),
+            ("<", "2023-06-01", false),
+            ("<", "2023-06-02", true),
+            (">=", "2023-06-01", true),
+            (">=", "2023-06-02", false),
+            (">", "2023-06-01", false),
+            (">", "2023-05-31", true),
+        ] {
+            let expected = if expected {
+                vec!["https://www.a.com/".to_string()]
+            } else {
+                Vec::new()
+            };
+
+            assert_eq!(
+                search(&format!(
+                    "DiscardNonMatching; Rule {{ Matches {{ LastUpdated({cmp} \"{date}\") }} }}"
+                )),
+                expected,
+                "LastUpdated({cmp} \"{date}\")"
+            );
+        }
+
+        assert_eq!(
+            search(
+                "DiscardNonMatching; Rule { Matches { LikelyHasPaywall(false), TrackerScore(< 1) } }"
+            ),
+            vec!["https://www.a.com/".to_string()]
+        );
+        assert_eq!(
+            search("Rule { Matches { LikelyHasAds(true) }, Action(Discard) }"),
+            vec!["https://www.a.com/".to_string()]
+        );
+        assert_eq!(
+            search("DiscardNonMatching; Rule { Matches { LikelyHasAds(true) } }"),
+            vec!["https://www.b.com/".to_string()]
+        );
+    }
+
+    #[test]
+    fn weighted_actions() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for (url, host_centrality) in [("https://www.a.com", 0.5), ("https://www.b.com", 0.01)] {
+            index
+                .insert(&Webpage {
+                    html: Html::parse(
+                        &format!(
+                            r#"
+                    <html>
+                        <head>
+                            <title>Website</title>
+                        </head>
+                        <body>
+                            {CONTENT} {}
+                        </body>
+                    </html>
+                "#,
+                            crate::rand_words(100)
+                        ),
+                        url,
+                    )
+                    .unwrap(),
+                    host_centrality,
+                    fetch_time_ms: 500,
+                    ..Default::default()
+                })
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().expect("failed to commit index");
+        let searcher = LocalSearcher::from(index);
+
+        let search = |optic: &str| {
+            searcher
+                .search(&SearchQuery {
+                    query: "website".to_string(),
+                    optic: Some(Optic::parse(optic).unwrap()),
+                    ..Default::default()
+                })
+                .unwrap()
+                .webpages
+                .into_iter()
+                .map(|page| page.url)
+                .collect::<Vec<_>>()
+        };
+
+        let a_first = vec![
+            "https://www.a.com/".to_string(),
+            "https://www.b.com/".to_string(),
+        ];
+        let b_first = vec![
+            "https://www.b.com/".to_string(),
+            "https://www.a.com/".to_string(),
+        ];
+
+        assert_eq!(
+            search("Rule { Matches { Site(\"b.com\") }, Action(Boost(0.5)) }").len(),
+            2
+        );
+        assert_eq!(
+            search("Rule { Matches { Site(\"b.com\") }, Action(Boost(100.5)) }"),
+            b_first
+        );
+        assert_eq!(
+            search("Rule { Matches { Site(\"a.com\") }, Action(Multiply(0.01)) }"),
+            b_first
+        );
+        assert_eq!(
+            search("Rule { Matches { Site(\"b.com\") }, Action(Multiply(0.01)) }"),
+            a_first
+        );
+        assert_eq!(
+            search(
+                r#"
+                Rule { Matches { Site("a.com") }, Action(SetSignal(Signal("host_centrality"), 0)) };
+                Rule { Matches { Site("b.com") }, Action(SetSignal(Signal("host_centrality"), 100000)) };
+                "#
+            ),
+            b_first
+        );
+
+        assert!(searcher
+            .search(&SearchQuery {
+                query: "website".to_string(),
+                optic: Some(
+                    Optic::parse(
+                        "Rule { Matches { Site(\"a.com\") }, Action(SetSignal(Signal(\"not_a_signal\"), 1)) }"
+                    )
+                    .unwrap()
+                ),
+                ..Default::default()
+            })
+            .is_err());
+    }
 }
@@ -15,8 +15,12 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 pub const MAX_TERMS_PER_QUERY: usize = 32;
+const MAX_GROUP_DEPTH: usize = 8;
+const MAX_FRESHNESS_AMOUNT: i64 = 10_000;
 
-use crate::bangs::BANG_PREFIXES;
+use chrono::{Duration, NaiveDate};
+
+use crate::{bangs::BANG_PREFIXES, webpage::lang::Lang};
 
 mod term;
 
@@ -143,8 +147,109 @@ fn url_field(input: &str) -> nom::IResult<&str, Term> {
     Ok((input, Term::Url(output)))
 }
 
+/// Parse a date on the form `YYYY-MM-DD`, `YYYY-MM` or `YYYY`.
+/// Partial dates resolve to the first day of the month or year.
+pub fn parse_date(input: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(&format!("{input}-01"), "%Y-%m-%d").ok())
+        .or_else(|| NaiveDate::parse_from_str(&format!("{input}-01-01"), "%Y-%m-%d").ok())
+}
+
+/// Seconds since epoch at the start of `date` (UTC).
+pub fn start_of_day_timestamp(date: NaiveDate) -> u64 {
+    date.and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time")
+        .and_utc()
+        .timestamp()
+        .max(0) as u64
+}
+
+/// Parse a freshness on the form `<amount><unit>` where unit is one of
+/// `h` (hours), `d` (days), `w` (weeks), `m` (30 days) or `y` (365 days).
+pub fn parse_freshness(input: &str) -> Option<Duration> {
+    let unit = input.chars().last()?;
+    let amount: i64 = input[..input.len() - unit.len_utf8()].parse().ok()?;
+
+    if amount <= 0 || amount > MAX_FRESHNESS_AMOUNT {
+        return None;
+    }
+
+    match unit {
+        'h' => Some(Duration::hours(amount)),
+        'd' => Some(Duration::days(amount)),
+        'w' => Some(Duration::weeks(amount)),
+        'm' => Some(Duration::days(30 * amount)),
+        'y' => Some(Duration::days(365 * amount)),
+        _ => None,
+    }
+}
+
+fn fail(input: &str) -> nom::Err<nom::error::Error<&str>> {
+    nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Fail))
+}
+
+fn after_field(input: &str) -> nom::IResult<&str, Term> {
+    // parse 'after:' and then a date
+    let (rest, _) = nom::bytes::complete::tag("after:")(input)?;
+    let (rest, output) = simple_str(rest)?;
+    let date = parse_date(output).ok_or_else(|| fail(input))?;
+
+    Ok((rest, Term::After(date)))
+}
+
+fn before_field(input: &str) -> nom::IResult<&str, Term> {
+    // parse 'before:' and then a date
+    let (rest, _) = nom::bytes::complete::tag("before:")(input)?;
+    let (rest, output) = simple_str(rest)?;
+    let date = parse_date(output).ok_or_else(|| fail(input))?;
+
+    Ok((rest, Term::Before(date)))
+}
+
+fn updated_field(input: &str) -> nom::IResult<&str, Term> {
+    // parse 'updated:' and then a freshness like '7d'
+    let (rest, _) = nom::bytes::complete::tag("updated:")(input)?;
+    let (rest, output) = simple_str(rest)?;
+    let freshness = parse_freshness(output).ok_or_else(|| fail(input))?;
+
+    Ok((rest, Term::Updated(freshness)))
+}
+
+fn lang_field(input: &str) -> nom::IResult<&str, Term> {
+    // parse 'lang:' and then a language code like 'de'
+    let (rest, _) = nom::bytes::complete::tag("lang:")(input)?;
+    let (rest, output) = simple_str(rest)?;
+    let lang = Lang::from_code(output).ok_or_else(|| fail(input))?;
+
+    Ok((rest, Term::Lang(lang)))
+}
+
+fn filetype_field(input: &str) -> nom::IResult<&str, Term> {
+    // parse 'filetype:' and then a file extension like 'pdf'
+    let (rest, _) = nom::bytes::complete::tag("filetype:")(input)?;
+    let (rest, output) = simple_str(rest)?;
+    let file_type = output.trim_start_matches('.').to_ascii_lowercase();
+
+    if file_type.is_empty() {
+        return Err(fail(input));
+    }
+
+    Ok((rest, Term::FileType(file_type)))
+}
+
 fn field_selector(input: &str) -> nom::IResult<&str, Term> {
-    nom::branch::alt((site_field, title_field, body_field, url_field))(input)
+    nom::branch::alt((
+        site_field,
+        title_field,
+        body_field,
+        url_field,
+        after_field,
+        before_field,
+        updated_field,
+        lang_field,
+        filetype_field,
+    ))(input)
 }
 
 fn not(input: &str) -> nom::IResult<&str, Term> {
@@ -181,6 +286,85 @@ fn ignore_weird_quotes(input: &str) -> nom::IResult<&str, &str> {
     nom::branch::alt((guillemet, up_down_quotes, rev_guillemet, squares))(input)
 }
 
+/// Find the byte offset of the parenthesis closing the group that
+/// `input` is the inside of. Parentheses inside quotes are ignored.
+fn closing_paren(input: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut in_quotes = false;
+
+    for (i, c) in input.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '(' if !in_quotes => {
+                depth += 1;
+
+                if depth > MAX_GROUP_DEPTH {
+                    return None;
+                }
+            }
+            ')' if !in_quotes => {
+                if depth == 0 {
+                    return Some(i);
+                }
+
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn group(input: &str) -> nom::IResult<&str, Term> {
+    let (input, _) = nom::character::complete::char('(')(input)?;
+
+    let end = closing_paren(input).ok_or(nom::Err::Error(nom::error::Error::new(
+        input,
+        nom::error::ErrorKind::Fail,
+    )))?;
+
+    let inner = &input[..end];
+    let rest = &input[end + 1..];
+
+    if inner.chars().all(char::is_whitespace) {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Fail,
+        )));
+    }
+
+    let (_, terms) = expression(inner)?;
+
+    Ok((rest, Term::Group(terms)))
+}
+
+fn or_operator(input: &str) -> nom::IResult<&str, &str> {
+    let (input, _) = nom::character::complete::multispace0(input)?;
+    let (input, op) = nom::branch::alt((
+        nom::bytes::complete::tag("OR"),
+        nom::bytes::complete::tag("|"),
+    ))(input)?;
+
+    // the operator must stand on its own, otherwise it is the start of a term like 'ORACLE'
+    nom::combinator::peek(nom::branch::alt((
+        nom::character::complete::multispace1,
+        nom::bytes::complete::tag("("),
+    )))(input)?;
+
+    Ok((input, op))
+}
+
+fn or_term(input: &str) -> nom::IResult<&str, Term> {
+    let (input, mut alternatives) = nom::multi::separated_list1(or_operator, term)(input)?;
+
+    if alternatives.len() == 1 {
+        Ok((input, alternatives.pop().unwrap()))
+    } else {
+        Ok((input, Term::Or(alternatives)))
+    }
+}
+
 fn term(input: &str) -> nom::IResult<&str, Term> {
     let (mut input, _) = trim_leading_whitespace(input)?;
 
@@ -188,7 +372,13 @@ fn term(input: &str) -> nom::IResult<&str, Term> {
         input = new_input;
     }
 
-    nom::branch::alt((phrase_term, bang, field_selector, not, simple_term))(input)
+    nom::branch::alt((group, phrase_term, bang, field_selector, not, simple_term))(input)
+}
+
+/// A sequence of terms that are implicitly AND'ed together.
+/// `OR` binds tighter than the implicit AND, so `a b OR c` is parsed as `a (b OR c)`.
+fn expression(input: &str) -> nom::IResult<&str, Vec<Term>> {
+    nom::multi::many1(or_term)(input)
 }
 
 pub fn parse(query: &str) -> anyhow::Result<Vec<Term>> {
@@ -196,24 +386,69 @@ pub fn parse(query: &str) -> anyhow::Result<Vec<Term>> {
         return Ok(vec![]);
     }
 
-    nom::multi::many1(term)(query)
+    expression(query)
         .map(|(_, res)| res)
         .map_err(|e| anyhow::anyhow!("Failed to parse query: {:?}", e))
 }
 
+/// Truncate the query to at most `MAX_TERMS_PER_QUERY` terms. Terms
+/// nested inside groups and `OR` alternatives count towards the limit.
 pub fn truncate(terms: Vec<Term>) -> Vec<Term> {
-    terms
-        .into_iter()
-        .take(MAX_TERMS_PER_QUERY)
-        .map(|t| t.truncate())
-        .collect()
+    let mut remaining = MAX_TERMS_PER_QUERY;
+    truncate_terms(terms, &mut remaining)
+}
+
+fn truncate_terms(terms: Vec<Term>, remaining: &mut usize) -> Vec<Term> {
+    let mut res = Vec::new();
+
+    for term in terms {
+        if *remaining == 0 {
+            break;
+        }
+
+        if let Some(term) = truncate_term(term, remaining) {
+            res.push(term);
+        }
+    }
+
+    res
+}
+
+fn truncate_term(term: Term, remaining: &mut usize) -> Option<Term> {
+    match term {
+        Term::Group(terms) => {
+            let terms = truncate_terms(terms, remaining);
+
+            if terms.is_empty() {
+                None
+            } else {
+                Some(Term::Group(terms))
+            }
+        }
+        Term::Or(alternatives) => {
+            let mut alternatives = truncate_terms(alternatives, remaining);
+
+            match alternatives.len() {
+                0 => None,
+                1 => alternatives.pop(),
+                _ => Some(Term::Or(alternatives)),
+            }
+        }
+        Term::Not(inner) => truncate_term(*inner, remaining).map(|t| Term::Not(Box::new(t))),
+        term => {
+            *remaining -= 1;
+            Some(term.truncate())
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use chrono::{Duration, NaiveDate};
     use proptest::prelude::*;
 
     use super::{SimpleOrPhrase, Term};
+    use crate::webpage::lang::Lang;
 
     fn parse(input: &str) -> Vec<Term> {
         super::truncate(super::parse(input).unwrap())
@@ -295,6 +530,66 @@ mod tests {
         );
     }
 
+    #[test]
+    fn dates() {
+        assert_eq!(
+            parse("changelog after:2024-01-01 before:2024-06"),
+            vec![
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("changelog".to_string().into())),
+                Term::After(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()),
+                Term::Before(NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()),
+            ]
+        );
+
+        assert_eq!(
+            parse("news updated:7d"),
+            vec![
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("news".to_string().into())),
+                Term::Updated(Duration::days(7)),
+            ]
+        );
+
+        assert_eq!(
+            parse("after:yesterday updated:7x"),
+            vec![
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("after:yesterday".to_string().into())),
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("updated:7x".to_string().into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn lang_and_filetype() {
+        assert_eq!(
+            parse("manual lang:de filetype:PDF"),
+            vec![
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("manual".to_string().into())),
+                Term::Lang(Lang::from_code("deu").unwrap()),
+                Term::FileType("pdf".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            parse("lang:klingon"),
+            vec![Term::SimpleOrPhrase(SimpleOrPhrase::Simple(
+                "lang:klingon".to_string().into()
+            ))]
+        );
+
+        assert_eq!(parse("filetype:.pdf")[0].to_string(), "filetype:pdf");
+        assert_eq!(parse("lang:deu")[0].to_string(), "lang:de");
+    }
+
+    #[test]
+    fn freshness_display() {
+        for freshness in ["12h", "3d", "2w", "6m", "1y"] {
+            let query = format!("updated:{freshness}");
+            assert_eq!(parse(&query)[0].to_string(), query);
+        }
+
+        assert_eq!(parse("updated:14d")[0].to_string(), "updated:2w");
+    }
+
     #[test]
     fn empty() {
         assert_eq!(parse(""), vec![]);
@@ -377,6 +672,120 @@ mod tests {
         );
     }
 
+    #[test]
+    fn or() {
+        assert_eq!(
+            parse("rust OR golang"),
+            vec![Term::Or(vec![
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("rust".to_string().into())),
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("golang".to_string().into())),
+            ])]
+        );
+
+        assert_eq!(
+            parse("async rust | golang"),
+            vec![
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("async".to_string().into())),
+                Term::Or(vec![
+                    Term::SimpleOrPhrase(SimpleOrPhrase::Simple("rust".to_string().into())),
+                    Term::SimpleOrPhrase(SimpleOrPhrase::Simple("golang".to_string().into())),
+                ])
+            ]
+        );
+
+        assert_eq!(
+            parse("rust or golang"),
+            vec![
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("rust".to_string().into())),
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("or".to_string().into())),
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("golang".to_string().into())),
+            ]
+        );
+
+        assert_eq!(
+            parse("rust ORACLE"),
+            vec![
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("rust".to_string().into())),
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("ORACLE".to_string().into())),
+            ]
+        );
+
+        assert_eq!(
+            parse("rust OR"),
+            vec![
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("rust".to_string().into())),
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("OR".to_string().into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn group() {
+        assert_eq!(
+            parse("(rust OR golang) intitle:async -site:example.com"),
+            vec![
+                Term::Group(vec![Term::Or(vec![
+                    Term::SimpleOrPhrase(SimpleOrPhrase::Simple("rust".to_string().into())),
+                    Term::SimpleOrPhrase(SimpleOrPhrase::Simple("golang".to_string().into())),
+                ])]),
+                Term::Title(SimpleOrPhrase::Simple("async".to_string().into())),
+                Term::Not(Box::new(Term::Site("example.com".to_string()))),
+            ]
+        );
+
+        assert_eq!(
+            parse("(async rust) OR -(\"green threads\" golang)"),
+            vec![Term::Or(vec![
+                Term::Group(vec![
+                    Term::SimpleOrPhrase(SimpleOrPhrase::Simple("async".to_string().into())),
+                    Term::SimpleOrPhrase(SimpleOrPhrase::Simple("rust".to_string().into())),
+                ]),
+                Term::Not(Box::new(Term::Group(vec![
+                    Term::SimpleOrPhrase(SimpleOrPhrase::Phrase(vec![
+                        "green".to_string(),
+                        "threads".to_string()
+                    ])),
+                    Term::SimpleOrPhrase(SimpleOrPhrase::Simple("golang".to_string().into())),
+                ]))),
+            ])]
+        );
+
+        assert_eq!(
+            parse("(rust"),
+            vec![Term::SimpleOrPhrase(SimpleOrPhrase::Simple(
+                "(rust".to_string().into()
+            ))]
+        );
+
+        assert_eq!(
+            parse("() rust)"),
+            vec![
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("()".to_string().into())),
+                Term::SimpleOrPhrase(SimpleOrPhrase::Simple("rust)".to_string().into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn display_roundtrip() {
+        for query in [
+            "(rust OR golang) intitle:async -site:example.com",
+            "a OR (b c) OR -d",
+            "(\"green threads\" OR async) rust",
+        ] {
+            let terms = parse(query);
+            let displayed = terms.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+            assert_eq!(displayed.join(" "), query);
+        }
+    }
+
+    #[test]
+    fn deeply_nested_groups() {
+        let query = format!("{}rust{}", "(".repeat(1024), ")".repeat(1024));
+        assert_eq!(parse(&query).len(), 1);
+    }
+
     #[test]
     fn unicode() {
         let query = "🦀";
@@ -393,6 +802,17 @@ mod tests {
         let q = q.repeat(1024);
         let terms = parse(&q);
         assert!(terms.len() < 1020);
+
+        let q = format!("({})", "test OR ".repeat(1024));
+        let terms = parse(&q);
+        assert_eq!(terms.len(), 1);
+        match &terms[0] {
+            Term::Group(inner) => match &inner[0] {
+                Term::Or(alternatives) => assert!(alternatives.len() <= super::MAX_TERMS_PER_QUERY),
+                _ => panic!("expected alternatives"),
+            },
+            _ => panic!("expected group"),
+        }
     }
 
     proptest! {
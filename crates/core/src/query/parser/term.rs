@@ -14,6 +14,10 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>
 
+use chrono::{Duration, NaiveDate};
+
+use crate::webpage::lang::Lang;
+
 const MAX_PHRASE_LENGTH: usize = 32;
 const MAX_TERM_LENGTH_CHARS: usize = 1024;
 
@@ -103,6 +107,29 @@ pub enum Term {
     Url(SimpleOrPhrase),
     PossibleBang { prefix: char, bang: String },
     Not(Box<Term>),
+    Or(Vec<Term>),
+    Group(Vec<Term>),
+    After(NaiveDate),
+    Before(NaiveDate),
+    Updated(Duration),
+    Lang(Lang),
+    FileType(String),
+}
+
+fn fmt_freshness(freshness: &Duration, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let hours = freshness.num_hours();
+
+    if hours % (24 * 365) == 0 {
+        write!(f, "{}y", hours / (24 * 365))
+    } else if hours % (24 * 30) == 0 {
+        write!(f, "{}m", hours / (24 * 30))
+    } else if hours % (24 * 7) == 0 {
+        write!(f, "{}w", hours / (24 * 7))
+    } else if hours % 24 == 0 {
+        write!(f, "{}d", hours / 24)
+    } else {
+        write!(f, "{}h", hours)
+    }
 }
 
 impl std::fmt::Display for Term {
@@ -115,6 +142,38 @@ impl std::fmt::Display for Term {
             Term::Body(body) => write!(f, "inbody:{}", body),
             Term::Url(url) => write!(f, "inurl:{}", url),
             Term::PossibleBang { prefix, bang } => write!(f, "{}{}", prefix, bang),
+            Term::After(date) => write!(f, "after:{}", date.format("%Y-%m-%d")),
+            Term::Before(date) => write!(f, "before:{}", date.format("%Y-%m-%d")),
+            Term::Updated(freshness) => {
+                write!(f, "updated:")?;
+                fmt_freshness(freshness, f)
+            }
+            Term::Lang(lang) => write!(f, "lang:{}", lang),
+            Term::FileType(file_type) => write!(f, "filetype:{}", file_type),
+            Term::Or(alternatives) => {
+                for (i, term) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " OR ")?;
+                    }
+
+                    write!(f, "{}", term)?;
+                }
+
+                Ok(())
+            }
+            Term::Group(terms) => {
+                write!(f, "(")?;
+
+                for (i, term) in terms.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+
+                    write!(f, "{}", term)?;
+                }
+
+                write!(f, ")")
+            }
         }
     }
 }
@@ -127,14 +186,35 @@ impl Term {
         }
     }
 
+    /// Text of all simple terms and phrases that must or may match,
+    /// including those nested inside groups and `OR` alternatives.
+    pub fn simple_texts(&self) -> Vec<String> {
+        match self {
+            Term::SimpleOrPhrase(term) => vec![term.as_string()],
+            Term::Or(terms) | Term::Group(terms) => {
+                terms.iter().flat_map(|term| term.simple_texts()).collect()
+            }
+            _ => vec![],
+        }
+    }
+
     pub fn truncate(self) -> Term {
         match self {
             Term::SimpleOrPhrase(s) => Term::SimpleOrPhrase(s.truncate()),
             Term::Site(s) => Term::Site(s),
+            Term::After(d) => Term::After(d),
+            Term::Before(d) => Term::Before(d),
+            Term::Updated(d) => Term::Updated(d),
+            Term::Lang(l) => Term::Lang(l),
+            Term::FileType(s) => Term::FileType(s.chars().take(MAX_TERM_LENGTH_CHARS).collect()),
             Term::Title(s) => Term::Title(s.truncate()),
             Term::Body(s) => Term::Body(s.truncate()),
             Term::Url(s) => Term::Url(s.truncate()),
             Term::Not(n) => Term::Not(Box::new(n.truncate())),
+            Term::Or(alternatives) => {
+                Term::Or(alternatives.into_iter().map(|t| t.truncate()).collect())
+            }
+            Term::Group(terms) => Term::Group(terms.into_iter().map(|t| t.truncate()).collect()),
             Term::PossibleBang { prefix, bang } => Term::PossibleBang {
                 prefix,
                 bang: bang.chars().take(MAX_TERM_LENGTH_CHARS).collect(),
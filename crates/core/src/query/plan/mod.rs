@@ -14,13 +14,23 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/
 
+use std::ops::Bound;
+
 use itertools::Itertools;
 use tantivy::tokenizer::Tokenizer as _;
 mod node;
 
 pub use node::Node;
 
-use crate::schema::{self, text_field::TextField, TextFieldEnum};
+use crate::{
+    schema::{
+        self,
+        fast_field::{self, FastField},
+        text_field::TextField,
+        FastFieldEnum, TextFieldEnum,
+    },
+    webpage::lang::Lang,
+};
 
 use super::{
     parser::{SimpleOrPhrase, SimpleTerm},
@@ -39,6 +49,52 @@ impl Term {
     }
 }
 
+/// Matches documents where the value of a fast field is within the bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FastFieldRange {
+    field: FastFieldEnum,
+    lower: Bound<u64>,
+    upper: Bound<u64>,
+}
+
+impl FastFieldRange {
+    pub fn new(field: FastFieldEnum, lower: Bound<u64>, upper: Bound<u64>) -> Self {
+        Self {
+            field,
+            lower,
+            upper,
+        }
+    }
+
+    pub fn equals(field: FastFieldEnum, value: u64) -> Self {
+        Self::new(field, Bound::Included(value), Bound::Included(value))
+    }
+
+    /// Webpages where the detected language is `lang`.
+    pub fn language(lang: Lang) -> Self {
+        Self::equals(fast_field::Language.into(), lang.id())
+    }
+
+    /// Webpages last updated at or after `timestamp` (seconds since epoch).
+    pub fn updated_after(timestamp: u64) -> Self {
+        Self::new(
+            fast_field::LastUpdated.into(),
+            Bound::Included(timestamp),
+            Bound::Unbounded,
+        )
+    }
+
+    /// Webpages last updated before `timestamp` (seconds since epoch).
+    /// Webpages where the update time is unknown are not included.
+    pub fn updated_before(timestamp: u64) -> Self {
+        Self::new(
+            fast_field::LastUpdated.into(),
+            Bound::Included(1),
+            Bound::Excluded(timestamp),
+        )
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Occur {
     Must,
@@ -71,6 +127,7 @@ impl From<Occur> for tantivy::query::Occur {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Query {
     Term(Term),
+    Range(FastFieldRange),
     Boolean { clauses: Vec<(Occur, Query)> },
 }
 
@@ -78,7 +135,7 @@ impl Query {
     #[cfg(test)]
     pub fn len(&self) -> usize {
         match self {
-            Query::Term(_) => 1,
+            Query::Term(_) | Query::Range(_) => 1,
             Query::Boolean { clauses } => clauses.iter().map(|(_, q)| q.len()).sum(),
         }
     }
@@ -95,16 +152,22 @@ impl Query {
                     match query {
                         Query::Boolean {
                             clauses: inner_clauses,
-                        } if inner_clauses
-                            .iter()
-                            .all(|(inner_occur, _)| occur == *inner_occur) =>
+                        } if occur != Occur::MustNot
+                            && inner_clauses
+                                .iter()
+                                .all(|(inner_occur, _)| occur == *inner_occur) =>
                         {
                             new_clauses.extend(inner_clauses);
                         }
 
+                        // a negated operand of an OR (`a OR -b`) must stay its own
+                        // clause, otherwise it would exclude `b` from the entire query
                         Query::Boolean {
                             clauses: inner_clauses,
-                        } if inner_clauses.len() == 1 => {
+                        } if inner_clauses.len() == 1
+                            && !(occur == Occur::Should
+                                && inner_clauses[0].0 == Occur::MustNot) =>
+                        {
                             let (inner_occur, q) = inner_clauses.into_iter().next().unwrap();
 
                             new_clauses.push((Occur::compose(occur, inner_occur), q));
@@ -118,6 +181,7 @@ impl Query {
                 }
             }
             Query::Term(term) => Query::Term(term),
+            Query::Range(range) => Query::Range(range),
         }
     }
 
@@ -131,6 +195,7 @@ impl Query {
                     .collect(),
             },
             Query::Term(term) => Query::Term(term),
+            Query::Range(range) => Query::Range(range),
         }
     }
 
@@ -186,18 +251,53 @@ impl Query {
                     }
                 }
             },
-            Query::Boolean { clauses } => {
-                let mut t_clauses = Vec::new();
-                for (occur, query) in clauses {
-                    if let Some(query) = query.as_tantivy(lang, schema) {
-                        t_clauses.push(((*occur).into(), query));
-                    }
-                }
+            Query::Range(FastFieldRange {
+                field,
+                lower,
+                upper,
+            }) => Some(Box::new(tantivy::query::RangeQuery::new_u64_bounds(
+                field.name().to_string(),
+                *lower,
+                *upper,
+            ))),
+            Query::Boolean { clauses } => Some(boolean_as_tantivy(clauses, false, lang, schema)),
+        }
+    }
+}
 
-                Some(Box::new(tantivy::query::BooleanQuery::new(t_clauses)))
-            }
+fn boolean_as_tantivy(
+    clauses: &[(Occur, Query)],
+    nested: bool,
+    lang: Option<&whatlang::Lang>,
+    schema: &tantivy::schema::Schema,
+) -> Box<dyn tantivy::query::Query> {
+    let mut t_clauses: Vec<(tantivy::query::Occur, Box<dyn tantivy::query::Query>)> = Vec::new();
+    for (occur, query) in clauses {
+        let query = match query {
+            Query::Boolean { clauses } => Some(boolean_as_tantivy(clauses, true, lang, schema)),
+            _ => query.as_tantivy(lang, schema),
+        };
+
+        if let Some(query) = query {
+            t_clauses.push(((*occur).into(), query));
         }
     }
+
+    // tantivy never matches a boolean query that only excludes documents,
+    // so a nested negation (like `-b` in `a OR -b`) must start from all documents.
+    if nested
+        && !t_clauses.is_empty()
+        && t_clauses
+            .iter()
+            .all(|(occur, _)| *occur == tantivy::query::Occur::MustNot)
+    {
+        t_clauses.push((
+            tantivy::query::Occur::Must,
+            Box::new(tantivy::query::AllQuery),
+        ));
+    }
+
+    Box::new(tantivy::query::BooleanQuery::new(t_clauses))
 }
 
 fn process_tantivy_term<T: TextField>(
@@ -416,6 +516,35 @@ mod tests {
         assert_eq!(query.into_query().compact(), expected);
     }
 
+    #[test]
+    fn test_compact_negated_or() {
+        let a = Query::Term(Term {
+            text: SimpleOrPhrase::Simple(SimpleTerm::from("a".to_string())),
+            field: text_field::Title.into(),
+        });
+        let b = Query::Term(Term {
+            text: SimpleOrPhrase::Simple(SimpleTerm::from("b".to_string())),
+            field: text_field::Title.into(),
+        });
+
+        let query = parse("a", &[text_field::Title.into()])
+            .or(Node::Not(Box::new(parse("b", &[text_field::Title.into()]))));
+
+        let expected = Query::Boolean {
+            clauses: vec![
+                (Occur::Should, a),
+                (
+                    Occur::Should,
+                    Query::Boolean {
+                        clauses: vec![(Occur::MustNot, b)],
+                    },
+                ),
+            ],
+        };
+
+        assert_eq!(query.into_query(), expected);
+    }
+
     #[test]
     fn test_sliding_window() {
         let window_size = 3;
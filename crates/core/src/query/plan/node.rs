@@ -15,18 +15,20 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/
 
 use ahash::HashSetExt;
+use chrono::Utc;
 
-use crate::query::parser::{SimpleOrPhrase, Term as ParserTerm};
+use crate::query::parser::{start_of_day_timestamp, SimpleOrPhrase, Term as ParserTerm};
 use crate::schema::text_field::{self, TextField as _};
 use crate::{query::parser::SimpleTerm, schema::TextFieldEnum};
 
 type HashSet<T> = std::collections::HashSet<T, ahash::RandomState>;
 
-use super::{Occur, Term};
+use super::{FastFieldRange, Occur, Term};
 
 #[derive(Debug, Clone)]
 pub enum Node {
     Term(Term),
+    Range(FastFieldRange),
     And(Box<Node>, Box<Node>),
     Or(Box<Node>, Box<Node>),
     Not(Box<Node>),
@@ -36,6 +38,7 @@ impl PartialEq for Node {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Node::Term(a), Node::Term(b)) => a == b,
+            (Node::Range(a), Node::Range(b)) => a == b,
             (Node::And(a, b), Node::And(c, d)) => (a == c && b == d) || (a == d && b == c),
             (Node::Or(a, b), Node::Or(c, d)) => (a == c && b == d) || (a == d && b == c),
             (Node::Not(a), Node::Not(b)) => a == b,
@@ -50,6 +53,7 @@ impl std::hash::Hash for Node {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         match self {
             Node::Term(term) => term.hash(state),
+            Node::Range(range) => range.hash(state),
             Node::And(left, right) => {
                 left.hash(state);
                 right.hash(state);
@@ -77,6 +81,7 @@ impl Node {
     fn into_non_compacted_query(self) -> super::Query {
         match self {
             Node::Term(term) => super::Query::Term(term),
+            Node::Range(range) => super::Query::Range(range),
             Node::And(left, right) => super::Query::Boolean {
                 clauses: vec![
                     (Occur::Must, left.into_non_compacted_query()),
@@ -162,6 +167,27 @@ impl Node {
                     .expect("fields should not be empty")
             }
             ParserTerm::Not(n) => Node::Not(Box::new(Node::from_term(*n))),
+            ParserTerm::Or(alternatives) => alternatives
+                .into_iter()
+                .map(Node::from_term)
+                .reduce(|left, right| left.or(right))
+                .expect("alternatives should not be empty"),
+            ParserTerm::Group(terms) => super::initial(terms).expect("groups should not be empty"),
+            ParserTerm::After(date) => {
+                Node::Range(FastFieldRange::updated_after(start_of_day_timestamp(date)))
+            }
+            ParserTerm::Before(date) => {
+                Node::Range(FastFieldRange::updated_before(start_of_day_timestamp(date)))
+            }
+            ParserTerm::Lang(lang) => Node::Range(FastFieldRange::language(lang)),
+            ParserTerm::FileType(file_type) => Node::Term(Term {
+                text: SimpleOrPhrase::Simple(SimpleTerm::from(file_type)),
+                field: text_field::FileType.into(),
+            }),
+            ParserTerm::Updated(freshness) => {
+                let since = (Utc::now() - freshness).timestamp().max(0) as u64;
+                Node::Range(FastFieldRange::updated_after(since))
+            }
         }
     }
 
@@ -213,6 +239,7 @@ impl Optimisation for DistributiveLaw {
     fn optimise(&self, node: Node) -> Node {
         match node {
             Node::Term(term) => Node::Term(term),
+            Node::Range(range) => Node::Range(range),
             Node::Not(inner) => Node::Not(Box::new(self.optimise(*inner))),
             Node::Or(left, right) => Node::Or(
                 Box::new(self.optimise(*left)),
@@ -272,6 +299,7 @@ impl Optimisation for Deduplicate {
     fn optimise(&self, node: Node) -> Node {
         match node {
             Node::Term(term) => Node::Term(term),
+            Node::Range(range) => Node::Range(range),
             Node::Not(inner) => Node::Not(Box::new(self.optimise(*inner))),
             Node::Or(left, right) => {
                 let left = self.optimise(*left);
@@ -78,11 +78,20 @@ pub struct Score {
 
 impl ScoreSegmentTweaker<Score> for InitialSegmentScoreTweaker {
     fn score(&mut self, doc: DocId, _score: tantivy::Score) -> Score {
+        let optic_coefficients = self.computer.optic_coefficients(doc);
+
         let mut total = self
             .computer
             .compute_signals(doc)
             .flatten()
-            .map(|computed| self.computer.coefficient(&computed.signal) * computed.score)
+            .map(|computed| {
+                let coefficient = optic_coefficients
+                    .get(computed.signal)
+                    .copied()
+                    .unwrap_or_else(|| self.computer.coefficient(&computed.signal));
+
+                coefficient * computed.score
+            })
             .sum();
 
         if let Some(boost) = self.computer.boosts(doc) {
@@ -268,6 +268,73 @@ mod tests {
         assert_eq!(result.webpages[1].url, "https://www.a.com/");
     }
 
+    #[test]
+    fn trustrank_ranking() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        index
+            .insert(&Webpage {
+                html: Html::parse(
+                    &format!(
+                        r#"
+                    <html>
+                        <head>
+                            <title>Website A</title>
+                        </head>
+                        <body>
+                            {CONTENT} {}
+                        </body>
+                    </html>
+                "#,
+                        crate::rand_words(100)
+                    ),
+                    "https://www.a.com",
+                )
+                .unwrap(),
+                fetch_time_ms: 500,
+                host_pagerank_rank: 0,
+                ..Default::default()
+            })
+            .expect("failed to insert webpage");
+        index
+            .insert(&Webpage {
+                html: Html::parse(
+                    &format!(
+                        r#"
+                    <html>
+                        <head>
+                            <title>Website B</title>
+                        </head>
+                        <body>
+                            {CONTENT} {}
+                        </body>
+                    </html>
+                "#,
+                        crate::rand_words(100)
+                    ),
+                    "https://www.b.com",
+                )
+                .unwrap(),
+                fetch_time_ms: 500,
+                host_trustrank_rank: 0,
+                ..Default::default()
+            })
+            .expect("failed to insert webpage");
+
+        index.commit().expect("failed to commit index");
+        let searcher = LocalSearcher::from(index);
+        let result = searcher
+            .search(&SearchQuery {
+                query: "example".to_string(),
+                ..Default::default()
+            })
+            .expect("Search failed");
+        // plain pagerank is only used when an optic or model gives it a coefficient
+        assert_eq!(result.webpages.len(), 2);
+        assert_eq!(result.webpages[0].url, "https://www.b.com/");
+        assert_eq!(result.webpages[1].url, "https://www.a.com/");
+    }
+
     #[test]
     fn freshness_ranking() {
         let mut index = Index::temporary().expect("Unable to open index");
@@ -762,6 +829,7 @@ mod tests {
             }),
             host_centrality_threshold: None,
             minimum_clean_words: None,
+            regions_path: None,
             batch_size: 10,
             autocommit_after_num_inserts:
                 crate::config::defaults::Indexing::autocommit_after_num_inserts(),
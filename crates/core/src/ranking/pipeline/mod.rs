@@ -38,6 +38,8 @@ pub trait RankableWebpage: collector::Doc + Send + Sync {
     fn set_score(&mut self, score: f64);
     fn boost(&self) -> Option<f64>;
     fn signals(&self) -> &EnumMap<SignalEnum, f64>;
+    /// Signal coefficients set by optic rules that match the webpage.
+    fn optic_coefficients(&self) -> &EnumMap<SignalEnum, f64>;
 
     fn boost_score(&mut self) {
         if let Some(boost) = self.boost() {
@@ -61,6 +63,9 @@ struct RankingStage<T> {
     derank_similar: bool,
     model: Option<Arc<LambdaMART>>,
     coefficients: SignalCoefficient,
+    /// Whether the optic of the query sets signal coefficients. The model can't
+    /// take these into account, so the linear score is used for all results instead.
+    optic_sets_signals: bool,
 }
 
 impl<T: RankableWebpage> RankingStage<T> {
@@ -83,7 +88,8 @@ impl<T: RankableWebpage> RankingStage<T> {
             BucketCollector::new(self.stage_top_n.max(top_n) + offset, collector_config);
 
         for mut website in websites {
-            website.set_score(self.calculate_score(website.signals()));
+            website
+                .set_score(self.calculate_score(website.signals(), website.optic_coefficients()));
             website.boost_score();
             collector.insert(website);
         }
@@ -95,23 +101,35 @@ impl<T: RankableWebpage> RankingStage<T> {
             .collect()
     }
 
-    fn calculate_score(&self, signals: &EnumMap<SignalEnum, f64>) -> f64 {
-        match self.model.as_ref() {
+    fn calculate_score(
+        &self,
+        signals: &EnumMap<SignalEnum, f64>,
+        optic_coefficients: &EnumMap<SignalEnum, f64>,
+    ) -> f64 {
+        let linear = || {
+            signals
+                .iter()
+                .map(|(signal, score)| {
+                    let coefficient = optic_coefficients
+                        .get(signal)
+                        .copied()
+                        .unwrap_or_else(|| self.coefficients.get(&signal));
+
+                    coefficient * score
+                })
+                .sum()
+        };
+
+        match self.model.as_ref().filter(|_| !self.optic_sets_signals) {
             Some(model) => {
                 let coeff = self.coefficients.get(&super::signal::LambdaMart.into());
                 if coeff == 0.0 {
-                    signals
-                        .iter()
-                        .map(|(signal, score)| self.coefficients.get(&signal) * score)
-                        .sum()
+                    linear()
                 } else {
                     coeff * model.predict(signals)
                 }
             }
-            None => signals
-                .iter()
-                .map(|(signal, score)| self.coefficients.get(&signal) * score)
-                .sum(),
+            None => linear(),
         }
     }
 
@@ -119,6 +137,12 @@ impl<T: RankableWebpage> RankingStage<T> {
         self.scorer.set_query_info(query);
 
         self.coefficients = query.signal_coefficients();
+        self.optic_sets_signals = query.optic.as_ref().is_some_and(|optic| {
+            optic
+                .rules
+                .iter()
+                .any(|rule| matches!(rule.action, optics::Action::SetSignal { .. }))
+        });
     }
 }
 
@@ -265,6 +289,55 @@ mod tests {
         assert_eq!(res, expected);
     }
 
+    #[test]
+    fn optic_signals_with_lambdamart() {
+        let model =
+            Arc::new(LambdaMART::parse(include_str!("../../../testcases/lambdamart.txt")).unwrap());
+
+        let mut signals = EnumMap::new();
+        signals.insert(ranking::signal::HostCentrality.into(), 0.5);
+
+        let mut optic_coefficients = EnumMap::new();
+        optic_coefficients.insert(ranking::signal::HostCentrality.into(), 2.0);
+
+        let pipeline = RankingPipeline::<LocalRecallRankingWebpage>::recall_stage(
+            &mut SearchQuery::default(),
+            Some(model.clone()),
+            None,
+            CollectorConfig::default(),
+            20,
+        );
+        assert_eq!(
+            pipeline
+                .stage
+                .calculate_score(&signals, &optic_coefficients),
+            10.0 * model.predict(&signals)
+        );
+
+        // coefficients set by the optic are used instead of the model
+        let pipeline = RankingPipeline::<LocalRecallRankingWebpage>::recall_stage(
+            &mut SearchQuery {
+                optic: Some(
+                    optics::Optic::parse(
+                        r#"Rule { Matches { Site("a.com") }, Action(SetSignal(Signal("host_centrality"), 2)) }"#,
+                    )
+                    .unwrap(),
+                ),
+                ..Default::default()
+            },
+            Some(model),
+            None,
+            CollectorConfig::default(),
+            20,
+        );
+        assert_eq!(
+            pipeline
+                .stage
+                .calculate_score(&signals, &optic_coefficients),
+            1.0
+        );
+    }
+
     #[test]
     fn offsets() {
         let num_results = 20;
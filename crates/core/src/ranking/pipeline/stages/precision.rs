@@ -77,6 +77,10 @@ impl RankableWebpage for PrecisionRankingWebpage {
     fn signals(&self) -> &EnumMap<SignalEnum, f64> {
         self.ranking.signals()
     }
+
+    fn optic_coefficients(&self) -> &EnumMap<SignalEnum, f64> {
+        self.ranking.optic_coefficients()
+    }
 }
 
 impl PrecisionRankingWebpage {
@@ -112,6 +116,7 @@ impl RankingPipeline<PrecisionRankingWebpage> {
             derank_similar: true,
             model: lambda,
             coefficients: Default::default(),
+            optic_sets_signals: false,
         };
 
         Ok(Self {
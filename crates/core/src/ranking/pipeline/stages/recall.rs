@@ -84,6 +84,10 @@ impl RecallRankingWebpage {
         self.local.boost()
     }
 
+    pub fn optic_coefficients(&self) -> &EnumMap<SignalEnum, f64> {
+        self.local.optic_coefficients()
+    }
+
     pub fn set_score(&mut self, score: f64) {
         self.local.set_score(score)
     }
@@ -119,6 +123,10 @@ impl RankableWebpage for RecallRankingWebpage {
     fn signals(&self) -> &EnumMap<SignalEnum, f64> {
         self.local.signals()
     }
+
+    fn optic_coefficients(&self) -> &EnumMap<SignalEnum, f64> {
+        self.local.optic_coefficients()
+    }
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode)]
@@ -126,6 +134,7 @@ pub struct LocalRecallRankingWebpage {
     pointer: WebpagePointer,
     signals: EnumMap<SignalEnum, f64>,
     optic_boost: Option<f64>,
+    optic_coefficients: EnumMap<SignalEnum, f64>,
     title_embedding: Option<StoredEmbeddings>,
     keyword_embedding: Option<StoredEmbeddings>,
     score: f64,
@@ -143,6 +152,7 @@ impl LocalRecallRankingWebpage {
             pointer,
             signals,
             optic_boost: None,
+            optic_coefficients: EnumMap::new(),
             title_embedding: None,
             keyword_embedding: None,
             score,
@@ -179,6 +189,7 @@ impl LocalRecallRankingWebpage {
             signals: EnumMap::new(),
             score: pointer.score.total,
             optic_boost: None,
+            optic_coefficients: EnumMap::new(),
             pointer: pointer.clone(),
             title_embedding: title_embedding.map(StoredEmbeddings),
             keyword_embedding: keyword_embedding.map(StoredEmbeddings),
@@ -194,6 +205,8 @@ impl LocalRecallRankingWebpage {
             res.optic_boost = Some(boost);
         }
 
+        res.optic_coefficients = computer.optic_coefficients(pointer.address.doc_id);
+
         res
     }
 
@@ -225,6 +238,10 @@ impl LocalRecallRankingWebpage {
         self.optic_boost
     }
 
+    pub fn optic_coefficients(&self) -> &EnumMap<SignalEnum, f64> {
+        &self.optic_coefficients
+    }
+
     pub fn set_score(&mut self, score: f64) {
         self.score = score;
     }
@@ -246,6 +263,10 @@ impl RankableWebpage for LocalRecallRankingWebpage {
     fn signals(&self) -> &EnumMap<SignalEnum, f64> {
         &self.signals
     }
+
+    fn optic_coefficients(&self) -> &EnumMap<SignalEnum, f64> {
+        &self.optic_coefficients
+    }
 }
 
 impl collector::Doc for LocalRecallRankingWebpage {
@@ -272,6 +293,7 @@ impl RankingPipeline<LocalRecallRankingWebpage> {
             derank_similar: true,
             model: lambdamart,
             coefficients: Default::default(),
+            optic_sets_signals: false,
         };
 
         Self {
@@ -314,6 +336,7 @@ impl RankingPipeline<api::ScoredWebpagePointer> {
             derank_similar: true,
             model: lambdamart,
             coefficients: Default::default(),
+            optic_sets_signals: false,
         };
 
         Self {
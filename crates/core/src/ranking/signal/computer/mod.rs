@@ -14,11 +14,16 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::query::optic::AsSearchableRule;
+use crate::query::optic::{AsSearchableRule, RuleEffect};
 use crate::query::{Query, MAX_TERMS_FOR_NGRAM_LOOKUPS};
 use crate::schema::text_field::TextField;
 use crate::Result;
-use crate::{enum_map::EnumMap, fastfield_reader, schema::TextFieldEnum, webpage::Webpage};
+use crate::{
+    enum_map::{EnumMap, EnumSet},
+    fastfield_reader,
+    schema::TextFieldEnum,
+    webpage::Webpage,
+};
 
 use std::cell::RefCell;
 
@@ -51,7 +56,18 @@ pub struct TextFieldData {
 
 pub struct RuleBoost {
     docset: Box<dyn Scorer>,
-    boost: f64,
+    effect: RuleEffect,
+}
+
+impl RuleBoost {
+    /// Whether the rule matches `doc`. Documents must be checked in ascending order.
+    fn matches(&mut self, doc: DocId) -> bool {
+        if self.docset.doc() > doc {
+            return false;
+        }
+
+        self.docset.doc() == doc || self.docset.seek(doc) == doc
+    }
 }
 
 pub struct OpticBoosts {
@@ -78,6 +94,8 @@ impl SegmentReader {
 pub struct QueryData {
     simple_terms: Vec<String>,
     optic_rules: Vec<optics::Rule>,
+    /// Signals that optic rules set the coefficient of.
+    optic_signals: EnumSet<SignalEnum>,
     selected_region: Option<crate::webpage::Region>,
     lang: Option<whatlang::Lang>,
 }
@@ -142,20 +160,36 @@ impl SignalComputer {
             .map(|hours_since_update| 1.0 / ((hours_since_update as f64 + 1.0).log2()))
             .collect();
 
-        let query = query.as_ref().map(|q| QueryData {
-            simple_terms: q.simple_terms().to_vec(),
-            optic_rules: q
+        let query = query.as_ref().map(|q| {
+            let optic_rules: Vec<optics::Rule> = q
                 .optics()
                 .iter()
                 .flat_map(|o| o.rules.iter())
-                .filter(|rule| match rule.action {
-                    optics::Action::Downrank(b) | optics::Action::Boost(b) => b != 0,
+                .filter(|rule| match &rule.action {
+                    optics::Action::Downrank(b) | optics::Action::Boost(b) => *b != 0.0,
+                    optics::Action::Multiply(factor) => *factor != 1.0,
+                    optics::Action::SetSignal { .. } => true,
                     optics::Action::Discard => false,
                 })
                 .cloned()
-                .collect(),
-            selected_region: q.region().cloned(),
-            lang: q.lang(),
+                .collect();
+
+            let mut optic_signals = EnumSet::new();
+            for rule in &optic_rules {
+                if let optics::Action::SetSignal { signal, .. } = &rule.action {
+                    if let Some(signal) = SignalEnum::from_optic_name(signal) {
+                        optic_signals.insert(signal);
+                    }
+                }
+            }
+
+            QueryData {
+                simple_terms: q.simple_terms().to_vec(),
+                optic_rules,
+                optic_signals,
+                selected_region: q.region().cloned(),
+                lang: q.lang(),
+            }
         });
 
         let mut s = Self {
@@ -270,7 +304,7 @@ impl SignalComputer {
                         .unwrap()
                         .scorer(segment_reader, 0.0)
                         .unwrap(),
-                    boost: rule.boost,
+                    effect: rule.effect,
                 })
                 .collect();
         }
@@ -326,30 +360,68 @@ impl SignalComputer {
         self.segment_reader.as_ref().map(|segment_reader| {
             let mut downrank = 0.0;
             let mut boost = 0.0;
+            let mut multiplier = 1.0;
 
             for rule in &mut segment_reader.borrow_mut().optic_boosts.rules {
-                if rule.docset.doc() > doc {
+                if !rule.matches(doc) {
                     continue;
                 }
 
-                if rule.docset.doc() == doc || rule.docset.seek(doc) == doc {
-                    if rule.boost < 0.0 {
-                        downrank += rule.boost.abs();
-                    } else {
-                        boost += rule.boost;
-                    }
+                match rule.effect {
+                    RuleEffect::Boost(b) if b < 0.0 => downrank += b.abs(),
+                    RuleEffect::Boost(b) => boost += b,
+                    RuleEffect::Multiply(factor) => multiplier *= factor,
+                    RuleEffect::SetCoefficient(..) => {}
                 }
             }
 
-            if downrank > boost {
+            let boost = if downrank > boost {
                 let diff = downrank - boost;
                 1.0 / (1.0 + diff)
             } else {
                 boost - downrank + 1.0
-            }
+            };
+
+            boost * multiplier
         })
     }
 
+    /// The signal coefficients set by optic rules that match `doc`. If multiple
+    /// rules set the same signal, the last rule in the optic takes precedence.
+    pub fn optic_coefficients(&mut self, doc: DocId) -> EnumMap<SignalEnum, f64> {
+        let mut coefficients = EnumMap::new();
+
+        let has_optic_signals = self
+            .query_data
+            .as_ref()
+            .map(|query| !query.optic_signals.is_empty())
+            .unwrap_or(false);
+
+        if !has_optic_signals {
+            return coefficients;
+        }
+
+        if let Some(segment_reader) = self.segment_reader.as_ref() {
+            for rule in &mut segment_reader.borrow_mut().optic_boosts.rules {
+                if let RuleEffect::SetCoefficient(signal, coefficient) = rule.effect {
+                    if rule.matches(doc) {
+                        coefficients.insert(signal, coefficient);
+                    }
+                }
+            }
+        }
+
+        coefficients
+    }
+
+    /// Whether an optic rule sets the coefficient of `signal` for some documents.
+    pub fn has_optic_coefficient(&self, signal: &SignalEnum) -> bool {
+        self.query_data
+            .as_ref()
+            .map(|query| query.optic_signals.contains(*signal))
+            .unwrap_or(false)
+    }
+
     pub fn precompute_score(&self, webpage: &Webpage) -> f64 {
         SignalEnum::all()
             .filter_map(|signal| {
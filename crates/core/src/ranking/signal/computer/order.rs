@@ -43,7 +43,9 @@ impl SignalComputeOrder {
         let mut other_signals = Vec::new();
 
         for signal in SignalEnum::all() {
-            if signal_computer.coefficient(&signal) == 0.0 {
+            if signal_computer.coefficient(&signal) == 0.0
+                && !signal_computer.has_optic_coefficient(&signal)
+            {
                 continue;
             }
 
@@ -79,7 +81,10 @@ impl SignalComputeOrder {
             .chain(
                 self.other_signals
                     .iter()
-                    .filter(|signal| signal_computer.coefficient(signal) > 0.0)
+                    .filter(|signal| {
+                        signal_computer.coefficient(signal) > 0.0
+                            || signal_computer.has_optic_coefficient(signal)
+                    })
                     .map(move |signal| {
                         signal
                             .compute(doc, signal_computer)
@@ -73,7 +73,7 @@ fn score_region(webpage_region: crate::webpage::Region, computer: &SignalCompute
                 .query_data()
                 .and_then(|q| q.selected_region())
                 .map_or(0.0, |region| {
-                    if region != crate::webpage::Region::All && region == webpage_region {
+                    if region != crate::webpage::Region::ALL && region == webpage_region {
                         50.0
                     } else {
                         0.0
@@ -432,7 +432,7 @@ impl Signal for Region {
 
     fn precompute(self, webpage: &Webpage, signal_computer: &SignalComputer) -> Option<f64> {
         let region =
-            crate::webpage::Region::guess_from(webpage).unwrap_or(crate::webpage::Region::All);
+            crate::webpage::Region::guess_from(webpage).unwrap_or(crate::webpage::Region::ALL);
         Some(score_region(region, signal_computer))
     }
 
@@ -671,3 +671,79 @@ impl Signal for LinkDensity {
         Some(score_link_density(val as f64 / FLOAT_SCALING as f64))
     }
 }
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+    bincode::Encode,
+    bincode::Decode,
+)]
+pub struct HostPagerankRank;
+impl Signal for HostPagerankRank {
+    fn default_coefficient(&self) -> f64 {
+        0.0
+    }
+
+    fn as_field(&self) -> Option<Field> {
+        Some(Field::Fast(schema::fast_field::HostPagerankRank.into()))
+    }
+
+    fn precompute(self, webpage: &Webpage, _: &SignalComputer) -> Option<f64> {
+        Some(score_rank(webpage.host_pagerank_rank as f64))
+    }
+
+    fn compute(&self, doc: DocId, signal_computer: &SignalComputer) -> Option<f64> {
+        let seg_reader = signal_computer.segment_reader().unwrap().borrow_mut();
+        let fastfield_reader = seg_reader.fastfield_reader().get_field_reader(doc);
+
+        let val = fastfield_reader
+            .get(self.as_fastfield().unwrap())
+            .and_then(|v| v.as_u64())
+            .unwrap();
+        Some(score_rank(val as f64))
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+    bincode::Encode,
+    bincode::Decode,
+)]
+pub struct HostTrustrankRank;
+impl Signal for HostTrustrankRank {
+    fn default_coefficient(&self) -> f64 {
+        0.25
+    }
+
+    fn as_field(&self) -> Option<Field> {
+        Some(Field::Fast(schema::fast_field::HostTrustrankRank.into()))
+    }
+
+    fn precompute(self, webpage: &Webpage, _: &SignalComputer) -> Option<f64> {
+        Some(score_rank(webpage.host_trustrank_rank as f64))
+    }
+
+    fn compute(&self, doc: DocId, signal_computer: &SignalComputer) -> Option<f64> {
+        let seg_reader = signal_computer.segment_reader().unwrap().borrow_mut();
+        let fastfield_reader = seg_reader.fastfield_reader().get_field_reader(doc);
+
+        let val = fastfield_reader
+            .get(self.as_fastfield().unwrap())
+            .and_then(|v| v.as_u64())
+            .unwrap();
+        Some(score_rank(val as f64))
+    }
+}
@@ -115,6 +115,8 @@ pub enum SignalEnum {
     LinkDensity,
     TitleEmbeddingSimilarity,
     KeywordEmbeddingSimilarity,
+    HostPagerankRank,
+    HostTrustrankRank,
 }
 
 enum_dispatch_from_discriminant!(SignalEnumDiscriminants => SignalEnum,
@@ -159,6 +161,8 @@ enum_dispatch_from_discriminant!(SignalEnumDiscriminants => SignalEnum,
     LinkDensity,
     TitleEmbeddingSimilarity,
     KeywordEmbeddingSimilarity,
+    HostPagerankRank,
+    HostTrustrankRank,
 ]);
 
 impl SignalEnum {
@@ -179,6 +183,13 @@ impl SignalEnum {
             .copied()
             .map(SignalEnum::from)
     }
+
+    /// Find the signal an optic refers to by its serialized name (e.g. `host_centrality`).
+    pub fn from_optic_name(name: &str) -> Option<SignalEnum> {
+        name.parse::<SignalEnumDiscriminants>()
+            .ok()
+            .map(SignalEnum::from)
+    }
 }
 
 impl InsertEnumMapKey for SignalEnum {
@@ -25,7 +25,7 @@ use crate::{
     enum_dispatch_from_discriminant,
     enum_map::InsertEnumMapKey,
     simhash,
-    webpage::{html::FnCache, Html, Webpage},
+    webpage::{html::FnCache, lang::Lang, Html, Webpage},
     Result,
 };
 
@@ -129,6 +129,9 @@ pub enum FastFieldEnum {
     LinkDensity,
     TitleEmbeddings,
     KeywordEmbeddings,
+    Language,
+    HostPagerankRank,
+    HostTrustrankRank,
 }
 
 enum_dispatch_from_discriminant!(FastFieldEnumDiscriminants => FastFieldEnum,
@@ -172,6 +175,9 @@ enum_dispatch_from_discriminant!(FastFieldEnumDiscriminants => FastFieldEnum,
     LinkDensity,
     TitleEmbeddings,
     KeywordEmbeddings,
+    Language,
+    HostPagerankRank,
+    HostTrustrankRank,
 ]);
 
 impl FastFieldEnum {
@@ -454,7 +460,7 @@ impl FastField for Region {
         } else {
             doc.add_u64(
                 self.tantivy_field(schema),
-                crate::webpage::region::Region::All.id(),
+                crate::webpage::region::Region::ALL.id(),
             );
         }
 
@@ -1232,3 +1238,88 @@ impl FastField for KeywordEmbeddings {
         Ok(())
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Language;
+impl FastField for Language {
+    fn name(&self) -> &str {
+        "language"
+    }
+
+    fn is_stored(&self) -> bool {
+        true
+    }
+
+    fn add_html_tantivy(
+        &self,
+        html: &Html,
+        _cache: &mut FnCache,
+        doc: &mut TantivyDocument,
+        schema: &tantivy::schema::Schema,
+    ) -> Result<()> {
+        doc.add_u64(
+            self.tantivy_field(schema),
+            html.lang().map_or(0, |lang| Lang::from(*lang).id()),
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HostPagerankRank;
+impl FastField for HostPagerankRank {
+    fn name(&self) -> &str {
+        "host_pagerank_rank"
+    }
+
+    fn add_html_tantivy(
+        &self,
+        _html: &Html,
+        _cache: &mut FnCache,
+        _doc: &mut TantivyDocument,
+        _schema: &tantivy::schema::Schema,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn add_webpage_tantivy(
+        &self,
+        webpage: &Webpage,
+        doc: &mut TantivyDocument,
+        schema: &tantivy::schema::Schema,
+    ) -> Result<()> {
+        doc.add_u64(self.tantivy_field(schema), webpage.host_pagerank_rank);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HostTrustrankRank;
+impl FastField for HostTrustrankRank {
+    fn name(&self) -> &str {
+        "host_trustrank_rank"
+    }
+
+    fn add_html_tantivy(
+        &self,
+        _html: &Html,
+        _cache: &mut FnCache,
+        _doc: &mut TantivyDocument,
+        _schema: &tantivy::schema::Schema,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn add_webpage_tantivy(
+        &self,
+        webpage: &Webpage,
+        doc: &mut TantivyDocument,
+        schema: &tantivy::schema::Schema,
+    ) -> Result<()> {
+        doc.add_u64(self.tantivy_field(schema), webpage.host_trustrank_rank);
+
+        Ok(())
+    }
+}
@@ -184,6 +184,7 @@ pub enum TextFieldEnum {
     InsertionTimestamp,
     RecipeFirstIngredientTagId,
     Keywords,
+    FileType,
 }
 
 enum_dispatch_from_discriminant!(TextFieldEnumDiscriminants => TextFieldEnum,
@@ -220,6 +221,7 @@ enum_dispatch_from_discriminant!(TextFieldEnumDiscriminants => TextFieldEnum,
     InsertionTimestamp,
     RecipeFirstIngredientTagId,
     Keywords,
+    FileType,
 ]);
 
 impl TextFieldEnum {
@@ -1544,3 +1546,35 @@ impl TextField for Keywords {
         Ok(())
     }
 }
+
+/// The type of the document (e.g. `pdf`), which is the lowercased file extension
+/// of the url path for html pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileType;
+impl TextField for FileType {
+    fn name(&self) -> &str {
+        "file_type"
+    }
+
+    fn tokenizer(&self, _: Option<&whatlang::Lang>) -> Tokenizer {
+        Tokenizer::Identity(Identity {})
+    }
+
+    fn add_html_tantivy(
+        &self,
+        html: &Html,
+        _cache: &mut FnCache,
+        doc: &mut TantivyDocument,
+        schema: &tantivy::schema::Schema,
+    ) -> Result<()> {
+        let file_type = html.file_type().unwrap_or_default();
+
+        doc.add_text(
+            self.tantivy_field(schema)
+                .unwrap_or_else(|| panic!("could not find field '{}' in index", self.name())),
+            file_type,
+        );
+
+        Ok(())
+    }
+}
@@ -92,6 +92,10 @@ impl RankableWebpage for ScoredWebpagePointer {
             ScoredWebpagePointer::Live(p) => p.website.signals(),
         }
     }
+
+    fn optic_coefficients(&self) -> &EnumMap<SignalEnum, f64> {
+        self.as_ranking().optic_coefficients()
+    }
 }
 
 impl collector::Doc for ScoredWebpagePointer {
@@ -33,7 +33,7 @@ use crate::{
     config::defaults,
     ranking::{pipeline::LocalRecallRankingWebpage, SignalCoefficient},
     search_prettifier::DisplayedWebpage,
-    webpage::region::Region,
+    webpage::{lang::Lang, region::Region},
 };
 
 pub const NUM_RESULTS_PER_PAGE: usize = 20;
@@ -79,6 +79,9 @@ pub struct SearchQuery {
     pub count_results_exact: bool,
     pub return_body: Option<ReturnBody>,
     pub return_structured_data: bool,
+    pub updated_after: Option<u64>,
+    pub updated_before: Option<u64>,
+    pub languages: Vec<Lang>,
 
     pub signal_coefficients: SignalCoefficient,
 }
@@ -107,6 +110,9 @@ impl Default for SearchQuery {
             count_results_exact: defaults::SearchQuery::count_results_exact(),
             return_body: None,
             return_structured_data: defaults::SearchQuery::return_structured_data(),
+            updated_after: None,
+            updated_before: None,
+            languages: Vec::new(),
             signal_coefficients: Default::default(),
         }
     }
@@ -22,7 +22,7 @@ use url::Url;
 
 use crate::{
     ranking::{bitvec_similarity, inbound_similarity},
-    webgraph::{remote::RemoteWebgraph, EdgeLimit, Node, NodeID},
+    webgraph::{clusters::ClusterKind, remote::RemoteWebgraph, EdgeLimit, Node, NodeID},
     webpage::url_ext::UrlExt,
     SortableFloat,
 };
@@ -31,6 +31,16 @@ use crate::{
 pub struct ScoredNode {
     pub node: Node,
     pub score: f64,
+    /// The community of the host, if it belongs to one.
+    pub cluster: Option<NodeID>,
+}
+
+#[derive(Debug)]
+pub struct HostCluster {
+    pub host: Node,
+    pub kind: ClusterKind,
+    pub size: u64,
+    pub members: Vec<Node>,
 }
 
 pub struct SimilarHostsFinder {
@@ -141,7 +151,7 @@ impl SimilarHostsFinder {
             .await
             .unwrap_or_default();
 
-        nodes
+        let mut similar: Vec<_> = nodes
             .into_iter()
             .zip_eq(scores)
             .filter_map(|(node, SortableFloat(score))| {
@@ -152,7 +162,11 @@ impl SimilarHostsFinder {
                 {
                     Some(dom) => {
                         if !domains.contains(&dom) {
-                            Some(ScoredNode { node, score })
+                            Some(ScoredNode {
+                                node,
+                                score,
+                                cluster: None,
+                            })
                         } else {
                             None
                         }
@@ -161,6 +175,74 @@ impl SimilarHostsFinder {
                 }
             })
             .take(orig_limit)
-            .collect()
+            .collect();
+
+        let ids: Vec<_> = similar.iter().map(|scored| scored.node.id()).collect();
+
+        let clusters = self
+            .webgraph
+            .batch_cluster(&ids, ClusterKind::Community)
+            .await
+            .unwrap_or_default();
+
+        for (scored, cluster) in similar.iter_mut().zip(clusters) {
+            scored.cluster = cluster.map(|cluster| cluster.id);
+        }
+
+        similar
+    }
+
+    /// The clusters of `hosts` with at most `limit` of their most important members each.
+    pub async fn clusters(
+        &self,
+        hosts: &[String],
+        kind: ClusterKind,
+        limit: usize,
+    ) -> Vec<HostCluster> {
+        let hosts: Vec<_> = hosts
+            .iter()
+            .map(|url| Node::from(url.to_string()).into_host())
+            .collect();
+
+        let ids: Vec<_> = hosts.iter().map(|host| host.id()).collect();
+
+        let clusters = self
+            .webgraph
+            .batch_cluster(&ids, kind)
+            .await
+            .unwrap_or_default();
+
+        let mut res = Vec::new();
+
+        for (host, cluster) in hosts.into_iter().zip(clusters) {
+            let Some(cluster) = cluster else {
+                continue;
+            };
+
+            let member_ids: Vec<_> = cluster
+                .members
+                .into_iter()
+                .filter(|member| *member != host.id())
+                .take(limit)
+                .collect();
+
+            let members = self
+                .webgraph
+                .batch_get_node(&member_ids)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .flatten()
+                .collect();
+
+            res.push(HostCluster {
+                host,
+                kind,
+                size: cluster.size,
+                members,
+            });
+        }
+
+        res
     }
 }
@@ -16,6 +16,7 @@
 
 use crate::distributed::retry_strategy::ExponentialBackoff;
 use crate::{config::S3Config, config::WarcSource, Error, Result};
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::fs::File;
@@ -25,12 +26,13 @@ use std::str::FromStr;
 use std::thread::sleep;
 use std::time::Duration;
 
-use flate2::read::MultiGzDecoder;
+use flate2::read::{MultiGzDecoder, ZlibDecoder};
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use fnv::FnvHashSet;
 #[cfg(test)]
 use proptest_derive::Arbitrary;
+use sha1::{Digest, Sha1};
 
 use tracing::{debug, trace};
 
@@ -62,6 +64,66 @@ fn decode_string(raw: &[u8]) -> String {
     }
 }
 
+/// Base32 encoding (RFC 4648) as used for the digests in WARC headers.
+fn base32(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+    let mut res = String::with_capacity(data.len().div_ceil(5) * 8);
+
+    for chunk in data.chunks(5) {
+        let mut buf = [0u8; 5];
+        buf[..chunk.len()].copy_from_slice(chunk);
+        let bits = buf.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+        let num_chars = (chunk.len() * 8).div_ceil(5);
+
+        for i in 0..8 {
+            if i < num_chars {
+                res.push(ALPHABET[((bits >> (35 - i * 5)) & 31) as usize] as char);
+            } else {
+                res.push('=');
+            }
+        }
+    }
+
+    res
+}
+
+/// The value of a `WARC-Block-Digest` or `WARC-Payload-Digest` header.
+fn sha1_digest(parts: &[&[u8]]) -> String {
+    let mut hasher = Sha1::new();
+
+    for part in parts {
+        hasher.update(part);
+    }
+
+    format!("sha1:{}", base32(&hasher.finalize()))
+}
+
+/// Remove the chunked transfer encoding from a payload.
+/// Returns `None` if the payload is not validly chunked.
+fn dechunk(mut raw: &[u8]) -> Option<Vec<u8>> {
+    let mut res = Vec::new();
+
+    loop {
+        let line_end = raw.windows(2).position(|w| w == b"\r\n")?;
+        let size = std::str::from_utf8(&raw[..line_end]).ok()?;
+        let size = size.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        raw = &raw[line_end + 2..];
+
+        if size == 0 {
+            return Some(res);
+        }
+
+        if raw.len() < size {
+            return None;
+        }
+
+        res.extend_from_slice(&raw[..size]);
+        raw = raw[size..].strip_prefix(b"\r\n")?;
+    }
+}
+
 impl WarcFile {
     pub fn new(bytes: Vec<u8>) -> Self {
         Self { bytes }
@@ -80,7 +142,7 @@ impl WarcFile {
     pub fn records(&self) -> RecordIterator<&[u8]> {
         RecordIterator {
             reader: BufReader::new(MultiGzDecoder::new(&self.bytes[..])),
-            num_reads: 0,
+            pending: PendingRecord::default(),
         }
     }
 
@@ -215,21 +277,229 @@ pub struct WarcRecord {
     pub metadata: Metadata,
 }
 
+/// The headers of a http request or response, in the order they were sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpHeaders(Vec<(String, String)>);
+
+impl HttpHeaders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.0.push((name.into(), value.into()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// The value of the first header with the name. Header names are case-insensitive.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.get_all(name).next()
+    }
+
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.0
+            .iter()
+            .filter(move |(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// All the links in the `Link` headers, e.g. `<https://example.com/>; rel="canonical"`.
+    /// Relative urls are resolved against `base`.
+    pub fn links(&self, base: &url::Url) -> Vec<HttpLink> {
+        self.get_all("link")
+            .flat_map(split_links)
+            .filter_map(|link| HttpLink::parse(link, base))
+            .collect()
+    }
+
+    /// The canonical url from a `Link: <...>; rel="canonical"` header.
+    pub fn canonical_url(&self, base: &url::Url) -> Option<url::Url> {
+        self.links(base)
+            .into_iter()
+            .find(|link| link.has_rel("canonical"))
+            .map(|link| link.url)
+    }
+
+    fn parse(block: &str) -> (Option<u16>, Self) {
+        let mut status_code = None;
+        let mut headers = Self::new();
+
+        for (i, line) in block.lines().enumerate() {
+            if i == 0 && line.starts_with("HTTP/") {
+                status_code = line
+                    .split_whitespace()
+                    .nth(1)
+                    .and_then(|code| code.parse().ok());
+                continue;
+            }
+
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push(name.trim(), value.trim());
+            }
+        }
+
+        (status_code, headers)
+    }
+
+    /// The headers to store with a payload that was decoded when it was received.
+    /// The transfer encoding and length of the original response no longer describe the
+    /// payload, so they are kept with an `X-Crawler-` prefix like other crawlers do.
+    pub fn for_decoded_payload(&self, payload_len: usize) -> Self {
+        let mut res = Self::new();
+
+        for (name, value) in self.iter() {
+            if name.eq_ignore_ascii_case("transfer-encoding")
+                || name.eq_ignore_ascii_case("content-length")
+            {
+                res.push(format!("X-Crawler-{name}"), value);
+            } else {
+                res.push(name, value);
+            }
+        }
+
+        res.push("Content-Length", payload_len.to_string());
+
+        res
+    }
+
+    fn write(&self, first_line: Option<String>) -> String {
+        let mut lines: Vec<String> = first_line.into_iter().collect();
+        lines.extend(self.iter().map(|(name, value)| format!("{name}: {value}")));
+
+        let mut res = lines.join("\r\n");
+        res.push_str("\r\n\r\n");
+        res
+    }
+}
+
+impl From<&reqwest::header::HeaderMap> for HttpHeaders {
+    fn from(headers: &reqwest::header::HeaderMap) -> Self {
+        let mut res = Self::new();
+
+        for (name, value) in headers {
+            if let Ok(value) = value.to_str() {
+                res.push(name.as_str(), value);
+            }
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+impl proptest::arbitrary::Arbitrary for HttpHeaders {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        use proptest::strategy::Strategy;
+
+        proptest::collection::vec(("[A-Za-z][A-Za-z0-9-]{0,15}", "[!-~]{0,30}"), 0..4)
+            .prop_map(HttpHeaders)
+            .boxed()
+    }
+}
+
+/// Split the value of a `Link` header into the individual links.
+/// Commas inside `<...>` or quotes are part of the link.
+fn split_links(value: &str) -> Vec<&str> {
+    let mut res = Vec::new();
+    let mut in_url = false;
+    let mut in_quotes = false;
+    let mut start = 0;
+
+    for (i, c) in value.char_indices() {
+        match c {
+            '<' if !in_quotes => in_url = true,
+            '>' if !in_quotes => in_url = false,
+            '"' if !in_url => in_quotes = !in_quotes,
+            ',' if !in_url && !in_quotes => {
+                res.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    res.push(&value[start..]);
+    res
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpLink {
+    pub url: url::Url,
+    pub rel: Vec<String>,
+    pub hreflang: Option<String>,
+}
+
+impl HttpLink {
+    fn parse(link: &str, base: &url::Url) -> Option<Self> {
+        let link = link.trim();
+        let (target, params) = link.strip_prefix('<')?.split_once('>')?;
+        let url = base.join(target.trim()).ok()?;
+
+        let mut rel = Vec::new();
+        let mut hreflang = None;
+
+        for param in params.split(';') {
+            let Some((key, value)) = param.split_once('=') else {
+                continue;
+            };
+
+            let value = value.trim().trim_matches('"');
+
+            match key.trim().to_ascii_lowercase().as_str() {
+                "rel" => rel.extend(value.split_whitespace().map(|r| r.to_ascii_lowercase())),
+                "hreflang" => hreflang = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        Some(Self { url, rel, hreflang })
+    }
+
+    pub fn has_rel(&self, rel: &str) -> bool {
+        self.rel.iter().any(|r| r == rel)
+    }
+}
+
 #[derive(Debug)]
 #[cfg_attr(test, derive(Clone, Arbitrary, PartialEq))]
 pub struct Request {
     // WARC-Target-URI
     pub url: String,
+    pub headers: HttpHeaders,
 }
 
 impl Request {
     fn from_raw(record: RawWarcRecord) -> Result<Self> {
+        let content = decode_string(&record.content[..]);
+        let block = content.split("\r\n\r\n").next().unwrap_or_default();
+
+        // skip the request line (e.g. `GET / HTTP/1.1`)
+        let headers = match block.split_once("\r\n") {
+            Some((request_line, headers)) if request_line.contains(" HTTP/") => {
+                HttpHeaders::parse(headers).1
+            }
+            _ => HttpHeaders::new(),
+        };
+
         Ok(Self {
             url: record
                 .header
                 .get("WARC-TARGET-URI")
                 .ok_or(Error::WarcParse("No target url".to_string()))?
                 .to_owned(),
+            headers,
         })
     }
 }
@@ -275,29 +545,142 @@ impl Display for PayloadType {
 #[derive(Debug)]
 #[cfg_attr(test, derive(Clone, Arbitrary, PartialEq))]
 pub struct Response {
-    pub body: String,
+    /// The payload exactly as it is stored in the record. Use [`Response::text`]
+    /// to get the decoded content.
+    pub body: Vec<u8>,
     pub payload_type: Option<PayloadType>,
+    /// The http status code. `None` for records written before the status was stored.
+    pub status_code: Option<u16>,
+    pub headers: HttpHeaders,
+    /// The `WARC-Refers-To-Date` of a revisit record, i.e. when the payload it refers
+    /// to was captured.
+    pub refers_to_date: Option<String>,
+    /// The `WARC-Date` of the record, i.e. when the response was captured.
+    /// The writer uses the current time if it is not set.
+    pub date: Option<String>,
 }
 
 impl Response {
     fn from_raw(record: RawWarcRecord) -> Result<Self> {
-        let content = decode_string(&record.content[..]);
-
-        let (_header, content) = content
-            .split_once("\r\n\r\n")
+        let header_end = record
+            .content
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
             .ok_or(Error::WarcParse("Invalid http body".to_string()))?;
 
+        let header = decode_string(&record.content[..header_end]);
+        let (status_code, headers) = HttpHeaders::parse(&header);
+
+        let mut body = record.content;
+        body.drain(..header_end + 4);
+
         Ok(Self {
-            body: content.to_string(),
+            body,
             payload_type: record
                 .header
                 .get("WARC-IDENTIFIED-PAYLOAD-TYPE")
                 .and_then(|p| PayloadType::from_str(p).ok()),
+            status_code,
+            headers,
+            refers_to_date: record.header.get("WARC-REFERS-TO-DATE").cloned(),
+            date: record.header.get("WARC-DATE").cloned(),
         })
     }
+
+    /// The payload with the transfer and content encodings of the response removed.
+    /// Other crawlers often store the payload exactly as it was sent by the server.
+    pub fn payload(&self) -> Cow<'_, [u8]> {
+        let mut payload = Cow::Borrowed(&self.body[..]);
+
+        if self
+            .headers
+            .get_all("transfer-encoding")
+            .any(|value| value.to_ascii_lowercase().contains("chunked"))
+        {
+            if let Some(dechunked) = dechunk(&payload) {
+                payload = Cow::Owned(dechunked);
+            }
+        }
+
+        let content_encoding = self
+            .headers
+            .get("content-encoding")
+            .map(|value| value.trim().to_ascii_lowercase());
+
+        let mut decoded = Vec::new();
+        let res = match content_encoding.as_deref() {
+            Some("gzip") | Some("x-gzip") => {
+                MultiGzDecoder::new(&payload[..]).read_to_end(&mut decoded)
+            }
+            Some("deflate") => ZlibDecoder::new(&payload[..]).read_to_end(&mut decoded),
+            _ => return payload,
+        };
+
+        match res {
+            Ok(_) => Cow::Owned(decoded),
+            Err(_) => payload,
+        }
+    }
+
+    /// The target of a `301 Moved Permanently` or `308 Permanent Redirect` response.
+    /// Relative locations are resolved against `base`.
+    pub fn permanent_redirect(&self, base: &url::Url) -> Option<url::Url> {
+        if !matches!(self.status_code, Some(301 | 308)) {
+            return None;
+        }
+
+        self.headers
+            .get("location")
+            .and_then(|location| base.join(location.trim()).ok())
+    }
+
+    /// Whether the response is a redirect, in which case the payload is not the content of the page.
+    pub fn is_redirect(&self) -> bool {
+        matches!(self.status_code, Some(301 | 302 | 303 | 307 | 308))
+    }
+
+    /// Whether the response is a `304 Not Modified`, in which case there is no payload
+    /// and the content of the page is in the capture the response refers to.
+    pub fn is_not_modified(&self) -> bool {
+        self.status_code == Some(304)
+    }
+
+    /// The payload decoded as text. The charset from the `Content-Type` header is used
+    /// when it decodes the payload without errors, otherwise the encoding is detected.
+    pub fn text(&self) -> String {
+        let payload = self.payload();
+
+        if let Some(encoding) = self.charset() {
+            let (text, _, had_errors) = encoding.decode(&payload);
+
+            if !had_errors {
+                return text.into_owned();
+            }
+        }
+
+        decode_string(&payload)
+    }
+
+    fn charset(&self) -> Option<&'static encoding_rs::Encoding> {
+        let content_type = self.headers.get("content-type")?;
+
+        content_type
+            .split(';')
+            .skip(1)
+            .find_map(|param| {
+                let (key, value) = param.split_once('=')?;
+
+                if key.trim().eq_ignore_ascii_case("charset") {
+                    Some(value.trim().trim_matches('"'))
+                } else {
+                    None
+                }
+            })
+            .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 #[cfg_attr(test, derive(Clone, Arbitrary, PartialEq))]
 pub struct Metadata {
     // fetchTimeMs
@@ -321,13 +704,138 @@ impl Metadata {
             }
         }
 
-        Err(Error::WarcParse("Failed to parse metadata".to_string()).into())
+        // metadata from other crawlers does not necessarily have the fetch time
+        Ok(Self::default())
+    }
+}
+
+/// A part of a [`WarcRecord`] read from a single WARC record.
+enum RecordPart {
+    Request(Request),
+    Response(Response),
+    /// A revisit record that refers to a payload stored elsewhere.
+    Revisit,
+    Metadata(Metadata),
+}
+
+impl RecordPart {
+    /// Parse the part from a raw record. Returns `None` for records that are not part of
+    /// a [`WarcRecord`], e.g. `warcinfo` records or dns responses.
+    fn from_raw(record: RawWarcRecord) -> Result<Option<Self>> {
+        let Some(warc_type) = record.header.get("WARC-TYPE") else {
+            return Ok(None);
+        };
+
+        let content_type = record.header.get("CONTENT-TYPE");
+        let has_content_type = |expected: &str| {
+            content_type
+                .map(|content_type| content_type.starts_with(expected))
+                .unwrap_or(true)
+        };
+
+        let part = match warc_type.to_ascii_lowercase().as_str() {
+            "request" => Self::Request(Request::from_raw(record)?),
+            "response" if has_content_type("application/http") => {
+                Self::Response(Response::from_raw(record)?)
+            }
+            "revisit" if has_content_type("application/http") => {
+                let not_modified = record
+                    .header
+                    .get("WARC-PROFILE")
+                    .map(|profile| profile.ends_with(REVISIT_NOT_MODIFIED_PROFILE))
+                    .unwrap_or(false);
+
+                // the response to a conditional request has no payload,
+                // so the revisit record contains the entire response
+                if not_modified {
+                    Self::Response(Response::from_raw(record)?)
+                } else {
+                    Self::Revisit
+                }
+            }
+            "metadata" if has_content_type("application/warc-fields") => {
+                Self::Metadata(Metadata::from_raw(record)?)
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(part))
+    }
+}
+
+/// The parts of the [`WarcRecord`] that is currently being read.
+/// Records from other crawlers might not have a request or metadata record,
+/// or might write the records in a different order.
+#[derive(Default)]
+struct PendingRecord {
+    url: Option<String>,
+    request: Option<Request>,
+    response: Option<Response>,
+    revisit: bool,
+    metadata: Option<Metadata>,
+}
+
+impl PendingRecord {
+    fn has_response(&self) -> bool {
+        self.response.is_some() || self.revisit
+    }
+
+    fn is_complete(&self) -> bool {
+        self.request.is_some() && self.has_response() && self.metadata.is_some()
+    }
+
+    /// Whether the part belongs to the pending record.
+    /// Parts without a target uri are assumed to belong to the record.
+    fn accepts(&self, url: Option<&str>, part: &RecordPart) -> bool {
+        if let (Some(pending_url), Some(url)) = (&self.url, url) {
+            if pending_url != url {
+                return false;
+            }
+        }
+
+        match part {
+            RecordPart::Request(_) => self.request.is_none(),
+            RecordPart::Response(_) | RecordPart::Revisit => !self.has_response(),
+            RecordPart::Metadata(_) => self.metadata.is_none(),
+        }
+    }
+
+    fn add(&mut self, url: Option<String>, part: RecordPart) {
+        if self.url.is_none() {
+            self.url = url;
+        }
+
+        match part {
+            RecordPart::Request(request) => self.request = Some(request),
+            RecordPart::Response(response) => self.response = Some(response),
+            RecordPart::Revisit => self.revisit = true,
+            RecordPart::Metadata(metadata) => self.metadata = Some(metadata),
+        }
+    }
+
+    /// The finished record. Revisits of payloads stored in other records are skipped.
+    fn finish(self) -> Option<WarcRecord> {
+        let response = self.response?;
+
+        let request = match self.request {
+            Some(request) => request,
+            None => Request {
+                url: self.url?,
+                headers: HttpHeaders::new(),
+            },
+        };
+
+        Some(WarcRecord {
+            request,
+            response,
+            metadata: self.metadata.unwrap_or_default(),
+        })
     }
 }
 
 pub struct RecordIterator<R: Read> {
     reader: BufReader<MultiGzDecoder<R>>,
-    num_reads: usize,
+    pending: PendingRecord,
 }
 
 impl<R: Read> RecordIterator<R> {
@@ -430,92 +938,39 @@ impl<R: Read> Iterator for RecordIterator<R> {
     type Item = Result<WarcRecord>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.num_reads == 0 {
-            self.next_raw()?.ok()?; // skip warc_info
-        }
-        self.num_reads += 1;
+        loop {
+            let raw = match self.next_raw() {
+                Some(Ok(raw)) => raw,
+                Some(Err(err)) => return Some(Err(err)),
+                None => return std::mem::take(&mut self.pending).finish().map(Ok),
+            };
 
-        let mut request = None;
-        let mut response = None;
-        let mut metadata = None;
+            let url = raw.header.get("WARC-TARGET-URI").cloned();
 
-        while let Some(item) = self.next_raw() {
-            if item.is_err() {
-                return Some(Err(item.err().unwrap()));
-            }
+            let part = match RecordPart::from_raw(raw) {
+                Ok(Some(part)) => part,
+                Ok(None) => continue,
+                Err(err) => return Some(Err(Error::WarcParse(err.to_string()).into())),
+            };
 
-            let item = item.unwrap();
-
-            if let Some(warc_type) = item.header.get("WARC-TYPE") {
-                if warc_type.as_str() == "request" {
-                    if request.is_some() {
-                        return Some(Err(Error::WarcParse(
-                            "Already have a request but got another.".to_string(),
-                        )
-                        .into()));
-                    }
-
-                    match Request::from_raw(item) {
-                        Ok(req) => {
-                            request = Some(req);
-                        }
-                        Err(err) => return Some(Err(Error::WarcParse(err.to_string()).into())),
-                    };
-                } else if warc_type.as_str() == "response" || warc_type.as_str() == "revisit" {
-                    if let Some(content_type) = item.header.get("CONTENT-TYPE") {
-                        if !content_type.starts_with("application/http") {
-                            continue;
-                        }
-                    }
-
-                    if response.is_some() {
-                        return Some(Err(Error::WarcParse(
-                            "Already have a response but got another.".to_string(),
-                        )
-                        .into()));
-                    }
-
-                    match Response::from_raw(item) {
-                        Ok(res) => {
-                            response = Some(res);
-                        }
-                        Err(err) => {
-                            return Some(Err(Error::WarcParse(err.to_string()).into()));
-                        }
-                    };
-                } else if warc_type.as_str() == "metadata" {
-                    if let Some(content_type) = item.header.get("CONTENT-TYPE") {
-                        if !content_type.starts_with("application/warc-fields") {
-                            continue;
-                        }
-                    }
-
-                    if metadata.is_some() {
-                        return Some(Err(Error::WarcParse(
-                            "Already have metadata but got another.".to_string(),
-                        )
-                        .into()));
-                    }
-
-                    match Metadata::from_raw(item) {
-                        Ok(met) => {
-                            metadata = Some(met);
-                        }
-                        Err(err) => return Some(Err(Error::WarcParse(err.to_string()).into())),
-                    }
-                }
+            let finished = if self.pending.accepts(url.as_deref(), &part) {
+                None
+            } else {
+                Some(std::mem::take(&mut self.pending))
+            };
+
+            self.pending.add(url, part);
+
+            if let Some(record) = finished.and_then(PendingRecord::finish) {
+                return Some(Ok(record));
             }
 
-            if request.is_some() && response.is_some() && metadata.is_some() {
-                break;
+            if self.pending.is_complete() {
+                if let Some(record) = std::mem::take(&mut self.pending).finish() {
+                    return Some(Ok(record));
+                }
             }
         }
-
-        Some(Ok(WarcRecord {
-            request: request?,
-            response: response?,
-            metadata: metadata?,
-        }))
     }
 }
 
@@ -562,85 +1017,188 @@ impl DeduplicatedWarcWriter {
     }
 }
 
+const WARC_VERSION: &str = "WARC/1.1";
+const REVISIT_NOT_MODIFIED_PROFILE: &str = "/revisit/server-not-modified";
+
+fn record_id() -> String {
+    format!("<urn:uuid:{}>", uuid::Uuid::new_v4())
+}
+
+fn warc_date() -> String {
+    chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// Writes gzipped WARC files. Every record is compressed as a separate gzip member,
+/// so a record can be read without decompressing the records before it.
 pub struct WarcWriter {
     num_writes: usize,
-    writer: GzEncoder<Vec<u8>>,
+    warcinfo_id: String,
+    bytes: Vec<u8>,
 }
 
 impl WarcWriter {
     pub fn new() -> Self {
-        let mut writer = GzEncoder::new(Default::default(), Compression::best());
+        let mut writer = Self {
+            num_writes: 0,
+            warcinfo_id: record_id(),
+            bytes: Vec::new(),
+        };
+
+        let date = warc_date();
+        let info = format!(
+            "software: Stract\r\n\
+             format: WARC File Format 1.1\r\n\
+             conformsTo: http://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/\r\n\
+             isPartOf: crawl[{date}]\r\n"
+        );
 
-        writer.write_all("WARC/1.0\r\n".as_bytes()).unwrap();
         writer
-            .write_all("WARC-Type: warcinfo\r\n".as_bytes())
+            .write_record(
+                &[
+                    ("WARC-Type", "warcinfo".to_string()),
+                    ("WARC-Record-ID", writer.warcinfo_id.clone()),
+                    ("WARC-Date", date),
+                    ("Content-Type", "application/warc-fields".to_string()),
+                ],
+                &[info.as_bytes()],
+            )
             .unwrap();
 
-        let date = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
-        let content = format!("ISPARTOF: crawl[{}]", date);
-        let content_len = content.len();
-
         writer
-            .write_all(format!("Content-Length: {content_len}\r\n").as_bytes())
-            .unwrap();
-        writer.write_all("\r\n".as_bytes()).unwrap();
-        writer.write_all(content.as_bytes()).unwrap();
-        writer.write_all("\r\n\r\n".as_bytes()).unwrap();
+    }
 
-        writer.flush().unwrap();
+    /// Write a single WARC record. The block is the concatenation of `block`.
+    fn write_record(&mut self, header: &[(&str, String)], block: &[&[u8]]) -> Result<()> {
+        let content_len: usize = block.iter().map(|part| part.len()).sum();
 
-        Self {
-            num_writes: 0,
-            writer,
+        let mut writer = GzEncoder::new(&mut self.bytes, Compression::best());
+
+        writer.write_all(WARC_VERSION.as_bytes())?;
+        writer.write_all("\r\n".as_bytes())?;
+
+        for (name, value) in header {
+            writer.write_all(format!("{name}: {value}\r\n").as_bytes())?;
         }
-    }
 
-    pub fn write(&mut self, record: &WarcRecord) -> Result<()> {
-        self.writer.write_all("WARC/1.0\r\n".as_bytes())?;
+        writer.write_all(format!("Content-Length: {content_len}\r\n").as_bytes())?;
+        writer.write_all("\r\n".as_bytes())?;
+
+        for part in block {
+            writer.write_all(part)?;
+        }
 
-        self.writer.write_all("WARC-Type: request\r\n".as_bytes())?;
-        self.writer
-            .write_all(format!("WARC-Target-URI: {}\r\n", record.request.url).as_bytes())?;
-        self.writer.write_all("Content-Length: 0\r\n".as_bytes())?;
-        self.writer.write_all("\r\n".as_bytes())?;
-        self.writer.write_all("\r\n\r\n".as_bytes())?;
+        writer.write_all("\r\n\r\n".as_bytes())?;
+        writer.finish()?;
 
-        self.writer.write_all("WARC/1.0\r\n".as_bytes())?;
-        self.writer
-            .write_all("WARC-Type: response\r\n".as_bytes())?;
+        Ok(())
+    }
 
-        if let Some(payload_type) = &record.response.payload_type {
-            self.writer.write_all(
-                format!("WARC-Identified-Payload-Type: {payload_type}\r\n").as_bytes(),
-            )?;
+    pub fn write(&mut self, record: &WarcRecord) -> Result<()> {
+        let date = record.response.date.clone().unwrap_or_else(warc_date);
+        let response_id = record_id();
+        let url = &record.request.url;
+
+        let http_request = record
+            .request
+            .headers
+            .write(Some(format!("GET {url} HTTP/1.1")));
+
+        self.write_record(
+            &[
+                ("WARC-Type", "request".to_string()),
+                ("WARC-Record-ID", record_id()),
+                ("WARC-Date", date.clone()),
+                ("WARC-Target-URI", url.clone()),
+                ("WARC-Warcinfo-ID", self.warcinfo_id.clone()),
+                ("WARC-Concurrent-To", response_id.clone()),
+                (
+                    "Content-Type",
+                    "application/http; msgtype=request".to_string(),
+                ),
+                ("WARC-Block-Digest", sha1_digest(&[http_request.as_bytes()])),
+            ],
+            &[http_request.as_bytes()],
+        )?;
+
+        let status_line = record.response.status_code.map(|code| {
+            match reqwest::StatusCode::from_u16(code)
+                .ok()
+                .and_then(|status| status.canonical_reason())
+            {
+                Some(reason) => format!("HTTP/1.1 {code} {reason}"),
+                None => format!("HTTP/1.1 {code}"),
+            }
+        });
+
+        let has_http_header = status_line.is_some() || !record.response.headers.is_empty();
+        let http_header = record.response.headers.write(status_line);
+        let body = &record.response.body[..];
+
+        // a `304 Not Modified` response is a revisit of the payload from an earlier crawl
+        let revisit = record.response.is_not_modified();
+        let mut header = if revisit {
+            vec![
+                ("WARC-Type", "revisit".to_string()),
+                (
+                    "WARC-Profile",
+                    format!("http://netpreserve.org/warc/1.1{REVISIT_NOT_MODIFIED_PROFILE}"),
+                ),
+            ]
+        } else {
+            vec![("WARC-Type", "response".to_string())]
+        };
+
+        header.extend([
+            ("WARC-Record-ID", response_id.clone()),
+            ("WARC-Date", date.clone()),
+            ("WARC-Target-URI", url.clone()),
+            ("WARC-Warcinfo-ID", self.warcinfo_id.clone()),
+        ]);
+
+        if has_http_header {
+            header.push((
+                "Content-Type",
+                "application/http; msgtype=response".to_string(),
+            ));
         }
 
-        let body = record.response.body.as_bytes();
-        let content_len = body.len() + 4; // +4 is for the \r\n\r\n between http header and body
-        self.writer
-            .write_all(format!("Content-Length: {content_len}\r\n").as_bytes())?;
+        header.push((
+            "WARC-Block-Digest",
+            sha1_digest(&[http_header.as_bytes(), body]),
+        ));
 
-        self.writer.write_all("\r\n".as_bytes())?;
-        // write the http-header here if we want to in the future
-        self.writer.write_all("\r\n\r\n".as_bytes())?;
+        if revisit {
+            // the payload is in the earlier capture, so there is no payload to digest
+            header.push(("WARC-Refers-To-Target-URI", url.clone()));
 
-        self.writer.write_all(body)?;
-        self.writer.write_all("\r\n\r\n".as_bytes())?;
+            if let Some(date) = &record.response.refers_to_date {
+                header.push(("WARC-Refers-To-Date", date.clone()));
+            }
+        } else {
+            header.push(("WARC-Payload-Digest", sha1_digest(&[body])));
+        }
 
-        self.writer.write_all("WARC/1.0\r\n".as_bytes())?;
-        self.writer
-            .write_all("WARC-Type: metadata\r\n".as_bytes())?;
+        if let Some(payload_type) = &record.response.payload_type {
+            header.push(("WARC-Identified-Payload-Type", payload_type.to_string()));
+        }
 
-        let body = format!("fetchTimeMs: {}", record.metadata.fetch_time_ms);
-        let content_len = body.len();
+        self.write_record(&header, &[http_header.as_bytes(), body])?;
 
-        self.writer
-            .write_all(format!("Content-Length: {content_len}\r\n").as_bytes())?;
-        self.writer.write_all("\r\n".as_bytes())?;
-        self.writer.write_all(body.as_bytes())?;
-        self.writer.write_all("\r\n\r\n".as_bytes())?;
+        let metadata = format!("fetchTimeMs: {}\r\n", record.metadata.fetch_time_ms);
 
-        self.writer.flush().unwrap();
+        self.write_record(
+            &[
+                ("WARC-Type", "metadata".to_string()),
+                ("WARC-Record-ID", record_id()),
+                ("WARC-Date", date),
+                ("WARC-Target-URI", url.clone()),
+                ("WARC-Warcinfo-ID", self.warcinfo_id.clone()),
+                ("WARC-Concurrent-To", response_id),
+                ("Content-Type", "application/warc-fields".to_string()),
+                ("WARC-Block-Digest", sha1_digest(&[metadata.as_bytes()])),
+            ],
+            &[metadata.as_bytes()],
+        )?;
 
         self.num_writes += 1;
 
@@ -648,11 +1206,11 @@ impl WarcWriter {
     }
 
     pub fn finish(self) -> Result<Vec<u8>> {
-        Ok(self.writer.finish()?)
+        Ok(self.bytes)
     }
 
     pub fn num_bytes(&self) -> usize {
-        self.writer.get_ref().len()
+        self.bytes.len()
     }
 
     pub fn num_writes(&self) -> usize {
@@ -714,10 +1272,90 @@ mod tests {
 
         assert_eq!(records.len(), 1);
         assert_eq!(&records[0].request.url, "http://0575ls.cn/news-52300.htm");
-        assert_eq!(&records[0].response.body, "body of response");
+        assert_eq!(records[0].response.text(), "body of response");
         assert_eq!(records[0].metadata.fetch_time_ms, 937);
     }
 
+    #[test]
+    fn http_headers() {
+        let raw = b"\
+                WARC/1.0\r\n\
+                WARC-Type: warcinfo\r\n\
+                Content-Length: 0\r\n\
+                \r\n\
+                \r\n\
+                \r\n\
+                WARC/1.0\r\n\
+                WARC-Type: request\r\n\
+                WARC-Target-URI: https://example.com/page\r\n\
+                Content-Type: application/http; msgtype=request\r\n\
+                Content-Length: 49\r\n\
+                \r\n\
+                GET /page HTTP/1.1\r\n\
+                User-Agent: StractBot/1.0\r\n\
+                \r\n\
+                \r\n\
+                \r\n\
+                WARC/1.0\r\n\
+                WARC-Type: response\r\n\
+                Content-Type: application/http; msgtype=response\r\n\
+                Content-Length: 147\r\n\
+                \r\n\
+                HTTP/1.1 200 OK\r\n\
+                Content-Type: text/html\r\n\
+                X-Robots-Tag: noindex\r\n\
+                Link: </canonical>; rel=\"canonical\", </de>; rel=\"alternate\"; hreflang=\"de\"\r\n\
+                \r\n\
+                body\r\n\
+                \r\n\
+                WARC/1.0\r\n\
+                WARC-Type: metadata\r\n\
+                Content-Length: 15\r\n\
+                \r\n\
+                fetchTimeMs: 42\r\n\
+                \r\n";
+        let mut e = GzEncoder::new(Vec::new(), Compression::default());
+        e.write_all(raw).unwrap();
+        let compressed = e.finish().unwrap();
+
+        let records: Vec<WarcRecord> = WarcFile::new(compressed)
+            .records()
+            .map(|res| res.unwrap())
+            .collect();
+
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+
+        assert_eq!(
+            record.request.headers.get("user-agent"),
+            Some("StractBot/1.0")
+        );
+        assert_eq!(record.response.status_code, Some(200));
+        assert_eq!(record.response.text(), "body");
+        assert_eq!(record.response.headers.get("x-robots-tag"), Some("noindex"));
+
+        let base = url::Url::parse("https://example.com/page").unwrap();
+        let links = record.response.headers.links(&base);
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[1].hreflang.as_deref(), Some("de"));
+        assert!(links[1].has_rel("alternate"));
+        assert_eq!(
+            record.response.headers.canonical_url(&base),
+            Some(url::Url::parse("https://example.com/canonical").unwrap())
+        );
+
+        // the headers survive a round trip through the writer
+        let mut writer = WarcWriter::new();
+        writer.write(record).unwrap();
+        let compressed = writer.finish().unwrap();
+        let read: Vec<WarcRecord> = WarcFile::new(compressed)
+            .records()
+            .map(|res| res.unwrap())
+            .collect();
+
+        assert_eq!(&read, &records);
+    }
+
     #[test]
     fn internet_archive_parse() {
         let data_path = Path::new("../../data/internet_archive.warc.gz");
@@ -744,10 +1382,15 @@ mod tests {
         let record1 = WarcRecord {
             request: Request {
                 url: "https://a.com".to_string(),
+                headers: HttpHeaders::new(),
             },
             response: Response {
-                body: "body of a".to_string(),
+                body: b"body of a".to_vec(),
                 payload_type: Some(PayloadType::Html),
+                status_code: None,
+                headers: HttpHeaders::new(),
+                refers_to_date: None,
+                date: None,
             },
             metadata: Metadata {
                 fetch_time_ms: 1337,
@@ -758,10 +1401,15 @@ mod tests {
         let record2 = WarcRecord {
             request: Request {
                 url: "https://b.com".to_string(),
+                headers: HttpHeaders::new(),
             },
             response: Response {
-                body: "body of b".to_string(),
+                body: b"body of b".to_vec(),
                 payload_type: None,
+                status_code: None,
+                headers: HttpHeaders::new(),
+                refers_to_date: None,
+                date: None,
             },
             metadata: Metadata {
                 fetch_time_ms: 4242,
@@ -778,11 +1426,11 @@ mod tests {
 
         assert_eq!(records.len(), 2);
         assert_eq!(&records[0].request.url, "https://a.com");
-        assert_eq!(&records[0].response.body, "body of a");
+        assert_eq!(records[0].response.text(), "body of a");
         assert_eq!(records[0].metadata.fetch_time_ms, 1337);
 
         assert_eq!(&records[1].request.url, "https://b.com");
-        assert_eq!(&records[1].response.body, "body of b");
+        assert_eq!(records[1].response.text(), "body of b");
         assert_eq!(records[1].metadata.fetch_time_ms, 4242);
     }
 
@@ -794,10 +1442,15 @@ mod tests {
         let record = WarcRecord {
             request: Request {
                 url: "https://a.com".to_string(),
+                headers: HttpHeaders::new(),
             },
             response: Response {
-                body: utf8.to_string(),
+                body: utf8.as_bytes().to_vec(),
                 payload_type: Some(PayloadType::Html),
+                status_code: None,
+                headers: HttpHeaders::new(),
+                refers_to_date: None,
+                date: None,
             },
             metadata: Metadata { fetch_time_ms: 0 },
         };
@@ -811,7 +1464,7 @@ mod tests {
 
         assert_eq!(records.len(), 1);
         assert_eq!(&records[0].request.url, "https://a.com");
-        assert_eq!(&records[0].response.body, utf8);
+        assert_eq!(records[0].response.text(), utf8);
         assert_eq!(records[0].metadata.fetch_time_ms, 0);
     }
 
@@ -826,10 +1479,15 @@ mod tests {
         let record = WarcRecord {
             request: Request {
                 url: "https://a.com".to_string(),
+                headers: HttpHeaders::new(),
             },
             response: Response {
-                body: body.to_string(),
+                body: body.as_bytes().to_vec(),
                 payload_type: Some(PayloadType::Html),
+                status_code: None,
+                headers: HttpHeaders::new(),
+                refers_to_date: None,
+                date: None,
             },
             metadata: Metadata { fetch_time_ms: 0 },
         };
@@ -843,8 +1501,237 @@ mod tests {
 
         assert_eq!(records.len(), 1);
         assert_eq!(&records[0].request.url, "https://a.com");
-        assert_eq!(&records[0].response.body, body);
+        assert_eq!(records[0].response.text(), body);
+        assert_eq!(records[0].metadata.fetch_time_ms, 0);
+    }
+
+    #[test]
+    fn digests() {
+        assert_eq!(base32(b"foobar"), "MZXW6YTBOI======");
+        assert_eq!(sha1_digest(&[b""]), "sha1:3I42H3S6NNFQ2MSVX7XZKYAYSCX5QBYJ");
+    }
+
+    fn decompress(compressed: &[u8]) -> String {
+        let mut bytes = Vec::new();
+        MultiGzDecoder::new(compressed)
+            .read_to_end(&mut bytes)
+            .unwrap();
+        String::from_utf8_lossy(&bytes).to_string()
+    }
+
+    #[test]
+    fn gzip_member_per_record() {
+        let mut writer = WarcWriter::new();
+        writer
+            .write(&WarcRecord {
+                request: Request {
+                    url: "https://a.com/".to_string(),
+                    headers: HttpHeaders::new(),
+                },
+                response: Response {
+                    body: b"<html></html>".to_vec(),
+                    payload_type: Some(PayloadType::Html),
+                    status_code: Some(200),
+                    headers: HttpHeaders::new(),
+                    refers_to_date: None,
+                    date: None,
+                },
+                metadata: Metadata { fetch_time_ms: 42 },
+            })
+            .unwrap();
+        let compressed = writer.finish().unwrap();
+
+        // a single gzip decoder stops after the first member, which is the warcinfo record
+        let mut first = String::new();
+        flate2::read::GzDecoder::new(&compressed[..])
+            .read_to_string(&mut first)
+            .unwrap();
+        assert!(first.contains("WARC-Type: warcinfo"));
+        assert!(!first.contains("WARC-Type: request"));
+
+        let all = decompress(&compressed);
+        assert!(all.contains("WARC-Type: request"));
+        assert!(all.contains("WARC-Type: response"));
+        assert!(all.contains("WARC-Type: metadata"));
+    }
+
+    #[test]
+    fn binary_payload() {
+        let body = vec![0, 159, 146, 150, 255, b'\r', b'\n', b'\r', b'\n', 42];
+
+        let mut headers = HttpHeaders::new();
+        headers.push("Content-Type", "application/octet-stream");
+
+        let record = WarcRecord {
+            request: Request {
+                url: "https://a.com/file".to_string(),
+                headers: HttpHeaders::new(),
+            },
+            response: Response {
+                body: body.clone(),
+                payload_type: None,
+                status_code: Some(200),
+                headers,
+                refers_to_date: None,
+                date: Some("2024-01-01T00:00:00Z".to_string()),
+            },
+            metadata: Metadata { fetch_time_ms: 10 },
+        };
+
+        let mut writer = WarcWriter::new();
+        writer.write(&record).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let output = decompress(&compressed);
+        assert!(output.starts_with("WARC/1.1\r\nWARC-Type: warcinfo\r\n"));
+        assert!(output.contains("WARC-Record-ID: <urn:uuid:"));
+        assert!(output.contains("WARC-Date: "));
+        assert!(output.contains("WARC-Concurrent-To: <urn:uuid:"));
+        assert!(output.contains(&format!(
+            "WARC-Payload-Digest: {}\r\n",
+            sha1_digest(&[&body])
+        )));
+
+        let records: Vec<WarcRecord> = WarcFile::new(compressed)
+            .records()
+            .map(|res| res.unwrap())
+            .collect();
+
+        assert_eq!(records, vec![record]);
+    }
+
+    #[test]
+    fn not_modified_revisit() {
+        let record = WarcRecord {
+            request: Request {
+                url: "https://a.com/".to_string(),
+                headers: HttpHeaders::new(),
+            },
+            response: Response {
+                body: Vec::new(),
+                payload_type: None,
+                status_code: Some(304),
+                headers: HttpHeaders::new(),
+                refers_to_date: Some("2024-01-01T00:00:00Z".to_string()),
+                date: Some("2024-01-02T00:00:00Z".to_string()),
+            },
+            metadata: Metadata::default(),
+        };
+
+        let mut writer = WarcWriter::new();
+        writer.write(&record).unwrap();
+        let compressed = writer.finish().unwrap();
+
+        let output = decompress(&compressed);
+        assert!(output.contains("WARC-Type: revisit\r\n"));
+        assert!(output.contains(
+            "WARC-Profile: http://netpreserve.org/warc/1.1/revisit/server-not-modified\r\n"
+        ));
+        assert!(output.contains("WARC-Refers-To-Target-URI: https://a.com/\r\n"));
+        assert!(output.contains("WARC-Refers-To-Date: 2024-01-01T00:00:00Z\r\n"));
+        assert!(!output.contains("WARC-Payload-Digest"));
+
+        let records: Vec<WarcRecord> = WarcFile::new(compressed)
+            .records()
+            .map(|res| res.unwrap())
+            .collect();
+
+        assert_eq!(records, vec![record]);
+    }
+
+    fn raw_record(header: &[&str], block: &[u8]) -> Vec<u8> {
+        let mut res = b"WARC/1.0\r\n".to_vec();
+
+        for line in header {
+            res.extend_from_slice(line.as_bytes());
+            res.extend_from_slice(b"\r\n");
+        }
+
+        res.extend_from_slice(format!("Content-Length: {}\r\n\r\n", block.len()).as_bytes());
+        res.extend_from_slice(block);
+        res.extend_from_slice(b"\r\n\r\n");
+
+        res
+    }
+
+    #[test]
+    fn records_from_other_crawlers() {
+        let mut raw = raw_record(
+            &[
+                "WARC-Type: warcinfo",
+                "Content-Type: application/warc-fields",
+            ],
+            b"software: warcio\r\n",
+        );
+
+        // the response is written before the request and there is no metadata
+        raw.extend(raw_record(
+            &[
+                "WARC-Type: response",
+                "WARC-Target-URI: http://a.com/",
+                "Content-Type: application/http; msgtype=response",
+            ],
+            b"HTTP/1.1 200 OK\r\n\
+            Content-Type: text/html; charset=windows-1252\r\n\
+            Transfer-Encoding: chunked\r\n\
+            \r\n\
+            5\r\n\
+            caf\xe9!\r\n\
+            0\r\n\
+            \r\n",
+        ));
+        raw.extend(raw_record(
+            &[
+                "WARC-Type: request",
+                "WARC-Target-URI: http://a.com/",
+                "Content-Type: application/http; msgtype=request",
+            ],
+            b"GET / HTTP/1.1\r\nHost: a.com\r\n\r\n",
+        ));
+
+        // the payload is stored in another record
+        raw.extend(raw_record(
+            &[
+                "WARC-Type: revisit",
+                "WARC-Target-URI: http://b.com/",
+                "WARC-Profile: http://netpreserve.org/warc/1.1/revisit/identical-payload-digest",
+                "Content-Type: application/http; msgtype=response",
+            ],
+            b"HTTP/1.1 200 OK\r\n\r\n",
+        ));
+
+        let mut gzipped = GzEncoder::new(Vec::new(), Compression::default());
+        gzipped.write_all(b"<p>hello</p>").unwrap();
+        let mut response = b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\n\r\n".to_vec();
+        response.extend(gzipped.finish().unwrap());
+
+        raw.extend(raw_record(
+            &[
+                "WARC-Type: response",
+                "WARC-Target-URI: http://c.com/",
+                "Content-Type: application/http; msgtype=response",
+            ],
+            &response,
+        ));
+
+        let mut e = GzEncoder::new(Vec::new(), Compression::default());
+        e.write_all(&raw).unwrap();
+        let compressed = e.finish().unwrap();
+
+        let records: Vec<WarcRecord> = WarcFile::new(compressed)
+            .records()
+            .map(|res| res.unwrap())
+            .collect();
+
+        assert_eq!(records.len(), 2);
+
+        assert_eq!(&records[0].request.url, "http://a.com/");
+        assert_eq!(records[0].request.headers.get("host"), Some("a.com"));
+        assert_eq!(records[0].response.text(), "café!");
         assert_eq!(records[0].metadata.fetch_time_ms, 0);
+
+        assert_eq!(&records[1].request.url, "http://c.com/");
+        assert_eq!(records[1].response.text(), "<p>hello</p>");
     }
 
     #[test]
@@ -18,11 +18,12 @@ use std::path::Path;
 
 use crate::executor::Executor;
 
-use super::Webgraph;
+use super::{LinkFilter, Webgraph};
 
 pub struct WebgraphBuilder {
     path: Box<Path>,
     executor: Executor,
+    link_filter: LinkFilter,
 }
 
 impl WebgraphBuilder {
@@ -30,6 +31,7 @@ impl WebgraphBuilder {
         Self {
             path: path.as_ref().into(),
             executor: Executor::multi_thread("webgraph").unwrap(),
+            link_filter: LinkFilter::default(),
         }
     }
 
@@ -38,7 +40,16 @@ impl WebgraphBuilder {
         self
     }
 
+    /// Leave the links that are rejected by `filter` out when the graph is read.
+    pub fn link_filter(mut self, filter: LinkFilter) -> Self {
+        self.link_filter = filter;
+        self
+    }
+
     pub fn open(self) -> Webgraph {
-        Webgraph::open(self.path, self.executor)
+        let mut graph = Webgraph::open(self.path, self.executor);
+        graph.set_link_filter(self.link_filter);
+
+        graph
     }
 }
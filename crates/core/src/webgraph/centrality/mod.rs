@@ -24,6 +24,7 @@ pub mod approx_harmonic;
 pub mod betweenness;
 pub mod derived_harmonic;
 pub mod harmonic;
+pub mod pagerank;
 
 #[derive(Debug, Clone, Copy)]
 pub enum TopNodes {
@@ -75,7 +76,16 @@ where
     I: Iterator<Item = (NodeID, f64)>,
     P: AsRef<Path>,
 {
-    let mut store = speedy_kv::Db::open_or_create(output.as_ref().join("harmonic")).unwrap();
+    store_centrality(centralities, output, "harmonic")
+}
+
+/// Store the centralities in `{name}` and the rank of each node in `{name}_rank`.
+pub fn store_centrality<I, P>(centralities: I, output: P, name: &str) -> speedy_kv::Db<NodeID, f64>
+where
+    I: Iterator<Item = (NodeID, f64)>,
+    P: AsRef<Path>,
+{
+    let mut store = speedy_kv::Db::open_or_create(output.as_ref().join(name)).unwrap();
 
     for (node_id, centrality) in centralities {
         store.insert(node_id, centrality).unwrap();
@@ -88,7 +98,7 @@ where
     store.merge_all_segments().unwrap();
 
     let mut rank_store: speedy_kv::Db<crate::webgraph::NodeID, u64> =
-        speedy_kv::Db::open_or_create(output.as_ref().join("harmonic_rank")).unwrap();
+        speedy_kv::Db::open_or_create(output.as_ref().join(format!("{name}_rank"))).unwrap();
 
     for (rank, node_id) in ExternalSorter::new()
         .with_chunk_size(100_000_000)
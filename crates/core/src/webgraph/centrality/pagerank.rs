@@ -0,0 +1,295 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{collections::HashSet, path::Path};
+
+use dashmap::DashMap;
+use rayon::prelude::*;
+
+use crate::webgraph::{EdgeLimit, Node, NodeID, Webgraph};
+
+pub const DAMPING: f64 = 0.85;
+pub const MAX_ITERATIONS: usize = 100;
+
+/// The iterations stop when the L1 distance between two
+/// consecutive rank vectors is below this threshold.
+pub const TOLERANCE: f64 = 1e-6;
+
+/// Where the random surfer jumps to when it teleports or hits a node without outgoing edges.
+enum Teleport {
+    Uniform,
+    Seeds(HashSet<NodeID>),
+}
+
+impl Teleport {
+    fn prob(&self, node: &NodeID, num_nodes: usize) -> f64 {
+        match self {
+            Teleport::Uniform => 1.0 / num_nodes as f64,
+            Teleport::Seeds(seeds) => {
+                if seeds.contains(node) {
+                    1.0 / seeds.len() as f64
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+// PageRank computed by power iteration over the edges of the graph.
+// The mass of dangling nodes is redistributed according to the teleport
+// vector, so the ranks always sum to 1.
+//
+// The personalised variant only teleports to a set of trusted hosts. Rank
+// therefore has to flow from the trusted hosts through links, which makes it
+// hard for link farms that are not linked from the trusted part of the web
+// to gain any rank (see TrustRank by Gyöngyi et al.).
+pub struct PageRank {
+    inner: speedy_kv::Db<NodeID, f64>,
+}
+
+impl PageRank {
+    pub fn build<P: AsRef<Path>>(graph: &Webgraph, output: P) -> Self {
+        Self::store(calculate(graph, Teleport::Uniform), output)
+    }
+
+    /// Personalised PageRank seeded from `trusted_hosts`.
+    /// In a page graph the seeds are the pages of the trusted hosts that the graph knows
+    /// the host of, which are the pages with incoming links.
+    pub fn build_personalized<P: AsRef<Path>>(
+        graph: &Webgraph,
+        trusted_hosts: &[Node],
+        output: P,
+    ) -> Self {
+        let seeds = seeds(graph, trusted_hosts);
+
+        tracing::info!("found {} seed nodes", seeds.len());
+
+        Self::store(calculate(graph, Teleport::Seeds(seeds)), output)
+    }
+
+    fn store<P: AsRef<Path>>(ranks: DashMap<NodeID, f64>, output: P) -> Self {
+        let mut res = Self {
+            inner: speedy_kv::Db::open_or_create(output).unwrap(),
+        };
+
+        for (node, rank) in ranks {
+            if rank <= 0.0 {
+                continue;
+            }
+
+            res.inner.insert(node, rank).unwrap();
+
+            if res.inner.uncommitted_inserts() >= 10_000_000 {
+                res.inner.commit().unwrap();
+            }
+        }
+
+        res.inner.commit().unwrap();
+        res.inner.merge_all_segments().unwrap();
+
+        res
+    }
+
+    pub fn get(&self, node: &NodeID) -> Option<f64> {
+        self.inner.get(node).unwrap()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (NodeID, f64)> + '_ {
+        self.inner.iter()
+    }
+}
+
+fn seeds(graph: &Webgraph, trusted_hosts: &[Node]) -> HashSet<NodeID> {
+    let mut seeds = HashSet::new();
+
+    for host in trusted_hosts {
+        let host = host.clone().into_host().id();
+
+        if graph.id2node(&host).is_some() {
+            seeds.insert(host);
+        }
+
+        seeds.extend(graph.pages_by_host(&host));
+    }
+
+    seeds
+}
+
+fn calculate(graph: &Webgraph, teleport: Teleport) -> DashMap<NodeID, f64> {
+    let nodes: Vec<NodeID> = graph.nodes().collect();
+    let num_nodes = nodes.len();
+
+    tracing::info!("found {} nodes in graph", num_nodes);
+
+    if num_nodes == 0 || matches!(&teleport, Teleport::Seeds(seeds) if seeds.is_empty()) {
+        return DashMap::new();
+    }
+
+    let mut ranks: DashMap<NodeID, f64> = nodes
+        .par_iter()
+        .map(|node| (*node, teleport.prob(node, num_nodes)))
+        .collect();
+
+    for iteration in 0..MAX_ITERATIONS {
+        let next: DashMap<NodeID, f64> = DashMap::new();
+
+        let dangling: f64 = nodes
+            .par_iter()
+            .map(|node| {
+                let rank = ranks.get(node).map(|r| *r).unwrap_or_default();
+
+                let edges = graph.raw_outgoing_edges(node, EdgeLimit::Unlimited);
+
+                if edges.is_empty() {
+                    return rank;
+                }
+
+                let share = DAMPING * rank / edges.len() as f64;
+
+                for edge in edges {
+                    *next.entry(edge.to).or_default() += share;
+                }
+
+                0.0
+            })
+            .sum();
+
+        let jump = 1.0 - DAMPING + DAMPING * dangling;
+
+        let diff: f64 = nodes
+            .par_iter()
+            .map(|node| {
+                let mut rank = next.entry(*node).or_default();
+                *rank += jump * teleport.prob(node, num_nodes);
+
+                (*rank - ranks.get(node).map(|r| *r).unwrap_or_default()).abs()
+            })
+            .sum();
+
+        ranks = next;
+
+        tracing::info!("iteration {}: diff {}", iteration + 1, diff);
+
+        if diff < TOLERANCE {
+            break;
+        }
+    }
+
+    ranks
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        executor::Executor,
+        webgraph::{Compression, WebgraphWriter},
+    };
+
+    use super::*;
+
+    fn graph(edges: &[(&str, &str)]) -> Webgraph {
+        let mut graph = WebgraphWriter::new(
+            crate::gen_temp_path(),
+            Executor::single_thread(),
+            Compression::default(),
+            None,
+        );
+
+        for (from, to) in edges {
+            graph.insert(Node::from(*from), Node::from(*to), String::new());
+        }
+
+        graph.commit();
+
+        graph.finalize()
+    }
+
+    fn rank(pagerank: &PageRank, node: &str) -> f64 {
+        pagerank.get(&Node::from(node).id()).unwrap_or_default()
+    }
+
+    #[test]
+    fn simple() {
+        let graph = crate::webgraph::tests::test_graph();
+        let pagerank = PageRank::build(&graph, crate::gen_temp_path());
+
+        let sum: f64 = pagerank.iter().map(|(_, r)| r).sum();
+        assert!((sum - 1.0).abs() < 1e-4);
+
+        assert!(rank(&pagerank, "C") > rank(&pagerank, "A"));
+        assert!(rank(&pagerank, "A") > rank(&pagerank, "B"));
+        assert!(rank(&pagerank, "B") > rank(&pagerank, "D"));
+    }
+
+    #[test]
+    fn symmetric_cycle() {
+        let graph = graph(&[("a.com", "b.com"), ("b.com", "c.com"), ("c.com", "a.com")]);
+        let pagerank = PageRank::build(&graph, crate::gen_temp_path());
+
+        for node in ["a.com", "b.com", "c.com"] {
+            assert!((rank(&pagerank, node) - 1.0 / 3.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn link_farm_gets_no_trust() {
+        let graph = graph(&[
+            ("trusted.com", "good.com"),
+            ("good.com", "trusted.com"),
+            ("good.com", "other.com"),
+            ("spam1.com", "spam2.com"),
+            ("spam2.com", "spam1.com"),
+            ("spam3.com", "spam1.com"),
+            ("spam4.com", "spam1.com"),
+            ("spam5.com", "spam1.com"),
+        ]);
+
+        let pagerank = PageRank::build(&graph, crate::gen_temp_path());
+        assert!(rank(&pagerank, "spam1.com") > rank(&pagerank, "good.com"));
+
+        let trusted = PageRank::build_personalized(
+            &graph,
+            &[Node::from("trusted.com")],
+            crate::gen_temp_path(),
+        );
+
+        assert!(rank(&trusted, "good.com") > 0.0);
+        assert!(rank(&trusted, "other.com") > 0.0);
+        assert_eq!(rank(&trusted, "spam1.com"), 0.0);
+        assert_eq!(rank(&trusted, "spam2.com"), 0.0);
+    }
+
+    #[test]
+    fn pages_of_trusted_hosts_are_seeds() {
+        let graph = graph(&[
+            ("https://trusted.com/a", "https://example.com/"),
+            ("https://trusted.com/a", "https://trusted.com/b"),
+            ("https://spam.com/", "https://spam.com/a"),
+        ]);
+
+        let trusted = PageRank::build_personalized(
+            &graph,
+            &[Node::from("trusted.com")],
+            crate::gen_temp_path(),
+        );
+
+        assert!(rank(&trusted, "https://trusted.com/a") > 0.0);
+        assert!(rank(&trusted, "https://trusted.com/b") > 0.0);
+        assert!(rank(&trusted, "https://example.com/") > 0.0);
+        assert_eq!(rank(&trusted, "https://spam.com/a"), 0.0);
+    }
+}
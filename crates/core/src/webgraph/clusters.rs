@@ -0,0 +1,664 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Clusters of hosts in the host graph: weakly and strongly connected components
+//! and communities found by label propagation.
+//!
+//! Every cluster is identified by the smallest [`NodeID`] among its members.
+//! Only clusters with at least two members are stored, along with their most
+//! important members.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap},
+    path::Path,
+};
+
+use utoipa::ToSchema;
+
+use crate::SortableFloat;
+
+use super::{EdgeLimit, NodeID, Webgraph};
+
+/// Maximum number of members that are stored for each cluster.
+pub const MAX_STORED_MEMBERS: usize = 1024;
+
+const LABEL_PROPAGATION_ITERATIONS: usize = 20;
+
+/// Maximum number of ingoing and outgoing edges considered for each node during label propagation.
+const LABEL_PROPAGATION_EDGE_LIMIT: usize = 1024;
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    bincode::Encode,
+    bincode::Decode,
+    ToSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum ClusterKind {
+    /// Hosts that are connected when the direction of the links is ignored.
+    WeaklyConnected,
+    /// Hosts that can all reach each other by following links.
+    StronglyConnected,
+    /// Densely linked groups of hosts found by label propagation.
+    Community,
+}
+
+impl ClusterKind {
+    pub const ALL: [ClusterKind; 3] = [
+        ClusterKind::WeaklyConnected,
+        ClusterKind::StronglyConnected,
+        ClusterKind::Community,
+    ];
+
+    fn dir_name(&self) -> &'static str {
+        match self {
+            ClusterKind::WeaklyConnected => "weakly_connected",
+            ClusterKind::StronglyConnected => "strongly_connected",
+            ClusterKind::Community => "communities",
+        }
+    }
+
+    fn calculate(&self, graph: &Webgraph) -> BTreeMap<NodeID, NodeID> {
+        match self {
+            ClusterKind::WeaklyConnected => weakly_connected_components(graph),
+            ClusterKind::StronglyConnected => strongly_connected_components(graph),
+            ClusterKind::Community => label_propagation(graph, LABEL_PROPAGATION_ITERATIONS),
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode,
+)]
+pub struct Cluster {
+    pub id: NodeID,
+    pub size: u64,
+    /// The [`MAX_STORED_MEMBERS`] most important members of the cluster, most important first.
+    pub members: Vec<NodeID>,
+}
+
+/// Components of the graph when edges are treated as undirected.
+/// Maps each node to the smallest node in its component.
+pub fn weakly_connected_components(graph: &Webgraph) -> BTreeMap<NodeID, NodeID> {
+    let mut parents: HashMap<NodeID, NodeID> = graph.nodes().map(|node| (node, node)).collect();
+
+    fn find(parents: &mut HashMap<NodeID, NodeID>, node: NodeID) -> NodeID {
+        let mut root = node;
+        while let Some(parent) = parents.get(&root).copied() {
+            if parent == root {
+                break;
+            }
+            root = parent;
+        }
+
+        // path compression
+        let mut node = node;
+        while node != root {
+            let parent = parents.insert(node, root).unwrap_or(root);
+            node = parent;
+        }
+
+        root
+    }
+
+    for edge in graph.edges() {
+        parents.entry(edge.from).or_insert(edge.from);
+        parents.entry(edge.to).or_insert(edge.to);
+
+        let a = find(&mut parents, edge.from);
+        let b = find(&mut parents, edge.to);
+
+        // the smallest node becomes the root, so it identifies the component
+        match a.cmp(&b) {
+            std::cmp::Ordering::Less => {
+                parents.insert(b, a);
+            }
+            std::cmp::Ordering::Greater => {
+                parents.insert(a, b);
+            }
+            std::cmp::Ordering::Equal => {}
+        }
+    }
+
+    let nodes: Vec<_> = parents.keys().copied().collect();
+
+    nodes
+        .into_iter()
+        .map(|node| (node, find(&mut parents, node)))
+        .collect()
+}
+
+/// Strongly connected components found with an iterative version of Tarjan's algorithm.
+/// Maps each node to the smallest node in its component.
+pub fn strongly_connected_components(graph: &Webgraph) -> BTreeMap<NodeID, NodeID> {
+    struct Frame {
+        node: NodeID,
+        edges: Vec<NodeID>,
+        next: usize,
+    }
+
+    let mut index: HashMap<NodeID, u64> = HashMap::new();
+    let mut lowlink: HashMap<NodeID, u64> = HashMap::new();
+    let mut on_stack: HashMap<NodeID, bool> = HashMap::new();
+    let mut stack: Vec<NodeID> = Vec::new();
+    let mut next_index = 0;
+
+    let mut res = BTreeMap::new();
+
+    let successors = |node: &NodeID| -> Vec<NodeID> {
+        graph
+            .raw_outgoing_edges(node, EdgeLimit::Unlimited)
+            .into_iter()
+            .map(|edge| edge.to)
+            .collect()
+    };
+
+    for root in graph.nodes() {
+        if index.contains_key(&root) {
+            continue;
+        }
+
+        let mut frames = vec![Frame {
+            node: root,
+            edges: successors(&root),
+            next: 0,
+        }];
+        index.insert(root, next_index);
+        lowlink.insert(root, next_index);
+        next_index += 1;
+        stack.push(root);
+        on_stack.insert(root, true);
+
+        while let Some(frame) = frames.last_mut() {
+            if let Some(next) = frame.edges.get(frame.next).copied() {
+                frame.next += 1;
+
+                match index.get(&next).copied() {
+                    None => {
+                        index.insert(next, next_index);
+                        lowlink.insert(next, next_index);
+                        next_index += 1;
+                        stack.push(next);
+                        on_stack.insert(next, true);
+
+                        frames.push(Frame {
+                            node: next,
+                            edges: successors(&next),
+                            next: 0,
+                        });
+                    }
+                    Some(next_idx) => {
+                        if on_stack.get(&next).copied().unwrap_or(false) {
+                            let node = frame.node;
+                            let low = lowlink[&node].min(next_idx);
+                            lowlink.insert(node, low);
+                        }
+                    }
+                }
+
+                continue;
+            }
+
+            let node = frame.node;
+            frames.pop();
+
+            if let Some(parent) = frames.last() {
+                let low = lowlink[&parent.node].min(lowlink[&node]);
+                lowlink.insert(parent.node, low);
+            }
+
+            if lowlink[&node] == index[&node] {
+                let mut component = Vec::new();
+
+                while let Some(member) = stack.pop() {
+                    on_stack.insert(member, false);
+                    component.push(member);
+
+                    if member == node {
+                        break;
+                    }
+                }
+
+                let id = component.iter().min().copied().unwrap_or(node);
+                res.extend(component.into_iter().map(|member| (member, id)));
+            }
+        }
+    }
+
+    res
+}
+
+/// Communities found by asynchronous label propagation on the undirected graph.
+/// Each node starts in its own community and repeatedly joins the community that
+/// most of its neighbours belong to, until no node changes community.
+/// Maps each node to the smallest node in its community.
+pub fn label_propagation(graph: &Webgraph, max_iterations: usize) -> BTreeMap<NodeID, NodeID> {
+    let mut labels: BTreeMap<NodeID, NodeID> = graph.nodes().map(|node| (node, node)).collect();
+    let nodes: Vec<NodeID> = labels.keys().copied().collect();
+    let limit = EdgeLimit::Limit(LABEL_PROPAGATION_EDGE_LIMIT);
+
+    for iteration in 0..max_iterations {
+        let mut changed = 0;
+
+        for node in &nodes {
+            let neighbours = graph
+                .raw_outgoing_edges(node, limit)
+                .into_iter()
+                .map(|edge| edge.to)
+                .chain(
+                    graph
+                        .raw_ingoing_edges(node, limit)
+                        .into_iter()
+                        .map(|edge| edge.from),
+                )
+                .filter(|neighbour| neighbour != node);
+
+            let mut counts: BTreeMap<NodeID, usize> = BTreeMap::new();
+            for neighbour in neighbours {
+                if let Some(label) = labels.get(&neighbour) {
+                    *counts.entry(*label).or_default() += 1;
+                }
+            }
+
+            let Some(max) = counts.values().max().copied() else {
+                continue;
+            };
+
+            let current = labels[node];
+
+            // keep the current label on ties to make the propagation converge
+            if counts.get(&current).copied() == Some(max) {
+                continue;
+            }
+
+            let best = counts
+                .into_iter()
+                .find(|(_, count)| *count == max)
+                .map(|(label, _)| label)
+                .unwrap();
+
+            labels.insert(*node, best);
+            changed += 1;
+        }
+
+        tracing::info!(
+            "label propagation iteration {}: {} changed",
+            iteration + 1,
+            changed
+        );
+
+        if changed == 0 {
+            break;
+        }
+    }
+
+    // labels are renamed to the smallest member of the community
+    let mut smallest: HashMap<NodeID, NodeID> = HashMap::new();
+    for (node, label) in &labels {
+        let id = smallest.entry(*label).or_insert(*node);
+        *id = (*id).min(*node);
+    }
+
+    labels
+        .into_iter()
+        .map(|(node, label)| (node, smallest[&label]))
+        .collect()
+}
+
+struct ClusterStore {
+    clusters: speedy_kv::Db<NodeID, NodeID>,
+    members: speedy_kv::Db<NodeID, Cluster>,
+}
+
+impl ClusterStore {
+    fn open<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            clusters: speedy_kv::Db::open_or_create(path.as_ref().join("clusters")).unwrap(),
+            members: speedy_kv::Db::open_or_create(path.as_ref().join("members")).unwrap(),
+        }
+    }
+
+    fn store(&mut self, assignments: BTreeMap<NodeID, NodeID>, importance: &HashMap<NodeID, f64>) {
+        // the least important member is on top, so it is the one that is replaced.
+        // Ties are broken in favour of the smallest node
+        type Members = BinaryHeap<Reverse<(SortableFloat, Reverse<NodeID>)>>;
+
+        let mut sizes: BTreeMap<NodeID, u64> = BTreeMap::new();
+        let mut members: BTreeMap<NodeID, Members> = BTreeMap::new();
+
+        for (node, id) in &assignments {
+            *sizes.entry(*id).or_default() += 1;
+
+            let score = importance.get(node).copied().unwrap_or_default();
+            let top = members.entry(*id).or_default();
+            top.push(Reverse((SortableFloat(score), Reverse(*node))));
+
+            if top.len() > MAX_STORED_MEMBERS {
+                top.pop();
+            }
+        }
+
+        let clusters: BTreeMap<NodeID, Cluster> = members
+            .into_iter()
+            .map(|(id, top)| {
+                let cluster = Cluster {
+                    id,
+                    size: sizes[&id],
+                    members: top
+                        .into_sorted_vec()
+                        .into_iter()
+                        .map(|Reverse((_, Reverse(node)))| node)
+                        .collect(),
+                };
+
+                (id, cluster)
+            })
+            .collect();
+
+        for (node, id) in assignments {
+            if clusters[&id].size < 2 {
+                continue;
+            }
+
+            self.clusters.insert(node, id).unwrap();
+
+            if self.clusters.uncommitted_inserts() >= 1_000_000 {
+                self.clusters.commit().unwrap();
+            }
+        }
+
+        for (id, cluster) in clusters {
+            if cluster.size < 2 {
+                continue;
+            }
+
+            self.members.insert(id, cluster).unwrap();
+
+            if self.members.uncommitted_inserts() >= 100_000 {
+                self.members.commit().unwrap();
+            }
+        }
+
+        self.clusters.commit().unwrap();
+        self.clusters.merge_all_segments().unwrap();
+        self.members.commit().unwrap();
+        self.members.merge_all_segments().unwrap();
+    }
+
+    fn get(&self, node: &NodeID) -> Option<Cluster> {
+        let id = self.clusters.get(node).unwrap()?;
+        self.members.get(&id).unwrap()
+    }
+}
+
+/// The persisted clusters of a host graph.
+pub struct HostClusters {
+    weakly_connected: ClusterStore,
+    strongly_connected: ClusterStore,
+    communities: ClusterStore,
+}
+
+impl HostClusters {
+    /// Calculate and store the clusters of the graph. The members of a cluster are ranked
+    /// by their host centrality, or by their number of ingoing links if no centrality is given.
+    pub fn build<P: AsRef<Path>>(
+        graph: &Webgraph,
+        host_centrality: Option<&speedy_kv::Db<NodeID, f64>>,
+        output: P,
+    ) -> Self {
+        let importance: HashMap<NodeID, f64> = graph
+            .nodes()
+            .map(|node| {
+                let score = match host_centrality {
+                    Some(store) => store.get(&node).unwrap().unwrap_or_default(),
+                    None => graph
+                        .raw_ingoing_edges(&node, EdgeLimit::Limit(LABEL_PROPAGATION_EDGE_LIMIT))
+                        .len() as f64,
+                };
+
+                (node, score)
+            })
+            .collect();
+
+        let mut res = Self::open(output);
+
+        for kind in ClusterKind::ALL {
+            tracing::info!("calculating {} clusters", kind.dir_name());
+            let assignments = kind.calculate(graph);
+            res.store_mut(kind).store(assignments, &importance);
+        }
+
+        res
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+
+        Self {
+            weakly_connected: ClusterStore::open(
+                path.join(ClusterKind::WeaklyConnected.dir_name()),
+            ),
+            strongly_connected: ClusterStore::open(
+                path.join(ClusterKind::StronglyConnected.dir_name()),
+            ),
+            communities: ClusterStore::open(path.join(ClusterKind::Community.dir_name())),
+        }
+    }
+
+    fn store(&self, kind: ClusterKind) -> &ClusterStore {
+        match kind {
+            ClusterKind::WeaklyConnected => &self.weakly_connected,
+            ClusterKind::StronglyConnected => &self.strongly_connected,
+            ClusterKind::Community => &self.communities,
+        }
+    }
+
+    fn store_mut(&mut self, kind: ClusterKind) -> &mut ClusterStore {
+        match kind {
+            ClusterKind::WeaklyConnected => &mut self.weakly_connected,
+            ClusterKind::StronglyConnected => &mut self.strongly_connected,
+            ClusterKind::Community => &mut self.communities,
+        }
+    }
+
+    /// The cluster of `node`, or `None` if the node is not part of a cluster with other hosts.
+    pub fn get(&self, node: &NodeID, kind: ClusterKind) -> Option<Cluster> {
+        self.store(kind).get(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        executor::Executor,
+        webgraph::{Compression, Node, WebgraphWriter},
+    };
+
+    use super::*;
+
+    fn graph(edges: &[(&str, &str)]) -> Webgraph {
+        let mut graph = WebgraphWriter::new(
+            crate::gen_temp_path(),
+            Executor::single_thread(),
+            Compression::default(),
+            None,
+        );
+
+        for (from, to) in edges {
+            graph.insert(Node::from(*from), Node::from(*to), String::new());
+        }
+
+        graph.commit();
+
+        graph.finalize()
+    }
+
+    fn id(node: &str) -> NodeID {
+        Node::from(node).id()
+    }
+
+    fn same(assignments: &BTreeMap<NodeID, NodeID>, a: &str, b: &str) -> bool {
+        assignments[&id(a)] == assignments[&id(b)]
+    }
+
+    #[test]
+    fn weakly_connected() {
+        let graph = graph(&[("a.com", "b.com"), ("c.com", "b.com"), ("d.com", "e.com")]);
+
+        let components = weakly_connected_components(&graph);
+
+        assert_eq!(components.len(), 5);
+        assert!(same(&components, "a.com", "b.com"));
+        assert!(same(&components, "a.com", "c.com"));
+        assert!(same(&components, "d.com", "e.com"));
+        assert!(!same(&components, "a.com", "d.com"));
+
+        let smallest = [id("a.com"), id("b.com"), id("c.com")]
+            .into_iter()
+            .min()
+            .unwrap();
+        assert_eq!(components[&id("c.com")], smallest);
+    }
+
+    #[test]
+    fn strongly_connected() {
+        let test_graph = crate::webgraph::tests::test_graph();
+        let components = strongly_connected_components(&test_graph);
+
+        assert!(same(&components, "A", "B"));
+        assert!(same(&components, "A", "C"));
+        assert!(!same(&components, "A", "D"));
+
+        let graph = graph(&[
+            ("a.com", "b.com"),
+            ("b.com", "a.com"),
+            ("b.com", "c.com"),
+            ("c.com", "d.com"),
+            ("d.com", "c.com"),
+        ]);
+        let components = strongly_connected_components(&graph);
+
+        assert!(same(&components, "a.com", "b.com"));
+        assert!(same(&components, "c.com", "d.com"));
+        assert!(!same(&components, "a.com", "c.com"));
+    }
+
+    #[test]
+    fn communities() {
+        // two cliques connected by a single edge
+        let mut edges = Vec::new();
+        for group in [
+            ["a1.com", "a2.com", "a3.com", "a4.com"],
+            ["b1.com", "b2.com", "b3.com", "b4.com"],
+        ] {
+            for from in group {
+                for to in group {
+                    if from != to {
+                        edges.push((from, to));
+                    }
+                }
+            }
+        }
+        edges.push(("a1.com", "b1.com"));
+
+        let graph = graph(&edges);
+        let communities = label_propagation(&graph, LABEL_PROPAGATION_ITERATIONS);
+
+        for node in ["a2.com", "a3.com", "a4.com"] {
+            assert!(same(&communities, "a1.com", node));
+        }
+        for node in ["b2.com", "b3.com", "b4.com"] {
+            assert!(same(&communities, "b1.com", node));
+        }
+        assert!(!same(&communities, "a1.com", "b1.com"));
+    }
+
+    #[test]
+    fn store() {
+        let graph = graph(&[("a.com", "b.com"), ("b.com", "a.com"), ("b.com", "c.com")]);
+
+        let path = crate::gen_temp_path();
+        HostClusters::build(&graph, None, &path);
+        let clusters = HostClusters::open(&path);
+
+        let cluster = clusters
+            .get(&id("a.com"), ClusterKind::WeaklyConnected)
+            .unwrap();
+        assert_eq!(cluster.size, 3);
+        assert_eq!(cluster.members.len(), 3);
+        assert_eq!(
+            clusters.get(&id("c.com"), ClusterKind::WeaklyConnected),
+            Some(cluster)
+        );
+
+        let cluster = clusters
+            .get(&id("a.com"), ClusterKind::StronglyConnected)
+            .unwrap();
+        assert_eq!(cluster.size, 2);
+        assert!(cluster.members.contains(&id("b.com")));
+
+        // c.com is its own strongly connected component
+        assert_eq!(
+            clusters.get(&id("c.com"), ClusterKind::StronglyConnected),
+            None
+        );
+    }
+
+    #[test]
+    fn representative_members() {
+        let graph = graph(&[
+            ("a.com", "b.com"),
+            ("c.com", "b.com"),
+            ("c.com", "a.com"),
+            ("b.com", "d.com"),
+        ]);
+
+        // without centrality the hosts with the most ingoing links come first
+        let path = crate::gen_temp_path();
+        let clusters = HostClusters::build(&graph, None, &path);
+        let cluster = clusters
+            .get(&id("a.com"), ClusterKind::WeaklyConnected)
+            .unwrap();
+        assert_eq!(cluster.members.len(), 4);
+        assert_eq!(cluster.members[0], id("b.com"));
+        assert_eq!(cluster.members[3], id("c.com"));
+
+        let centrality_path = crate::gen_temp_path();
+        let mut centrality: speedy_kv::Db<NodeID, f64> =
+            speedy_kv::Db::open_or_create(&centrality_path).unwrap();
+        centrality.insert(id("d.com"), 0.9).unwrap();
+        centrality.insert(id("c.com"), 0.5).unwrap();
+        centrality.insert(id("a.com"), 0.1).unwrap();
+        centrality.commit().unwrap();
+
+        let path = crate::gen_temp_path();
+        let clusters = HostClusters::build(&graph, Some(&centrality), &path);
+        let cluster = clusters
+            .get(&id("a.com"), ClusterKind::WeaklyConnected)
+            .unwrap();
+        assert_eq!(
+            cluster.members,
+            vec![id("d.com"), id("c.com"), id("a.com"), id("b.com")]
+        );
+    }
+}
@@ -16,6 +16,8 @@
 
 use utoipa::ToSchema;
 
+use crate::webpage::{LinkPosition, LinkRel};
+
 use super::{FullNodeID, Node, NodeID};
 
 pub const MAX_LABEL_LENGTH: usize = 1024;
@@ -48,6 +50,102 @@ impl EdgeLabel for () {
     }
 }
 
+/// What is known about a link from one node to another.
+#[derive(
+    Debug,
+    Clone,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+    bincode::Encode,
+    bincode::Decode,
+)]
+pub struct LinkLabel {
+    /// The anchor text of the link.
+    pub text: String,
+    pub rel: LinkRel,
+    pub position: LinkPosition,
+    /// Unix timestamp in seconds of the first crawl where the link was seen. 0 if unknown.
+    pub first_seen: u64,
+    /// Unix timestamp in seconds of the latest crawl where the link was seen. 0 if unknown.
+    pub last_seen: u64,
+}
+
+impl LinkLabel {
+    /// Combine two observations of the same link.
+    /// The most recent observation decides the text, rel and position of the link.
+    pub fn merge(&mut self, other: LinkLabel) {
+        let first_seen = match (self.first_seen, other.first_seen) {
+            (0, seen) | (seen, 0) => seen,
+            (a, b) => a.min(b),
+        };
+
+        if other.last_seen > self.last_seen {
+            *self = other;
+        }
+
+        self.first_seen = first_seen;
+    }
+}
+
+impl From<String> for LinkLabel {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            ..Default::default()
+        }
+    }
+}
+
+impl EdgeLabel for LinkLabel {
+    fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::encode_to_vec(self, bincode::config::standard())?)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let (label, _) = bincode::decode_from_slice(bytes, bincode::config::standard())?;
+        Ok(label)
+    }
+}
+
+/// Links to leave out when the graph is read, e.g. so nofollow and sponsored links
+/// do not count towards the centrality of the sites they point to.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    bincode::Encode,
+    bincode::Decode,
+)]
+#[serde(default)]
+pub struct LinkFilter {
+    pub ignore_nofollow: bool,
+    pub ignore_sponsored: bool,
+    pub ignore_ugc: bool,
+    pub ignore_navigation: bool,
+}
+
+impl LinkFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn keep(&self, label: &LinkLabel) -> bool {
+        !((self.ignore_nofollow && label.rel.nofollow)
+            || (self.ignore_sponsored && label.rel.sponsored)
+            || (self.ignore_ugc && label.rel.ugc)
+            || (self.ignore_navigation && label.position == LinkPosition::Navigation))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, bincode::Encode, bincode::Decode)]
 pub struct Edge<L>
 where
@@ -99,3 +197,76 @@ pub struct FullEdge {
     pub to: Node,
     pub label: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_labels() {
+        let mut label = LinkLabel {
+            text: "old".to_string(),
+            first_seen: 10,
+            last_seen: 20,
+            ..Default::default()
+        };
+
+        label.merge(LinkLabel {
+            text: "new".to_string(),
+            rel: LinkRel {
+                nofollow: true,
+                ..Default::default()
+            },
+            first_seen: 30,
+            last_seen: 30,
+            ..Default::default()
+        });
+
+        assert_eq!(label.text, "new");
+        assert!(label.rel.nofollow);
+        assert_eq!(label.first_seen, 10);
+        assert_eq!(label.last_seen, 30);
+
+        label.merge(LinkLabel::from("unknown".to_string()));
+
+        assert_eq!(label.text, "new");
+        assert_eq!(label.first_seen, 10);
+        assert_eq!(label.last_seen, 30);
+    }
+
+    #[test]
+    fn filter() {
+        let label = LinkLabel {
+            rel: LinkRel {
+                sponsored: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert!(LinkFilter::default().is_empty());
+        assert!(LinkFilter::default().keep(&label));
+
+        let filter = LinkFilter {
+            ignore_nofollow: true,
+            ..Default::default()
+        };
+        assert!(filter.keep(&label));
+
+        let filter = LinkFilter {
+            ignore_sponsored: true,
+            ..Default::default()
+        };
+        assert!(!filter.keep(&label));
+
+        let filter = LinkFilter {
+            ignore_navigation: true,
+            ..Default::default()
+        };
+        assert!(filter.keep(&label));
+        assert!(!filter.keep(&LinkLabel {
+            position: LinkPosition::Navigation,
+            ..Default::default()
+        }));
+    }
+}
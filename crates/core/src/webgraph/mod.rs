@@ -13,6 +13,7 @@
 //
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
@@ -24,7 +25,8 @@ use rand::seq::SliceRandom;
 use rayon::prelude::*;
 
 use self::id_node_db::Id2NodeDb;
-use self::segment::Segment;
+use self::segment::{Segment, SegmentWriter};
+use self::store::LabelFormat;
 use crate::executor::Executor;
 
 pub use builder::WebgraphBuilder;
@@ -36,6 +38,7 @@ pub use writer::WebgraphWriter;
 
 mod builder;
 pub mod centrality;
+pub mod clusters;
 mod compression;
 mod edge;
 mod id_node_db;
@@ -45,13 +48,30 @@ mod segment;
 mod shortest_path;
 mod store;
 mod store_writer;
+mod tombstones;
 mod writer;
 
 type SegmentID = String;
 
-#[derive(serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode, Default)]
+/// Where `compact` moves the old id2node while the compacted one takes its place.
+const OLD_ID2NODE: &str = "id2node_old";
+
+#[derive(serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode)]
 struct Meta {
     comitted_segments: Vec<SegmentID>,
+    /// The format of the edge labels in the segments. Metadata without a format
+    /// was written before the format was stored, when labels were plain text.
+    #[serde(default)]
+    label_format: LabelFormat,
+}
+
+impl Default for Meta {
+    fn default() -> Self {
+        Self {
+            comitted_segments: Vec::new(),
+            label_format: LabelFormat::LinkLabel,
+        }
+    }
 }
 
 impl Meta {
@@ -100,6 +120,24 @@ pub struct Webgraph {
     executor: Arc<Executor>,
     id2node: Id2NodeDb,
     meta: Meta,
+    link_filter: LinkFilter,
+}
+
+/// Sort the edges by `key` and merge the labels of edges with the same key,
+/// which happens when an edge is present in more than one segment.
+fn dedup_labelled_edges<F>(edges: &mut Vec<Edge<LinkLabel>>, key: F)
+where
+    F: Fn(&Edge<LinkLabel>) -> NodeID,
+{
+    edges.sort_by_key(&key);
+    edges.dedup_by(|a, b| {
+        if key(a) == key(b) {
+            b.label.merge(std::mem::take(&mut a.label));
+            true
+        } else {
+            false
+        }
+    });
 }
 
 impl Webgraph {
@@ -123,11 +161,23 @@ impl Webgraph {
 
         fs::create_dir_all(path.as_ref().join("segments")).unwrap();
 
+        // a compaction crashed while the compacted id2node was swapped in. the old one
+        // has all the nodes of the compacted graph, so it is put back if needed.
+        let old_id2node_path = path.as_ref().join(OLD_ID2NODE);
+        if old_id2node_path.exists() {
+            if path.as_ref().join("id2node").exists() {
+                fs::remove_dir_all(&old_id2node_path).unwrap();
+            } else {
+                fs::rename(&old_id2node_path, path.as_ref().join("id2node")).unwrap();
+            }
+        }
+
         let mut segments = Vec::new();
         for segment in &meta.comitted_segments {
             segments.push(Segment::open(
                 path.as_ref().join("segments"),
                 segment.clone(),
+                meta.label_format,
             ));
         }
 
@@ -137,10 +187,30 @@ impl Webgraph {
             executor: Arc::new(executor),
             id2node: Id2NodeDb::open(path.as_ref().join("id2node")),
             meta,
+            link_filter: LinkFilter::default(),
+        }
+    }
+
+    /// Leave the links that are rejected by `filter` out when the graph is read.
+    /// Compaction and merges are not affected by the filter.
+    pub fn set_link_filter(&mut self, filter: LinkFilter) {
+        self.link_filter = filter;
+
+        for segment in &mut self.segments {
+            segment.set_link_filter(filter);
         }
     }
 
+    /// Move the segments of `other` into the graph. Both graphs must store their labels
+    /// in the same format, so graphs from before the format changed must be compacted first.
     pub fn merge(&mut self, other: Webgraph) -> io::Result<()> {
+        if self.meta.label_format != other.meta.label_format {
+            return Err(io::Error::other(format!(
+                "cannot merge webgraphs with {:?} and {:?} labels, compact the older graph first",
+                self.meta.label_format, other.meta.label_format
+            )));
+        }
+
         let other_folder = other.path.clone();
         self.id2node.merge(other.id2node);
         self.id2node.flush();
@@ -152,7 +222,10 @@ impl Webgraph {
 
             self.meta.comitted_segments.push(segment.id());
             drop(segment);
-            self.segments.push(Segment::open(new_path, id));
+
+            let mut segment = Segment::open(new_path, id, self.meta.label_format);
+            segment.set_link_filter(self.link_filter);
+            self.segments.push(segment);
         }
 
         fs::remove_dir_all(other_folder)?;
@@ -162,6 +235,180 @@ impl Webgraph {
         Ok(())
     }
 
+    /// Update the graph with the edges from a fresh crawl.
+    ///
+    /// The outgoing edges of every node with outgoing edges in `other` or in `recrawled`
+    /// are replaced by the edges from `other`, so links that have been removed from a
+    /// recrawled page are removed from the graph. Pages that no longer have any
+    /// outgoing links only show up in `recrawled`.
+    ///
+    /// Links that are still present are kept in the old segments as well,
+    /// so they remember when they were first seen.
+    pub fn update(&mut self, other: Webgraph, recrawled: &[NodeID]) -> io::Result<()> {
+        let mut recrawled: HashSet<NodeID> = recrawled.iter().copied().collect();
+        recrawled.extend(other.edges().map(|e| e.from));
+
+        for node in &recrawled {
+            let current: HashSet<NodeID> = other
+                .raw_outgoing_edges(node, EdgeLimit::Unlimited)
+                .into_iter()
+                .map(|e| e.to)
+                .collect();
+
+            for segment in &mut self.segments {
+                // edges removed by the link filter are stale as well
+                for edge in segment.unfiltered_outgoing_edges(node, &EdgeLimit::Unlimited) {
+                    if !current.contains(&edge.to) {
+                        segment.delete_edge(&edge.from, &edge.to);
+                    }
+                }
+            }
+        }
+
+        self.commit();
+
+        self.merge(other)
+    }
+
+    /// Delete the edge from `from` to `to`.
+    /// Deletions are visible after [`Webgraph::commit`].
+    pub fn delete_edge(&mut self, from: &NodeID, to: &NodeID) {
+        for segment in &mut self.segments {
+            if segment
+                .unfiltered_outgoing_edges(from, &EdgeLimit::Unlimited)
+                .iter()
+                .any(|e| e.to == *to)
+            {
+                segment.delete_edge(from, to);
+            }
+        }
+    }
+
+    /// Delete all outgoing edges of `node`, e.g. because the page has been recrawled.
+    /// Deletions are visible after [`Webgraph::commit`].
+    pub fn delete_outgoing_edges(&mut self, node: &NodeID) {
+        for segment in &mut self.segments {
+            for edge in segment.unfiltered_outgoing_edges(node, &EdgeLimit::Unlimited) {
+                segment.delete_edge(&edge.from, &edge.to);
+            }
+        }
+    }
+
+    /// Delete all edges going to or from `node`.
+    /// Deletions are visible after [`Webgraph::commit`].
+    pub fn delete_node(&mut self, node: &NodeID) {
+        for segment in &mut self.segments {
+            let has_edges = !segment
+                .unfiltered_outgoing_edges(node, &EdgeLimit::Limit(1))
+                .is_empty()
+                || !segment
+                    .unfiltered_ingoing_edges(node, &EdgeLimit::Limit(1))
+                    .is_empty();
+
+            if has_edges {
+                segment.delete_node(node);
+            }
+        }
+    }
+
+    /// Delete the host and all of its pages from the graph.
+    /// Deletions are visible after [`Webgraph::commit`].
+    pub fn delete_host(&mut self, host: &Node) {
+        let host = host.clone().into_host().id();
+
+        for page in self.pages_by_host(&host) {
+            self.delete_node(&page);
+        }
+
+        self.delete_node(&host);
+    }
+
+    pub fn commit(&mut self) {
+        for segment in &mut self.segments {
+            segment.commit();
+        }
+    }
+
+    /// Rewrite all segments into a single segment without the deleted edges.
+    /// Nodes that are not part of any edge after the deletions are removed from the graph.
+    pub fn compact(
+        &mut self,
+        compression: Compression,
+        host_centrality_rank_store: Option<Arc<speedy_kv::Db<NodeID, u64>>>,
+    ) -> io::Result<()> {
+        // compaction also rewrites the labels of older graphs in the current format
+        if self.segments.len() <= 1
+            && !self.segments.iter().any(|s| s.has_deletions())
+            && self.meta.label_format == LabelFormat::LinkLabel
+        {
+            return Ok(());
+        }
+
+        let path = Path::new(&self.path);
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut writer = SegmentWriter::open(
+            path.join("segments"),
+            id.clone(),
+            compression,
+            host_centrality_rank_store,
+        );
+
+        let id2node_path = path.join("id2node");
+        let compacted_id2node_path = path.join("id2node_compacted");
+
+        // left behind by a compaction that crashed before the metadata was saved
+        if compacted_id2node_path.exists() {
+            fs::remove_dir_all(&compacted_id2node_path)?;
+        }
+
+        let mut id2node = Id2NodeDb::open(&compacted_id2node_path);
+
+        for segment in &self.segments {
+            for edge in segment.edges_with_label() {
+                let (Some(from), Some(to)) = (self.id2node(&edge.from), self.id2node(&edge.to))
+                else {
+                    continue;
+                };
+
+                id2node.put(&edge.from, &from);
+                id2node.put(&edge.to, &to);
+
+                writer.insert(InnerEdge {
+                    from: FullNodeID::from(from),
+                    to: FullNodeID::from(to),
+                    label: edge.label,
+                });
+            }
+        }
+
+        id2node.flush();
+        drop(id2node);
+
+        let mut segment = writer.finalize();
+        segment.set_link_filter(self.link_filter);
+
+        let old_segments = std::mem::replace(&mut self.segments, vec![segment]);
+        self.meta.comitted_segments = vec![id];
+        self.meta.label_format = LabelFormat::LinkLabel;
+        self.save_metadata();
+
+        for segment in old_segments {
+            let segment_path = segment.path();
+            drop(segment);
+            fs::remove_dir_all(segment_path)?;
+        }
+
+        // the old id2node is only deleted once the compacted one is in place,
+        // so a crash never leaves the graph without one (see `Webgraph::open`)
+        let old_id2node_path = path.join(OLD_ID2NODE);
+        fs::rename(&id2node_path, &old_id2node_path)?;
+        fs::rename(&compacted_id2node_path, &id2node_path)?;
+        self.id2node = Id2NodeDb::open(&id2node_path);
+        fs::remove_dir_all(&old_id2node_path)?;
+
+        Ok(())
+    }
+
     pub fn optimize_read(&mut self) {
         self.executor
             .map(|s| s.optimize_read(), self.segments.iter_mut())
@@ -171,10 +418,7 @@ impl Webgraph {
     }
 
     pub fn ingoing_edges(&self, node: Node, limit: EdgeLimit) -> Vec<FullEdge> {
-        let dedup = |edges: &mut Vec<Edge<String>>| {
-            edges.sort_by_key(|e| e.from);
-            edges.dedup_by_key(|e| e.from);
-        };
+        let dedup = |edges: &mut Vec<Edge<LinkLabel>>| dedup_labelled_edges(edges, |e| e.from);
 
         self.inner_edges(
             |segment| segment.ingoing_edges_with_label(&node.id(), &limit),
@@ -184,7 +428,7 @@ impl Webgraph {
         .map(|e| FullEdge {
             from: self.id2node(&e.from).unwrap(),
             to: self.id2node(&e.to).unwrap(),
-            label: e.label,
+            label: e.label.text,
         })
         .collect()
     }
@@ -220,11 +464,8 @@ impl Webgraph {
         &self,
         node: &NodeID,
         limit: EdgeLimit,
-    ) -> Vec<Edge<String>> {
-        let dedup = |edges: &mut Vec<Edge<String>>| {
-            edges.sort_by_key(|e| e.from);
-            edges.dedup_by_key(|e| e.from);
-        };
+    ) -> Vec<Edge<LinkLabel>> {
+        let dedup = |edges: &mut Vec<Edge<LinkLabel>>| dedup_labelled_edges(edges, |e| e.from);
 
         self.inner_edges(
             |segment| segment.ingoing_edges_with_label(node, &limit),
@@ -236,11 +477,8 @@ impl Webgraph {
         &self,
         node: &NodeID,
         limit: EdgeLimit,
-    ) -> Vec<Edge<String>> {
-        let dedup = |edges: &mut Vec<Edge<String>>| {
-            edges.sort_by_key(|e| e.from);
-            edges.dedup_by_key(|e| e.from);
-        };
+    ) -> Vec<Edge<LinkLabel>> {
+        let dedup = |edges: &mut Vec<Edge<LinkLabel>>| dedup_labelled_edges(edges, |e| e.to);
 
         self.inner_edges(
             |segment| segment.outgoing_edges_with_label(node, &limit),
@@ -249,10 +487,7 @@ impl Webgraph {
     }
 
     pub fn outgoing_edges(&self, node: Node, limit: EdgeLimit) -> Vec<FullEdge> {
-        let dedup = |edges: &mut Vec<Edge<String>>| {
-            edges.sort_by_key(|e| e.to);
-            edges.dedup_by_key(|e| e.to);
-        };
+        let dedup = |edges: &mut Vec<Edge<LinkLabel>>| dedup_labelled_edges(edges, |e| e.to);
 
         self.inner_edges(
             |segment| segment.outgoing_edges_with_label(&node.id(), &limit),
@@ -262,7 +497,7 @@ impl Webgraph {
         .map(|e| FullEdge {
             from: self.id2node(&e.from).unwrap(),
             to: self.id2node(&e.to).unwrap(),
-            label: e.label,
+            label: e.label.text,
         })
         .collect()
     }
@@ -343,6 +578,8 @@ impl Webgraph {
 
 #[cfg(test)]
 pub mod tests {
+    use crate::webpage::{LinkPosition, LinkRel};
+
     use super::*;
 
     pub fn test_edges() -> Vec<(Node, Node, String)> {
@@ -485,6 +722,358 @@ pub mod tests {
         );
     }
 
+    fn writer() -> WebgraphWriter {
+        WebgraphWriter::new(
+            crate::gen_temp_path(),
+            Executor::single_thread(),
+            Compression::default(),
+            None,
+        )
+    }
+
+    fn outgoing(graph: &Webgraph, node: &str) -> Vec<Node> {
+        let mut res: Vec<_> = graph
+            .outgoing_edges(Node::from(node), EdgeLimit::Unlimited)
+            .into_iter()
+            .map(|e| e.to)
+            .collect();
+        res.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+        res
+    }
+
+    #[test]
+    fn update_replaces_outgoing_edges() {
+        let mut graph = test_graph();
+
+        let mut fresh = writer();
+        fresh.insert(Node::from("A"), Node::from("D"), String::new());
+        let fresh = fresh.finalize();
+
+        graph.update(fresh, &[]).unwrap();
+
+        assert_eq!(outgoing(&graph, "A"), vec![Node::from("D")]);
+        assert_eq!(outgoing(&graph, "B"), vec![Node::from("C")]);
+        assert_eq!(
+            graph
+                .ingoing_edges(Node::from("D"), EdgeLimit::Unlimited)
+                .len(),
+            1
+        );
+        assert_eq!(
+            graph
+                .raw_ingoing_edges(&Node::from("C").id(), EdgeLimit::Unlimited)
+                .len(),
+            2
+        );
+        assert_eq!(
+            graph
+                .raw_ingoing_edges(&Node::from("C").id(), EdgeLimit::Limit(1))
+                .len(),
+            1
+        );
+
+        assert_eq!(graph.distances(Node::from("A")).get(&Node::from("B")), None);
+    }
+
+    #[test]
+    fn delete_edges() {
+        let mut graph = test_graph();
+
+        graph.delete_edge(&Node::from("A").id(), &Node::from("B").id());
+        assert_eq!(outgoing(&graph, "A").len(), 2);
+
+        graph.commit();
+        assert_eq!(outgoing(&graph, "A"), vec![Node::from("C")]);
+        assert_eq!(
+            graph.raw_outgoing_edges(&Node::from("A").id(), EdgeLimit::Limit(1)),
+            vec![Edge {
+                from: Node::from("A").id(),
+                to: Node::from("C").id(),
+                label: (),
+            }]
+        );
+
+        graph.delete_outgoing_edges(&Node::from("C").id());
+        graph.commit();
+
+        assert!(outgoing(&graph, "C").is_empty());
+        assert_eq!(graph.edges().count(), 3);
+    }
+
+    #[test]
+    fn delete_host() {
+        let mut wrt = writer();
+        wrt.insert(
+            Node::from("https://a.com/1"),
+            Node::from("https://spam.com/1"),
+            String::new(),
+        );
+        wrt.insert(
+            Node::from("https://spam.com/1"),
+            Node::from("https://spam.com/2"),
+            String::new(),
+        );
+        wrt.insert(
+            Node::from("https://spam.com/2"),
+            Node::from("https://a.com/1"),
+            String::new(),
+        );
+        wrt.insert(
+            Node::from("https://a.com/1"),
+            Node::from("https://b.com/1"),
+            String::new(),
+        );
+        // no page links to this one, so it is only known from its outgoing edges
+        wrt.insert(
+            Node::from("https://spam.com/3"),
+            Node::from("https://b.com/1"),
+            String::new(),
+        );
+        let mut graph = wrt.finalize();
+
+        graph.delete_host(&Node::from("spam.com"));
+        graph.commit();
+
+        assert_eq!(
+            outgoing(&graph, "https://a.com/1"),
+            vec![Node::from("https://b.com/1")]
+        );
+        assert!(outgoing(&graph, "https://spam.com/1").is_empty());
+        assert!(outgoing(&graph, "https://spam.com/3").is_empty());
+        assert_eq!(
+            graph
+                .raw_ingoing_edges(&Node::from("https://b.com/1").id(), EdgeLimit::Unlimited)
+                .len(),
+            1
+        );
+        assert!(graph.pages_by_host(&Node::from("spam.com").id()).is_empty());
+    }
+
+    #[test]
+    fn compact() {
+        let mut graph = test_graph();
+
+        let mut fresh = writer();
+        fresh.insert(Node::from("D"), Node::from("E"), "new".to_string());
+        graph.update(fresh.finalize(), &[]).unwrap();
+
+        graph.delete_host(&Node::from("B"));
+        graph.commit();
+
+        assert_eq!(graph.segments.len(), 2);
+
+        graph.compact(Compression::default(), None).unwrap();
+        graph.optimize_read();
+
+        assert_eq!(graph.segments.len(), 1);
+        assert_eq!(graph.meta.comitted_segments.len(), 1);
+        assert!(!graph.segments[0].has_deletions());
+
+        let mut edges: Vec<_> = graph
+            .edges()
+            .map(|e| {
+                (
+                    graph.id2node(&e.from).unwrap(),
+                    graph.id2node(&e.to).unwrap(),
+                )
+            })
+            .collect();
+        edges.sort_by(|a, b| (a.0.as_str(), a.1.as_str()).cmp(&(b.0.as_str(), b.1.as_str())));
+
+        assert_eq!(
+            edges,
+            vec![
+                (Node::from("A"), Node::from("C")),
+                (Node::from("C"), Node::from("A")),
+                (Node::from("D"), Node::from("E")),
+            ]
+        );
+
+        assert_eq!(
+            graph.outgoing_edges(Node::from("D"), EdgeLimit::Unlimited)[0].label,
+            "new"
+        );
+
+        assert!(graph.id2node(&Node::from("B").id()).is_none());
+        assert_eq!(graph.nodes().count(), 4);
+
+        let path = graph.path.clone();
+        drop(graph);
+
+        let graph = Webgraph::builder(path).single_threaded().open();
+        assert_eq!(graph.edges().count(), 3);
+        assert_eq!(outgoing(&graph, "A"), vec![Node::from("C")]);
+    }
+
+    #[test]
+    fn metadata_without_label_format() {
+        let path = crate::gen_temp_path();
+        fs::create_dir_all(&path).unwrap();
+
+        let meta_path = path.join("metadata.json");
+        fs::write(&meta_path, r#"{"comitted_segments": []}"#).unwrap();
+
+        assert_eq!(Meta::open(&meta_path).label_format, LabelFormat::Text);
+        assert_eq!(
+            Meta::open(path.join("new.json")).label_format,
+            LabelFormat::LinkLabel
+        );
+    }
+
+    #[test]
+    fn open_after_interrupted_compaction() {
+        let graph = test_graph();
+        let path = Path::new(&graph.path).to_path_buf();
+        drop(graph);
+
+        // the old id2node was moved aside, but the compacted one never took its place
+        fs::rename(path.join("id2node"), path.join(OLD_ID2NODE)).unwrap();
+
+        let graph = Webgraph::builder(&path).single_threaded().open();
+        assert_eq!(
+            outgoing(&graph, "A"),
+            vec![Node::from("B"), Node::from("C")]
+        );
+        assert!(!path.join(OLD_ID2NODE).exists());
+    }
+
+    fn label(text: &str, seen: u64) -> LinkLabel {
+        LinkLabel {
+            text: text.to_string(),
+            first_seen: seen,
+            last_seen: seen,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn update_keeps_first_seen() {
+        let mut wrt = writer();
+        wrt.insert(Node::from("A"), Node::from("B"), label("old", 10));
+        let mut graph = wrt.finalize();
+
+        let mut fresh = writer();
+        fresh.insert(Node::from("A"), Node::from("B"), label("new", 20));
+        fresh.insert(Node::from("A"), Node::from("C"), label("c", 20));
+        graph.update(fresh.finalize(), &[]).unwrap();
+
+        let check = |graph: &Webgraph| {
+            let edges =
+                graph.raw_outgoing_edges_with_labels(&Node::from("A").id(), EdgeLimit::Unlimited);
+            assert_eq!(edges.len(), 2);
+
+            let edge = edges.iter().find(|e| e.to == Node::from("B").id()).unwrap();
+
+            assert_eq!(edge.label.text, "new");
+            assert_eq!(edge.label.first_seen, 10);
+            assert_eq!(edge.label.last_seen, 20);
+        };
+
+        check(&graph);
+
+        graph.compact(Compression::default(), None).unwrap();
+        check(&graph);
+    }
+
+    #[test]
+    fn update_clears_recrawled_pages() {
+        let mut wrt = writer();
+        wrt.insert(
+            Node::from("A"),
+            Node::from("B"),
+            LinkLabel {
+                rel: LinkRel {
+                    nofollow: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        wrt.insert(Node::from("A"), Node::from("C"), String::new());
+        wrt.insert(Node::from("C"), Node::from("B"), String::new());
+        let mut graph = wrt.finalize();
+
+        graph.set_link_filter(LinkFilter {
+            ignore_nofollow: true,
+            ..Default::default()
+        });
+
+        // C was recrawled but no longer has any links
+        let mut fresh = writer();
+        fresh.insert(Node::from("A"), Node::from("C"), String::new());
+        graph
+            .update(fresh.finalize(), &[Node::from("C").id()])
+            .unwrap();
+
+        assert!(outgoing(&graph, "C").is_empty());
+
+        graph.set_link_filter(LinkFilter::default());
+
+        // the nofollow link was hidden by the filter, but it is still gone from the page
+        assert_eq!(outgoing(&graph, "A"), vec![Node::from("C")]);
+        assert!(outgoing(&graph, "C").is_empty());
+    }
+
+    #[test]
+    fn link_filter() {
+        let mut wrt = writer();
+        wrt.insert(
+            Node::from("A"),
+            Node::from("B"),
+            LinkLabel {
+                rel: LinkRel {
+                    nofollow: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        wrt.insert(Node::from("A"), Node::from("C"), String::new());
+        wrt.insert(
+            Node::from("C"),
+            Node::from("B"),
+            LinkLabel {
+                position: LinkPosition::Navigation,
+                ..Default::default()
+            },
+        );
+        let mut graph = wrt.finalize();
+
+        assert_eq!(graph.edges().count(), 3);
+
+        graph.set_link_filter(LinkFilter {
+            ignore_nofollow: true,
+            ..Default::default()
+        });
+
+        assert_eq!(outgoing(&graph, "A"), vec![Node::from("C")]);
+        assert_eq!(
+            graph
+                .raw_outgoing_edges(&Node::from("A").id(), EdgeLimit::Limit(1))
+                .len(),
+            1
+        );
+        assert_eq!(
+            graph
+                .raw_ingoing_edges(&Node::from("B").id(), EdgeLimit::Unlimited)
+                .len(),
+            1
+        );
+        assert_eq!(graph.edges().count(), 2);
+
+        graph.set_link_filter(LinkFilter {
+            ignore_nofollow: true,
+            ignore_navigation: true,
+            ..Default::default()
+        });
+
+        assert!(graph
+            .raw_ingoing_edges(&Node::from("B").id(), EdgeLimit::Unlimited)
+            .is_empty());
+        assert_eq!(graph.edges().count(), 1);
+    }
+
     #[test]
     fn node_lowercase_name() {
         let n = Node::from("TEST".to_string());
@@ -14,7 +14,10 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>
 
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 
 use itertools::Itertools;
 use tokio::sync::Mutex;
@@ -33,13 +36,16 @@ use crate::{
         },
     },
     entrypoint::webgraph_server::{
-        GetNode, IngoingEdges, OutgoingEdges, RawIngoingEdges, RawIngoingEdgesWithLabels,
-        RawOutgoingEdges, RawOutgoingEdgesWithLabels, WebGraphService,
+        ClusterReply, GetCluster, GetNode, IngoingEdges, OutgoingEdges, RawIngoingEdges,
+        RawIngoingEdgesWithLabels, RawOutgoingEdges, RawOutgoingEdgesWithLabels, WebGraphService,
     },
     Result,
 };
 
-use super::{Edge, EdgeLimit, FullEdge, Node, NodeID};
+use super::{
+    clusters::{Cluster as HostCluster, ClusterKind},
+    Edge, EdgeLimit, FullEdge, LinkLabel, Node, NodeID,
+};
 
 struct WebgraphClientManager {
     granularity: WebgraphGranularity,
@@ -91,6 +97,7 @@ impl sonic::replication::ReusableClientManager for WebgraphClientManager {
 
 pub struct RemoteWebgraph {
     client: Mutex<sonic::replication::ReusableShardedClient<WebgraphClientManager>>,
+    clusters_unavailable: AtomicBool,
 }
 
 impl RemoteWebgraph {
@@ -101,6 +108,7 @@ impl RemoteWebgraph {
             client: Mutex::new(
                 sonic::replication::ReusableShardedClient::new(cluster, manager).await,
             ),
+            clusters_unavailable: AtomicBool::new(false),
         }
     }
 
@@ -173,6 +181,46 @@ impl RemoteWebgraph {
         Ok(nodes)
     }
 
+    pub async fn cluster(&self, id: NodeID, kind: ClusterKind) -> Result<Option<HostCluster>> {
+        Ok(self.batch_cluster(&[id], kind).await?.pop().flatten())
+    }
+
+    /// The clusters of the nodes. Once the servers have replied that they have no clusters,
+    /// they are not asked again and no node is in a cluster.
+    pub async fn batch_cluster(
+        &self,
+        ids: &[NodeID],
+        kind: ClusterKind,
+    ) -> Result<Vec<Option<HostCluster>>> {
+        if ids.is_empty() || self.clusters_unavailable.load(Ordering::Relaxed) {
+            return Ok(vec![None; ids.len()]);
+        }
+
+        let reqs = ids
+            .iter()
+            .map(|&id| GetCluster { node: id, kind })
+            .collect_vec();
+
+        let res = self
+            .conn()
+            .await
+            .batch_send(&reqs, &AllShardsSelector, &RandomReplicaSelector)
+            .await?;
+
+        let replies = res
+            .into_iter()
+            .flat_map(|(_, rep)| rep.into_iter().map(|(_, replies)| replies));
+
+        match merge_cluster_replies(replies, ids.len()) {
+            Some(clusters) => Ok(clusters),
+            None => {
+                tracing::info!("webgraph has no clusters");
+                self.clusters_unavailable.store(true, Ordering::Relaxed);
+                Ok(vec![None; ids.len()])
+            }
+        }
+    }
+
     pub async fn ingoing_edges(&self, node: Node, limit: EdgeLimit) -> Result<Vec<FullEdge>> {
         let res = self
             .conn()
@@ -217,7 +265,7 @@ impl RemoteWebgraph {
         &self,
         id: NodeID,
         limit: EdgeLimit,
-    ) -> Result<Vec<Edge<String>>> {
+    ) -> Result<Vec<Edge<LinkLabel>>> {
         let res = self
             .conn()
             .await
@@ -241,7 +289,7 @@ impl RemoteWebgraph {
         &self,
         ids: &[NodeID],
         limit: EdgeLimit,
-    ) -> Result<Vec<Vec<Edge<String>>>> {
+    ) -> Result<Vec<Vec<Edge<LinkLabel>>>> {
         let reqs: Vec<_> = ids
             .iter()
             .map(|id| RawIngoingEdgesWithLabels { node: *id, limit })
@@ -343,7 +391,7 @@ impl RemoteWebgraph {
         &self,
         id: NodeID,
         limit: EdgeLimit,
-    ) -> Result<Vec<Edge<String>>> {
+    ) -> Result<Vec<Edge<LinkLabel>>> {
         let res = self
             .conn()
             .await
@@ -394,3 +442,74 @@ impl RemoteWebgraph {
         Ok(edges)
     }
 }
+
+/// Merge the replies of the shards to a batch of [`GetCluster`] requests.
+/// Returns `None` if the shards replied and none of them have clusters.
+fn merge_cluster_replies(
+    replies: impl Iterator<Item = Vec<ClusterReply>>,
+    num_ids: usize,
+) -> Option<Vec<Option<HostCluster>>> {
+    let mut clusters = vec![None; num_ids];
+    let mut replied = false;
+    let mut available = false;
+
+    for shard_replies in replies {
+        replied = true;
+
+        for (i, reply) in shard_replies.into_iter().enumerate() {
+            if let ClusterReply::Cluster(cluster) = reply {
+                available = true;
+
+                if let Some(cluster) = cluster {
+                    clusters[i] = Some(cluster);
+                }
+            }
+        }
+    }
+
+    if replied && !available {
+        None
+    } else {
+        Some(clusters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_replies() {
+        let cluster = HostCluster {
+            id: NodeID::from(1u64),
+            size: 2,
+            members: vec![NodeID::from(1u64), NodeID::from(2u64)],
+        };
+
+        let merged = merge_cluster_replies(
+            vec![
+                vec![ClusterReply::Cluster(None), ClusterReply::Cluster(None)],
+                vec![
+                    ClusterReply::Cluster(None),
+                    ClusterReply::Cluster(Some(cluster.clone())),
+                ],
+            ]
+            .into_iter(),
+            2,
+        );
+        assert_eq!(merged, Some(vec![None, Some(cluster)]));
+
+        // no server has clusters
+        let merged = merge_cluster_replies(
+            vec![vec![ClusterReply::Unavailable, ClusterReply::Unavailable]].into_iter(),
+            2,
+        );
+        assert_eq!(merged, None);
+
+        // no server replied, so we can't tell
+        assert_eq!(
+            merge_cluster_replies(std::iter::empty(), 2),
+            Some(vec![None, None])
+        );
+    }
+}
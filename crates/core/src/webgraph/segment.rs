@@ -19,13 +19,18 @@ use std::{
     sync::Arc,
 };
 
+use itertools::Either;
+
 use super::{
-    store::EdgeStore, store_writer::EdgeStoreWriter, Compression, Edge, EdgeLimit, InnerEdge,
-    NodeID,
+    store::{EdgeStore, LabelFormat},
+    store_writer::EdgeStoreWriter,
+    tombstones::Tombstones,
+    Compression, Edge, EdgeLabel, EdgeLimit, InnerEdge, LinkFilter, LinkLabel, NodeID,
 };
 
 const ADJACENCY_STORE: &str = "adjacency";
 const REVERSED_ADJACENCY_STORE: &str = "reversed_adjacency";
+const TOMBSTONES: &str = "tombstones";
 
 pub struct SegmentWriter {
     adjacency: EdgeStoreWriter,
@@ -71,12 +76,16 @@ impl SegmentWriter {
         Segment {
             adjacency: self.adjacency.finalize(),
             reversed_adjacency: self.reversed_adjacency.finalize(),
+            tombstones: Tombstones::open(
+                Path::new(&self.folder_path).join(&self.id).join(TOMBSTONES),
+            ),
+            link_filter: LinkFilter::default(),
             folder_path: self.folder_path,
             id: self.id,
         }
     }
 
-    pub fn insert(&mut self, edge: InnerEdge<String>) {
+    pub fn insert(&mut self, edge: InnerEdge<LinkLabel>) {
         self.adjacency.put(edge.clone());
         self.reversed_adjacency.put(edge);
     }
@@ -85,21 +94,30 @@ impl SegmentWriter {
 pub struct Segment {
     adjacency: EdgeStore,
     reversed_adjacency: EdgeStore,
+    tombstones: Tombstones,
+    link_filter: LinkFilter,
     id: String,
     folder_path: String,
 }
 
 impl Segment {
-    pub fn open<P: AsRef<Path>>(folder_path: P, id: String) -> Self {
+    pub fn open<P: AsRef<Path>>(folder_path: P, id: String, label_format: LabelFormat) -> Self {
         Segment {
-            adjacency: EdgeStore::open(folder_path.as_ref().join(&id).join(ADJACENCY_STORE), false),
+            adjacency: EdgeStore::open(
+                folder_path.as_ref().join(&id).join(ADJACENCY_STORE),
+                false,
+                label_format,
+            ),
             reversed_adjacency: EdgeStore::open(
                 folder_path
                     .as_ref()
                     .join(&id)
                     .join(REVERSED_ADJACENCY_STORE),
                 true,
+                label_format,
             ),
+            tombstones: Tombstones::open(folder_path.as_ref().join(&id).join(TOMBSTONES)),
+            link_filter: LinkFilter::default(),
             folder_path: folder_path
                 .as_ref()
                 .as_os_str()
@@ -110,24 +128,194 @@ impl Segment {
         }
     }
 
-    pub fn outgoing_edges_with_label(&self, node: &NodeID, limit: &EdgeLimit) -> Vec<Edge<String>> {
-        self.adjacency.get_with_label(node, limit)
+    /// Only return the edges that are kept by `filter` when the segment is read.
+    pub fn set_link_filter(&mut self, filter: LinkFilter) {
+        self.link_filter = filter;
+    }
+
+    /// Read the edges with `get` and remove the deleted edges and the edges that `keep` rejects.
+    /// The limit is applied after the edges have been removed, so the edges are read in
+    /// batches that double in size until there are enough edges left. This way a limited
+    /// read doesn't decode all the edges of a node just because the segment has deletions.
+    ///
+    /// All edges returned by `get` must go to or from `node`, so the tombstone of `node`
+    /// is only checked once.
+    fn live_edges<L, G, F>(&self, node: &NodeID, get: G, limit: &EdgeLimit, keep: F) -> Vec<Edge<L>>
+    where
+        L: EdgeLabel,
+        G: Fn(&EdgeLimit) -> Vec<Edge<L>>,
+        F: Fn(&Edge<L>) -> bool,
+    {
+        if self.tombstones.is_node_deleted(node) {
+            return Vec::new();
+        }
+
+        let is_live = |edge: &Edge<L>| {
+            let neighbour = if edge.from == *node {
+                &edge.to
+            } else {
+                &edge.from
+            };
+
+            !self.tombstones.is_node_deleted(neighbour)
+                && !self.tombstones.is_edge_deleted(&edge.from, &edge.to)
+                && keep(edge)
+        };
+
+        let limit = match limit {
+            EdgeLimit::Unlimited => {
+                return get(&EdgeLimit::Unlimited)
+                    .into_iter()
+                    .filter(|edge| is_live(edge))
+                    .collect()
+            }
+            EdgeLimit::Limit(limit) => *limit,
+        };
+
+        let mut batch = limit.max(1);
+
+        loop {
+            let edges = get(&EdgeLimit::Limit(batch));
+            let exhausted = edges.len() < batch;
+
+            let live: Vec<_> = edges
+                .into_iter()
+                .filter(|edge| is_live(edge))
+                .take(limit)
+                .collect();
+
+            if exhausted || live.len() == limit {
+                return live;
+            }
+
+            batch = batch.saturating_mul(2);
+        }
+    }
+
+    fn edges_with_label_from(
+        &self,
+        store: &EdgeStore,
+        node: &NodeID,
+        limit: &EdgeLimit,
+    ) -> Vec<Edge<LinkLabel>> {
+        if self.tombstones.is_empty() && self.link_filter.is_empty() {
+            return store.get_with_label(node, limit);
+        }
+
+        self.live_edges(
+            node,
+            |limit| store.get_with_label(node, limit),
+            limit,
+            |edge| self.link_filter.keep(&edge.label),
+        )
+    }
+
+    fn edges_without_label_from(
+        &self,
+        store: &EdgeStore,
+        node: &NodeID,
+        limit: &EdgeLimit,
+    ) -> Vec<Edge<()>> {
+        if !self.link_filter.is_empty() {
+            // the filter needs the labels
+            return self
+                .edges_with_label_from(store, node, limit)
+                .into_iter()
+                .map(|edge| Edge {
+                    from: edge.from,
+                    to: edge.to,
+                    label: (),
+                })
+                .collect();
+        }
+
+        self.unfiltered_edges_from(store, node, limit)
+    }
+
+    fn unfiltered_edges_from(
+        &self,
+        store: &EdgeStore,
+        node: &NodeID,
+        limit: &EdgeLimit,
+    ) -> Vec<Edge<()>> {
+        if self.tombstones.is_empty() {
+            return store.get_without_label(node, limit);
+        }
+
+        self.live_edges(
+            node,
+            |limit| store.get_without_label(node, limit),
+            limit,
+            |_| true,
+        )
+    }
+
+    pub fn outgoing_edges_with_label(
+        &self,
+        node: &NodeID,
+        limit: &EdgeLimit,
+    ) -> Vec<Edge<LinkLabel>> {
+        self.edges_with_label_from(&self.adjacency, node, limit)
     }
 
     pub fn outgoing_edges(&self, node: &NodeID, limit: &EdgeLimit) -> Vec<Edge<()>> {
-        self.adjacency.get_without_label(node, limit)
+        self.edges_without_label_from(&self.adjacency, node, limit)
     }
 
-    pub fn ingoing_edges_with_label(&self, node: &NodeID, limit: &EdgeLimit) -> Vec<Edge<String>> {
-        self.reversed_adjacency.get_with_label(node, limit)
+    pub fn ingoing_edges_with_label(
+        &self,
+        node: &NodeID,
+        limit: &EdgeLimit,
+    ) -> Vec<Edge<LinkLabel>> {
+        self.edges_with_label_from(&self.reversed_adjacency, node, limit)
     }
 
     pub fn ingoing_edges(&self, node: &NodeID, limit: &EdgeLimit) -> Vec<Edge<()>> {
-        self.reversed_adjacency.get_without_label(node, limit)
+        self.edges_without_label_from(&self.reversed_adjacency, node, limit)
+    }
+
+    /// The outgoing edges of `node` that have not been deleted, including the ones
+    /// that are removed by the link filter.
+    pub fn unfiltered_outgoing_edges(&self, node: &NodeID, limit: &EdgeLimit) -> Vec<Edge<()>> {
+        self.unfiltered_edges_from(&self.adjacency, node, limit)
+    }
+
+    /// The ingoing edges of `node` that have not been deleted, including the ones
+    /// that are removed by the link filter.
+    pub fn unfiltered_ingoing_edges(&self, node: &NodeID, limit: &EdgeLimit) -> Vec<Edge<()>> {
+        self.unfiltered_edges_from(&self.reversed_adjacency, node, limit)
     }
 
     pub fn pages_by_host(&self, host_node: &NodeID) -> Vec<NodeID> {
-        self.reversed_adjacency.nodes_by_host(host_node)
+        // each store only indexes the hosts of the nodes it has edges for, so pages
+        // without ingoing links are only in the host index of the forward adjacency
+        let mut pages = self.adjacency.nodes_by_host(host_node);
+        pages.extend(self.reversed_adjacency.nodes_by_host(host_node));
+        pages.sort();
+        pages.dedup();
+        pages.retain(|page| !self.tombstones.is_node_deleted(page));
+
+        pages
+    }
+
+    /// Delete the edge from `from` to `to` from the segment.
+    /// The deletion is visible after the segment has been committed.
+    pub fn delete_edge(&mut self, from: &NodeID, to: &NodeID) {
+        self.tombstones.delete_edge(from, to);
+    }
+
+    /// Delete all edges going to or from `node` from the segment.
+    /// The deletion is visible after the segment has been committed.
+    pub fn delete_node(&mut self, node: &NodeID) {
+        self.tombstones.delete_node(node);
+    }
+
+    pub fn has_deletions(&self) -> bool {
+        !self.tombstones.is_empty()
+    }
+
+    pub fn commit(&mut self) {
+        self.tombstones.commit();
     }
 
     pub fn id(&self) -> String {
@@ -139,12 +327,40 @@ impl Segment {
     }
 
     pub fn edges(&self) -> impl Iterator<Item = Edge<()>> + '_ + Send + Sync {
-        self.adjacency.iter_without_label()
+        if self.link_filter.is_empty() {
+            Either::Left(
+                self.adjacency
+                    .iter_without_label()
+                    .filter(move |edge| !self.tombstones.is_deleted(edge)),
+            )
+        } else {
+            Either::Right(
+                self.adjacency
+                    .iter_with_label()
+                    .filter(move |edge| {
+                        !self.tombstones.is_deleted(edge) && self.link_filter.keep(&edge.label)
+                    })
+                    .map(|edge| Edge {
+                        from: edge.from,
+                        to: edge.to,
+                        label: (),
+                    }),
+            )
+        }
+    }
+
+    /// All edges in the segment that have not been deleted, including the ones
+    /// that are removed by the link filter.
+    pub fn edges_with_label(&self) -> impl Iterator<Item = Edge<LinkLabel>> + '_ {
+        self.adjacency
+            .iter_with_label()
+            .filter(move |edge| !self.tombstones.is_deleted(edge))
     }
 
     pub fn optimize_read(&mut self) {
         self.adjacency.optimize_read();
         self.reversed_adjacency.optimize_read();
+        self.tombstones.optimize_read();
     }
 }
 
@@ -189,22 +405,22 @@ mod test {
         edges.push(InnerEdge {
             from: a.clone(),
             to: b.clone(),
-            label: String::new(),
+            label: LinkLabel::default(),
         });
         edges.push(InnerEdge {
             from: b.clone(),
             to: c.clone(),
-            label: String::new(),
+            label: LinkLabel::default(),
         });
         edges.push(InnerEdge {
             from: c.clone(),
             to: a.clone(),
-            label: String::new(),
+            label: LinkLabel::default(),
         });
         edges.push(InnerEdge {
             from: a.clone(),
             to: c.clone(),
-            label: String::new(),
+            label: LinkLabel::default(),
         });
 
         for edge in &edges {
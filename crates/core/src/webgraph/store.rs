@@ -23,7 +23,7 @@ use file_store::{
 use fst::Automaton;
 use itertools::Itertools;
 
-use super::{Compression, Edge, EdgeLimit, FullNodeID, NodeID};
+use super::{Compression, Edge, EdgeLimit, FullNodeID, LinkLabel, NodeID};
 
 #[derive(
     Debug,
@@ -150,13 +150,40 @@ impl ConstSerializable for NodeID {
 
 pub const NUM_LABELS_PER_BLOCK: usize = 128;
 
+/// How the edge labels are encoded in the label blocks.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    bincode::Encode,
+    bincode::Decode,
+)]
+pub enum LabelFormat {
+    /// The anchor text of the link. Graphs written before links had more than a text
+    /// are stored in this format.
+    #[default]
+    Text,
+    /// A [`LinkLabel`].
+    LinkLabel,
+}
+
 #[derive(bincode::Encode, bincode::Decode)]
 pub struct LabelBlock {
+    labels: Vec<LinkLabel>,
+}
+
+#[derive(bincode::Encode, bincode::Decode)]
+struct TextLabelBlock {
     labels: Vec<String>,
 }
 
 impl LabelBlock {
-    pub fn new(labels: Vec<String>) -> Self {
+    pub fn new(labels: Vec<LinkLabel>) -> Self {
         Self { labels }
     }
 
@@ -178,15 +205,28 @@ pub struct CompressedLabelBlock {
 }
 
 impl CompressedLabelBlock {
-    pub fn decompress(&self) -> LabelBlock {
+    pub fn decompress(&self, format: LabelFormat) -> LabelBlock {
         let bytes = self.compressions.decompress(&self.data);
-        let (res, _) = bincode::decode_from_slice(&bytes, bincode::config::standard()).unwrap();
-        res
+
+        match format {
+            LabelFormat::LinkLabel => {
+                let (res, _) =
+                    bincode::decode_from_slice(&bytes, bincode::config::standard()).unwrap();
+                res
+            }
+            LabelFormat::Text => {
+                let (res, _): (TextLabelBlock, _) =
+                    bincode::decode_from_slice(&bytes, bincode::config::standard()).unwrap();
+
+                LabelBlock::new(res.labels.into_iter().map(LinkLabel::from).collect())
+            }
+        }
     }
 }
 
 pub struct EdgeStore {
     reversed: bool,
+    label_format: LabelFormat,
     ranges: RangesDb,
     hosts: HostDb,
 
@@ -195,7 +235,7 @@ pub struct EdgeStore {
 }
 
 impl EdgeStore {
-    pub fn open<P: AsRef<Path>>(path: P, reversed: bool) -> Self {
+    pub fn open<P: AsRef<Path>>(path: P, reversed: bool, label_format: LabelFormat) -> Self {
         let ranges = RangesDb::open(path.as_ref().join("ranges"));
 
         let edge_labels = IterableStoreReader::open(path.as_ref().join("labels")).unwrap();
@@ -208,6 +248,7 @@ impl EdgeStore {
             edge_labels,
             edge_nodes,
             reversed,
+            label_format,
         }
     }
 
@@ -216,7 +257,7 @@ impl EdgeStore {
         self.hosts.optimize_read();
     }
 
-    pub fn get_with_label(&self, node: &NodeID, limit: &EdgeLimit) -> Vec<Edge<String>> {
+    pub fn get_with_label(&self, node: &NodeID, limit: &EdgeLimit) -> Vec<Edge<LinkLabel>> {
         let node_bytes = node.as_u64().to_le_bytes();
 
         match (
@@ -238,7 +279,7 @@ impl EdgeStore {
                 let edge_labels = self
                     .edge_labels
                     .slice(edge_range)
-                    .map(|r| r.unwrap().decompress())
+                    .map(|r| r.unwrap().decompress(self.label_format))
                     .flat_map(|block| block.labels.into_iter());
 
                 let edge_labels: Vec<_> = match limit {
@@ -322,6 +363,15 @@ impl EdgeStore {
         self.hosts.get(host)
     }
 
+    pub fn iter_with_label(&self) -> impl Iterator<Item = Edge<LinkLabel>> + '_ + Send + Sync {
+        self.ranges.nodes.iter_raw().flat_map(move |(key, _)| {
+            let node = u64::from_le_bytes((key.as_bytes()).try_into().unwrap());
+            let node = NodeID::from(node);
+
+            self.get_with_label(&node, &EdgeLimit::Unlimited)
+        })
+    }
+
     pub fn iter_without_label(&self) -> impl Iterator<Item = Edge<()>> + '_ + Send + Sync {
         self.ranges.nodes.iter_raw().flat_map(move |(key, val)| {
             let node = u64::from_le_bytes((key.as_bytes()).try_into().unwrap());
@@ -362,6 +412,37 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn text_labels() {
+        let compression = Compression::default();
+        let bytes = bincode::encode_to_vec(
+            TextLabelBlock {
+                labels: vec!["a".to_string(), "b".to_string()],
+            },
+            bincode::config::standard(),
+        )
+        .unwrap();
+
+        let block = CompressedLabelBlock {
+            compressions: compression,
+            data: compression.compress(&bytes),
+        };
+
+        assert_eq!(
+            block.decompress(LabelFormat::Text).labels,
+            vec![
+                LinkLabel::from("a".to_string()),
+                LinkLabel::from("b".to_string())
+            ]
+        );
+
+        let block = LabelBlock::new(vec![LinkLabel::from("c".to_string())]).compress(compression);
+        assert_eq!(
+            block.decompress(LabelFormat::LinkLabel).labels,
+            vec![LinkLabel::from("c".to_string())]
+        );
+    }
+
     #[test]
     fn test_insert() {
         let mut kv: EdgeStoreWriter = EdgeStoreWriter::new(
@@ -380,7 +461,7 @@ mod tests {
                 id: NodeID::from(1_u64),
                 host: NodeID::from(0_u64),
             },
-            label: "test".to_string(),
+            label: LinkLabel::from("test".to_string()),
         };
 
         kv.put(e.clone());
@@ -415,7 +496,7 @@ mod tests {
                 id: NodeID::from(1_u64),
                 host: NodeID::from(0_u64),
             },
-            label: "test".to_string(),
+            label: LinkLabel::from("test".to_string()),
         };
 
         kv.put(e.clone());
@@ -449,7 +530,7 @@ mod tests {
                     id: NodeID::from(1_u64),
                     host: NodeID::from(0_u64),
                 },
-                label: "test".to_string(),
+                label: LinkLabel::from("test".to_string()),
             };
 
             kv.put(e.clone());
@@ -488,7 +569,7 @@ mod tests {
                 id: NodeID::from(0_u64),
                 host: NodeID::from(0_u64),
             },
-            label: "1".to_string(),
+            label: LinkLabel::from("1".to_string()),
         };
 
         let e2 = InnerEdge {
@@ -500,7 +581,7 @@ mod tests {
                 id: NodeID::from(0_u64),
                 host: NodeID::from(0_u64),
             },
-            label: "2".to_string(),
+            label: LinkLabel::from("2".to_string()),
         };
 
         let e3 = InnerEdge {
@@ -512,7 +593,7 @@ mod tests {
                 id: NodeID::from(0_u64),
                 host: NodeID::from(0_u64),
             },
-            label: "3".to_string(),
+            label: LinkLabel::from("3".to_string()),
         };
 
         kv.put(e1.clone());
@@ -32,8 +32,8 @@ use file_store::iterable::{
 };
 
 use super::{
-    store::{CompressedLabelBlock, EdgeStore, HostDb, LabelBlock, RangesDb},
-    Compression, EdgeLabel, InnerEdge, NodeID,
+    store::{CompressedLabelBlock, EdgeStore, HostDb, LabelBlock, LabelFormat, RangesDb},
+    Compression, EdgeLabel, InnerEdge, LinkLabel, NodeID,
 };
 
 #[derive(bincode::Encode, bincode::Decode)]
@@ -67,8 +67,8 @@ impl<L: EdgeLabel> Eq for SortableEdge<L> {}
 
 struct SortedEdgeIterator<M, D>
 where
-    M: Iterator<Item = SortableEdge<String>>,
-    D: Iterator<Item = SortableEdge<String>>,
+    M: Iterator<Item = SortableEdge<LinkLabel>>,
+    D: Iterator<Item = SortableEdge<LinkLabel>>,
 {
     mem: file_store::Peekable<M>,
     file_reader: file_store::Peekable<D>,
@@ -76,10 +76,10 @@ where
 
 impl<M, D> Iterator for SortedEdgeIterator<M, D>
 where
-    M: Iterator<Item = SortableEdge<String>>,
-    D: Iterator<Item = SortableEdge<String>>,
+    M: Iterator<Item = SortableEdge<LinkLabel>>,
+    D: Iterator<Item = SortableEdge<LinkLabel>>,
 {
-    type Item = SortableEdge<String>;
+    type Item = SortableEdge<LinkLabel>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if let Some(edge) = self.mem.peek() {
@@ -101,7 +101,7 @@ where
 pub struct EdgeStoreWriter {
     reversed: bool,
     path: PathBuf,
-    edges: BTreeSet<SortableEdge<String>>,
+    edges: BTreeSet<SortableEdge<LinkLabel>>,
     stored_writers: Vec<PathBuf>,
     compression: Compression,
     host_centrality_rank_store: Option<Arc<speedy_kv::Db<NodeID, u64>>>,
@@ -151,25 +151,34 @@ impl EdgeStoreWriter {
         Ok(())
     }
 
-    pub fn put(&mut self, edge: InnerEdge<String>) {
+    pub fn put(&mut self, edge: InnerEdge<LinkLabel>) {
         let (sort_node, secondary_node) = if self.reversed {
             (edge.to.id, edge.from.id)
         } else {
             (edge.from.id, edge.to.id)
         };
 
-        self.edges.insert(SortableEdge {
+        let mut edge = SortableEdge {
             sort_node,
             secondary_node,
             edge,
-        });
+        };
+
+        // the same link can be seen in several crawls
+        if let Some(existing) = self.edges.take(&edge) {
+            let mut label = existing.edge.label;
+            label.merge(edge.edge.label);
+            edge.edge.label = label;
+        }
+
+        self.edges.insert(edge);
 
         if self.edges.len() >= MAX_BATCH_SIZE {
             self.flush_to_file().unwrap();
         }
     }
 
-    fn sorted_edges(mut self) -> impl Iterator<Item = SortableEdge<String>> {
+    fn sorted_edges(mut self) -> impl Iterator<Item = SortableEdge<LinkLabel>> {
         let readers = self
             .stored_writers
             .iter()
@@ -193,7 +202,16 @@ impl EdgeStoreWriter {
             &self.path,
         );
 
-        let mut store = final_writer.build_store(self.sorted_edges().dedup().map(|e| e.edge));
+        let edges = self.sorted_edges().coalesce(|mut a, b| {
+            if a == b {
+                a.edge.label.merge(b.edge.label);
+                Ok(a)
+            } else {
+                Err((a, b))
+            }
+        });
+
+        let mut store = final_writer.build_store(edges.map(|e| e.edge));
         store.optimize_read();
 
         store
@@ -263,7 +281,7 @@ impl FinalEdgeStoreWriter {
     /// The edges *must* have been de-duplicated by their from/to node.
     /// I.e. if the store is not reversed, there should only ever be a single
     /// put for each from node, and vice versa.
-    fn put_store(&mut self, edges: &mut [InnerEdge<String>]) {
+    fn put_store(&mut self, edges: &mut [InnerEdge<LinkLabel>]) {
         if edges.is_empty() {
             return;
         }
@@ -356,11 +374,27 @@ impl FinalEdgeStoreWriter {
         );
     }
 
+    /// Remove duplicate edges from a batch of edges with the same from/to node.
+    /// The labels of the duplicates are merged.
+    fn dedup_batch(&self, batch: &mut Vec<InnerEdge<LinkLabel>>) {
+        let other = |e: &InnerEdge<LinkLabel>| if self.reversed { e.from.id } else { e.to.id };
+
+        batch.sort_unstable_by_key(other);
+        batch.dedup_by(|a, b| {
+            if other(a) == other(b) {
+                b.label.merge(std::mem::take(&mut a.label));
+                true
+            } else {
+                false
+            }
+        });
+    }
+
     /// Build a new edge store from a set of edges.
     ///
     /// **IMPORTANT** The edges must be sorted by
     /// either the from or to node, depending on the value of `reversed`.
-    pub fn build_store(&mut self, edges: impl Iterator<Item = InnerEdge<String>>) -> EdgeStore {
+    pub fn build_store(&mut self, edges: impl Iterator<Item = InnerEdge<LinkLabel>>) -> EdgeStore {
         let mut inserts_since_last_flush = 0;
 
         // create batches of consecutive edges with the same from/to node
@@ -371,10 +405,7 @@ impl FinalEdgeStoreWriter {
                 if (self.reversed && edge.to.id != last_node)
                     || (!self.reversed && edge.from.id != last_node)
                 {
-                    batch.sort_unstable_by_key(
-                        |e: &InnerEdge<_>| if self.reversed { e.from.id } else { e.to.id },
-                    );
-                    batch.dedup_by_key(|e| if self.reversed { e.from.id } else { e.to.id });
+                    self.dedup_batch(&mut batch);
                     let batch_len = batch.len();
                     self.put_store(&mut batch);
                     batch.clear();
@@ -396,16 +427,13 @@ impl FinalEdgeStoreWriter {
         }
 
         if !batch.is_empty() {
-            batch.sort_unstable_by_key(
-                |e: &InnerEdge<_>| if self.reversed { e.from.id } else { e.to.id },
-            );
-            batch.dedup_by_key(|e| if self.reversed { e.from.id } else { e.to.id });
+            self.dedup_batch(&mut batch);
             self.put_store(&mut batch);
         }
 
         self.flush();
 
-        EdgeStore::open(&self.path, self.reversed)
+        EdgeStore::open(&self.path, self.reversed, LabelFormat::LinkLabel)
     }
 
     fn flush(&mut self) {
@@ -0,0 +1,130 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+use std::path::Path;
+
+use super::{Edge, EdgeLabel, NodeID};
+
+/// Deletions of edges and nodes in a segment.
+///
+/// Segments are immutable, so edges are deleted by writing a tombstone next to the segment.
+/// The tombstones only apply to the segment they belong to, which means that edges
+/// from segments merged into the graph after the deletion are not affected.
+/// Deleted edges are physically removed when the graph is compacted.
+pub struct Tombstones {
+    edges: speedy_kv::Db<Vec<u8>, Vec<u8>>,
+    nodes: speedy_kv::Db<Vec<u8>, Vec<u8>>,
+}
+
+impl Tombstones {
+    pub fn open<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            edges: speedy_kv::Db::open_or_create(path.as_ref().join("edges")).unwrap(),
+            nodes: speedy_kv::Db::open_or_create(path.as_ref().join("nodes")).unwrap(),
+        }
+    }
+
+    fn edge_key(from: &NodeID, to: &NodeID) -> Vec<u8> {
+        [from.as_u64().to_le_bytes(), to.as_u64().to_le_bytes()].concat()
+    }
+
+    /// Delete the edge from `from` to `to`.
+    pub fn delete_edge(&mut self, from: &NodeID, to: &NodeID) {
+        self.edges.insert_raw(Self::edge_key(from, to), vec![]);
+    }
+
+    /// Delete all edges going to or from `node`.
+    pub fn delete_node(&mut self, node: &NodeID) {
+        self.nodes
+            .insert_raw(node.as_u64().to_le_bytes().to_vec(), vec![]);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty() && self.nodes.is_empty()
+    }
+
+    pub fn is_node_deleted(&self, node: &NodeID) -> bool {
+        !self.nodes.is_empty()
+            && self
+                .nodes
+                .get_raw(&node.as_u64().to_le_bytes()[..])
+                .is_some()
+    }
+
+    /// Whether the edge itself has been deleted. Deletions of its nodes are not considered.
+    pub fn is_edge_deleted(&self, from: &NodeID, to: &NodeID) -> bool {
+        !self.edges.is_empty()
+            && self
+                .edges
+                .get_raw(Self::edge_key(from, to).as_slice())
+                .is_some()
+    }
+
+    pub fn is_deleted<L: EdgeLabel>(&self, edge: &Edge<L>) -> bool {
+        self.is_node_deleted(&edge.from)
+            || self.is_node_deleted(&edge.to)
+            || self.is_edge_deleted(&edge.from, &edge.to)
+    }
+
+    /// Make the deletions since the last commit visible.
+    pub fn commit(&mut self) {
+        self.edges.commit().unwrap();
+        self.nodes.commit().unwrap();
+    }
+
+    pub fn optimize_read(&mut self) {
+        self.edges.merge_all_segments().unwrap();
+        self.nodes.merge_all_segments().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deletions_are_visible_after_commit() {
+        let mut tombstones = Tombstones::open(crate::gen_temp_path());
+        assert!(tombstones.is_empty());
+
+        let a = NodeID::from(0_u64);
+        let b = NodeID::from(1_u64);
+        let c = NodeID::from(2_u64);
+
+        let edge = |from, to| Edge {
+            from,
+            to,
+            label: (),
+        };
+
+        tombstones.delete_edge(&a, &b);
+        tombstones.delete_node(&c);
+
+        assert!(!tombstones.is_deleted(&edge(a, b)));
+
+        tombstones.commit();
+
+        assert!(!tombstones.is_empty());
+        assert!(tombstones.is_deleted(&edge(a, b)));
+        assert!(!tombstones.is_deleted(&edge(b, a)));
+        assert!(tombstones.is_edge_deleted(&a, &b));
+        assert!(!tombstones.is_edge_deleted(&a, &c));
+        assert!(tombstones.is_deleted(&edge(a, c)));
+        assert!(tombstones.is_deleted(&edge(c, b)));
+        assert!(tombstones.is_node_deleted(&c));
+        assert!(!tombstones.is_node_deleted(&a));
+    }
+}
@@ -19,8 +19,8 @@ use std::{fs, path::Path, sync::Arc};
 use crate::executor::Executor;
 
 use super::{
-    id_node_db::Id2NodeDb, segment::SegmentWriter, Compression, FullNodeID, InnerEdge, Meta, Node,
-    NodeID, Webgraph, MAX_LABEL_LENGTH,
+    id_node_db::Id2NodeDb, segment::SegmentWriter, store::LabelFormat, Compression, FullNodeID,
+    InnerEdge, LinkFilter, LinkLabel, Meta, Node, NodeID, Webgraph, MAX_LABEL_LENGTH,
 };
 
 pub struct WebgraphWriter {
@@ -51,6 +51,7 @@ impl WebgraphWriter {
         fs::create_dir_all(&path).unwrap();
         let mut meta = Self::meta(&path);
         meta.comitted_segments.clear();
+        meta.label_format = LabelFormat::LinkLabel;
 
         fs::create_dir_all(path.as_ref().join("segments")).unwrap();
 
@@ -85,11 +86,14 @@ impl WebgraphWriter {
         id
     }
 
-    pub fn insert(&mut self, from: Node, to: Node, label: String) {
+    pub fn insert(&mut self, from: Node, to: Node, label: impl Into<LinkLabel>) {
         if from == to {
             return;
         }
 
+        let mut label = label.into();
+        label.text = label.text.chars().take(MAX_LABEL_LENGTH).collect();
+
         let (from_id, to_id) = (
             self.id_or_assign(from.clone()),
             self.id_or_assign(to.clone()),
@@ -98,7 +102,7 @@ impl WebgraphWriter {
         let edge = InnerEdge {
             from: from_id,
             to: to_id,
-            label: label.chars().take(MAX_LABEL_LENGTH).collect(),
+            label,
         };
 
         self.segment.insert(edge);
@@ -118,6 +122,7 @@ impl WebgraphWriter {
             executor: self.executor.into(),
             id2node: self.id2node,
             meta: self.meta,
+            link_filter: LinkFilter::default(),
         }
     }
 }
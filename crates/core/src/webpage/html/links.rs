@@ -14,10 +14,10 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use kuchiki::iter::NodeEdge;
+use kuchiki::{iter::NodeEdge, Attributes, ElementData};
 use url::Url;
 
-use crate::webpage::{url_ext::UrlExt, Link};
+use crate::webpage::{url_ext::UrlExt, Link, LinkPosition, LinkRel};
 
 use super::Html;
 
@@ -124,36 +124,37 @@ impl Html {
 
         let mut links = Vec::new();
         let mut open_links = Vec::new();
+        let mut navigation_depth = 0;
 
         for edge in self.root.traverse() {
             match edge {
                 NodeEdge::Start(node) => {
                     if let Some(element) = node.as_element() {
                         if &element.name.local == "a" {
-                            open_links.push((String::new(), element.attributes.clone()));
+                            let position = if navigation_depth > 0 {
+                                LinkPosition::Navigation
+                            } else {
+                                LinkPosition::Content
+                            };
+
+                            open_links.push((String::new(), element.attributes.clone(), position));
+                        } else if is_navigation(element) {
+                            navigation_depth += 1;
                         }
                     }
                 }
                 NodeEdge::End(node) => {
                     if let Some(element) = node.as_element() {
                         if &element.name.local == "a" {
-                            if let Some((text, attributes)) = open_links.pop() {
-                                if let Some(dest) = attributes.borrow().get("href") {
-                                    if dest.starts_with("mailto:") || dest.starts_with("tel:") {
-                                        continue;
-                                    }
-
-                                    if let Ok(dest) =
-                                        Url::parse(dest).or_else(|_| self.url().join(dest))
-                                    {
-                                        links.push(Link {
-                                            source: self.url().clone(),
-                                            destination: dest,
-                                            text: text.trim().to_string(),
-                                        });
-                                    }
-                                }
+                            if let Some((text, attributes, position)) = open_links.pop() {
+                                links.extend(self.anchor_link(
+                                    &text,
+                                    &attributes.borrow(),
+                                    position,
+                                ));
                             }
+                        } else if is_navigation(element) {
+                            navigation_depth -= 1;
                         }
                     }
 
@@ -162,7 +163,7 @@ impl Html {
                         let text = raw_text.trim();
 
                         if !text.is_empty() {
-                            for (link_text, _) in &mut open_links {
+                            for (link_text, _, _) in &mut open_links {
                                 link_text.push('\n');
                                 link_text.push_str(text);
                             }
@@ -172,29 +173,37 @@ impl Html {
             }
         }
 
-        while let Some((text, attributes)) = open_links.pop() {
-            if let Some(rel) = attributes.borrow().get("rel") {
-                if rel.contains("nofollow") || rel.contains("sponsored") || rel.contains("ugc") {
-                    continue;
-                }
-            }
+        while let Some((text, attributes, position)) = open_links.pop() {
+            links.extend(self.anchor_link(&text, &attributes.borrow(), position));
+        }
 
-            if let Some(dest) = attributes.borrow().get("href") {
-                if dest.starts_with("mailto:") || dest.starts_with("tel:") {
-                    continue;
-                }
+        links
+    }
 
-                if let Ok(dest) = Url::parse(dest).or_else(|_| self.url().join(dest)) {
-                    links.push(Link {
-                        source: self.url().clone(),
-                        destination: dest,
-                        text: text.trim().to_string(),
-                    });
-                }
-            }
+    fn anchor_link(
+        &self,
+        text: &str,
+        attributes: &Attributes,
+        position: LinkPosition,
+    ) -> Option<Link> {
+        let dest = attributes.get("href")?;
+
+        if dest.starts_with("mailto:") || dest.starts_with("tel:") {
+            return None;
         }
 
-        links
+        let destination = Url::parse(dest).or_else(|_| self.url().join(dest)).ok()?;
+
+        Some(Link {
+            source: self.url().clone(),
+            destination,
+            text: text.trim().to_string(),
+            rel: attributes
+                .get("rel")
+                .map(LinkRel::parse)
+                .unwrap_or_default(),
+            position,
+        })
     }
 
     fn links_tag(&self) -> Vec<Link> {
@@ -204,11 +213,7 @@ impl Html {
             if let Some(element) = node.as_node().as_element() {
                 if let Some(href) = element.attributes.borrow().get("href") {
                     if let Ok(href) = Url::parse(href).or_else(|_| self.url().join(href)) {
-                        links.push(Link {
-                            source: self.url().clone(),
-                            destination: href,
-                            text: String::new(),
-                        });
+                        links.push(Link::other(self.url().clone(), href));
                     }
                 }
             }
@@ -239,11 +244,7 @@ impl Html {
                             if let Ok(destination) = Url::parse(content.as_str())
                                 .or_else(|_| self.url().join(content.as_str()))
                             {
-                                return Some(Link {
-                                    source: self.url().clone(),
-                                    destination,
-                                    text: String::new(),
-                                });
+                                return Some(Link::other(self.url().clone(), destination));
                             }
                         }
                     }
@@ -263,11 +264,7 @@ impl Html {
                             if let Ok(destination) = Url::parse(content.as_str())
                                 .or_else(|_| self.url().join(content.as_str()))
                             {
-                                return Some(Link {
-                                    source: self.url().clone(),
-                                    destination,
-                                    text: String::new(),
-                                });
+                                return Some(Link::other(self.url().clone(), destination));
                             }
                         }
                     }
@@ -289,11 +286,7 @@ impl Html {
                         .ok()?;
 
                     if script_url.root_domain() != self.url().root_domain() {
-                        Some(Link {
-                            source: self.url().clone(),
-                            destination: script_url,
-                            text: String::new(),
-                        })
+                        Some(Link::other(self.url().clone(), script_url))
                     } else {
                         None
                     }
@@ -309,10 +302,65 @@ impl Html {
     }
 }
 
+/// Whether the element contains the navigation of the site rather than the content of the page.
+fn is_navigation(element: &ElementData) -> bool {
+    matches!(&*element.name.local, "nav" | "header" | "footer" | "aside")
+        || element
+            .attributes
+            .borrow()
+            .get("role")
+            .is_some_and(|role| role.eq_ignore_ascii_case("navigation"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn anchor_link_metadata() {
+        let raw = r#"
+            <html>
+                <body>
+                    <nav>
+                        <a href="/about">About</a>
+                    </nav>
+                    <div role="navigation"><a href="/contact">Contact</a></div>
+                    <p>
+                        <a href="https://example.com/" rel="nofollow noopener">Example</a>
+                        <a href="https://ads.com/" rel="Sponsored">Ad</a>
+                        <a href="https://forum.com/" rel="ugc">Forum</a>
+                    </p>
+                    <footer><a href="https://other.com/">Other</a></footer>
+                    <a href="https://last.com/">Last</a>
+                </body>
+            </html>
+        "#;
+
+        let webpage = Html::parse(raw, "https://www.example.com/").unwrap();
+        let links = webpage.anchor_links();
+
+        let link = |text: &str| links.iter().find(|link| link.text == text).unwrap();
+
+        assert_eq!(links.len(), 7);
+
+        assert_eq!(link("About").position, LinkPosition::Navigation);
+        assert_eq!(link("Contact").position, LinkPosition::Navigation);
+        assert_eq!(link("Other").position, LinkPosition::Navigation);
+        assert_eq!(link("Example").position, LinkPosition::Content);
+        assert_eq!(link("Last").position, LinkPosition::Content);
+
+        assert_eq!(
+            link("Example").rel,
+            LinkRel {
+                nofollow: true,
+                ..Default::default()
+            }
+        );
+        assert!(link("Ad").rel.sponsored);
+        assert!(link("Forum").rel.ugc);
+        assert_eq!(link("About").rel, LinkRel::default());
+    }
+
     #[test]
     fn simple_favicon() {
         let raw = r#"
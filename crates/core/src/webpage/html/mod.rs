@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{enum_map::EnumSet, Result};
+use crate::{enum_map::EnumSet, warc::HttpHeaders, Result};
 use chrono::{DateTime, FixedOffset, Utc};
 use itertools::Itertools;
 use kuchiki::{traits::TendrilSink, NodeRef};
@@ -49,6 +49,8 @@ pub struct Html {
     clean_text: Option<String>,
     lang: Option<Lang>,
     robots: Option<EnumSet<RobotsMeta>>,
+    /// The type of the document the page was rendered from (e.g. `pdf`).
+    file_type: Option<String>,
 }
 
 impl Html {
@@ -77,6 +79,7 @@ impl Html {
             lang: None,
             url,
             robots: None,
+            file_type: None,
         };
 
         if let Some(canonical) = res.canonical_url() {
@@ -91,6 +94,24 @@ impl Html {
         Ok(res)
     }
 
+    /// Apply the http response headers that affect how the page is indexed. `X-Robots-Tag` is
+    /// combined with the robots meta tags and a `Link` canonical header is used if the page
+    /// has no canonical link element.
+    pub fn apply_http_headers(&mut self, headers: &HttpHeaders, user_agent: Option<&str>) {
+        for value in headers.get_all("x-robots-tag") {
+            self.apply_x_robots_tag(value, user_agent);
+        }
+
+        if self.canonical_url().is_none() {
+            if let Some(canonical) = headers.canonical_url(&self.url) {
+                if canonical.root_domain() == self.url.root_domain() {
+                    self.url = canonical;
+                    self.url.normalize();
+                }
+            }
+        }
+    }
+
     pub fn lang(&self) -> Option<&'_ Lang> {
         self.lang.as_ref()
     }
@@ -120,6 +141,56 @@ impl Html {
         canonical_url
     }
 
+    /// The language tag (e.g. `de-at`) the page declares for itself, either through
+    /// the `lang` attribute on `<html>` or an alternate `<link hreflang="..">` pointing
+    /// to the page itself. The tag is lowercased and uses `-` as separator.
+    pub fn declared_lang(&self) -> Option<String> {
+        let normalize = |tag: &str| {
+            let tag = tag.trim().to_ascii_lowercase().replace('_', "-");
+
+            if tag.is_empty() {
+                None
+            } else {
+                Some(tag)
+            }
+        };
+
+        if let Some(html) = self.root.select_first("html") {
+            if let Some(tag) = html.attributes.borrow().get("lang").and_then(normalize) {
+                return Some(tag);
+            }
+        }
+
+        for node in self.root.select("link").unwrap() {
+            if let Some(element) = node.as_node().as_element() {
+                let attributes = element.attributes.borrow();
+
+                if attributes.get("rel") != Some("alternate") {
+                    continue;
+                }
+
+                let points_to_self = attributes
+                    .get("href")
+                    .and_then(|href| self.url().join(href).ok())
+                    .map(|mut url| {
+                        url.normalize();
+                        &url == self.url()
+                    })
+                    .unwrap_or(false);
+
+                if points_to_self {
+                    if let Some(tag) = attributes.get("hreflang").and_then(normalize) {
+                        if tag != "x-default" {
+                            return Some(tag);
+                        }
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
     pub fn clean_text(&self) -> Option<&String> {
         self.clean_text.as_ref()
     }
@@ -152,6 +223,20 @@ impl Html {
         &self.url
     }
 
+    pub fn set_file_type(&mut self, file_type: &str) {
+        self.file_type = Some(file_type.to_string());
+    }
+
+    /// The type of the document the page was rendered from, or the lowercased
+    /// file extension of the url path.
+    pub fn file_type(&self) -> Option<String> {
+        self.file_type.clone().or_else(|| {
+            self.url
+                .file_extension()
+                .map(|extension| extension.to_ascii_lowercase())
+        })
+    }
+
     pub fn metadata(&self) -> Vec<Meta> {
         let mut metas = Vec::new();
 
@@ -476,7 +561,10 @@ mod tests {
 
     use std::collections::HashMap;
 
-    use crate::{schema::create_schema, webpage::Link};
+    use crate::{
+        schema::create_schema,
+        webpage::{Link, LinkPosition, LinkRel},
+    };
 
     use super::*;
 
@@ -509,7 +597,9 @@ mod tests {
             vec![Link {
                 source: Url::parse("https://www.example.com/whatever").unwrap(),
                 destination: Url::parse("https://example.com").unwrap(),
-                text: "Link to example".to_string()
+                text: "Link to example".to_string(),
+                rel: LinkRel::default(),
+                position: LinkPosition::Content,
             }]
         );
         assert_eq!(webpage.clean_text(), Some(&CONTENT.to_string()));
@@ -952,7 +1042,9 @@ mod tests {
             vec![Link {
                 source: Url::parse("https://www.example.com/whatever").unwrap(),
                 destination: Url::parse("https://example.com").unwrap(),
-                text: "Link to example".to_string()
+                text: "Link to example".to_string(),
+                rel: LinkRel::default(),
+                position: LinkPosition::Content,
             },]
         );
     }
@@ -1036,6 +1128,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn http_canonical_header() {
+        let mut headers = HttpHeaders::new();
+        headers.push(
+            "Link",
+            r#"<https://example.com/style.css>; rel="preload", </canonical.html>; rel="canonical""#,
+        );
+
+        let mut html = Html::parse("<html></html>", "https://example.com/whatever").unwrap();
+        html.apply_http_headers(&headers, None);
+        assert_eq!(
+            html.url(),
+            &Url::parse("https://example.com/canonical.html").unwrap()
+        );
+
+        // the canonical link element takes precedence
+        let mut html = Html::parse(
+            r#"<html><head><link rel="canonical" href="https://example.com/element.html" /></head></html>"#,
+            "https://example.com/whatever",
+        )
+        .unwrap();
+        html.apply_http_headers(&headers, None);
+        assert_eq!(
+            html.url(),
+            &Url::parse("https://example.com/element.html").unwrap()
+        );
+    }
+
     #[test]
     fn recipe_first_ingredient_tag() {
         let html = Html::parse(
@@ -50,7 +50,64 @@ impl InsertEnumMapKey for RobotsMeta {
     }
 }
 
+/// `X-Robots-Tag` directives that take a value, so `max-snippet: 20` is not mistaken
+/// for a directive scoped to the user agent `max-snippet`.
+const VALUED_DIRECTIVES: [&str; 4] = [
+    "unavailable_after",
+    "max-snippet",
+    "max-image-preview",
+    "max-video-preview",
+];
+
+/// Parse the value of a `X-Robots-Tag` header. Directives can be scoped to a user agent
+/// (`otherbot: noindex`), in which case they only apply if `user_agent` contains the name.
+fn parse_x_robots_tag(value: &str, user_agent: Option<&str>) -> Vec<RobotsMeta> {
+    let mut directives = value;
+
+    if let Some((agent, rest)) = value.split_once(':') {
+        let agent = agent.trim().to_ascii_lowercase();
+
+        if !agent.contains(',') && !VALUED_DIRECTIVES.contains(&agent.as_str()) {
+            let applies = user_agent
+                .map(|user_agent| user_agent.to_ascii_lowercase().contains(&agent))
+                .unwrap_or(false);
+
+            if !applies {
+                return Vec::new();
+            }
+
+            directives = rest;
+        }
+    }
+
+    let mut res = Vec::new();
+
+    for part in directives.split(',') {
+        match part.trim().to_ascii_lowercase().as_str() {
+            "none" => {
+                res.push(RobotsMeta::NoIndex);
+                res.push(RobotsMeta::NoFollow);
+            }
+            part => {
+                if let Ok(meta) = part.parse::<RobotsMeta>() {
+                    res.push(meta);
+                }
+            }
+        }
+    }
+
+    res
+}
+
 impl Html {
+    /// Add the directives from a `X-Robots-Tag` header to the ones from the robots meta tags.
+    /// `user_agent` is the user agent the page was fetched with.
+    pub fn apply_x_robots_tag(&mut self, value: &str, user_agent: Option<&str>) {
+        for meta in parse_x_robots_tag(value, user_agent) {
+            self.robots.get_or_insert_with(EnumSet::new).insert(meta);
+        }
+    }
+
     pub fn parse_robots_meta(&self) -> Option<EnumSet<RobotsMeta>> {
         let mut robots = EnumSet::new();
 
@@ -97,6 +154,40 @@ impl Html {
 mod tests {
     use super::*;
 
+    #[test]
+    fn x_robots_tag() {
+        let html = || Html::parse("<html></html>", "https://www.example.com/").unwrap();
+
+        let mut page = html();
+        page.apply_x_robots_tag("noindex", None);
+        assert!(page.is_no_index());
+        assert!(!page.is_no_follow());
+
+        let mut page = html();
+        page.apply_x_robots_tag("none", None);
+        assert!(page.is_no_index());
+        assert!(page.is_no_follow());
+
+        let mut page = html();
+        page.apply_x_robots_tag("max-snippet: 20, nofollow", None);
+        assert!(!page.is_no_index());
+        assert!(page.is_no_follow());
+
+        let mut page = html();
+        page.apply_x_robots_tag(
+            "otherbot: noindex",
+            Some("Mozilla/5.0 (compatible; StractBot)"),
+        );
+        assert!(!page.is_no_index());
+
+        let mut page = html();
+        page.apply_x_robots_tag(
+            "stractbot: noindex",
+            Some("Mozilla/5.0 (compatible; StractBot)"),
+        );
+        assert!(page.is_no_index());
+    }
+
     #[test]
     fn robots_meta_tag() {
         let html = Html::parse(
@@ -0,0 +1,283 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use utoipa::ToSchema;
+
+/// Wrapper around `whatlang::Lang` that is identified by its ISO 639-1 code (e.g. `de`)
+/// when serialized and implements `ToSchema`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, bincode::Encode, bincode::Decode, ToSchema)]
+#[schema(value_type = String, example = "de")]
+pub struct Lang(#[bincode(with_serde)] whatlang::Lang);
+
+impl Lang {
+    /// Parse either an ISO 639-1 (`de`) or ISO 639-3 (`deu`) language code.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let code = code.to_ascii_lowercase();
+
+        whatlang::Lang::all()
+            .iter()
+            .find(|lang| iso_639_1(**lang) == code || lang.code() == code)
+            .map(|lang| Self(*lang))
+    }
+
+    /// The ISO 639-1 code of the language.
+    pub fn code(&self) -> &'static str {
+        iso_639_1(self.0)
+    }
+
+    /// The id stored in the language fast field. `0` is reserved for
+    /// webpages where the language could not be detected.
+    pub fn id(&self) -> u64 {
+        lang_id(self.0)
+    }
+
+    pub fn from_id(id: u64) -> Option<Self> {
+        whatlang::Lang::all()
+            .iter()
+            .find(|lang| lang_id(**lang) == id)
+            .map(|lang| Self(*lang))
+    }
+
+    pub fn as_whatlang(&self) -> whatlang::Lang {
+        self.0
+    }
+}
+
+impl From<whatlang::Lang> for Lang {
+    fn from(lang: whatlang::Lang) -> Self {
+        Self(lang)
+    }
+}
+
+impl std::fmt::Display for Lang {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl serde::Serialize for Lang {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Lang {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let code = String::deserialize(deserializer)?;
+
+        Lang::from_code(&code)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown language: {code}")))
+    }
+}
+
+/// The ids are persisted in the index, so they must never change. New languages
+/// should be given the next unused id.
+fn lang_id(lang: whatlang::Lang) -> u64 {
+    use whatlang::Lang;
+
+    match lang {
+        Lang::Epo => 1,
+        Lang::Eng => 2,
+        Lang::Rus => 3,
+        Lang::Cmn => 4,
+        Lang::Spa => 5,
+        Lang::Por => 6,
+        Lang::Ita => 7,
+        Lang::Ben => 8,
+        Lang::Fra => 9,
+        Lang::Deu => 10,
+        Lang::Ukr => 11,
+        Lang::Kat => 12,
+        Lang::Ara => 13,
+        Lang::Hin => 14,
+        Lang::Jpn => 15,
+        Lang::Heb => 16,
+        Lang::Yid => 17,
+        Lang::Pol => 18,
+        Lang::Amh => 19,
+        Lang::Jav => 20,
+        Lang::Kor => 21,
+        Lang::Nob => 22,
+        Lang::Dan => 23,
+        Lang::Swe => 24,
+        Lang::Fin => 25,
+        Lang::Tur => 26,
+        Lang::Nld => 27,
+        Lang::Hun => 28,
+        Lang::Ces => 29,
+        Lang::Ell => 30,
+        Lang::Bul => 31,
+        Lang::Bel => 32,
+        Lang::Mar => 33,
+        Lang::Kan => 34,
+        Lang::Ron => 35,
+        Lang::Slv => 36,
+        Lang::Hrv => 37,
+        Lang::Srp => 38,
+        Lang::Mkd => 39,
+        Lang::Lit => 40,
+        Lang::Lav => 41,
+        Lang::Est => 42,
+        Lang::Tam => 43,
+        Lang::Vie => 44,
+        Lang::Urd => 45,
+        Lang::Tha => 46,
+        Lang::Guj => 47,
+        Lang::Uzb => 48,
+        Lang::Pan => 49,
+        Lang::Aze => 50,
+        Lang::Ind => 51,
+        Lang::Tel => 52,
+        Lang::Pes => 53,
+        Lang::Mal => 54,
+        Lang::Ori => 55,
+        Lang::Mya => 56,
+        Lang::Nep => 57,
+        Lang::Sin => 58,
+        Lang::Khm => 59,
+        Lang::Tuk => 60,
+        Lang::Aka => 61,
+        Lang::Zul => 62,
+        Lang::Sna => 63,
+        Lang::Afr => 64,
+        Lang::Lat => 65,
+        Lang::Slk => 66,
+        Lang::Cat => 67,
+        Lang::Tgl => 68,
+        Lang::Hye => 69,
+    }
+}
+
+fn iso_639_1(lang: whatlang::Lang) -> &'static str {
+    use whatlang::Lang;
+
+    match lang {
+        Lang::Epo => "eo",
+        Lang::Eng => "en",
+        Lang::Rus => "ru",
+        Lang::Cmn => "zh",
+        Lang::Spa => "es",
+        Lang::Por => "pt",
+        Lang::Ita => "it",
+        Lang::Ben => "bn",
+        Lang::Fra => "fr",
+        Lang::Deu => "de",
+        Lang::Ukr => "uk",
+        Lang::Kat => "ka",
+        Lang::Ara => "ar",
+        Lang::Hin => "hi",
+        Lang::Jpn => "ja",
+        Lang::Heb => "he",
+        Lang::Yid => "yi",
+        Lang::Pol => "pl",
+        Lang::Amh => "am",
+        Lang::Jav => "jv",
+        Lang::Kor => "ko",
+        Lang::Nob => "nb",
+        Lang::Dan => "da",
+        Lang::Swe => "sv",
+        Lang::Fin => "fi",
+        Lang::Tur => "tr",
+        Lang::Nld => "nl",
+        Lang::Hun => "hu",
+        Lang::Ces => "cs",
+        Lang::Ell => "el",
+        Lang::Bul => "bg",
+        Lang::Bel => "be",
+        Lang::Mar => "mr",
+        Lang::Kan => "kn",
+        Lang::Ron => "ro",
+        Lang::Slv => "sl",
+        Lang::Hrv => "hr",
+        Lang::Srp => "sr",
+        Lang::Mkd => "mk",
+        Lang::Lit => "lt",
+        Lang::Lav => "lv",
+        Lang::Est => "et",
+        Lang::Tam => "ta",
+        Lang::Vie => "vi",
+        Lang::Urd => "ur",
+        Lang::Tha => "th",
+        Lang::Guj => "gu",
+        Lang::Uzb => "uz",
+        Lang::Pan => "pa",
+        Lang::Aze => "az",
+        Lang::Ind => "id",
+        Lang::Tel => "te",
+        Lang::Pes => "fa",
+        Lang::Mal => "ml",
+        Lang::Ori => "or",
+        Lang::Mya => "my",
+        Lang::Nep => "ne",
+        Lang::Sin => "si",
+        Lang::Khm => "km",
+        Lang::Tuk => "tk",
+        Lang::Aka => "ak",
+        Lang::Zul => "zu",
+        Lang::Sna => "sn",
+        Lang::Afr => "af",
+        Lang::Lat => "la",
+        Lang::Slk => "sk",
+        Lang::Cat => "ca",
+        Lang::Tgl => "tl",
+        Lang::Hye => "hy",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes() {
+        assert_eq!(Lang::from_code("de"), Some(Lang(whatlang::Lang::Deu)));
+        assert_eq!(Lang::from_code("deu"), Some(Lang(whatlang::Lang::Deu)));
+        assert_eq!(Lang::from_code("DE"), Some(Lang(whatlang::Lang::Deu)));
+        assert_eq!(Lang::from_code("xx"), None);
+
+        for lang in whatlang::Lang::all() {
+            let lang = Lang::from(*lang);
+            assert_eq!(Lang::from_code(lang.code()), Some(lang));
+            assert_eq!(Lang::from_id(lang.id()), Some(lang));
+        }
+
+        assert_eq!(Lang::from_id(0), None);
+    }
+
+    #[test]
+    fn stable_ids() {
+        assert_eq!(Lang::from(whatlang::Lang::Epo).id(), 1);
+        assert_eq!(Lang::from(whatlang::Lang::Eng).id(), 2);
+        assert_eq!(Lang::from(whatlang::Lang::Deu).id(), 10);
+        assert_eq!(Lang::from(whatlang::Lang::Dan).id(), 23);
+        assert_eq!(Lang::from(whatlang::Lang::Hye).id(), 69);
+
+        let mut ids = whatlang::Lang::all()
+            .iter()
+            .map(|lang| Lang::from(*lang).id())
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+
+        assert_eq!(ids.len(), whatlang::Lang::all().len());
+    }
+}
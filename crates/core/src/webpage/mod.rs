@@ -29,6 +29,8 @@ use url::Url;
 mod adservers;
 pub mod html;
 mod just_text;
+pub mod lang;
+pub mod pdf;
 pub mod region;
 pub mod safety_classifier;
 pub mod schema_org;
@@ -43,6 +45,9 @@ pub struct Webpage {
     pub backlink_labels: Vec<String>,
     pub host_centrality: f64,
     pub host_centrality_rank: u64,
+    pub host_pagerank_rank: u64,
+    /// Rank of the host by PageRank personalised to a set of trusted hosts.
+    pub host_trustrank_rank: u64,
     pub page_centrality: f64,
     pub page_centrality_rank: u64,
     pub fetch_time_ms: u64,
@@ -64,6 +69,8 @@ impl Default for Webpage {
             backlink_labels: Default::default(),
             host_centrality: Default::default(),
             host_centrality_rank: u64::MAX,
+            host_pagerank_rank: u64::MAX,
+            host_trustrank_rank: u64::MAX,
             page_centrality: Default::default(),
             page_centrality_rank: u64::MAX,
             fetch_time_ms: Default::default(),
@@ -86,6 +93,8 @@ impl From<Html> for Webpage {
             backlink_labels: Default::default(),
             host_centrality: Default::default(),
             host_centrality_rank: u64::MAX,
+            host_pagerank_rank: u64::MAX,
+            host_trustrank_rank: u64::MAX,
             page_centrality: Default::default(),
             page_centrality_rank: u64::MAX,
             fetch_time_ms: Default::default(),
@@ -152,6 +161,81 @@ pub struct Link {
     pub source: Url,
     pub destination: Url,
     pub text: String,
+    pub rel: LinkRel,
+    pub position: LinkPosition,
+}
+
+impl Link {
+    /// A link that is not an anchor in the page, e.g. from a `<link>` or `<meta>` tag.
+    fn other(source: Url, destination: Url) -> Self {
+        Self {
+            source,
+            destination,
+            text: String::new(),
+            rel: LinkRel::default(),
+            position: LinkPosition::default(),
+        }
+    }
+}
+
+/// The values of the `rel` attribute of a link that qualify how much the linking page vouches for the destination.
+/// See <https://developers.google.com/search/docs/crawling-indexing/qualify-outbound-links>.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+    bincode::Encode,
+    bincode::Decode,
+)]
+pub struct LinkRel {
+    pub nofollow: bool,
+    pub sponsored: bool,
+    pub ugc: bool,
+}
+
+impl LinkRel {
+    pub fn parse(rel: &str) -> Self {
+        let mut res = Self::default();
+
+        for value in rel.split_ascii_whitespace() {
+            if value.eq_ignore_ascii_case("nofollow") {
+                res.nofollow = true;
+            } else if value.eq_ignore_ascii_case("sponsored") {
+                res.sponsored = true;
+            } else if value.eq_ignore_ascii_case("ugc") {
+                res.ugc = true;
+            }
+        }
+
+        res
+    }
+}
+
+/// Where in the page a link is located.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+    bincode::Encode,
+    bincode::Decode,
+)]
+pub enum LinkPosition {
+    #[default]
+    Content,
+    /// Menus, headers, footers and sidebars that are repeated across the pages of a site.
+    Navigation,
 }
 
 pub type Meta = HashMap<String, String>;
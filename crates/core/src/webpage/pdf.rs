@@ -0,0 +1,531 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Text and metadata extraction from pdf documents.
+//!
+//! The extracted document is rendered as a simple html page, so pdfs
+//! go through the same indexing pipeline as all other pages.
+
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone};
+use itertools::Itertools;
+use lopdf::{Dictionary, Document, Object};
+use url::Url;
+
+use super::Html;
+use crate::{Error, Result};
+
+/// Only the text of the first pages is extracted from very long documents.
+const MAX_PAGES: usize = 500;
+
+/// Titles that pdf writers put in the metadata when the author didn't set one.
+const PLACEHOLDER_TITLES: [&str; 3] = ["untitled", "title", "slide 1"];
+
+/// Prefixes that office suites add to the titles of exported documents.
+const TITLE_PREFIXES: [&str; 3] = [
+    "Microsoft Word - ",
+    "Microsoft PowerPoint - ",
+    "Microsoft Excel - ",
+];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pdf {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub keywords: Option<String>,
+    pub created: Option<DateTime<FixedOffset>>,
+    pub modified: Option<DateTime<FixedOffset>>,
+    /// The paragraphs of the text in reading order.
+    pub paragraphs: Vec<String>,
+}
+
+impl Pdf {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        // lopdf panics on some malformed documents, which should not take down the indexer
+        std::panic::catch_unwind(|| Self::parse_document(bytes))
+            .unwrap_or_else(|_| Err(Error::PdfParse("the parser panicked".to_string()).into()))
+    }
+
+    fn parse_document(bytes: &[u8]) -> Result<Self> {
+        let doc = Document::load_mem(bytes).map_err(|err| Error::PdfParse(err.to_string()))?;
+
+        if doc.is_encrypted() {
+            return Err(Error::PdfParse("the document is encrypted".to_string()).into());
+        }
+
+        let mut pdf = Self::default();
+
+        if let Some(info) = info_dictionary(&doc) {
+            let field = |key: &[u8]| info_string(&doc, info, key);
+
+            pdf.title = field(b"Title").and_then(|title| clean_title(&title));
+            pdf.author = field(b"Author");
+            pdf.subject = field(b"Subject");
+            pdf.keywords = field(b"Keywords");
+            pdf.created = field(b"CreationDate").and_then(|date| parse_date(&date));
+            pdf.modified = field(b"ModDate").and_then(|date| parse_date(&date));
+        }
+
+        for page in doc.get_pages().into_keys().take(MAX_PAGES) {
+            // a page we fail to extract should not discard the rest of the document
+            if let Ok(text) = doc.extract_text(&[page]) {
+                pdf.paragraphs.extend(paragraphs(&text));
+            }
+        }
+
+        Ok(pdf)
+    }
+
+    /// The title from the metadata, or the first line of the document if it looks like a title.
+    pub fn title(&self) -> Option<String> {
+        self.title.clone().or_else(|| {
+            self.paragraphs
+                .first()
+                .filter(|paragraph| {
+                    let num_words = paragraph.split_whitespace().count();
+                    num_words > 0 && num_words <= 20
+                })
+                .cloned()
+        })
+    }
+
+    /// Render the document as a html page that can be indexed like any other page.
+    pub fn into_html(self, url: &str) -> Result<Html> {
+        let mut html = Html::parse_without_text(&self.as_html(url), url)?;
+        html.set_file_type("pdf");
+
+        Ok(html)
+    }
+
+    fn as_html(&self, url: &str) -> String {
+        let title = self.title().or_else(|| file_name(url)).unwrap_or_default();
+
+        let mut head = format!("<title>{}</title>", escape(&title));
+
+        let mut meta = |attr: &str, name: &str, content: &str| {
+            head.push_str(&format!(
+                r#"<meta {attr}="{name}" content="{}" />"#,
+                escape(content)
+            ));
+        };
+
+        if let Some(author) = &self.author {
+            meta("name", "author", author);
+        }
+
+        if let Some(subject) = &self.subject {
+            meta("name", "description", subject);
+        }
+
+        if let Some(keywords) = &self.keywords {
+            meta("name", "keywords", keywords);
+        }
+
+        if let Some(created) = &self.created {
+            meta("property", "article:published_time", &created.to_rfc3339());
+        }
+
+        if let Some(modified) = self.modified.as_ref().or(self.created.as_ref()) {
+            meta("property", "article:modified_time", &modified.to_rfc3339());
+        }
+
+        let body: String = self
+            .paragraphs
+            .iter()
+            .map(|paragraph| format!("<p>{}</p>", escape(paragraph)))
+            .collect();
+
+        format!("<html><head>{head}</head><body><article>{body}</article></body></html>")
+    }
+}
+
+fn info_dictionary(doc: &Document) -> Option<&Dictionary> {
+    let info = doc.trailer.get(b"Info").ok()?;
+    let (_, info) = doc.dereference(info).ok()?;
+
+    info.as_dict().ok()
+}
+
+fn info_string(doc: &Document, info: &Dictionary, key: &[u8]) -> Option<String> {
+    let (_, value) = doc.dereference(info.get(key).ok()?).ok()?;
+
+    match value {
+        Object::String(bytes, _) => {
+            let value = decode_text_string(bytes);
+            let value = value.trim();
+
+            if value.is_empty() {
+                None
+            } else {
+                Some(value.to_string())
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Decode a pdf text string. Text strings are either utf-16 with a byte order mark,
+/// utf-8 with a byte order mark (pdf 2.0) or `PDFDocEncoding`, which mostly agrees with latin-1.
+fn decode_text_string(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(&[0xfe, 0xff]) {
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+            .collect();
+
+        String::from_utf16_lossy(&units)
+    } else if let Some(utf8) = bytes.strip_prefix(&[0xef, 0xbb, 0xbf]) {
+        String::from_utf8_lossy(utf8).to_string()
+    } else {
+        bytes.iter().map(|b| *b as char).collect()
+    }
+}
+
+fn clean_title(title: &str) -> Option<String> {
+    let mut title = title.trim();
+
+    for prefix in TITLE_PREFIXES {
+        if let Some(stripped) = title.strip_prefix(prefix) {
+            title = stripped.trim();
+        }
+    }
+
+    let lowercase = title.to_lowercase();
+    if title.is_empty() || PLACEHOLDER_TITLES.contains(&lowercase.as_str()) {
+        return None;
+    }
+
+    Some(title.to_string())
+}
+
+/// The name of the file without extension, e.g. `annual report` for `/docs/annual_report.pdf`.
+fn file_name(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let name = url.path_segments()?.last()?;
+    let name = urlencoding::decode(name).ok()?;
+    let name = name
+        .rsplit_once('.')
+        .map_or(name.as_ref(), |(stem, _)| stem);
+    let name = name.replace(['_', '-'], " ");
+    let name = name.trim();
+
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// Parse a pdf date like `D:20230105133700+01'00'`. Everything after the year is optional.
+fn parse_date(date: &str) -> Option<DateTime<FixedOffset>> {
+    let date = date.trim();
+    let date = date.strip_prefix("D:").unwrap_or(date);
+
+    let num_digits = date.chars().take_while(|c| c.is_ascii_digit()).count();
+    let (digits, offset) = date.split_at(num_digits);
+
+    let field = |start: usize, default: u32| match digits.get(start..start + 2) {
+        Some(field) => field.parse().ok(),
+        None => Some(default),
+    };
+
+    let year = digits.get(..4)?.parse().ok()?;
+    let date = NaiveDate::from_ymd_opt(year, field(4, 1)?, field(6, 1)?)?.and_hms_opt(
+        field(8, 0)?,
+        field(10, 0)?,
+        field(12, 0)?,
+    )?;
+
+    let offset_secs = match offset.chars().next() {
+        Some(sign @ ('+' | '-')) => {
+            let mut parts = offset[1..]
+                .split(|c: char| !c.is_ascii_digit())
+                .filter(|part| !part.is_empty())
+                .map(|part| part.parse::<i32>().ok());
+
+            // a missing part is 0, but a malformed or out of range part makes the date invalid
+            let hours = parts
+                .next()
+                .unwrap_or(Some(0))
+                .filter(|hours| (0..=23).contains(hours))?;
+            let minutes = parts
+                .next()
+                .unwrap_or(Some(0))
+                .filter(|minutes| (0..=59).contains(minutes))?;
+            let secs = hours * 3600 + minutes * 60;
+
+            if sign == '-' {
+                -secs
+            } else {
+                secs
+            }
+        }
+        // `Z` or no offset
+        _ => 0,
+    };
+
+    FixedOffset::east_opt(offset_secs)?
+        .from_local_datetime(&date)
+        .single()
+}
+
+/// Split the extracted text of a page into paragraphs. Paragraphs are separated by blank lines
+/// and words that are hyphenated at the end of a line are joined.
+fn paragraphs(text: &str) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut current = String::new();
+
+    for line in text.lines() {
+        let line = line.split_whitespace().join(" ");
+
+        if line.is_empty() {
+            if !current.is_empty() {
+                res.push(std::mem::take(&mut current));
+            }
+
+            continue;
+        }
+
+        let hyphenated = current.ends_with('-')
+            && current
+                .chars()
+                .rev()
+                .nth(1)
+                .map(|c| c.is_alphabetic())
+                .unwrap_or(false)
+            && line.starts_with(|c: char| c.is_lowercase());
+
+        if hyphenated {
+            current.pop();
+        } else if !current.is_empty() {
+            current.push(' ');
+        }
+
+        current.push_str(&line);
+    }
+
+    if !current.is_empty() {
+        res.push(current);
+    }
+
+    res
+}
+
+/// Escape `text` so it can be inserted as text or an attribute value in html.
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::{
+        content::{Content, Operation},
+        dictionary, Stream,
+    };
+
+    use super::*;
+
+    fn test_pdf(info: Option<Dictionary>, lines: &[&str]) -> Vec<u8> {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+            "Encoding" => "WinAnsiEncoding",
+        });
+        let resources_id = doc.add_object(dictionary! {
+            "Font" => dictionary! {
+                "F1" => font_id,
+            },
+        });
+
+        let mut operations = Vec::new();
+
+        for (i, line) in lines.iter().enumerate() {
+            let y = 750 - 14 * i as i64;
+
+            operations.extend([
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                Operation::new("Td", vec![50.into(), y.into()]),
+                Operation::new("Tj", vec![Object::string_literal(*line)]),
+                Operation::new("ET", vec![]),
+            ]);
+        }
+
+        let content = Content { operations };
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+        });
+
+        let pages = dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        };
+        doc.objects.insert(pages_id, Object::Dictionary(pages));
+
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+
+        if let Some(info) = info {
+            let info_id = doc.add_object(info);
+            doc.trailer.set("Info", info_id);
+        }
+
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).unwrap();
+
+        bytes
+    }
+
+    #[test]
+    fn metadata_and_text() {
+        let info = dictionary! {
+            "Title" => Object::string_literal("Microsoft Word - Annual report 2023"),
+            "Author" => Object::string_literal("Statistics Denmark"),
+            "Subject" => Object::string_literal("Key figures for the year"),
+            "CreationDate" => Object::string_literal("D:20230105133700+01'00'"),
+            "ModDate" => Object::string_literal("D:20230301"),
+        };
+
+        let bytes = test_pdf(
+            Some(info),
+            &["The population grew by", "two percent this year."],
+        );
+        let pdf = Pdf::parse(&bytes).unwrap();
+
+        assert_eq!(pdf.title.as_deref(), Some("Annual report 2023"));
+        assert_eq!(pdf.author.as_deref(), Some("Statistics Denmark"));
+        assert_eq!(pdf.subject.as_deref(), Some("Key figures for the year"));
+        assert_eq!(
+            pdf.created,
+            Some(DateTime::parse_from_rfc3339("2023-01-05T13:37:00+01:00").unwrap())
+        );
+        assert_eq!(
+            pdf.modified,
+            Some(DateTime::parse_from_rfc3339("2023-03-01T00:00:00+00:00").unwrap())
+        );
+
+        let text = pdf.paragraphs.join(" ");
+        assert!(text.contains("population"));
+        assert!(text.contains("two percent"));
+
+        let mut html = pdf.into_html("https://example.com/report.pdf").unwrap();
+        html.parse_text();
+
+        assert_eq!(html.title().as_deref(), Some("Annual report 2023"));
+        assert_eq!(
+            html.updated_time(),
+            Some(DateTime::parse_from_rfc3339("2023-03-01T00:00:00+00:00").unwrap())
+        );
+        assert_eq!(
+            html.metadata_description().as_deref(),
+            Some("Key figures for the year")
+        );
+        assert!(!html.empty_all_text());
+    }
+
+    #[test]
+    fn title_fallbacks() {
+        let bytes = test_pdf(None, &["Installation manual"]);
+        let pdf = Pdf::parse(&bytes).unwrap();
+
+        assert_eq!(pdf.title, None);
+        assert_eq!(pdf.title().as_deref(), Some("Installation manual"));
+
+        let empty = Pdf::default();
+        let html = empty
+            .into_html("https://example.com/docs/user_guide-v2.pdf")
+            .unwrap();
+
+        assert_eq!(html.title().as_deref(), Some("user guide v2"));
+        assert_eq!(clean_title("Untitled"), None);
+    }
+
+    #[test]
+    fn file_type() {
+        let html = Pdf::default()
+            .into_html("https://example.com/download?id=42")
+            .unwrap();
+
+        assert_eq!(html.file_type().as_deref(), Some("pdf"));
+    }
+
+    #[test]
+    fn not_a_pdf() {
+        assert!(Pdf::parse(b"<html></html>").is_err());
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(
+            parse_date("D:19990209153925-08'00'"),
+            Some(DateTime::parse_from_rfc3339("1999-02-09T15:39:25-08:00").unwrap())
+        );
+        assert_eq!(
+            parse_date("D:2001"),
+            Some(DateTime::parse_from_rfc3339("2001-01-01T00:00:00+00:00").unwrap())
+        );
+        assert_eq!(
+            parse_date("20200101120000Z"),
+            Some(DateTime::parse_from_rfc3339("2020-01-01T12:00:00+00:00").unwrap())
+        );
+        assert_eq!(
+            parse_date("D:20200101120000+05'30'"),
+            Some(DateTime::parse_from_rfc3339("2020-01-01T12:00:00+05:30").unwrap())
+        );
+        assert_eq!(parse_date("D:20201301"), None);
+        assert_eq!(parse_date("D:20200101120000+2147483647'00'"), None);
+        assert_eq!(parse_date("D:20200101120000+99999999999'00'"), None);
+        assert_eq!(parse_date("D:20200101120000-24'00'"), None);
+        assert_eq!(parse_date("D:20200101120000+05'60'"), None);
+        assert_eq!(parse_date("yesterday"), None);
+    }
+
+    #[test]
+    fn text_strings() {
+        assert_eq!(
+            decode_text_string(&[0xfe, 0xff, 0x00, 0x52, 0x00, 0xe9, 0x00, 0x73]),
+            "Rés"
+        );
+        assert_eq!(decode_text_string(b"caf\xe9"), "café");
+    }
+
+    #[test]
+    fn join_paragraphs() {
+        assert_eq!(
+            paragraphs("The quick brown fox jum-\nped over\n\n  the   lazy dog\n"),
+            vec![
+                "The quick brown fox jumped over".to_string(),
+                "the lazy dog".to_string()
+            ]
+        );
+    }
+}
@@ -20,118 +20,318 @@ use utoipa::ToSchema;
 
 use crate::{Error, Result};
 
-use super::Webpage;
+use super::{lang::Lang, url_ext::UrlExt, Webpage};
 
+const DEFAULT_REGIONS: &str = include_str!("regions.toml");
+
+static REGION_TABLE: once_cell::sync::OnceCell<RegionTable> = once_cell::sync::OnceCell::new();
+
+/// A region that webpages can be assigned to and that can be selected when searching.
+/// Regions are identified by their id in the [`RegionTable`] and serialized by their name.
 #[derive(
-    Debug,
-    PartialEq,
-    Eq,
-    Clone,
-    Copy,
-    Hash,
-    serde::Serialize,
-    serde::Deserialize,
-    bincode::Encode,
-    bincode::Decode,
-    Default,
-    ToSchema,
+    Debug, PartialEq, Eq, Clone, Copy, Hash, bincode::Encode, bincode::Decode, Default, ToSchema,
 )]
-pub enum Region {
-    #[default]
-    All,
-    Denmark,
-    France,
-    Germany,
-    Spain,
-    US,
-}
-
-pub const ALL_REGIONS: [Region; 6] = [
-    Region::All,
-    Region::Denmark,
-    Region::France,
-    Region::Germany,
-    Region::Spain,
-    Region::US,
-];
+#[schema(value_type = String, example = "Denmark")]
+pub struct Region(u64);
 
 impl Region {
+    pub const ALL: Region = Region(0);
+
+    /// All regions in the installed region table, starting with [`Region::ALL`].
+    pub fn all() -> Vec<Region> {
+        std::iter::once(Region::ALL)
+            .chain(
+                RegionTable::global()
+                    .regions
+                    .iter()
+                    .map(|entry| Region(entry.id)),
+            )
+            .collect()
+    }
+
     pub fn name(&self) -> String {
-        // TODO: Translate these strings.
-
-        // The names are used in the UI. In the future we should
-        // actually use region, but right now we only detect region
-        // from the language of the page, so it makes more sense to
-        // just use the language name.
-        match self {
-            Region::All => "All Languages".to_string(),
-            Region::Denmark => "Danish".to_string(),
-            Region::France => "French".to_string(),
-            Region::Germany => "German".to_string(),
-            Region::Spain => "Spainish".to_string(),
-            Region::US => "English".to_string(),
+        match RegionTable::global().get(self) {
+            Some(entry) => entry.display_name.clone(),
+            None => "All Languages".to_string(),
         }
     }
 
     pub fn gl(&self) -> String {
-        match self {
-            Region::All => "all".to_string(),
-            Region::Denmark => "dk".to_string(),
-            Region::France => "fr".to_string(),
-            Region::Germany => "ger".to_string(),
-            Region::Spain => "spa".to_string(),
-            Region::US => "us".to_string(),
+        match RegionTable::global().get(self) {
+            Some(entry) => entry.gl.clone(),
+            None => "all".to_string(),
         }
     }
 
     pub fn id(&self) -> u64 {
-        ALL_REGIONS
-            .iter()
-            .enumerate()
-            .find(|(_, region)| self == *region)
-            .map(|(id, _)| id as u64)
-            .unwrap()
+        self.0
     }
 
     pub fn from_gl(gl: &str) -> Result<Self> {
-        match gl {
-            "all" => Ok(Region::All),
-            "dk" => Ok(Region::Denmark),
-            "fr" => Ok(Region::France),
-            "ger" => Ok(Region::Germany),
-            "spa" => Ok(Region::Spain),
-            "us" => Ok(Region::US),
-            _ => Err(Error::UnknownRegion.into()),
+        if gl == "all" {
+            return Ok(Region::ALL);
         }
+
+        RegionTable::global()
+            .by_gl
+            .get(gl)
+            .map(|id| Region(*id))
+            .ok_or_else(|| Error::UnknownRegion.into())
     }
 
-    pub fn guess_from(webpage: &Webpage) -> Result<Self> {
-        match webpage.html.lang() {
-            Some(lang) => match lang {
-                whatlang::Lang::Eng => Ok(Region::US),
-                whatlang::Lang::Spa => Ok(Region::Spain),
-                whatlang::Lang::Fra => Ok(Region::France),
-                whatlang::Lang::Deu => Ok(Region::Germany),
-                whatlang::Lang::Dan => Ok(Region::Denmark),
-                _ => Err(Error::UnknownRegion.into()),
-            },
-            None => Err(Error::UnknownRegion.into()),
+    pub fn from_name(name: &str) -> Result<Self> {
+        if name.eq_ignore_ascii_case("all") {
+            return Ok(Region::ALL);
         }
+
+        RegionTable::global()
+            .by_name
+            .get(&name.to_ascii_lowercase())
+            .map(|id| Region(*id))
+            .ok_or_else(|| Error::UnknownRegion.into())
+    }
+
+    pub fn guess_from(webpage: &Webpage) -> Result<Self> {
+        RegionTable::global()
+            .guess(
+                webpage.html.declared_lang().as_deref(),
+                webpage.html.url().tld(),
+                webpage.html.lang().copied(),
+            )
+            .ok_or_else(|| Error::UnknownRegion.into())
     }
 
-    pub fn from_id(doc: u64) -> Self {
-        ALL_REGIONS[doc as usize]
+    pub fn from_id(id: u64) -> Self {
+        Region(id)
     }
 
+    /// The language of the region if it only has a single language.
     pub fn lang(&self) -> Option<whatlang::Lang> {
-        match self {
-            Region::Denmark => Some(whatlang::Lang::Dan),
-            Region::France => Some(whatlang::Lang::Fra),
-            Region::Germany => Some(whatlang::Lang::Deu),
-            Region::Spain => Some(whatlang::Lang::Spa),
-            Region::US => Some(whatlang::Lang::Eng),
-            Region::All => None,
+        match RegionTable::global().get(self)?.languages.as_slice() {
+            [lang] => Some(lang.as_whatlang()),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match RegionTable::global().get(self) {
+            Some(entry) => write!(f, "{}", entry.name),
+            None if *self == Region::ALL => write!(f, "All"),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl serde::Serialize for Region {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Region {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+
+        // regions missing from the installed table are serialized by their id
+        Region::from_name(&name)
+            .ok()
+            .or_else(|| name.parse().ok().map(Region))
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown region: {name}")))
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct RegionEntry {
+    pub id: u64,
+    pub name: String,
+    pub display_name: String,
+    pub gl: String,
+    #[serde(default)]
+    pub tlds: Vec<String>,
+    #[serde(default)]
+    pub languages: Vec<Lang>,
+    #[serde(default)]
+    pub hreflang: Vec<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct RegionFile {
+    #[serde(rename = "region", default)]
+    regions: Vec<RegionEntry>,
+}
+
+/// Maps country code top-level domains, languages and hreflang tags to regions.
+/// The default table (`regions.toml`) is embedded in the binary, but a different
+/// table can be installed at startup with [`RegionTable::install`].
+#[derive(Debug, Clone)]
+pub struct RegionTable {
+    regions: Vec<RegionEntry>,
+    by_id: HashMap<u64, usize>,
+    by_name: HashMap<String, u64>,
+    by_gl: HashMap<String, u64>,
+    by_tld: HashMap<String, u64>,
+    by_lang: HashMap<Lang, u64>,
+    by_hreflang: HashMap<String, u64>,
+}
+
+impl Default for RegionTable {
+    fn default() -> Self {
+        Self::parse(DEFAULT_REGIONS).expect("default region table should be valid")
+    }
+}
+
+fn insert_unique<K>(map: &mut HashMap<K, u64>, key: K, id: u64) -> Result<()>
+where
+    K: std::hash::Hash + Eq + std::fmt::Debug + Clone,
+{
+    match map.insert(key.clone(), id) {
+        Some(other) if other != id => Err(anyhow::anyhow!(
+            "{key:?} is used by both region {other} and region {id}"
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_ascii_lowercase().replace('_', "-")
+}
+
+impl RegionTable {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(toml: &str) -> Result<Self> {
+        let file: RegionFile = toml::from_str(toml)?;
+        Self::new(file.regions)
+    }
+
+    pub fn new(regions: Vec<RegionEntry>) -> Result<Self> {
+        let mut table = Self {
+            regions: Vec::with_capacity(regions.len()),
+            by_id: HashMap::new(),
+            by_name: HashMap::new(),
+            by_gl: HashMap::new(),
+            by_tld: HashMap::new(),
+            by_lang: HashMap::new(),
+            by_hreflang: HashMap::new(),
+        };
+
+        for entry in regions {
+            let id = entry.id;
+
+            if id == Region::ALL.id() {
+                return Err(anyhow::anyhow!(
+                    "region id {id} is reserved for all regions"
+                ));
+            }
+
+            if table.by_id.contains_key(&id) {
+                return Err(anyhow::anyhow!("duplicate region id {id}"));
+            }
+
+            insert_unique(&mut table.by_name, entry.name.to_ascii_lowercase(), id)?;
+            insert_unique(&mut table.by_gl, entry.gl.clone(), id)?;
+
+            for tld in &entry.tlds {
+                let tld = tld.trim_start_matches('.').to_ascii_lowercase();
+                insert_unique(&mut table.by_tld, tld, id)?;
+            }
+
+            for lang in &entry.languages {
+                insert_unique(&mut table.by_lang, *lang, id)?;
+            }
+
+            for tag in &entry.hreflang {
+                insert_unique(&mut table.by_hreflang, normalize_tag(tag), id)?;
+            }
+
+            table.by_id.insert(id, table.regions.len());
+            table.regions.push(entry);
         }
+
+        Ok(table)
+    }
+
+    /// Install the table used to resolve regions. This must happen before regions
+    /// are used for the first time, as the default table is installed otherwise.
+    pub fn install(self) -> Result<()> {
+        REGION_TABLE
+            .set(self)
+            .map_err(|_| anyhow::anyhow!("a region table has already been installed"))
+    }
+
+    /// Install the table at `path` if one is configured.
+    pub fn install_from_path(path: Option<&str>) -> Result<()> {
+        match path {
+            Some(path) => Self::open(path)?.install(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn global() -> &'static RegionTable {
+        REGION_TABLE.get_or_init(RegionTable::default)
+    }
+
+    pub fn get(&self, region: &Region) -> Option<&RegionEntry> {
+        self.by_id.get(&region.0).map(|idx| &self.regions[*idx])
+    }
+
+    /// Guess the region of a page from the language tag it declares (e.g. `de-at`),
+    /// the public suffix of its domain and the language detected from its text.
+    /// A declared tag with a country takes precedence over the country code top-level
+    /// domain, which in turn takes precedence over the language alone.
+    pub fn guess(
+        &self,
+        declared_lang: Option<&str>,
+        tld: Option<&str>,
+        detected_lang: Option<whatlang::Lang>,
+    ) -> Option<Region> {
+        let declared_lang = declared_lang.map(normalize_tag);
+
+        let with_country = declared_lang
+            .as_deref()
+            .and_then(|tag| tag.rsplit_once('-'))
+            .and_then(|(_, country)| {
+                let tag = declared_lang.as_deref()?;
+
+                self.by_hreflang
+                    .get(tag)
+                    .or_else(|| self.by_tld.get(country))
+                    .copied()
+            });
+
+        // the country code is the last label of suffixes like `co.uk`
+        let from_tld = || {
+            let tld = tld?.rsplit('.').next()?.to_ascii_lowercase();
+            self.by_tld.get(&tld).copied()
+        };
+
+        let without_country = || {
+            let tag = declared_lang.as_deref()?;
+            let lang = tag.split('-').next()?;
+
+            self.by_hreflang
+                .get(tag)
+                .or_else(|| self.by_lang.get(&Lang::from_code(lang)?))
+                .copied()
+        };
+
+        let from_detected = || self.by_lang.get(&Lang::from(detected_lang?)).copied();
+
+        with_country
+            .or_else(from_tld)
+            .or_else(without_country)
+            .or_else(from_detected)
+            .map(Region)
     }
 }
 
@@ -233,26 +433,166 @@ mod tests {
 
     use super::*;
 
+    fn region(name: &str) -> Region {
+        Region::from_name(name).unwrap()
+    }
+
     #[test]
     fn simple() {
         let mut a = RegionCount::open(gen_temp_path().join("region_count.json"));
 
-        a.increment(&Region::Denmark);
-        a.increment(&Region::Denmark);
-        a.increment(&Region::US);
+        a.increment(&region("Denmark"));
+        a.increment(&region("Denmark"));
+        a.increment(&region("US"));
 
         let mut b = RegionCount::open(gen_temp_path().join("region_count.json"));
 
-        b.increment(&Region::US);
-        b.increment(&Region::Germany);
+        b.increment(&region("US"));
+        b.increment(&region("Japan"));
 
         a.merge(b);
 
-        assert_eq!(a.map.get(&Region::Denmark), Some(&2));
-        assert_eq!(a.map.get(&Region::US), Some(&2));
-        assert_eq!(a.map.get(&Region::Germany), Some(&1));
+        assert_eq!(a.map.get(&region("Denmark")), Some(&2));
+        assert_eq!(a.map.get(&region("US")), Some(&2));
+        assert_eq!(a.map.get(&region("Japan")), Some(&1));
 
-        assert_eq!(a.score(&Region::Denmark), 0.4);
-        assert_eq!(a.score(&Region::France), 0.0);
+        assert_eq!(a.score(&region("Denmark")), 0.4);
+        assert_eq!(a.score(&region("France")), 0.0);
+    }
+
+    #[test]
+    fn serialization_is_backwards_compatible() {
+        for (name, id, gl) in [
+            ("All", 0, "all"),
+            ("Denmark", 1, "dk"),
+            ("France", 2, "fr"),
+            ("Germany", 3, "ger"),
+            ("Spain", 4, "spa"),
+            ("US", 5, "us"),
+        ] {
+            let region = Region::from_name(name).unwrap();
+
+            assert_eq!(region.id(), id);
+            assert_eq!(region.gl(), gl);
+            assert_eq!(Region::from_gl(gl).unwrap(), region);
+            assert_eq!(
+                serde_json::to_string(&region).unwrap(),
+                format!("\"{name}\"")
+            );
+            assert_eq!(
+                serde_json::from_str::<Region>(&format!("\"{name}\"")).unwrap(),
+                region
+            );
+        }
+
+        assert!(Region::from_gl("xx").is_err());
+        assert!(Region::all().len() > 6);
+    }
+
+    #[test]
+    fn guess() {
+        let table = RegionTable::global();
+
+        assert_eq!(
+            table.guess(Some("de-AT"), Some("com"), Some(whatlang::Lang::Deu)),
+            Some(region("Austria"))
+        );
+        assert_eq!(
+            table.guess(Some("en"), Some("co.uk"), Some(whatlang::Lang::Eng)),
+            Some(region("UK"))
+        );
+        assert_eq!(
+            table.guess(Some("pt"), Some("com"), None),
+            Some(region("Brazil"))
+        );
+        assert_eq!(
+            table.guess(None, Some("com"), Some(whatlang::Lang::Jpn)),
+            Some(region("Japan"))
+        );
+        assert_eq!(table.guess(Some("x-klingon"), Some("com"), None), None);
+    }
+
+    #[test]
+    fn custom_table() {
+        let table = RegionTable::parse(
+            r#"
+            [[region]]
+            id = 1
+            name = "Denmark"
+            display_name = "Denmark"
+            gl = "dk"
+            tlds = ["dk", "gl"]
+            languages = ["da"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(table.guess(None, Some("gl"), None), Some(Region(1)));
+        assert_eq!(table.guess(None, Some("de"), None), None);
+
+        assert!(RegionTable::parse(
+            r#"
+            [[region]]
+            id = 0
+            name = "Everywhere"
+            display_name = "Everywhere"
+            gl = "ev"
+            "#,
+        )
+        .is_err());
+
+        assert!(RegionTable::parse(
+            r#"
+            [[region]]
+            id = 1
+            name = "Denmark"
+            display_name = "Denmark"
+            gl = "dk"
+            tlds = ["dk"]
+
+            [[region]]
+            id = 2
+            name = "Greenland"
+            display_name = "Greenland"
+            gl = "gl"
+            tlds = ["dk"]
+            "#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn declared_lang() {
+        let webpage = Webpage::test_parse(
+            r#"
+            <html lang="de-AT">
+                <head><title>Test</title></head>
+                <body>Ein Test</body>
+            </html>
+            "#,
+            "https://www.example.com",
+        )
+        .unwrap();
+
+        assert_eq!(webpage.html.declared_lang().as_deref(), Some("de-at"));
+        assert_eq!(Region::guess_from(&webpage).unwrap(), region("Austria"));
+
+        let webpage = Webpage::test_parse(
+            r#"
+            <html>
+                <head>
+                    <title>Test</title>
+                    <link rel="alternate" hreflang="en-gb" href="https://www.example.com/" />
+                    <link rel="alternate" hreflang="en-us" href="https://www.example.com/us" />
+                </head>
+                <body>A test</body>
+            </html>
+            "#,
+            "https://www.example.com",
+        )
+        .unwrap();
+
+        assert_eq!(webpage.html.declared_lang().as_deref(), Some("en-gb"));
+        assert_eq!(Region::guess_from(&webpage).unwrap(), region("UK"));
     }
 }
@@ -36,6 +36,7 @@ pub trait UrlExt {
     fn subdomain(&self) -> Option<&str>;
     fn is_homepage(&self) -> bool;
     fn tld(&self) -> Option<&str>;
+    fn file_extension(&self) -> Option<&str>;
 }
 
 impl UrlExt for url::Url {
@@ -105,6 +106,19 @@ impl UrlExt for url::Url {
         let suffix = std::str::from_utf8(ICANN_LIST.suffix(host.as_bytes())?.as_bytes()).ok()?;
         Some(suffix)
     }
+
+    fn file_extension(&self) -> Option<&str> {
+        let (_, extension) = self.path_segments()?.last()?.rsplit_once('.')?;
+
+        if extension.is_empty()
+            || extension.len() > 8
+            || !extension.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return None;
+        }
+
+        Some(extension)
+    }
 }
 
 #[cfg(test)]
@@ -141,4 +155,19 @@ mod tests {
         let url: Url = Url::parse("http://example.com").unwrap();
         assert_eq!(url.tld().unwrap(), "com");
     }
+
+    #[test]
+    fn file_extension() {
+        let url: Url = Url::parse("http://example.com/papers/report.PDF?page=2").unwrap();
+        assert_eq!(url.file_extension(), Some("PDF"));
+
+        let url: Url = Url::parse("http://example.com/index.html").unwrap();
+        assert_eq!(url.file_extension(), Some("html"));
+
+        let url: Url = Url::parse("http://example.com/v1.2/docs").unwrap();
+        assert_eq!(url.file_extension(), None);
+
+        let url: Url = Url::parse("http://example.com/").unwrap();
+        assert_eq!(url.file_extension(), None);
+    }
 }
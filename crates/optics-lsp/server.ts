@@ -15,6 +15,12 @@ const ls = new OpticsBackend(sendDiagnosticsCallback);
 
 connection.onNotification((...args) => ls.onNotification(...args));
 connection.onHover((params) => ls.onHover(params));
+connection.onDefinition((params) => ls.onGotoDefinition(params));
+connection.onDocumentFormatting((params) => ls.onDocumentFormatting(params));
+connection.onCompletion((params) => ls.onCompletion(params));
+connection.onDocumentSymbol((params) => ls.onDocumentSymbol(params));
+connection.onPrepareRename((params) => ls.onPrepareRename(params));
+connection.onRenameRequest((params) => ls.onRename(params));
 
 connection.onInitialize(() => {
     return {
@@ -25,6 +31,15 @@ connection.onInitialize(() => {
                 change: TextDocumentSyncKind.Full,
             },
             hoverProvider: true,
+            definitionProvider: true,
+            documentFormattingProvider: true,
+            completionProvider: {
+                triggerCharacters: ['"', '(', '{'],
+            },
+            documentSymbolProvider: true,
+            renameProvider: {
+                prepareProvider: true,
+            },
         },
     };
 });
@@ -0,0 +1,251 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use lsp_types::{CompletionItem, CompletionItemKind, Documentation, MarkupContent, MarkupKind};
+use optics::Token;
+
+use crate::{docs, schema_org};
+
+const BLOCKS: &[Token<'static>] = &[
+    Token::Rule,
+    Token::Like,
+    Token::Dislike,
+    Token::DiscardNonMatching,
+    Token::Import,
+    Token::RuleSet,
+];
+
+const RULE: &[Token<'static>] = &[Token::Matches, Token::Action];
+
+const MATCH_LOCATIONS: &[Token<'static>] = &[
+    Token::Site,
+    Token::Url,
+    Token::Domain,
+    Token::Title,
+    Token::Description,
+    Token::Content,
+    Token::MicroformatTag,
+    Token::Schema,
+    Token::LastUpdated,
+    Token::LikelyHasAds,
+    Token::LikelyHasPaywall,
+    Token::TrackerScore,
+    Token::HostCentrality,
+];
+
+const ACTIONS: &[Token<'static>] = &[
+    Token::Boost,
+    Token::Downrank,
+    Token::Multiply,
+    Token::SetSignal,
+    Token::Discard,
+];
+
+const HOST_PREFERENCE: &[Token<'static>] = &[Token::Site];
+
+const SET_SIGNAL: &[Token<'static>] = &[Token::Signal];
+
+const BOOLS: &[Token<'static>] = &[Token::True, Token::False];
+
+/// Where in the optic the cursor is.
+struct Context<'a> {
+    /// The keyword that opened the innermost `{` or `(` around the cursor.
+    /// `None` if the cursor is at the top level of the optic.
+    enclosing: Option<Option<Token<'a>>>,
+    /// Whether the cursor is inside a string.
+    in_string: bool,
+}
+
+impl<'a> Context<'a> {
+    fn new(source: &'a str, offset: usize) -> Self {
+        let mut stack: Vec<Option<Token<'a>>> = Vec::new();
+        let mut last_keyword = None;
+        let mut in_string = false;
+
+        for (start, token, end) in optics::lex(source).filter_map(|elem| elem.ok()) {
+            // the span of string tokens excludes the quotes
+            if let Token::String(_) = token {
+                if start <= offset && offset <= end {
+                    in_string = true;
+                    break;
+                }
+            }
+
+            if start >= offset {
+                break;
+            }
+
+            match token {
+                Token::OpenParenthesis => stack.push(last_keyword.clone()),
+                Token::OpenBracket => stack.push(last_keyword.take()),
+                Token::CloseParenthesis => {
+                    stack.pop();
+                }
+                Token::CloseBracket => {
+                    stack.pop();
+                    last_keyword = None;
+                }
+                Token::SemiColon | Token::Comma => last_keyword = None,
+                Token::String(_)
+                | Token::Number(_)
+                | Token::True
+                | Token::False
+                | Token::Less
+                | Token::LessOrEqual
+                | Token::Greater
+                | Token::GreaterOrEqual
+                | Token::Equal => {}
+                keyword => last_keyword = Some(keyword),
+            }
+        }
+
+        Self {
+            enclosing: stack.pop(),
+            in_string,
+        }
+    }
+}
+
+fn keyword_item(token: &Token<'_>) -> CompletionItem {
+    CompletionItem {
+        label: token.to_string(),
+        kind: Some(CompletionItemKind::KEYWORD),
+        documentation: docs::token_docs(token).map(|docs| {
+            Documentation::MarkupContent(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: docs.to_string(),
+            })
+        }),
+        ..Default::default()
+    }
+}
+
+fn schema_item(name: &str, in_string: bool) -> CompletionItem {
+    CompletionItem {
+        label: name.to_string(),
+        kind: Some(CompletionItemKind::CLASS),
+        detail: Some(format!("https://schema.org/{name}")),
+        insert_text: (!in_string).then(|| format!("\"{name}\"")),
+        ..Default::default()
+    }
+}
+
+/// The completions for the cursor at `offset` in `source`.
+pub fn completions(source: &str, offset: usize) -> Vec<CompletionItem> {
+    let context = Context::new(source, offset);
+
+    if let Some(Some(Token::Schema)) = context.enclosing {
+        return schema_org::TYPES
+            .iter()
+            .map(|name| schema_item(name, context.in_string))
+            .collect();
+    }
+
+    if context.in_string {
+        return Vec::new();
+    }
+
+    let keywords = match context.enclosing {
+        None | Some(Some(Token::RuleSet)) => BLOCKS,
+        Some(Some(Token::Rule)) => RULE,
+        Some(Some(Token::Matches)) => MATCH_LOCATIONS,
+        Some(Some(Token::Action)) => ACTIONS,
+        Some(Some(Token::Like | Token::Dislike)) => HOST_PREFERENCE,
+        Some(Some(Token::SetSignal)) => SET_SIGNAL,
+        Some(Some(Token::LikelyHasAds | Token::LikelyHasPaywall)) => BOOLS,
+        Some(_) => &[],
+    };
+
+    keywords.iter().map(keyword_item).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Completions at the position of `|` in `source`.
+    fn labels(source: &str) -> Vec<String> {
+        let offset = source.find('|').unwrap();
+        let source = source.replace('|', "");
+
+        completions(&source, offset)
+            .into_iter()
+            .map(|item| item.label)
+            .collect()
+    }
+
+    fn keywords(tokens: &[Token<'_>]) -> Vec<String> {
+        tokens.iter().map(|token| token.to_string()).collect()
+    }
+
+    #[test]
+    fn blocks() {
+        assert_eq!(labels("|"), keywords(BLOCKS));
+        assert_eq!(labels("Like(Site(\"a.com\")); |"), keywords(BLOCKS));
+        assert_eq!(labels("RuleSet(\"blogs\") { | }"), keywords(BLOCKS));
+    }
+
+    #[test]
+    fn rules() {
+        assert_eq!(labels("Rule { | }"), keywords(RULE));
+        assert_eq!(labels("Rule { Matches { | } }"), keywords(MATCH_LOCATIONS));
+        assert_eq!(
+            labels("Rule { Matches { Site(\"a.com\"), | } }"),
+            keywords(MATCH_LOCATIONS)
+        );
+        assert_eq!(
+            labels("Rule { Matches { Site(\"a.com\") }, Action(|) }"),
+            keywords(ACTIONS)
+        );
+        assert_eq!(
+            labels("Rule { Matches { LikelyHasAds(|) } }"),
+            keywords(BOOLS)
+        );
+        assert_eq!(
+            labels("Rule { Matches { Site(\"a.com\") }, Action(SetSignal(|)) }"),
+            keywords(SET_SIGNAL)
+        );
+        assert_eq!(labels("Like(|)"), keywords(HOST_PREFERENCE));
+    }
+
+    #[test]
+    fn strings() {
+        assert!(labels("Like(Site(\"a.c|om\"))").is_empty());
+        assert!(labels("Rule { Matches { Site(\"|\") } }").is_empty());
+    }
+
+    #[test]
+    fn schemas() {
+        let source = "Rule { Matches { Schema(|) } }";
+        let offset = source.find('|').unwrap();
+        let items = completions(&source.replace('|', ""), offset);
+
+        assert_eq!(items.len(), schema_org::TYPES.len());
+        assert_eq!(items[0].label, schema_org::TYPES[0]);
+        assert_eq!(
+            items[0].insert_text,
+            Some(format!("\"{}\"", schema_org::TYPES[0]))
+        );
+
+        // the quotes are already there when completing inside a string
+        let source = "Rule { Matches { Schema(\"Blog|\") } }";
+        let offset = source.find('|').unwrap();
+        let items = completions(&source.replace('|', ""), offset);
+
+        assert_eq!(items.len(), schema_org::TYPES.len());
+        assert!(items.iter().all(|item| item.insert_text.is_none()));
+    }
+}
@@ -64,6 +64,20 @@ pub fn token_docs(token: &optics::Token) -> Option<&'static str> {
         As an example, `Schema(\"BlogPosting\")` matches all pages that contains the https://schema.org/BlogPosting entity. Note that `Schema` \
         does not support the pattern syntax, but only simple strings.",
 
+        optics::Token::LastUpdated => "`LastUpdated(> \"YYYY-MM-DD\")` matches any search result that was last updated after (`>`, `>=`), before (`<`, `<=`) \
+        or on (`=`) the specified date. Results where the update time is unknown never match.",
+
+        optics::Token::LikelyHasAds => "`LikelyHasAds(true)` matches any search result that likely contains ads. Use `LikelyHasAds(false)` to match results without ads.",
+
+        optics::Token::LikelyHasPaywall => "`LikelyHasPaywall(true)` matches any search result that is likely behind a paywall. \
+        Use `LikelyHasPaywall(false)` to match results without a paywall.",
+
+        optics::Token::TrackerScore => "`TrackerScore(< 5)` matches any search result where the number of trackers on the page satisfies the comparison. \
+        The comparison can be one of `<`, `<=`, `>`, `>=` and `=`.",
+
+        optics::Token::HostCentrality => "`HostCentrality(> 0.1)` matches any search result where the centrality of the host satisfies the comparison. \
+        The centrality is a number between 0 and 1 that indicates how central the host is in the webgraph.",
+
         optics::Token::Ranking => "When results are ranked we take a weighted sum of various signals to give each webpage a score for the specific query. \
         The top scored results are then presented to the user. `Ranking` allows you to alter the weight of all the `Signal`s and text `Field`s.",
 
@@ -75,12 +89,20 @@ pub fn token_docs(token: &optics::Token) -> Option<&'static str> {
         Some supported fields are e.g. \"title\", \"body\", \"backlink_text\" and \"site\". \
         A complete list of available fields can be seen in the code (https://github.com/StractOrg/Stract/blob/main/src/schema.rs)",
 
-        optics::Token::Action => "`Action` defines which action should be applied to the matching search result. The result can either be boosted, downranked or discarded.",
+        optics::Token::Action => "`Action` defines which action should be applied to the matching search result. The result can either be boosted, downranked, \
+        have its score multiplied, have the weight of a ranking signal changed or be discarded.",
 
-        optics::Token::Boost => "`Boost(...)` boosts the search result by the number specified in `...`.",
+        optics::Token::Boost => "`Boost(...)` boosts the search result by the number specified in `...`. The number can be fractional, e.g. `Boost(1.5)`.",
 
         optics::Token::Downrank => "`Downrank(...)` downranks the search result by the number specified in `...`. A higher number further downranks the search result.",
 
+        optics::Token::Multiply => "`Multiply(...)` multiplies the score of the search result by the number specified in `...`. \
+        A number below 1 lowers the score (e.g. `Multiply(0.3)`) and a number above 1 raises it.",
+
+        optics::Token::SetSignal => "`SetSignal(Signal(\"...\"), ...)` sets the coefficient of a ranking signal to the number specified in the second `...` for the matching search results. \
+        For example, `SetSignal(Signal(\"host_centrality\"), 2.0)` makes the centrality of the host twice as important for the matching results. \
+        When an optic sets signal coefficients, the results are ranked by the weighted sum of the signals instead of the learned ranking model.",
+
         optics::Token::Discard => "`Discard` discards the matching search result completely from the results page.",
 
         optics::Token::Like => "`Like(Site(...))` lets you like specific hosts. During ranking, we will calculate a centrality meassure from all you liked sites \
@@ -91,6 +113,13 @@ pub fn token_docs(token: &optics::Token) -> Option<&'static str> {
         so results that are heavily linked to from your disliked sites will be downranked. Note therefore, that `Dislike` not only alters the ranking of the specifc site, \
         but also sites that are heavily linked to from the disliked site.",
 
+        optics::Token::Import => "`Import(\"...\")` includes the rules, likes and dislikes from another optic or rule set. \
+        If a `RuleSet` with the given name is defined in the optic, the rules from that rule set are imported. \
+        Otherwise the name refers to another optic file relative to this one (the `.optic` extension can be left out).",
+
+        optics::Token::RuleSet => "`RuleSet(\"...\") { ... }` defines a named group of rules, likes and dislikes. \
+        The rule set is only applied to the search results when it is imported using `Import(\"...\")`.",
+
         _ => return None,
     })
 }
@@ -14,18 +14,29 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod completion;
 mod docs;
+mod schema_org;
 
-use std::collections::HashMap;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+};
 
 use itertools::Itertools;
 use lsp_types::{
     notification::{DidChangeTextDocument, DidOpenTextDocument, Notification},
-    Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams, DidOpenTextDocumentParams, Hover,
-    HoverContents, HoverParams, LanguageString, MarkedString, Position, PublishDiagnosticsParams,
-    Range, Url,
+    CompletionParams, CompletionResponse, Diagnostic, DiagnosticSeverity, DiagnosticTag,
+    DidChangeTextDocumentParams, DidOpenTextDocumentParams, DocumentFormattingParams,
+    DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, LanguageString, Location,
+    MarkedString, Position, PrepareRenameResponse, PublishDiagnosticsParams, Range, RenameParams,
+    SymbolKind, TextDocumentPositionParams, TextEdit, Url, WorkspaceEdit,
+};
+use optics::{
+    ast::{RawHostPreference, RawOptic},
+    Optic, OpticLoader,
 };
-use optics::Optic;
 use thiserror::Error;
 use wasm_bindgen::prelude::*;
 
@@ -58,15 +69,31 @@ impl From<Error> for JsValue {
 struct File {
     source: String,
     optic: Result<Optic, optics::Error>,
+    /// The urls of the optics this file tried to import, directly or through other imports.
+    imports: HashSet<Url>,
 }
 impl File {
-    fn new(source: String) -> Self {
+    fn new(source: String, loader: &dyn OpticLoader) -> Self {
         File {
-            optic: optics::parse(&source),
+            optic: optics::parse_with_loader(&source, loader),
             source,
+            imports: HashSet::new(),
         }
     }
 
+    /// Parse the optic at `url` and resolve its imports from the other open files.
+    fn parse(url: &Url, source: String, files: &HashMap<Url, File>) -> Self {
+        let loader = OpenFiles {
+            base: url,
+            files,
+            tried: RefCell::default(),
+        };
+
+        let mut file = File::new(source, &loader);
+        file.imports = loader.tried.into_inner();
+        file
+    }
+
     fn error(&self) -> Option<optics::Error> {
         if let Err(err) = &self.optic {
             Some(err.clone())
@@ -83,6 +110,107 @@ impl File {
         self.tokens()
             .find(|(start, _, end)| offset >= *start && offset <= *end)
     }
+
+    /// The span of the site inside a `Like(...)` or `Dislike(...)`, excluding the quotes.
+    fn site_span(&self, pref: &RawHostPreference) -> Option<(usize, usize)> {
+        let (pref_start, pref_end) = pref.span();
+
+        self.tokens().find_map(|(start, token, end)| {
+            (matches!(token, optics::Token::String(_)) && start >= pref_start && end <= pref_end)
+                .then_some((start, end))
+        })
+    }
+
+    /// The liked or disliked site at `offset` and its span.
+    fn site_at_offset(&self, offset: usize) -> Option<(String, (usize, usize))> {
+        let raw = optics::ast::parse(&self.source).ok()?;
+
+        raw.all_host_preferences().into_iter().find_map(|pref| {
+            let (start, end) = self.site_span(pref)?;
+            (offset >= start && offset <= end).then(|| (pref.site().to_string(), (start, end)))
+        })
+    }
+
+    /// Rename the site at `offset` in all the `Like` and `Dislike` blocks where it occurs.
+    fn rename_site(&self, offset: usize, new_name: &str) -> Option<Vec<TextEdit>> {
+        let (site, _) = self.site_at_offset(offset)?;
+        let raw = optics::ast::parse(&self.source).ok()?;
+
+        Some(
+            raw.all_host_preferences()
+                .into_iter()
+                .filter(|pref| pref.site() == site)
+                .filter_map(|pref| self.site_span(pref))
+                .map(|span| TextEdit {
+                    range: span_to_range(span, &self.source),
+                    new_text: new_name.to_string(),
+                })
+                .collect(),
+        )
+    }
+}
+
+/// The urls an import of `name` in the optic at `base` can refer to.
+fn import_candidates(base: &Url, name: &str) -> Vec<Url> {
+    [name.to_string(), format!("{name}.optic")]
+        .iter()
+        .filter_map(|name| base.join(name).ok())
+        .collect()
+}
+
+/// Loads imports from the files that are open in the editor.
+/// Imports are relative to the optic they are written in.
+struct OpenFiles<'a> {
+    base: &'a Url,
+    files: &'a HashMap<Url, File>,
+    /// Every url an import was looked up at, so the importing file can be
+    /// parsed again when one of them is opened or changed.
+    tried: RefCell<HashSet<Url>>,
+}
+
+impl OpticLoader for OpenFiles<'_> {
+    fn load(&self, name: &str) -> Option<String> {
+        let candidates = import_candidates(self.base, name);
+        self.tried.borrow_mut().extend(candidates.iter().cloned());
+
+        // we can't read files from disk, so imports of optics that are
+        // not open in the editor are reported as unresolved.
+        candidates
+            .into_iter()
+            .find_map(|url| self.files.get(&url).map(|file| file.source.clone()))
+    }
+
+    fn resolve(&self, importer: &str, import: &str) -> String {
+        let base = match importer {
+            "" => Some(self.base.clone()),
+            importer => Url::parse(importer).ok(),
+        };
+
+        base.and_then(|base| base.join(import).ok())
+            .map(|url| url.to_string())
+            .unwrap_or_else(|| import.to_string())
+    }
+}
+
+/// Store the new `source` of the file at `url` and parse the open files that import it again.
+/// Returns the urls of the files that were parsed again.
+fn update_file(files: &mut HashMap<Url, File>, url: Url, source: String) -> Vec<Url> {
+    let file = File::parse(&url, source, files);
+    files.insert(url.clone(), file);
+
+    let importers: Vec<Url> = files
+        .iter()
+        .filter(|(other, file)| **other != url && file.imports.contains(&url))
+        .map(|(other, _)| other.clone())
+        .collect();
+
+    for importer in &importers {
+        let source = files[importer].source.clone();
+        let file = File::parse(importer, source, files);
+        files.insert(importer.clone(), file);
+    }
+
+    importers
 }
 
 #[wasm_bindgen]
@@ -110,18 +238,16 @@ impl OpticsBackend {
                 let DidOpenTextDocumentParams { text_document } =
                     serde_wasm_bindgen::from_value(params).unwrap();
 
-                self.handle_change(text_document.uri.clone(), text_document.text);
-                self.send_diagnostics(text_document.uri);
+                self.handle_change(text_document.uri, text_document.text);
             }
             DidChangeTextDocument::METHOD => {
                 let params: DidChangeTextDocumentParams =
                     serde_wasm_bindgen::from_value(params).unwrap();
 
                 self.handle_change(
-                    params.text_document.uri.clone(),
+                    params.text_document.uri,
                     params.content_changes[0].text.clone(),
                 );
-                self.send_diagnostics(params.text_document.uri);
             }
             _ => log(&format!("on_notification {method} {params:?}")),
         }
@@ -134,6 +260,64 @@ impl OpticsBackend {
 
         Ok(serde_wasm_bindgen::to_value(&self.handle_hover(params))?)
     }
+
+    #[wasm_bindgen(js_name = onGotoDefinition)]
+    pub fn on_goto_definition(&mut self, params: JsValue) -> Result<JsValue, Error> {
+        log(&format!("on_goto_definition {params:?}"));
+        let params: GotoDefinitionParams = serde_wasm_bindgen::from_value(params).unwrap();
+
+        Ok(serde_wasm_bindgen::to_value(
+            &self.handle_goto_definition(params),
+        )?)
+    }
+
+    #[wasm_bindgen(js_name = onDocumentFormatting)]
+    pub fn on_document_formatting(&mut self, params: JsValue) -> Result<JsValue, Error> {
+        log(&format!("on_document_formatting {params:?}"));
+        let params: DocumentFormattingParams = serde_wasm_bindgen::from_value(params).unwrap();
+
+        Ok(serde_wasm_bindgen::to_value(
+            &self.handle_document_formatting(params),
+        )?)
+    }
+
+    #[wasm_bindgen(js_name = onCompletion)]
+    pub fn on_completion(&mut self, params: JsValue) -> Result<JsValue, Error> {
+        log(&format!("on_completion {params:?}"));
+        let params: CompletionParams = serde_wasm_bindgen::from_value(params).unwrap();
+
+        Ok(serde_wasm_bindgen::to_value(
+            &self.handle_completion(params),
+        )?)
+    }
+
+    #[wasm_bindgen(js_name = onDocumentSymbol)]
+    pub fn on_document_symbol(&mut self, params: JsValue) -> Result<JsValue, Error> {
+        log(&format!("on_document_symbol {params:?}"));
+        let params: DocumentSymbolParams = serde_wasm_bindgen::from_value(params).unwrap();
+
+        Ok(serde_wasm_bindgen::to_value(
+            &self.handle_document_symbol(params),
+        )?)
+    }
+
+    #[wasm_bindgen(js_name = onPrepareRename)]
+    pub fn on_prepare_rename(&mut self, params: JsValue) -> Result<JsValue, Error> {
+        log(&format!("on_prepare_rename {params:?}"));
+        let params: TextDocumentPositionParams = serde_wasm_bindgen::from_value(params).unwrap();
+
+        Ok(serde_wasm_bindgen::to_value(
+            &self.handle_prepare_rename(params),
+        )?)
+    }
+
+    #[wasm_bindgen(js_name = onRename)]
+    pub fn on_rename(&mut self, params: JsValue) -> Result<JsValue, Error> {
+        log(&format!("on_rename {params:?}"));
+        let params: RenameParams = serde_wasm_bindgen::from_value(params).unwrap();
+
+        Ok(serde_wasm_bindgen::to_value(&self.handle_rename(params))?)
+    }
 }
 
 impl OpticsBackend {
@@ -167,24 +351,149 @@ impl OpticsBackend {
             })
     }
 
+    fn handle_goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Option<GotoDefinitionResponse> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let file = self.files.get(&uri)?;
+        let offset =
+            position_to_byte_offset(&params.text_document_position_params.position, &file.source)?;
+
+        let raw = optics::ast::parse(&file.source).ok()?;
+        let import = raw
+            .all_imports()
+            .into_iter()
+            .find(|import| offset >= import.span.0 && offset <= import.span.1)?;
+
+        if let Some(rule_set) = raw
+            .all_rule_sets()
+            .into_iter()
+            .find(|rule_set| rule_set.name == import.name)
+        {
+            return Some(GotoDefinitionResponse::Scalar(Location {
+                uri,
+                range: Range {
+                    start: offset_to_pos(rule_set.span.0, &file.source),
+                    end: offset_to_pos(rule_set.span.1, &file.source),
+                },
+            }));
+        }
+
+        let candidates = import_candidates(&uri, &import.name);
+        let target = candidates
+            .iter()
+            .find(|url| self.files.contains_key(url))
+            .or_else(|| candidates.last())?;
+
+        Some(GotoDefinitionResponse::Scalar(Location {
+            uri: target.clone(),
+            range: Range::default(),
+        }))
+    }
+
+    fn handle_document_formatting(
+        &self,
+        params: DocumentFormattingParams,
+    ) -> Option<Vec<TextEdit>> {
+        let file = self.files.get(&params.text_document.uri)?;
+        let formatted = optics::format(&file.source).ok()?;
+
+        if formatted == file.source {
+            return Some(Vec::new());
+        }
+
+        Some(vec![TextEdit {
+            range: Range {
+                start: Position::new(0, 0),
+                end: offset_to_pos(file.source.len(), &file.source),
+            },
+            new_text: formatted,
+        }])
+    }
+
+    fn handle_completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let file = self
+            .files
+            .get(&params.text_document_position.text_document.uri)?;
+
+        // the cursor is often at the very end of the file while typing
+        let offset = position_to_byte_offset(&params.text_document_position.position, &file.source)
+            .unwrap_or(file.source.len());
+
+        Some(CompletionResponse::Array(completion::completions(
+            &file.source,
+            offset,
+        )))
+    }
+
+    fn handle_document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Option<DocumentSymbolResponse> {
+        let file = self.files.get(&params.text_document.uri)?;
+        let raw = optics::ast::parse(&file.source).ok()?;
+
+        Some(DocumentSymbolResponse::Nested(document_symbols(&raw, file)))
+    }
+
+    fn handle_prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Option<PrepareRenameResponse> {
+        let file = self.files.get(&params.text_document.uri)?;
+        let offset = position_to_byte_offset(&params.position, &file.source)?;
+        let (_, span) = file.site_at_offset(offset)?;
+
+        Some(PrepareRenameResponse::Range(span_to_range(
+            span,
+            &file.source,
+        )))
+    }
+
+    fn handle_rename(&self, params: RenameParams) -> Option<WorkspaceEdit> {
+        let uri = params.text_document_position.text_document.uri;
+        let file = self.files.get(&uri)?;
+        let offset =
+            position_to_byte_offset(&params.text_document_position.position, &file.source)?;
+        let edits = file.rename_site(offset, &params.new_name)?;
+
+        Some(WorkspaceEdit {
+            changes: Some([(uri, edits)].into_iter().collect()),
+            ..Default::default()
+        })
+    }
+
     fn handle_change(&mut self, url: Url, source: String) {
-        self.files.insert(url, File::new(source));
+        let importers = update_file(&mut self.files, url.clone(), source);
+
+        self.send_diagnostics(url);
+        for importer in importers {
+            self.send_diagnostics(importer);
+        }
     }
 
     fn send_diagnostics(&self, url: Url) {
         if let Some(f) = self.files.get(&url) {
-            self.send_diagnostic(url, f.error().map(|err| err_to_diagnostic(err, &f.source)));
+            let diagnostics = match f.error() {
+                Some(err) => vec![err_to_diagnostic(err, &f.source)],
+                None => optics::lint(&f.source)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|lint| lint_to_diagnostic(lint, &f.source))
+                    .collect(),
+            };
+
+            self.send_diagnostic(url, diagnostics);
         }
     }
 
-    fn send_diagnostic(&self, url: Url, diagnostic: Option<Diagnostic>) {
+    fn send_diagnostic(&self, url: Url, diagnostics: Vec<Diagnostic>) {
         let this = &JsValue::null();
 
         let params = PublishDiagnosticsParams {
             uri: url,
-            diagnostics: diagnostic
-                .map(|diagnostic| vec![diagnostic])
-                .unwrap_or_default(),
+            diagnostics,
             version: None,
         };
         log(&format!("Sending diagnostic {params:?}"));
@@ -274,6 +583,21 @@ fn err_to_diagnostic(err: optics::Error, source: &str) -> Diagnostic {
                 ..Default::default()
             }
         }
+        optics::Error::DateParse {
+            token: (start, tok, end),
+        } => {
+            let message =
+                format!("Failed to parse token \"{tok}\" as a date (expected YYYY-MM-DD)");
+            Diagnostic {
+                range: Range {
+                    start: offset_to_pos(start, source),
+                    end: offset_to_pos(end, source),
+                },
+                severity: Some(DiagnosticSeverity::ERROR),
+                message,
+                ..Default::default()
+            }
+        }
         optics::Error::Unknown(start, end) => {
             let message = "We encountered an unknown error".to_string();
             Diagnostic {
@@ -298,12 +622,211 @@ fn err_to_diagnostic(err: optics::Error, source: &str) -> Diagnostic {
                 ..Default::default()
             }
         }
+        optics::Error::UnresolvedImport {
+            token: (start, _, end),
+            name,
+        } => {
+            let message = format!("Could not find an optic or rule set named \"{name}\"");
+            Diagnostic {
+                range: Range {
+                    start: offset_to_pos(start, source),
+                    end: offset_to_pos(end, source),
+                },
+                severity: Some(DiagnosticSeverity::ERROR),
+                message,
+                ..Default::default()
+            }
+        }
+        optics::Error::ImportParse {
+            token: (start, _, end),
+            name,
+        } => {
+            let message = format!("The imported optic \"{name}\" contains errors");
+            Diagnostic {
+                range: Range {
+                    start: offset_to_pos(start, source),
+                    end: offset_to_pos(end, source),
+                },
+                severity: Some(DiagnosticSeverity::ERROR),
+                message,
+                ..Default::default()
+            }
+        }
+        optics::Error::ImportCycle {
+            token: (start, _, end),
+            cycle,
+        } => {
+            let message = format!("Import cycle: {}", cycle.iter().join(" -> "));
+            Diagnostic {
+                range: Range {
+                    start: offset_to_pos(start, source),
+                    end: offset_to_pos(end, source),
+                },
+                severity: Some(DiagnosticSeverity::ERROR),
+                message,
+                ..Default::default()
+            }
+        }
+        optics::Error::DuplicateRuleSet {
+            token: (start, _, end),
+            name,
+        } => {
+            let message = format!("The rule set \"{name}\" is defined multiple times");
+            Diagnostic {
+                range: Range {
+                    start: offset_to_pos(start, source),
+                    end: offset_to_pos(end, source),
+                },
+                severity: Some(DiagnosticSeverity::ERROR),
+                message,
+                ..Default::default()
+            }
+        }
+        optics::Error::ImportDepth {
+            token: (start, _, end),
+            limit,
+        } => {
+            let message = format!("Imports can be nested at most {limit} levels deep");
+            Diagnostic {
+                range: Range {
+                    start: offset_to_pos(start, source),
+                    end: offset_to_pos(end, source),
+                },
+                severity: Some(DiagnosticSeverity::ERROR),
+                message,
+                ..Default::default()
+            }
+        }
+        optics::Error::ImportSize {
+            token: (start, _, end),
+            limit,
+        } => {
+            let message =
+                format!("Imports can expand to at most {limit} imports, rules and preferences");
+            Diagnostic {
+                range: Range {
+                    start: offset_to_pos(start, source),
+                    end: offset_to_pos(end, source),
+                },
+                severity: Some(DiagnosticSeverity::ERROR),
+                message,
+                ..Default::default()
+            }
+        }
         optics::Error::RankingStagesMismatch => {
             unreachable!("this error cannot occur at compile time")
         }
     }
 }
 
+fn span_to_range((start, end): (usize, usize), source: &str) -> Range {
+    Range {
+        start: offset_to_pos(start, source),
+        end: offset_to_pos(end, source),
+    }
+}
+
+#[allow(deprecated)]
+fn symbol(
+    name: String,
+    detail: Option<String>,
+    kind: SymbolKind,
+    range: Range,
+    selection_range: Range,
+    children: Option<Vec<DocumentSymbol>>,
+) -> DocumentSymbol {
+    DocumentSymbol {
+        name,
+        detail,
+        kind,
+        tags: None,
+        deprecated: None,
+        range,
+        selection_range,
+        children,
+    }
+}
+
+/// Symbols for the rules, likes, dislikes and rule sets in the optic.
+fn document_symbols(optic: &RawOptic, file: &File) -> Vec<DocumentSymbol> {
+    let mut symbols = Vec::new();
+
+    for rule in &optic.rules {
+        let range = span_to_range(rule.span, &file.source);
+
+        let (name, detail) = match optics::Rule::try_from(rule.clone()) {
+            Ok(parsed) if !parsed.matches.is_empty() => (
+                parsed.matches.iter().flatten().join(", "),
+                Some(parsed.action.to_string()),
+            ),
+            Ok(parsed) => ("Rule".to_string(), Some(parsed.action.to_string())),
+            Err(_) => ("Rule".to_string(), None),
+        };
+
+        symbols.push(symbol(name, detail, SymbolKind::OBJECT, range, range, None));
+    }
+
+    for pref in &optic.host_preferences {
+        let range = span_to_range(pref.span(), &file.source);
+        let selection_range = file
+            .site_span(pref)
+            .map_or(range, |span| span_to_range(span, &file.source));
+
+        let detail = match pref {
+            RawHostPreference::Like { .. } => "Like",
+            RawHostPreference::Dislike { .. } => "Dislike",
+        };
+
+        symbols.push(symbol(
+            pref.site().to_string(),
+            Some(detail.to_string()),
+            SymbolKind::PROPERTY,
+            range,
+            selection_range,
+            None,
+        ));
+    }
+
+    for rule_set in &optic.rule_sets {
+        let selection_range = span_to_range(rule_set.span, &file.source);
+
+        symbols.push(symbol(
+            rule_set.name.clone(),
+            Some("RuleSet".to_string()),
+            SymbolKind::NAMESPACE,
+            span_to_range(rule_set.block_span, &file.source),
+            selection_range,
+            Some(document_symbols(&rule_set.optic, file)),
+        ));
+    }
+
+    symbols.sort_by_key(|symbol| (symbol.range.start.line, symbol.range.start.character));
+
+    symbols
+}
+
+fn lint_to_diagnostic(lint: optics::Lint, source: &str) -> Diagnostic {
+    let tags = match lint.kind {
+        optics::LintKind::UnusedRuleSet | optics::LintKind::UnreachableRule => {
+            Some(vec![DiagnosticTag::UNNECESSARY])
+        }
+        optics::LintKind::DuplicateRule
+        | optics::LintKind::ConflictingHostPreference
+        | optics::LintKind::NeverMatches => None,
+    };
+
+    Diagnostic {
+        range: Range {
+            start: offset_to_pos(lint.span.0, source),
+            end: offset_to_pos(lint.span.1, source),
+        },
+        severity: Some(DiagnosticSeverity::WARNING),
+        message: lint.message,
+        tags,
+        ..Default::default()
+    }
+}
+
 fn offset_to_pos(offset: usize, src: &str) -> Position {
     if src[..offset].is_empty() {
         return Position::new(0, 0);
@@ -337,3 +860,184 @@ fn position_to_byte_offset(pos: &Position, src: &str) -> Option<usize> {
         })
         .map(|(idx, _)| idx)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(path: &str) -> Url {
+        Url::parse(&format!("file:///optics/{path}")).unwrap()
+    }
+
+    #[test]
+    fn imports_from_open_files() {
+        let mut files = HashMap::new();
+        let base = url("main.optic");
+
+        let file = File::parse(&base, r#"Import("shared")"#.to_string(), &files);
+        assert!(matches!(
+            file.error(),
+            Some(optics::Error::UnresolvedImport { name, .. }) if name == "shared"
+        ));
+
+        files.insert(
+            url("shared.optic"),
+            File::new(r#"Like(Site("a.com"))"#.to_string(), &optics::NoLoader),
+        );
+
+        let file = File::parse(&base, r#"Import("shared")"#.to_string(), &files);
+        assert!(file.error().is_none());
+    }
+
+    #[test]
+    fn nested_imports_are_relative() {
+        let mut files = HashMap::new();
+
+        update_file(
+            &mut files,
+            url("lists/spam.optic"),
+            r#"Dislike(Site("spam.com"))"#.to_string(),
+        );
+        update_file(
+            &mut files,
+            url("lists/all.optic"),
+            r#"Import("spam")"#.to_string(),
+        );
+        update_file(
+            &mut files,
+            url("main.optic"),
+            r#"Import("lists/all")"#.to_string(),
+        );
+
+        let main = &files[&url("main.optic")];
+        assert!(main.error().is_none());
+        assert_eq!(
+            main.optic.as_ref().unwrap().host_rankings.disliked,
+            vec!["spam.com".to_string()]
+        );
+    }
+
+    #[test]
+    fn importers_are_updated() {
+        let mut files = HashMap::new();
+
+        assert!(update_file(
+            &mut files,
+            url("main.optic"),
+            r#"Import("lists/all")"#.to_string()
+        )
+        .is_empty());
+        assert!(files[&url("main.optic")].error().is_some());
+
+        // opening the missing import resolves it in the importer
+        assert_eq!(
+            update_file(
+                &mut files,
+                url("lists/all.optic"),
+                r#"Import("spam")"#.to_string()
+            ),
+            vec![url("main.optic")]
+        );
+        assert!(matches!(
+            files[&url("main.optic")].error(),
+            Some(optics::Error::UnresolvedImport { name, .. }) if name == "spam"
+        ));
+
+        // files that import the changed file indirectly are updated as well
+        let mut updated = update_file(
+            &mut files,
+            url("lists/spam.optic"),
+            r#"Dislike(Site("spam.com"))"#.to_string(),
+        );
+        updated.sort();
+        assert_eq!(updated, vec![url("lists/all.optic"), url("main.optic")]);
+        assert!(files[&url("main.optic")].error().is_none());
+
+        // errors in an imported file are reported in the importer
+        update_file(
+            &mut files,
+            url("lists/spam.optic"),
+            r#"Dislike(Site("#.to_string(),
+        );
+        assert!(matches!(
+            files[&url("main.optic")].error(),
+            Some(optics::Error::ImportParse { .. })
+        ));
+    }
+
+    #[test]
+    fn symbols() {
+        let source = r#"Like(Site("a.com"));
+RuleSet("blogs") {
+    Rule {
+        Matches {
+            Site("blog.com")
+        },
+        Action(Boost(2))
+    }
+};
+Dislike(Site("b.com"))"#;
+        let file = File::new(source.to_string(), &optics::NoLoader);
+        let raw = optics::ast::parse(source).unwrap();
+
+        let symbols = document_symbols(&raw, &file);
+
+        assert_eq!(
+            symbols
+                .iter()
+                .map(|symbol| (symbol.name.as_str(), symbol.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                ("a.com", SymbolKind::PROPERTY),
+                ("blogs", SymbolKind::NAMESPACE),
+                ("b.com", SymbolKind::PROPERTY),
+            ]
+        );
+
+        let rule_set = &symbols[1];
+        assert_eq!(rule_set.range.start, Position::new(1, 0));
+        assert_eq!(rule_set.range.end, Position::new(8, 1));
+        assert_eq!(rule_set.selection_range.start, Position::new(1, 9));
+        assert_eq!(rule_set.selection_range.end, Position::new(1, 14));
+
+        let children = rule_set.children.as_ref().unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].kind, SymbolKind::OBJECT);
+        assert_eq!(children[0].detail.as_deref(), Some("Action(Boost(2))"));
+
+        // the site is selected inside the quotes
+        assert_eq!(symbols[0].selection_range.start, Position::new(0, 11));
+        assert_eq!(symbols[0].selection_range.end, Position::new(0, 16));
+    }
+
+    #[test]
+    fn rename() {
+        let source =
+            r#"Like(Site("a.com")); Dislike(Site("b.com")); RuleSet("x") { Like(Site("a.com")) }"#;
+        let file = File::new(source.to_string(), &optics::NoLoader);
+
+        let edits = file
+            .rename_site(source.find("a.com").unwrap() + 1, "c.com")
+            .unwrap();
+
+        assert_eq!(edits.len(), 2);
+        assert!(edits.iter().all(|edit| edit.new_text == "c.com"));
+
+        let mut renamed = source.to_string();
+        for edit in edits.iter().rev() {
+            let start = position_to_byte_offset(&edit.range.start, source).unwrap();
+            let end = position_to_byte_offset(&edit.range.end, source).unwrap();
+            renamed.replace_range(start..end, &edit.new_text);
+        }
+
+        assert_eq!(
+            renamed,
+            r#"Like(Site("c.com")); Dislike(Site("b.com")); RuleSet("x") { Like(Site("c.com")) }"#
+        );
+
+        // only sites in likes and dislikes can be renamed
+        assert!(file
+            .rename_site(source.find("Like").unwrap(), "c.com")
+            .is_none());
+    }
+}
@@ -0,0 +1,135 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+/// Commonly used schema.org types that can be matched with `Schema("...")`.
+pub const TYPES: &[&str] = &[
+    "AboutPage",
+    "AggregateRating",
+    "Answer",
+    "Article",
+    "AudioObject",
+    "Blog",
+    "BlogPosting",
+    "Book",
+    "BreadcrumbList",
+    "Brand",
+    "BroadcastEvent",
+    "BusinessEvent",
+    "CheckoutPage",
+    "Claim",
+    "ClaimReview",
+    "Clip",
+    "CollectionPage",
+    "Comment",
+    "Company",
+    "ContactPage",
+    "Course",
+    "CreativeWork",
+    "CreativeWorkSeries",
+    "DataCatalog",
+    "DataDownload",
+    "Dataset",
+    "DiscussionForumPosting",
+    "EducationEvent",
+    "EducationalOrganization",
+    "Episode",
+    "Event",
+    "FAQPage",
+    "FoodEstablishment",
+    "Game",
+    "GovernmentOrganization",
+    "HowTo",
+    "HowToStep",
+    "ImageGallery",
+    "ImageObject",
+    "ItemList",
+    "ItemPage",
+    "JobPosting",
+    "LearningResource",
+    "LiveBlogPosting",
+    "LocalBusiness",
+    "MediaObject",
+    "MedicalCondition",
+    "MedicalEntity",
+    "MedicalWebPage",
+    "Menu",
+    "MobileApplication",
+    "Movie",
+    "MusicAlbum",
+    "MusicEvent",
+    "MusicGroup",
+    "MusicPlaylist",
+    "MusicRecording",
+    "NewsArticle",
+    "NewsMediaOrganization",
+    "Offer",
+    "OpinionNewsArticle",
+    "Organization",
+    "Person",
+    "Place",
+    "PodcastEpisode",
+    "PodcastSeries",
+    "Product",
+    "ProductGroup",
+    "ProfilePage",
+    "QAPage",
+    "Question",
+    "Rating",
+    "Recipe",
+    "Report",
+    "Restaurant",
+    "Review",
+    "ReviewNewsArticle",
+    "ScholarlyArticle",
+    "SearchResultsPage",
+    "Service",
+    "SocialMediaPosting",
+    "SoftwareApplication",
+    "SoftwareSourceCode",
+    "SportsEvent",
+    "SportsTeam",
+    "Store",
+    "TechArticle",
+    "Thing",
+    "TVEpisode",
+    "TVSeries",
+    "VideoGame",
+    "VideoObject",
+    "WebApplication",
+    "WebPage",
+    "WebSite",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorted_and_unique() {
+        for window in TYPES.windows(2) {
+            assert!(
+                window[0].to_lowercase() < window[1].to_lowercase(),
+                "{} should come after {}",
+                window[0],
+                window[1]
+            );
+        }
+
+        assert!(TYPES
+            .iter()
+            .all(|name| name.chars().all(|c| c.is_ascii_alphanumeric())));
+    }
+}
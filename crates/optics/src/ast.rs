@@ -25,11 +25,13 @@ lalrpop_mod!(pub parser, "/parser.rs");
 pub static PARSER: once_cell::sync::Lazy<parser::BlocksParser> =
     once_cell::sync::Lazy::new(parser::BlocksParser::new);
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub struct RawOptic {
     pub rules: Vec<RawRule>,
     pub host_preferences: Vec<RawHostPreference>,
     pub discard_non_matching: bool,
+    pub imports: Vec<RawImport>,
+    pub rule_sets: Vec<RawRuleSet>,
 }
 
 impl From<Vec<RawOpticBlock>> for RawOptic {
@@ -37,12 +39,16 @@ impl From<Vec<RawOpticBlock>> for RawOptic {
         let mut rules = Vec::new();
         let mut host_preferences = Vec::new();
         let mut discard_non_matching = false;
+        let mut imports = Vec::new();
+        let mut rule_sets = Vec::new();
 
         for block in blocks {
             match block {
                 RawOpticBlock::Rule(rule) => rules.push(rule),
                 RawOpticBlock::HostPreference(pref) => host_preferences.push(pref),
                 RawOpticBlock::DiscardNonMatching => discard_non_matching = true,
+                RawOpticBlock::Import(import) => imports.push(import),
+                RawOpticBlock::RuleSet(rule_set) => rule_sets.push(rule_set),
             }
         }
 
@@ -50,27 +56,108 @@ impl From<Vec<RawOpticBlock>> for RawOptic {
             rules,
             host_preferences,
             discard_non_matching,
+            imports,
+            rule_sets,
         }
     }
 }
 
+impl RawOptic {
+    /// All rule sets defined in the optic, including rule sets nested inside other rule sets.
+    pub fn all_rule_sets(&self) -> Vec<&RawRuleSet> {
+        self.rule_sets
+            .iter()
+            .flat_map(|rule_set| std::iter::once(rule_set).chain(rule_set.optic.all_rule_sets()))
+            .collect()
+    }
+
+    /// All imports in the optic, including imports inside rule sets.
+    pub fn all_imports(&self) -> Vec<&RawImport> {
+        self.imports
+            .iter()
+            .chain(
+                self.rule_sets
+                    .iter()
+                    .flat_map(|rule_set| rule_set.optic.all_imports()),
+            )
+            .collect()
+    }
+
+    /// All likes and dislikes in the optic, including those inside rule sets.
+    pub fn all_host_preferences(&self) -> Vec<&RawHostPreference> {
+        self.host_preferences
+            .iter()
+            .chain(
+                self.rule_sets
+                    .iter()
+                    .flat_map(|rule_set| rule_set.optic.all_host_preferences()),
+            )
+            .collect()
+    }
+}
+
 #[derive(Debug)]
 pub enum RawOpticBlock {
     Rule(RawRule),
     HostPreference(RawHostPreference),
     DiscardNonMatching,
+    Import(RawImport),
+    RuleSet(RawRuleSet),
 }
 
-#[derive(Debug, PartialEq)]
+/// `Import("...")` of a rule set or another optic.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RawImport {
+    pub name: String,
+    /// Byte offsets of the name in the source.
+    pub span: (usize, usize),
+}
+
+/// `RuleSet("...") { ... }` defines a named set of blocks that is only applied when imported.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RawRuleSet {
+    pub name: String,
+    /// Byte offsets of the name in the source.
+    pub span: (usize, usize),
+    /// Byte offsets of the entire rule set in the source.
+    pub block_span: (usize, usize),
+    pub optic: RawOptic,
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct RawRule {
     pub matches: Vec<RawMatchBlock>,
     pub action: Option<RawAction>,
+    /// Byte offsets of the rule in the source.
+    pub span: (usize, usize),
 }
 
-#[derive(Debug, PartialEq)]
+// rules are compared by their content, so identical rules at different positions are equal.
+impl PartialEq for RawRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.matches == other.matches && self.action == other.action
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum RawHostPreference {
-    Like(String),
-    Dislike(String),
+    Like { site: String, span: (usize, usize) },
+    Dislike { site: String, span: (usize, usize) },
+}
+
+impl RawHostPreference {
+    pub fn site(&self) -> &str {
+        match self {
+            RawHostPreference::Like { site, .. } | RawHostPreference::Dislike { site, .. } => site,
+        }
+    }
+
+    /// Byte offsets of the entire `Like(...)` or `Dislike(...)` in the source.
+    pub fn span(&self) -> (usize, usize) {
+        match self {
+            RawHostPreference::Like { span, .. } | RawHostPreference::Dislike { span, .. } => *span,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -86,15 +173,48 @@ pub enum RawMatchPart {
     Content(String),
     MicroformatTag(String),
     Schema(String),
+    /// Seconds since epoch at the start of the date.
+    LastUpdated(RawComparison, u64),
+    LikelyHasAds(bool),
+    LikelyHasPaywall(bool),
+    TrackerScore(RawComparison, u64),
+    HostCentrality(RawComparison, f64),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RawComparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum RawAction {
-    Boost(u64),
-    Downrank(u64),
+    Boost(f64),
+    Downrank(f64),
+    Multiply(f64),
+    SetSignal { signal: String, coefficient: f64 },
     Discard,
 }
 
+/// Parse a non-negative weight used by actions.
+pub(crate) fn parse_weight(value: &str) -> Option<f64> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|weight| weight.is_finite() && *weight >= 0.0)
+}
+
+/// Parse a `YYYY-MM-DD` date into the timestamp (seconds since epoch) at the start of the day in UTC.
+pub(crate) fn parse_date(date: &str) -> Option<u64> {
+    let date = chrono::NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()?;
+    let timestamp = date.and_hms_opt(0, 0, 0)?.and_utc().timestamp();
+
+    u64::try_from(timestamp).ok()
+}
+
 pub fn parse(optic: &str) -> ModResult<RawOptic> {
     match PARSER.parse(lexer::lex(optic)) {
         Ok(blocks) => Ok(RawOptic::from(blocks)),
@@ -159,6 +279,7 @@ mod tests {
                             "/this/is/a/*/pattern".to_string()
                         )])],
                         action: None,
+                        ..Default::default()
                     },
                     RawRule {
                         matches: vec![RawMatchBlock(vec![
@@ -166,10 +287,13 @@ mod tests {
                             RawMatchPart::Site("example.com".to_string()),
                         ])],
                         action: None,
+                        ..Default::default()
                     },
                 ],
                 host_preferences: vec![],
                 discard_non_matching: false,
+                imports: vec![],
+                rule_sets: vec![],
             }
         );
     }
@@ -202,17 +326,21 @@ mod tests {
                         matches: vec![RawMatchBlock(vec![RawMatchPart::Url(
                             "/this/is/a/*/pattern".to_string()
                         )])],
-                        action: Some(RawAction::Boost(2)),
+                        action: Some(RawAction::Boost(2.0)),
+                        ..Default::default()
                     },
                     RawRule {
                         matches: vec![RawMatchBlock(vec![RawMatchPart::Site(
                             "example.com".to_string()
                         )])],
-                        action: Some(RawAction::Downrank(4)),
+                        action: Some(RawAction::Downrank(4.0)),
+                        ..Default::default()
                     },
                 ],
                 host_preferences: vec![],
                 discard_non_matching: false,
+                imports: vec![],
+                rule_sets: vec![],
             }
         );
     }
@@ -246,19 +374,121 @@ mod tests {
                         matches: vec![RawMatchBlock(vec![RawMatchPart::Url(
                             "/this/is/a/*/pattern".to_string()
                         )])],
-                        action: Some(RawAction::Boost(2)),
+                        action: Some(RawAction::Boost(2.0)),
+                        ..Default::default()
                     },
                     RawRule {
                         matches: vec![RawMatchBlock(vec![RawMatchPart::Site(
                             "example.com".to_string()
                         )])],
-                        action: Some(RawAction::Downrank(4)),
+                        action: Some(RawAction::Downrank(4.0)),
+                        ..Default::default()
                     },
                 ],
                 host_preferences: vec![],
                 discard_non_matching: true,
+                imports: vec![],
+                rule_sets: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn fast_field_predicates() {
+        let optic = parse(
+            r#"
+            Rule {
+                Matches {
+                    LastUpdated(> "2023-01-01"),
+                    LikelyHasAds(false),
+                    LikelyHasPaywall(true),
+                    TrackerScore(<= 5),
+                    HostCentrality(>= 0.1),
+                    TrackerScore(3)
+                }
+            };
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            optic,
+            RawOptic {
+                rules: vec![RawRule {
+                    matches: vec![RawMatchBlock(vec![
+                        RawMatchPart::LastUpdated(RawComparison::Greater, 1_672_531_200),
+                        RawMatchPart::LikelyHasAds(false),
+                        RawMatchPart::LikelyHasPaywall(true),
+                        RawMatchPart::TrackerScore(RawComparison::LessOrEqual, 5),
+                        RawMatchPart::HostCentrality(RawComparison::GreaterOrEqual, 0.1),
+                        RawMatchPart::TrackerScore(RawComparison::Equal, 3),
+                    ])],
+                    action: None,
+                    ..Default::default()
+                }],
+                host_preferences: vec![],
+                discard_non_matching: false,
+                imports: vec![],
+                rule_sets: vec![],
             }
         );
+
+        assert!(matches!(
+            parse(r#"Rule { Matches { LastUpdated(> "yesterday") } }"#),
+            Err(Error::DateParse { .. })
+        ));
+        assert!(matches!(
+            parse(r#"Rule { Matches { TrackerScore(< 0.5) } }"#),
+            Err(Error::NumberParse { .. })
+        ));
+    }
+
+    #[test]
+    fn imports_and_rule_sets() {
+        let source = r#"
+            Import("no-content-farms");
+            RuleSet("blogs") {
+                Import("shared/indieweb.optic");
+                Rule {
+                    Matches {
+                        Schema("BlogPosting")
+                    },
+                    Action(Boost(2))
+                }
+            };
+        "#;
+        let optic = parse(source).unwrap();
+
+        assert_eq!(
+            optic.imports,
+            vec![RawImport {
+                name: "no-content-farms".to_string(),
+                span: (
+                    source.find("no-content-farms").unwrap(),
+                    source.find("no-content-farms").unwrap() + "no-content-farms".len()
+                ),
+            }]
+        );
+        assert!(optic.rules.is_empty());
+        assert_eq!(optic.rule_sets.len(), 1);
+
+        let rule_set = &optic.rule_sets[0];
+        assert_eq!(rule_set.name, "blogs");
+        assert_eq!(&source[rule_set.span.0..rule_set.span.1], "blogs");
+        assert!(
+            source[rule_set.block_span.0..rule_set.block_span.1].starts_with("RuleSet(\"blogs\")")
+        );
+        assert!(source[rule_set.block_span.0..rule_set.block_span.1].ends_with('}'));
+        assert_eq!(rule_set.optic.rules.len(), 1);
+
+        assert_eq!(
+            optic
+                .all_imports()
+                .into_iter()
+                .map(|import| import.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["no-content-farms", "shared/indieweb.optic"]
+        );
     }
 
     #[test]
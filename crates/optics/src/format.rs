@@ -0,0 +1,335 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Formatting of optic source code.
+//!
+//! The formatter only changes whitespace. All tokens and comments are kept in
+//! their original order, and blank lines between blocks are preserved (but
+//! collapsed to at most one).
+
+use crate::{lex, Result, Token};
+
+const INDENT: &str = "    ";
+
+/// Format the source of an optic. The source must be lexically valid, but doesn't have to parse.
+pub fn format(source: &str) -> Result<String> {
+    let tokens = lex(source).collect::<Result<Vec<_>>>()?;
+
+    let mut printer = Printer::default();
+    let mut prev_end = 0;
+
+    for (start, token, end) in tokens {
+        // the span of string tokens excludes the quotes
+        let (start, end) = match token {
+            Token::String(_) => (start - 1, end + 1),
+            _ => (start, end),
+        };
+
+        printer.trivia(&source[prev_end..start]);
+        printer.token(&token);
+
+        prev_end = end;
+    }
+
+    printer.trivia(&source[prev_end..]);
+
+    Ok(printer.finish())
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    indent: usize,
+    parens: usize,
+    /// The next token should start on a new line.
+    pending_newline: bool,
+    /// The next token should be separated from the previous by a space.
+    pending_space: bool,
+    /// The source had a blank line before the next token.
+    pending_blank: bool,
+}
+
+impl Printer {
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n')
+    }
+
+    fn newline(&mut self) {
+        self.pending_newline = false;
+        self.pending_space = false;
+
+        let len = self.out.trim_end_matches(' ').len();
+        self.out.truncate(len);
+
+        if !self.at_line_start() {
+            self.out.push('\n');
+        }
+    }
+
+    fn blank_line(&mut self) {
+        self.newline();
+
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") && !self.out.ends_with("{\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn write(&mut self, s: &str) {
+        if self.pending_blank {
+            self.pending_blank = false;
+            self.blank_line();
+        } else if self.pending_newline {
+            self.newline();
+        }
+
+        if self.at_line_start() {
+            for _ in 0..self.indent {
+                self.out.push_str(INDENT);
+            }
+        } else if self.pending_space {
+            self.out.push(' ');
+        }
+
+        self.pending_space = false;
+        self.out.push_str(s);
+    }
+
+    /// Handle the whitespace and comments between two tokens.
+    fn trivia(&mut self, gap: &str) {
+        let mut newlines = 0;
+        let mut rest = gap;
+
+        while let Some(c) = rest.chars().next() {
+            let comment_len = if rest.starts_with("//") {
+                Some(rest.find('\n').unwrap_or(rest.len()))
+            } else if rest.starts_with("/*") {
+                Some(rest.find("*/").map_or(rest.len(), |end| end + 2))
+            } else {
+                None
+            };
+
+            match comment_len {
+                Some(len) => {
+                    self.comment(rest[..len].trim_end(), newlines);
+                    newlines = 0;
+                    rest = &rest[len..];
+                }
+                None => {
+                    if c == '\n' {
+                        newlines += 1;
+                    }
+
+                    rest = &rest[c.len_utf8()..];
+                }
+            }
+        }
+
+        if newlines > 1 && !self.out.is_empty() {
+            self.pending_blank = true;
+        }
+    }
+
+    fn comment(&mut self, comment: &str, newlines_before: usize) {
+        let own_line = newlines_before > 0 || self.out.is_empty();
+        let newline_after = self.pending_newline;
+
+        if newlines_before > 1 {
+            self.blank_line();
+        } else if own_line {
+            self.newline();
+        } else {
+            // keep trailing comments on the line they were written
+            self.pending_newline = false;
+            self.pending_space = true;
+        }
+
+        self.pending_blank = false;
+        self.write(comment);
+
+        self.pending_newline = comment.starts_with("//") || own_line || newline_after;
+        self.pending_space = !self.pending_newline;
+    }
+
+    /// Allow the next token to follow a closing bracket on the same line.
+    fn attach_to_block(&mut self) {
+        self.pending_blank = false;
+
+        if self.out.ends_with('}') {
+            self.pending_newline = false;
+        }
+    }
+
+    fn token(&mut self, token: &Token<'_>) {
+        match token {
+            Token::OpenBracket => {
+                self.pending_space = true;
+                self.write("{");
+                self.indent += 1;
+                self.pending_newline = true;
+            }
+            Token::CloseBracket => {
+                self.indent = self.indent.saturating_sub(1);
+                self.pending_blank = false;
+
+                if self.out.ends_with('{') {
+                    self.pending_newline = false;
+                    self.pending_space = false;
+                } else {
+                    self.pending_newline = true;
+                }
+
+                self.write("}");
+                self.pending_newline = true;
+            }
+            Token::SemiColon => {
+                self.attach_to_block();
+                self.pending_space = false;
+                self.write(";");
+                self.pending_newline = true;
+            }
+            Token::Comma => {
+                self.attach_to_block();
+                self.pending_space = false;
+                self.write(",");
+
+                if self.parens > 0 {
+                    self.pending_space = true;
+                } else {
+                    self.pending_newline = true;
+                }
+            }
+            Token::OpenParenthesis => {
+                self.pending_space = false;
+                self.write("(");
+                self.parens += 1;
+            }
+            Token::CloseParenthesis => {
+                self.attach_to_block();
+                self.pending_space = false;
+                self.write(")");
+                self.parens = self.parens.saturating_sub(1);
+            }
+            Token::Less
+            | Token::LessOrEqual
+            | Token::Greater
+            | Token::GreaterOrEqual
+            | Token::Equal => {
+                self.write(&token.to_string());
+                self.pending_space = true;
+            }
+            _ => self.write(&token.to_string()),
+        }
+    }
+
+    fn finish(self) -> String {
+        let out = self.out.trim_end();
+
+        if out.is_empty() {
+            String::new()
+        } else {
+            format!("{out}\n")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple() {
+        let source = r#"DiscardNonMatching;Rule{Matches{Site("a.com"),Url("/b")},Action(Boost(2))};Like(Site("x.com"))"#;
+
+        assert_eq!(
+            format(source).unwrap(),
+            r#"DiscardNonMatching;
+Rule {
+    Matches {
+        Site("a.com"),
+        Url("/b")
+    },
+    Action(Boost(2))
+};
+Like(Site("x.com"))
+"#
+        );
+    }
+
+    #[test]
+    fn comments_and_blank_lines() {
+        let source = r#"
+// blogs
+Rule{Matches{Site("a.com"),  Url("/b") // trailing
+},
+    Action(SetSignal(Signal("host_centrality"),2))};
+
+
+
+/* block */
+Rule { Matches { TrackerScore(<=2), LastUpdated(>"2023-01-01") } }
+"#;
+
+        assert_eq!(
+            format(source).unwrap(),
+            r#"// blogs
+Rule {
+    Matches {
+        Site("a.com"),
+        Url("/b") // trailing
+    },
+    Action(SetSignal(Signal("host_centrality"), 2))
+};
+
+/* block */
+Rule {
+    Matches {
+        TrackerScore(<= 2),
+        LastUpdated(> "2023-01-01")
+    }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn idempotent() {
+        let source = r#"
+            RuleSet("blogs") {
+                Rule { Matches { Schema("BlogPosting") }, Action(Boost(3)) };
+                // nothing here yet
+                RuleSet("empty") {}
+            };
+            Import("blogs"); // import it
+            Dislike(Site("spam.com"));
+        "#;
+
+        let formatted = format(source).unwrap();
+        assert_eq!(format(&formatted).unwrap(), formatted);
+
+        assert!(formatted.contains("RuleSet(\"empty\") {}"));
+        assert!(formatted.contains("Import(\"blogs\"); // import it\n"));
+        assert_eq!(
+            crate::parse(&formatted).unwrap(),
+            crate::parse(source).unwrap()
+        );
+    }
+
+    #[test]
+    fn lex_errors() {
+        assert!(format("Rule { Matches { Site(\"a.com) } }").is_err());
+        assert_eq!(format("").unwrap(), "");
+    }
+}
@@ -0,0 +1,548 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Resolution of `Import("...")` blocks.
+//!
+//! An import first refers to a `RuleSet` with the same name defined in the same
+//! optic. If no such rule set exists, the source of the import is fetched from an
+//! [`OpticLoader`]. Imported optics are resolved recursively and the rules, likes
+//! and dislikes of all imports are merged into the importing optic. Other settings,
+//! like `DiscardNonMatching`, only apply to the optic they are written in.
+//! Both the depth of nested imports and
+//! the size of the expanded optic are limited, as imports can otherwise expand
+//! exponentially (e.g. rule sets that import the previous rule set twice).
+
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::ast::{self, RawImport, RawOptic, RawRuleSet};
+use crate::{Error, Result};
+
+/// Loads the source of optics referenced by `Import("...")`.
+pub trait OpticLoader {
+    /// Return the source of the optic called `name`, or `None` if it doesn't exist.
+    fn load(&self, name: &str) -> Option<String>;
+
+    /// The name of the optic that `Import("import")` refers to when it is written in
+    /// the optic called `importer`. The root optic has an empty name.
+    /// By default, imports refer to the same optic regardless of where they are written.
+    fn resolve(&self, importer: &str, import: &str) -> String {
+        let _ = importer;
+        import.to_string()
+    }
+}
+
+/// Loader that cannot resolve any imports. Only rule sets defined in the optic itself can be imported.
+pub struct NoLoader;
+
+impl OpticLoader for NoLoader {
+    fn load(&self, _: &str) -> Option<String> {
+        None
+    }
+}
+
+impl OpticLoader for HashMap<String, String> {
+    fn load(&self, name: &str) -> Option<String> {
+        self.get(name).cloned()
+    }
+}
+
+/// Loads optics from files in a directory. An import of `name` loads `<root>/name`,
+/// or `<root>/name.optic` if the former doesn't exist. Imports inside a loaded optic
+/// are relative to the directory of that optic. Imports can not refer to files
+/// outside the root directory, neither through `..` nor through symlinks.
+pub struct DirectoryLoader {
+    root: PathBuf,
+}
+
+impl DirectoryLoader {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl DirectoryLoader {
+    /// Read `path` if it is a file inside the root directory after following symlinks.
+    fn read(&self, path: &Path) -> Option<String> {
+        let root = self.root.canonicalize().ok()?;
+        let path = self.root.join(path).canonicalize().ok()?;
+
+        if !path.starts_with(&root) {
+            return None;
+        }
+
+        std::fs::read_to_string(path).ok()
+    }
+}
+
+impl OpticLoader for DirectoryLoader {
+    fn load(&self, name: &str) -> Option<String> {
+        let path = Path::new(name);
+
+        if !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return None;
+        }
+
+        self.read(path)
+            .or_else(|| self.read(&path.with_extension("optic")))
+    }
+
+    fn resolve(&self, importer: &str, import: &str) -> String {
+        let dir = Path::new(importer).parent().unwrap_or(Path::new(""));
+        let mut path = PathBuf::new();
+
+        for component in dir.join(import).components() {
+            match component {
+                Component::ParentDir
+                    if matches!(path.components().next_back(), Some(Component::Normal(_))) =>
+                {
+                    path.pop();
+                }
+                Component::CurDir => {}
+                component => path.push(component),
+            }
+        }
+
+        path.to_string_lossy().into_owned()
+    }
+}
+
+/// Maximum number of nested imports.
+pub const MAX_IMPORT_DEPTH: usize = 16;
+
+/// Maximum number of imports, rules and host preferences the resolved optic can expand to.
+pub const MAX_EXPANDED_BLOCKS: usize = 10_000;
+
+/// Resolve all imports in `optic` and merge the imported blocks into it.
+pub fn resolve(optic: RawOptic, loader: &dyn OpticLoader) -> Result<RawOptic> {
+    Resolver {
+        loader,
+        stack: Vec::new(),
+        expanded: 0,
+    }
+    .resolve_optic("", optic)
+}
+
+struct Resolver<'a> {
+    loader: &'a dyn OpticLoader,
+    /// The imports currently being resolved. Used to detect cycles.
+    stack: Vec<String>,
+    /// The number of imports, rules and host preferences expanded so far.
+    expanded: usize,
+}
+
+impl Resolver<'_> {
+    /// Resolve an optic where `key` identifies the source it was parsed from.
+    fn resolve_optic(&mut self, key: &str, optic: RawOptic) -> Result<RawOptic> {
+        let mut rule_sets: HashMap<&str, &RawRuleSet> = HashMap::new();
+
+        for rule_set in optic.all_rule_sets() {
+            if rule_sets.insert(&rule_set.name, rule_set).is_some() {
+                return Err(Error::DuplicateRuleSet {
+                    token: token(&rule_set.name, rule_set.span),
+                    name: rule_set.name.clone(),
+                });
+            }
+        }
+
+        self.resolve_blocks(key, &optic, &rule_sets)
+    }
+
+    fn resolve_blocks(
+        &mut self,
+        key: &str,
+        optic: &RawOptic,
+        rule_sets: &HashMap<&str, &RawRuleSet>,
+    ) -> Result<RawOptic> {
+        let mut res = RawOptic {
+            rules: optic.rules.clone(),
+            host_preferences: optic.host_preferences.clone(),
+            discard_non_matching: optic.discard_non_matching,
+            ..Default::default()
+        };
+
+        for import in &optic.imports {
+            self.expand(import, 1)?;
+            let imported = self.resolve_import(key, import, rule_sets)?;
+
+            res.rules.extend(imported.rules);
+            res.host_preferences.extend(imported.host_preferences);
+        }
+
+        Ok(res)
+    }
+
+    fn resolve_import(
+        &mut self,
+        key: &str,
+        import: &RawImport,
+        rule_sets: &HashMap<&str, &RawRuleSet>,
+    ) -> Result<RawOptic> {
+        let rule_set = rule_sets.get(import.name.as_str());
+
+        let import_key = match rule_set {
+            Some(_) => format!("{key}#{}", import.name),
+            None => self.loader.resolve(key, &import.name),
+        };
+
+        if let Some(pos) = self.stack.iter().position(|k| *k == import_key) {
+            let mut cycle = self.stack[pos..].to_vec();
+            cycle.push(import_key);
+
+            return Err(Error::ImportCycle {
+                token: token(&import.name, import.span),
+                cycle: cycle
+                    .into_iter()
+                    .map(|k| k.trim_start_matches('#').to_string())
+                    .collect(),
+            });
+        }
+
+        if self.stack.len() >= MAX_IMPORT_DEPTH {
+            return Err(Error::ImportDepth {
+                token: token(&import.name, import.span),
+                limit: MAX_IMPORT_DEPTH,
+            });
+        }
+
+        self.stack.push(import_key.clone());
+
+        let res = match rule_set {
+            Some(rule_set) => self
+                .expand(import, num_blocks(&rule_set.optic))
+                .and_then(|_| self.resolve_blocks(key, &rule_set.optic, rule_sets)),
+            None => {
+                let source =
+                    self.loader
+                        .load(&import_key)
+                        .ok_or_else(|| Error::UnresolvedImport {
+                            token: token(&import.name, import.span),
+                            name: import.name.clone(),
+                        })?;
+
+                let optic = ast::parse(&source).map_err(|_| Error::ImportParse {
+                    token: token(&import.name, import.span),
+                    name: import.name.clone(),
+                })?;
+
+                // errors inside the imported optic are reported at the import
+                self.expand(import, num_blocks(&optic))
+                    .and_then(|_| self.resolve_optic(&import_key, optic))
+                    .map_err(|err| err.relocate(token(&import.name, import.span)))
+            }
+        };
+
+        self.stack.pop();
+
+        res
+    }
+
+    /// Account for `n` blocks expanded by `import`.
+    fn expand(&mut self, import: &RawImport, n: usize) -> Result<()> {
+        self.expanded += n;
+
+        if self.expanded > MAX_EXPANDED_BLOCKS {
+            return Err(Error::ImportSize {
+                token: token(&import.name, import.span),
+                limit: MAX_EXPANDED_BLOCKS,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+fn num_blocks(optic: &RawOptic) -> usize {
+    optic.rules.len() + optic.host_preferences.len()
+}
+
+fn token(name: &str, (start, end): (usize, usize)) -> (usize, String, usize) {
+    (start, name.to_string(), end)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_with_loader, Action, Optic};
+
+    use super::*;
+
+    fn loader() -> HashMap<String, String> {
+        [
+            (
+                "no-content-farms".to_string(),
+                r#"
+                Rule {
+                    Matches {
+                        Site("|contentfarm.com|")
+                    },
+                    Action(Downrank(10))
+                };
+                Dislike(Site("spam.com"))
+                "#
+                .to_string(),
+            ),
+            (
+                "nested".to_string(),
+                r#"
+                Import("no-content-farms");
+                DiscardNonMatching;
+                "#
+                .to_string(),
+            ),
+            ("cycle-a".to_string(), r#"Import("cycle-b")"#.to_string()),
+            ("cycle-b".to_string(), r#"Import("cycle-a")"#.to_string()),
+            ("broken".to_string(), r#"Rule {"#.to_string()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn import_from_loader() {
+        let optic = parse_with_loader(r#"Import("nested")"#, &loader()).unwrap();
+
+        assert_eq!(optic.rules.len(), 1);
+        assert_eq!(optic.rules[0].action, Action::Downrank(10.0));
+        assert_eq!(optic.host_rankings.disliked, vec!["spam.com".to_string()]);
+        // only rules, likes and dislikes are imported
+        assert!(!optic.discard_non_matching);
+    }
+
+    #[test]
+    fn rule_sets() {
+        let optic = Optic::parse(
+            r#"
+            RuleSet("unused") {
+                Rule {
+                    Matches {
+                        Site("a.com")
+                    }
+                }
+            };
+            RuleSet("blogs") {
+                Import("indieweb");
+                Rule {
+                    Matches {
+                        Schema("BlogPosting")
+                    },
+                    Action(Boost(3))
+                }
+            };
+            RuleSet("indieweb") {
+                Rule {
+                    Matches {
+                        MicroformatTag("|h-*")
+                    },
+                    Action(Boost(2))
+                }
+            };
+            Import("blogs");
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            optic
+                .rules
+                .iter()
+                .map(|rule| rule.action.clone())
+                .collect::<Vec<_>>(),
+            vec![Action::Boost(3.0), Action::Boost(2.0)]
+        );
+    }
+
+    #[test]
+    fn unresolved_import() {
+        assert!(matches!(
+            Optic::parse(r#"Import("no-content-farms")"#),
+            Err(Error::UnresolvedImport { name, .. }) if name == "no-content-farms"
+        ));
+
+        // the error is reported at the import in the root optic
+        let source = r#"Import("missing-nested")"#;
+        let mut loader = loader();
+        loader.insert(
+            "missing-nested".to_string(),
+            r#"Import("missing")"#.to_string(),
+        );
+
+        match parse_with_loader(source, &loader) {
+            Err(Error::UnresolvedImport {
+                token: (start, _, end),
+                name,
+            }) => {
+                assert_eq!(name, "missing");
+                assert_eq!(&source[start..end], "missing-nested");
+            }
+            res => panic!("unexpected result {res:?}"),
+        }
+
+        assert!(matches!(
+            parse_with_loader(r#"Import("broken")"#, &loader),
+            Err(Error::ImportParse { name, .. }) if name == "broken"
+        ));
+    }
+
+    #[test]
+    fn cycles() {
+        match parse_with_loader(r#"Import("cycle-a")"#, &loader()) {
+            Err(Error::ImportCycle { cycle, .. }) => {
+                assert_eq!(cycle, vec!["cycle-a", "cycle-b", "cycle-a"]);
+            }
+            res => panic!("unexpected result {res:?}"),
+        }
+
+        assert!(matches!(
+            Optic::parse(
+                r#"
+                RuleSet("a") { Import("b") };
+                RuleSet("b") { Import("a") };
+                Import("a");
+                "#
+            ),
+            Err(Error::ImportCycle { .. })
+        ));
+
+        assert!(matches!(
+            Optic::parse(r#"RuleSet("a") { Import("a") }; Import("a")"#),
+            Err(Error::ImportCycle { .. })
+        ));
+
+        // importing the same optic twice is not a cycle
+        assert!(
+            parse_with_loader(r#"Import("no-content-farms"); Import("nested")"#, &loader()).is_ok()
+        );
+    }
+
+    #[test]
+    fn limits() {
+        // every rule set imports the previous one twice, which doubles the number of rules
+        let mut source = r#"RuleSet("r0") { Rule { Matches { Site("a.com") } } };"#.to_string();
+        for i in 1..=20 {
+            source.push_str(&format!(
+                r#"RuleSet("r{i}") {{ Import("r{prev}"); Import("r{prev}") }};"#,
+                prev = i - 1
+            ));
+        }
+        source.push_str(r#"Import("r20")"#);
+
+        assert!(matches!(
+            Optic::parse(&source),
+            Err(Error::ImportDepth { limit, .. }) if limit == MAX_IMPORT_DEPTH
+        ));
+
+        let mut source = r#"RuleSet("r0") { Rule { Matches { Site("a.com") } } };"#.to_string();
+        for i in 1..=14 {
+            source.push_str(&format!(
+                r#"RuleSet("r{i}") {{ Import("r{prev}"); Import("r{prev}") }};"#,
+                prev = i - 1
+            ));
+        }
+        source.push_str(r#"Import("r14")"#);
+
+        assert!(matches!(
+            Optic::parse(&source),
+            Err(Error::ImportSize { limit, .. }) if limit == MAX_EXPANDED_BLOCKS
+        ));
+
+        let optic = Optic::parse(&source.replace("Import(\"r14\")", "Import(\"r4\")")).unwrap();
+        assert_eq!(optic.rules.len(), 16);
+    }
+
+    #[test]
+    fn duplicate_rule_sets() {
+        assert!(matches!(
+            Optic::parse(r#"RuleSet("a") { }; RuleSet("a") { }"#),
+            Err(Error::DuplicateRuleSet { name, .. }) if name == "a"
+        ));
+    }
+
+    #[test]
+    fn directory_loader() {
+        let dir = std::env::temp_dir().join(format!("optics-imports-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("shared")).unwrap();
+        std::fs::write(
+            dir.join("shared").join("blocklist.optic"),
+            r#"Rule { Matches { Site("|spam.com|") }, Action(Discard) }"#,
+        )
+        .unwrap();
+
+        let loader = DirectoryLoader::new(&dir);
+
+        let optic = parse_with_loader(r#"Import("shared/blocklist")"#, &loader).unwrap();
+        assert_eq!(optic.host_rankings.blocked, vec!["spam.com".to_string()]);
+
+        assert!(loader.load("shared/blocklist.optic").is_some());
+        assert!(loader.load("../blocklist").is_none());
+        assert!(loader.load("/etc/passwd").is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn directory_loader_relative_imports() {
+        let dir = std::env::temp_dir().join(format!("optics-relative-{}", std::process::id()));
+        let root = dir.join("root");
+        std::fs::create_dir_all(root.join("shared").join("lists")).unwrap();
+        std::fs::write(
+            root.join("shared").join("all.optic"),
+            r#"Import("lists/spam"); Import("../other")"#,
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("shared").join("lists").join("spam.optic"),
+            r#"Rule { Matches { Site("|spam.com|") }, Action(Discard) }"#,
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("other.optic"),
+            r#"Rule { Matches { Site("|other.com|") }, Action(Discard) }"#,
+        )
+        .unwrap();
+
+        let loader = DirectoryLoader::new(&root);
+
+        let mut optic = parse_with_loader(r#"Import("shared/all")"#, &loader).unwrap();
+        optic.host_rankings.blocked.sort();
+        assert_eq!(
+            optic.host_rankings.blocked,
+            vec!["other.com".to_string(), "spam.com".to_string()]
+        );
+
+        // nested imports can't escape the root either
+        std::fs::write(dir.join("secret.optic"), r#"Like(Site("secret.com"))"#).unwrap();
+        std::fs::write(root.join("escape.optic"), r#"Import("../secret")"#).unwrap();
+        assert!(matches!(
+            parse_with_loader(r#"Import("escape")"#, &loader),
+            Err(Error::UnresolvedImport { .. })
+        ));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.join("secret.optic"), root.join("link.optic")).unwrap();
+            assert!(loader.load("link").is_none());
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -31,6 +31,8 @@ pub enum Token<'a> {
 
     DiscardNonMatching,
     Rule,
+    RuleSet,
+    Import,
     RankingPipeline,
     Ranking,
     Stage,
@@ -48,9 +50,23 @@ pub enum Token<'a> {
     Action,
     Boost,
     Downrank,
+    Multiply,
+    SetSignal,
     Discard,
     Like,
     Dislike,
+    LastUpdated,
+    LikelyHasAds,
+    LikelyHasPaywall,
+    TrackerScore,
+    HostCentrality,
+    True,
+    False,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
 
     String(&'a str),
     Number(&'a str),
@@ -67,6 +83,8 @@ impl<'a> Display for Token<'a> {
             Token::CloseParenthesis => f.write_str(")"),
             Token::DiscardNonMatching => f.write_str("DiscardNonMatching"),
             Token::Rule => f.write_str("Rule"),
+            Token::RuleSet => f.write_str("RuleSet"),
+            Token::Import => f.write_str("Import"),
             Token::RankingPipeline => f.write_str("RankingPipeline"),
             Token::Ranking => f.write_str("Ranking"),
             Token::Stage => f.write_str("Stage"),
@@ -84,9 +102,23 @@ impl<'a> Display for Token<'a> {
             Token::Action => f.write_str("Action"),
             Token::Boost => f.write_str("Boost"),
             Token::Downrank => f.write_str("Downrank"),
+            Token::Multiply => f.write_str("Multiply"),
+            Token::SetSignal => f.write_str("SetSignal"),
             Token::Discard => f.write_str("Discard"),
             Token::Like => f.write_str("Like"),
             Token::Dislike => f.write_str("Dislike"),
+            Token::LastUpdated => f.write_str("LastUpdated"),
+            Token::LikelyHasAds => f.write_str("LikelyHasAds"),
+            Token::LikelyHasPaywall => f.write_str("LikelyHasPaywall"),
+            Token::TrackerScore => f.write_str("TrackerScore"),
+            Token::HostCentrality => f.write_str("HostCentrality"),
+            Token::True => f.write_str("true"),
+            Token::False => f.write_str("false"),
+            Token::Less => f.write_str("<"),
+            Token::LessOrEqual => f.write_str("<="),
+            Token::Greater => f.write_str(">"),
+            Token::GreaterOrEqual => f.write_str(">="),
+            Token::Equal => f.write_str("="),
             Token::String(s) => write!(f, "\"{s}\""),
             Token::Number(n) => write!(f, "{n}"),
         }
@@ -122,6 +154,10 @@ enum Outer<'a> {
     DiscardNonMatching,
     #[token("Rule")]
     Rule,
+    #[token("RuleSet")]
+    RuleSet,
+    #[token("Import")]
+    Import,
     #[token("RankingPipeline")]
     RankingPipeline,
     #[token("Ranking")]
@@ -156,12 +192,40 @@ enum Outer<'a> {
     Boost,
     #[token("Downrank")]
     Downrank,
+    #[token("Multiply")]
+    Multiply,
+    #[token("SetSignal")]
+    SetSignal,
     #[token("Discard")]
     Discard,
     #[token("Like")]
     Like,
     #[token("Dislike")]
     Dislike,
+    #[token("LastUpdated")]
+    LastUpdated,
+    #[token("LikelyHasAds")]
+    LikelyHasAds,
+    #[token("LikelyHasPaywall")]
+    LikelyHasPaywall,
+    #[token("TrackerScore")]
+    TrackerScore,
+    #[token("HostCentrality")]
+    HostCentrality,
+    #[token("true")]
+    True,
+    #[token("false")]
+    False,
+    #[token("<")]
+    Less,
+    #[token("<=")]
+    LessOrEqual,
+    #[token(">")]
+    Greater,
+    #[token(">=")]
+    GreaterOrEqual,
+    #[token("=")]
+    Equal,
 
     #[regex(r"[+-]?([0-9]*[.])?[0-9]+", |lex| lex.slice())]
     Number(&'a str),
@@ -304,6 +368,8 @@ impl<'source> Iterator for LexerBridge<'source> {
                 Outer::OpenParenthesis => Some(Ok((s.start, Token::OpenParenthesis, s.end))),
                 Outer::CloseParenthesis => Some(Ok((s.start, Token::CloseParenthesis, s.end))),
                 Outer::Rule => Some(Ok((s.start, Token::Rule, s.end))),
+                Outer::RuleSet => Some(Ok((s.start, Token::RuleSet, s.end))),
+                Outer::Import => Some(Ok((s.start, Token::Import, s.end))),
                 Outer::Ranking => Some(Ok((s.start, Token::Ranking, s.end))),
                 Outer::Stage => Some(Ok((s.start, Token::Stage, s.end))),
                 Outer::RankingPipeline => Some(Ok((s.start, Token::RankingPipeline, s.end))),
@@ -321,9 +387,23 @@ impl<'source> Iterator for LexerBridge<'source> {
                 Outer::Action => Some(Ok((s.start, Token::Action, s.end))),
                 Outer::Boost => Some(Ok((s.start, Token::Boost, s.end))),
                 Outer::Downrank => Some(Ok((s.start, Token::Downrank, s.end))),
+                Outer::Multiply => Some(Ok((s.start, Token::Multiply, s.end))),
+                Outer::SetSignal => Some(Ok((s.start, Token::SetSignal, s.end))),
                 Outer::Discard => Some(Ok((s.start, Token::Discard, s.end))),
                 Outer::Like => Some(Ok((s.start, Token::Like, s.end))),
                 Outer::Dislike => Some(Ok((s.start, Token::Dislike, s.end))),
+                Outer::LastUpdated => Some(Ok((s.start, Token::LastUpdated, s.end))),
+                Outer::LikelyHasAds => Some(Ok((s.start, Token::LikelyHasAds, s.end))),
+                Outer::LikelyHasPaywall => Some(Ok((s.start, Token::LikelyHasPaywall, s.end))),
+                Outer::TrackerScore => Some(Ok((s.start, Token::TrackerScore, s.end))),
+                Outer::HostCentrality => Some(Ok((s.start, Token::HostCentrality, s.end))),
+                Outer::True => Some(Ok((s.start, Token::True, s.end))),
+                Outer::False => Some(Ok((s.start, Token::False, s.end))),
+                Outer::Less => Some(Ok((s.start, Token::Less, s.end))),
+                Outer::LessOrEqual => Some(Ok((s.start, Token::LessOrEqual, s.end))),
+                Outer::Greater => Some(Ok((s.start, Token::Greater, s.end))),
+                Outer::GreaterOrEqual => Some(Ok((s.start, Token::GreaterOrEqual, s.end))),
+                Outer::Equal => Some(Ok((s.start, Token::Equal, s.end))),
                 Outer::Number(n) => Some(Ok((s.start, Token::Number(n), s.end))),
                 Outer::DiscardNonMatching => Some(Ok((s.start, Token::DiscardNonMatching, s.end))),
             }
@@ -436,4 +516,28 @@ mod tests {
 
         assert_eq!(lexer.filter_map(std::result::Result::ok).count(), 0);
     }
+
+    #[test]
+    fn set_signal() {
+        let s = r#"SetSignal(Signal("host_centrality"), 2.0)"#;
+
+        let result: Vec<Token> = LexerBridge::new(s)
+            .filter_map(std::result::Result::ok)
+            .map(|(_, t, _)| t)
+            .collect();
+
+        let expected = vec![
+            Token::SetSignal,
+            Token::OpenParenthesis,
+            Token::Signal,
+            Token::OpenParenthesis,
+            Token::String("host_centrality"),
+            Token::CloseParenthesis,
+            Token::Comma,
+            Token::Number("2.0"),
+            Token::CloseParenthesis,
+        ];
+
+        assert_eq!(result, expected);
+    }
 }
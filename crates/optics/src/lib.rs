@@ -15,16 +15,22 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 pub mod ast;
+mod format;
+pub mod imports;
 mod lexer;
+mod lint;
 
 use itertools::Itertools;
 use std::fmt::Display;
 use thiserror::Error;
 use utoipa::ToSchema;
 
-use self::ast::{RawAction, RawMatchPart, RawOptic, RawRule};
+use self::ast::{RawAction, RawComparison, RawMatchPart, RawOptic, RawRule};
+pub use format::format;
+pub use imports::{DirectoryLoader, NoLoader, OpticLoader};
 pub use lexer::lex;
 pub use lexer::Token;
+pub use lint::{lint, Lint, LintKind};
 
 pub(crate) type Result<T> = std::result::Result<T, Error>;
 
@@ -53,6 +59,9 @@ pub enum Error {
     #[error("Could not parse as a number")]
     NumberParse { token: (usize, String, usize) },
 
+    #[error("Could not parse as a date (expected YYYY-MM-DD)")]
+    DateParse { token: (usize, String, usize) },
+
     #[error("Unknown parse error")]
     Unknown(usize, usize),
 
@@ -61,10 +70,74 @@ pub enum Error {
 
     #[error("Unsupported pattern")]
     Pattern,
+
+    #[error("Could not resolve import")]
+    UnresolvedImport {
+        /// the import in the optic
+        token: (usize, String, usize),
+        /// the name that could not be resolved
+        name: String,
+    },
+
+    #[error("Could not parse imported optic")]
+    ImportParse {
+        token: (usize, String, usize),
+        name: String,
+    },
+
+    #[error("Import cycle")]
+    ImportCycle {
+        token: (usize, String, usize),
+        /// the chain of imports that forms the cycle
+        cycle: Vec<String>,
+    },
+
+    #[error("Rule set is defined multiple times")]
+    DuplicateRuleSet {
+        token: (usize, String, usize),
+        name: String,
+    },
+
+    #[error("Imports are nested too deeply")]
+    ImportDepth {
+        token: (usize, String, usize),
+        /// the maximum number of nested imports
+        limit: usize,
+    },
+
+    #[error("Imports expand to too many rules")]
+    ImportSize {
+        token: (usize, String, usize),
+        /// the maximum number of imports, rules and preferences after expansion
+        limit: usize,
+    },
+}
+
+impl Error {
+    /// Move the location of import errors to `token`. Used to report errors that
+    /// happen inside an imported optic at the import itself.
+    fn relocate(self, token: (usize, String, usize)) -> Self {
+        match self {
+            Error::UnresolvedImport { name, .. } => Error::UnresolvedImport { token, name },
+            Error::ImportParse { name, .. } => Error::ImportParse { token, name },
+            Error::ImportCycle { cycle, .. } => Error::ImportCycle { token, cycle },
+            Error::DuplicateRuleSet { name, .. } => Error::DuplicateRuleSet { token, name },
+            Error::ImportDepth { limit, .. } => Error::ImportDepth { token, limit },
+            Error::ImportSize { limit, .. } => Error::ImportSize { token, limit },
+            err => err,
+        }
+    }
 }
 
+/// Parse an optic. Only rule sets defined in the optic itself can be imported.
 pub fn parse(optic: &str) -> Result<Optic> {
+    parse_with_loader(optic, &NoLoader)
+}
+
+/// Parse an optic where imports that don't refer to a rule set in the optic are loaded by `loader`.
+pub fn parse_with_loader(optic: &str, loader: &dyn OpticLoader) -> Result<Optic> {
     let raw_optic = ast::parse(optic)?;
+    let raw_optic = imports::resolve(raw_optic, loader)?;
 
     Optic::try_from(raw_optic)
 }
@@ -93,8 +166,8 @@ impl TryFrom<RawOptic> for Optic {
 
         for pref in raw.host_preferences {
             match pref {
-                ast::RawHostPreference::Like(host) => liked_hosts.push(host),
-                ast::RawHostPreference::Dislike(host) => disliked_hosts.push(host),
+                ast::RawHostPreference::Like { site, .. } => liked_hosts.push(site),
+                ast::RawHostPreference::Dislike { site, .. } => disliked_hosts.push(site),
             }
         }
 
@@ -114,7 +187,9 @@ impl TryFrom<RawRule> for Rule {
     type Error = Error;
 
     fn try_from(raw: RawRule) -> Result<Self> {
-        let RawRule { matches, action } = raw;
+        let RawRule {
+            matches, action, ..
+        } = raw;
 
         let matches = matches
             .into_iter()
@@ -127,7 +202,7 @@ impl TryFrom<RawRule> for Rule {
 
         Ok(Rule {
             matches,
-            action: action.map_or(Action::Boost(0), Action::from),
+            action: action.map_or(Action::Boost(0.0), Action::from),
         })
     }
 }
@@ -137,45 +212,56 @@ impl From<RawAction> for Action {
         match value {
             RawAction::Boost(boost) => Action::Boost(boost),
             RawAction::Downrank(down_boost) => Action::Downrank(down_boost),
+            RawAction::Multiply(factor) => Action::Multiply(factor),
+            RawAction::SetSignal {
+                signal,
+                coefficient,
+            } => Action::SetSignal {
+                signal,
+                coefficient,
+            },
             RawAction::Discard => Action::Discard,
         }
     }
 }
 
 #[derive(
-    Debug,
-    PartialEq,
-    Eq,
-    Clone,
-    serde::Serialize,
-    serde::Deserialize,
-    bincode::Encode,
-    bincode::Decode,
+    Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode,
 )]
-pub struct Matching {
-    pub pattern: Vec<PatternPart>,
-    pub location: MatchLocation,
+pub enum Matching {
+    /// Matches if the pattern matches the text at the location.
+    Pattern {
+        pattern: Vec<PatternPart>,
+        location: MatchLocation,
+    },
+    /// Matches if the value of the fast field satisfies the predicate.
+    FastField(FastFieldPredicate),
 }
 
 impl Display for Matching {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = match self.location {
-            MatchLocation::Site => "Site",
-            MatchLocation::Url => "Url",
-            MatchLocation::Domain => "Domain",
-            MatchLocation::Title => "Title",
-            MatchLocation::Description => "Description",
-            MatchLocation::Content => "Content",
-            MatchLocation::MicroformatTag => "MicroformatTag",
-            MatchLocation::Schema => "Schema",
-        };
-        write!(f, "{s}(\"")?;
+        match self {
+            Matching::Pattern { pattern, location } => {
+                let s = match location {
+                    MatchLocation::Site => "Site",
+                    MatchLocation::Url => "Url",
+                    MatchLocation::Domain => "Domain",
+                    MatchLocation::Title => "Title",
+                    MatchLocation::Description => "Description",
+                    MatchLocation::Content => "Content",
+                    MatchLocation::MicroformatTag => "MicroformatTag",
+                    MatchLocation::Schema => "Schema",
+                };
+                write!(f, "{s}(\"")?;
+
+                for part in pattern {
+                    write!(f, "{part}")?;
+                }
 
-        for part in &self.pattern {
-            write!(f, "{part}")?;
+                write!(f, "\")")
+            }
+            Matching::FastField(predicate) => write!(f, "{predicate}"),
         }
-
-        write!(f, "\")")
     }
 }
 
@@ -192,6 +278,30 @@ impl TryFrom<RawMatchPart> for Matching {
             RawMatchPart::Content(s) => (s, MatchLocation::Content),
             RawMatchPart::MicroformatTag(s) => (s, MatchLocation::MicroformatTag),
             RawMatchPart::Schema(s) => (s, MatchLocation::Schema),
+            RawMatchPart::LastUpdated(cmp, timestamp) => {
+                return Ok(Self::FastField(FastFieldPredicate::LastUpdated(
+                    cmp.into(),
+                    timestamp,
+                )))
+            }
+            RawMatchPart::LikelyHasAds(value) => {
+                return Ok(Self::FastField(FastFieldPredicate::LikelyHasAds(value)))
+            }
+            RawMatchPart::LikelyHasPaywall(value) => {
+                return Ok(Self::FastField(FastFieldPredicate::LikelyHasPaywall(value)))
+            }
+            RawMatchPart::TrackerScore(cmp, score) => {
+                return Ok(Self::FastField(FastFieldPredicate::TrackerScore(
+                    cmp.into(),
+                    score,
+                )))
+            }
+            RawMatchPart::HostCentrality(cmp, centrality) => {
+                return Ok(Self::FastField(FastFieldPredicate::HostCentrality(
+                    cmp.into(),
+                    centrality,
+                )))
+            }
         };
 
         let mut pattern = Vec::new();
@@ -204,13 +314,94 @@ impl TryFrom<RawMatchPart> for Matching {
             }
         }
 
-        Ok(Self {
+        Ok(Self::Pattern {
             location: loc,
             pattern,
         })
     }
 }
 
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    serde::Serialize,
+    serde::Deserialize,
+    bincode::Encode,
+    bincode::Decode,
+)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+}
+
+impl From<RawComparison> for Comparison {
+    fn from(value: RawComparison) -> Self {
+        match value {
+            RawComparison::Less => Comparison::Less,
+            RawComparison::LessOrEqual => Comparison::LessOrEqual,
+            RawComparison::Greater => Comparison::Greater,
+            RawComparison::GreaterOrEqual => Comparison::GreaterOrEqual,
+            RawComparison::Equal => Comparison::Equal,
+        }
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Comparison::Less => write!(f, "<"),
+            Comparison::LessOrEqual => write!(f, "<="),
+            Comparison::Greater => write!(f, ">"),
+            Comparison::GreaterOrEqual => write!(f, ">="),
+            Comparison::Equal => write!(f, "="),
+        }
+    }
+}
+
+/// A predicate on one of the numeric fast fields of the index.
+#[derive(
+    Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode,
+)]
+pub enum FastFieldPredicate {
+    /// Seconds since epoch at the start of the day the page was last updated.
+    LastUpdated(Comparison, u64),
+    LikelyHasAds(bool),
+    LikelyHasPaywall(bool),
+    /// Number of trackers on the page.
+    TrackerScore(Comparison, u64),
+    HostCentrality(Comparison, f64),
+}
+
+impl Display for FastFieldPredicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FastFieldPredicate::LastUpdated(cmp, timestamp) => {
+                let date = i64::try_from(*timestamp)
+                    .ok()
+                    .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp, 0))
+                    .unwrap_or_default()
+                    .date_naive();
+
+                write!(f, "LastUpdated({cmp} \"{}\")", date.format("%Y-%m-%d"))
+            }
+            FastFieldPredicate::LikelyHasAds(value) => write!(f, "LikelyHasAds({value})"),
+            FastFieldPredicate::LikelyHasPaywall(value) => write!(f, "LikelyHasPaywall({value})"),
+            FastFieldPredicate::TrackerScore(cmp, score) => {
+                write!(f, "TrackerScore({cmp} {score})")
+            }
+            FastFieldPredicate::HostCentrality(cmp, centrality) => {
+                write!(f, "HostCentrality({cmp} {centrality})")
+            }
+        }
+    }
+}
+
 #[derive(
     Debug,
     PartialEq,
@@ -321,19 +512,20 @@ pub enum MatchLocation {
 }
 
 #[derive(
-    Debug,
-    PartialEq,
-    Eq,
-    Clone,
-    Copy,
-    serde::Serialize,
-    serde::Deserialize,
-    bincode::Encode,
-    bincode::Decode,
+    Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode,
 )]
 pub enum Action {
-    Boost(u64),
-    Downrank(u64),
+    /// Add to the score of matching results.
+    Boost(f64),
+    /// Subtract from the score of matching results.
+    Downrank(f64),
+    /// Multiply the score of matching results by a factor.
+    Multiply(f64),
+    /// Override the coefficient of a ranking signal for matching results.
+    SetSignal {
+        signal: String,
+        coefficient: f64,
+    },
     Discard,
 }
 
@@ -344,6 +536,11 @@ impl Display for Action {
         match self {
             Action::Boost(b) => write!(f, "Boost({b})")?,
             Action::Downrank(d) => write!(f, "Downrank({d})")?,
+            Action::Multiply(m) => write!(f, "Multiply({m})")?,
+            Action::SetSignal {
+                signal,
+                coefficient,
+            } => write!(f, "SetSignal(Signal(\"{signal}\"), {coefficient})")?,
             Action::Discard => write!(f, "Discard")?,
         }
 
@@ -371,6 +568,10 @@ impl Optic {
     pub fn parse(optic: &str) -> Result<Self> {
         parse(optic)
     }
+
+    pub fn parse_with_loader(optic: &str, loader: &dyn OpticLoader) -> Result<Self> {
+        parse_with_loader(optic, loader)
+    }
 }
 
 impl Display for Optic {
@@ -388,14 +589,7 @@ impl Display for Optic {
 }
 
 #[derive(
-    Debug,
-    PartialEq,
-    Eq,
-    Clone,
-    serde::Serialize,
-    serde::Deserialize,
-    bincode::Encode,
-    bincode::Decode,
+    Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize, bincode::Encode, bincode::Decode,
 )]
 pub struct Rule {
     /// A list of matchings, structured as an OR of ANDs (i.e. the rule matches if all of the matchings inside one list match).
@@ -415,17 +609,19 @@ impl Rule {
                     return Vec::new();
                 }
 
-                let matching = &matching[0];
+                let Matching::Pattern { pattern, location } = &matching[0] else {
+                    return Vec::new();
+                };
 
-                if matching.pattern.len() != 3 {
+                if pattern.len() != 3 {
                     return Vec::new();
                 }
 
-                if matching.location == MatchLocation::Site
-                    && matching.pattern[0] == PatternPart::Anchor
-                    && matching.pattern[2] == PatternPart::Anchor
+                if *location == MatchLocation::Site
+                    && pattern[0] == PatternPart::Anchor
+                    && pattern[2] == PatternPart::Anchor
                 {
-                    if let PatternPart::Raw(site) = &matching.pattern[1] {
+                    if let PatternPart::Raw(site) = &pattern[1] {
                         res.push(site.clone());
                     } else {
                         return Vec::new();
@@ -494,7 +690,7 @@ impl Display for HostRankings {
                     .unwrap_or(host.clone())
             })
             .map(|host| {
-                vec![Matching {
+                vec![Matching::Pattern {
                     pattern: vec![
                         PatternPart::Anchor,
                         PatternPart::Raw(host),
@@ -538,7 +734,7 @@ impl HostRankings {
                     .unwrap_or(host.clone())
             })
             .map(|host| {
-                vec![Matching {
+                vec![Matching::Pattern {
                     pattern: vec![
                         PatternPart::Anchor,
                         PatternPart::Raw(host.clone()),
@@ -606,7 +802,7 @@ mod tests {
                 blocked: vec![],
             },
             rules: vec![Rule {
-                matches: vec![vec![Matching {
+                matches: vec![vec![Matching::Pattern {
                     pattern: vec![
                         PatternPart::Anchor,
                         PatternPart::Raw("test".to_string()),
@@ -614,7 +810,7 @@ mod tests {
                     ],
                     location: MatchLocation::Site,
                 }]],
-                action: Action::Boost(0),
+                action: Action::Boost(0.0),
             }],
             discard_non_matching: true,
         };
@@ -627,4 +823,70 @@ mod tests {
 
         assert_eq!(optic, parsed);
     }
+
+    #[test]
+    fn export_fast_field_predicates() {
+        let optic = Optic {
+            rules: vec![Rule {
+                matches: vec![vec![
+                    Matching::FastField(FastFieldPredicate::LastUpdated(
+                        Comparison::Greater,
+                        1_672_531_200,
+                    )),
+                    Matching::FastField(FastFieldPredicate::LikelyHasPaywall(false)),
+                    Matching::FastField(FastFieldPredicate::TrackerScore(Comparison::Less, 5)),
+                    Matching::FastField(FastFieldPredicate::HostCentrality(
+                        Comparison::GreaterOrEqual,
+                        0.1,
+                    )),
+                ]],
+                action: Action::Boost(2.0),
+            }],
+            ..Default::default()
+        };
+
+        let exported = optic.to_string();
+
+        assert!(exported.contains("LastUpdated(> \"2023-01-01\")"));
+        assert_eq!(Optic::parse(&exported).unwrap(), optic);
+    }
+
+    #[test]
+    fn export_weighted_actions() {
+        let rule = |action| Rule {
+            matches: vec![vec![Matching::Pattern {
+                pattern: vec![PatternPart::Raw("example".to_string())],
+                location: MatchLocation::Site,
+            }]],
+            action,
+        };
+
+        let optic = Optic {
+            rules: vec![
+                rule(Action::Boost(1.5)),
+                rule(Action::Downrank(0.25)),
+                rule(Action::Multiply(0.3)),
+                rule(Action::SetSignal {
+                    signal: "host_centrality".to_string(),
+                    coefficient: 2.0,
+                }),
+                rule(Action::SetSignal {
+                    signal: "update_timestamp".to_string(),
+                    coefficient: 0.5,
+                }),
+            ],
+            ..Default::default()
+        };
+
+        let exported = optic.to_string();
+
+        assert!(exported.contains("Action(Boost(1.5))"));
+        assert!(exported.contains("Action(SetSignal(Signal(\"host_centrality\"), 2))"));
+        assert_eq!(Optic::parse(&exported).unwrap(), optic);
+
+        assert!(matches!(
+            Optic::parse(r#"Rule { Matches { Site("a.com") }, Action(Multiply(-1)) }"#),
+            Err(Error::NumberParse { .. })
+        ));
+    }
 }
@@ -0,0 +1,374 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2024 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Lints for optics. The lints find parts of an optic that parse fine, but most
+//! likely don't do what the author intended.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{
+    self, RawAction, RawComparison, RawHostPreference, RawMatchBlock, RawMatchPart, RawOptic,
+};
+use crate::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    /// A rule is identical to an earlier rule.
+    DuplicateRule,
+    /// A rule can never affect the results.
+    UnreachableRule,
+    /// A rule set is never imported.
+    UnusedRuleSet,
+    /// A site is both liked and disliked.
+    ConflictingHostPreference,
+    /// A rule or part of a rule can never match any result.
+    NeverMatches,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub message: String,
+    /// Byte offsets in the source the lint refers to.
+    pub span: (usize, usize),
+}
+
+/// Lint the source of an optic. Imports are not resolved, so only the optic itself is checked.
+pub fn lint(source: &str) -> Result<Vec<Lint>> {
+    let optic = ast::parse(source)?;
+    let mut lints = Vec::new();
+
+    lint_optic(&optic, &mut lints);
+
+    let imported: HashSet<&str> = optic
+        .all_imports()
+        .into_iter()
+        .map(|import| import.name.as_str())
+        .collect();
+
+    for rule_set in optic.all_rule_sets() {
+        if !imported.contains(rule_set.name.as_str()) {
+            lints.push(Lint {
+                kind: LintKind::UnusedRuleSet,
+                message: format!(
+                    "The rule set \"{}\" is never imported and has no effect",
+                    rule_set.name
+                ),
+                span: rule_set.span,
+            });
+        }
+    }
+
+    lints.sort_by_key(|lint| lint.span);
+
+    Ok(lints)
+}
+
+fn lint_optic(optic: &RawOptic, lints: &mut Vec<Lint>) {
+    for (i, rule) in optic.rules.iter().enumerate() {
+        if optic.rules[..i].contains(rule) {
+            lints.push(Lint {
+                kind: LintKind::DuplicateRule,
+                message: "This rule is identical to an earlier rule".to_string(),
+                span: rule.span,
+            });
+        }
+
+        if rule.matches.is_empty() {
+            lints.push(Lint {
+                kind: LintKind::NeverMatches,
+                message: "A rule without a `Matches` block never matches any results".to_string(),
+                span: rule.span,
+            });
+        }
+
+        for block in &rule.matches {
+            if let Some(message) = never_matches(block) {
+                lints.push(Lint {
+                    kind: LintKind::NeverMatches,
+                    message,
+                    span: rule.span,
+                });
+            }
+        }
+    }
+
+    // with `DiscardNonMatching`, only results matching a rule that doesn't discard are kept.
+    let keeps_results = !optic.imports.is_empty()
+        || optic
+            .rules
+            .iter()
+            .any(|rule| rule.action != Some(RawAction::Discard));
+
+    if optic.discard_non_matching && !keeps_results {
+        for rule in &optic.rules {
+            lints.push(Lint {
+                kind: LintKind::UnreachableRule,
+                message: "`DiscardNonMatching` already discards all results, since no rule keeps any of them".to_string(),
+                span: rule.span,
+            });
+        }
+    }
+
+    let mut preferences: HashMap<&str, &RawHostPreference> = HashMap::new();
+
+    for pref in &optic.host_preferences {
+        let (site, span, liked) = match pref {
+            RawHostPreference::Like { site, span } => (site, span, true),
+            RawHostPreference::Dislike { site, span } => (site, span, false),
+        };
+
+        if let Some(prev) = preferences.insert(site, pref) {
+            if matches!(prev, RawHostPreference::Like { .. }) != liked {
+                lints.push(Lint {
+                    kind: LintKind::ConflictingHostPreference,
+                    message: format!("The site \"{site}\" is both liked and disliked"),
+                    span: *span,
+                });
+            }
+        }
+    }
+
+    for rule_set in &optic.rule_sets {
+        lint_optic(&rule_set.optic, lints);
+    }
+}
+
+/// The range of values that satisfy a set of comparisons.
+struct Range {
+    lower: f64,
+    lower_inclusive: bool,
+    upper: f64,
+    upper_inclusive: bool,
+}
+
+impl Default for Range {
+    fn default() -> Self {
+        Self {
+            lower: f64::NEG_INFINITY,
+            lower_inclusive: true,
+            upper: f64::INFINITY,
+            upper_inclusive: true,
+        }
+    }
+}
+
+impl Range {
+    fn restrict(&mut self, cmp: &RawComparison, value: f64) {
+        match cmp {
+            RawComparison::Less => self.restrict_upper(value, false),
+            RawComparison::LessOrEqual => self.restrict_upper(value, true),
+            RawComparison::Greater => self.restrict_lower(value, false),
+            RawComparison::GreaterOrEqual => self.restrict_lower(value, true),
+            RawComparison::Equal => {
+                self.restrict_lower(value, true);
+                self.restrict_upper(value, true);
+            }
+        }
+    }
+
+    fn restrict_lower(&mut self, value: f64, inclusive: bool) {
+        if value > self.lower || (value == self.lower && !inclusive) {
+            self.lower = value;
+            self.lower_inclusive = inclusive;
+        }
+    }
+
+    fn restrict_upper(&mut self, value: f64, inclusive: bool) {
+        if value < self.upper || (value == self.upper && !inclusive) {
+            self.upper = value;
+            self.upper_inclusive = inclusive;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.lower > self.upper
+            || (self.lower == self.upper && !(self.lower_inclusive && self.upper_inclusive))
+    }
+}
+
+/// Check whether a `Matches` block can never match anything. Returns the reason if so.
+fn never_matches(block: &RawMatchBlock) -> Option<String> {
+    if block.0.is_empty() {
+        return Some("An empty `Matches` block never matches any results".to_string());
+    }
+
+    let mut ranges: HashMap<&str, Range> = HashMap::new();
+    let mut flags: HashMap<&str, bool> = HashMap::new();
+
+    for part in &block.0 {
+        let (field, cmp, value) = match part {
+            RawMatchPart::Site(pattern)
+            | RawMatchPart::Url(pattern)
+            | RawMatchPart::Domain(pattern)
+            | RawMatchPart::Title(pattern)
+            | RawMatchPart::Description(pattern)
+            | RawMatchPart::Content(pattern)
+            | RawMatchPart::MicroformatTag(pattern)
+            | RawMatchPart::Schema(pattern) => {
+                let pattern = pattern.trim();
+                let inner_anchor = pattern
+                    .char_indices()
+                    .any(|(i, c)| c == '|' && i != 0 && i != pattern.len() - 1);
+
+                if inner_anchor {
+                    return Some(format!(
+                        "The pattern \"{pattern}\" never matches, since `|` can only be used at the start or end of a pattern"
+                    ));
+                }
+
+                continue;
+            }
+            RawMatchPart::LikelyHasAds(value) | RawMatchPart::LikelyHasPaywall(value) => {
+                let field = match part {
+                    RawMatchPart::LikelyHasAds(_) => "LikelyHasAds",
+                    _ => "LikelyHasPaywall",
+                };
+
+                if flags
+                    .insert(field, *value)
+                    .is_some_and(|prev| prev != *value)
+                {
+                    return Some(format!("`{field}` can't be both true and false"));
+                }
+
+                continue;
+            }
+            RawMatchPart::LastUpdated(cmp, value) => ("LastUpdated", cmp, *value as f64),
+            RawMatchPart::TrackerScore(cmp, value) => ("TrackerScore", cmp, *value as f64),
+            RawMatchPart::HostCentrality(cmp, value) => ("HostCentrality", cmp, *value),
+        };
+
+        let range = ranges.entry(field).or_default();
+        range.restrict(cmp, value);
+
+        if range.is_empty() {
+            return Some(format!(
+                "The comparisons on `{field}` contradict each other, so the block never matches any results"
+            ));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<LintKind> {
+        lint(source)
+            .unwrap()
+            .into_iter()
+            .map(|lint| lint.kind)
+            .collect()
+    }
+
+    #[test]
+    fn clean_optic() {
+        assert!(kinds(
+            r#"
+            DiscardNonMatching;
+            Rule { Matches { Site("|a.com|") }, Action(Boost(2)) };
+            Rule { Matches { Site("b.com") }, Action(Discard) };
+            Like(Site("a.com"));
+            Dislike(Site("b.com"));
+            "#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn duplicate_rules() {
+        let source = r#"
+            Rule { Matches { Site("a.com") }, Action(Boost(2)) };
+            Rule { Matches { Site("b.com") }, Action(Boost(2)) };
+            Rule {
+                Matches {
+                    Site("a.com")
+                },
+                Action(Boost(2))
+            };
+        "#;
+
+        let lints = lint(source).unwrap();
+
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].kind, LintKind::DuplicateRule);
+        assert!(source[lints[0].span.0..lints[0].span.1].starts_with("Rule {\n"));
+    }
+
+    #[test]
+    fn unreachable_rules() {
+        assert_eq!(
+            kinds(
+                r#"
+                DiscardNonMatching;
+                Rule { Matches { Site("a.com") }, Action(Discard) };
+                Rule { Matches { Site("b.com") }, Action(Discard) };
+                "#
+            ),
+            vec![LintKind::UnreachableRule, LintKind::UnreachableRule]
+        );
+    }
+
+    #[test]
+    fn unused_rule_sets() {
+        assert_eq!(
+            kinds(r#"RuleSet("used") { }; RuleSet("unused") { }; Import("used")"#),
+            vec![LintKind::UnusedRuleSet]
+        );
+    }
+
+    #[test]
+    fn conflicting_host_preferences() {
+        assert_eq!(
+            kinds(r#"Like(Site("a.com")); Like(Site("a.com")); Dislike(Site("a.com"))"#),
+            vec![LintKind::ConflictingHostPreference]
+        );
+    }
+
+    #[test]
+    fn never_matching_rules() {
+        assert_eq!(
+            kinds(r#"Rule { Matches { Site("a|b.com") } }"#),
+            vec![LintKind::NeverMatches]
+        );
+        assert_eq!(
+            kinds(r#"Rule { Matches { } }"#),
+            vec![LintKind::NeverMatches]
+        );
+        assert_eq!(kinds(r#"Rule { }"#), vec![LintKind::NeverMatches]);
+        assert_eq!(
+            kinds(r#"Rule { Matches { TrackerScore(< 2), TrackerScore(> 5) } }"#),
+            vec![LintKind::NeverMatches]
+        );
+        assert_eq!(
+            kinds(r#"Rule { Matches { HostCentrality(>= 0.5), HostCentrality(< 0.5) } }"#),
+            vec![LintKind::NeverMatches]
+        );
+        assert_eq!(
+            kinds(r#"Rule { Matches { LikelyHasAds(true), LikelyHasAds(false) } }"#),
+            vec![LintKind::NeverMatches]
+        );
+
+        // anchors at the start and end are fine, and so are ranges with a single value
+        assert!(kinds(
+            r#"Rule { Matches { Site("|a.com|"), TrackerScore(<= 2), TrackerScore(>= 2) } }"#
+        )
+        .is_empty());
+    }
+}
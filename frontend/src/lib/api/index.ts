@@ -110,10 +110,14 @@ export const api = {
       `/beta/api/autosuggest?${new URLSearchParams(params)}`,
       options,
     ),
+  exploreClusters: (body: ExploreClustersParams, options?: ApiOptions) =>
+    requestJson<ExploreCluster[]>('POST', `/beta/api/explore/clusters`, body, options),
   exploreExport: (body: ExploreExportOpticParams, options?: ApiOptions) =>
     requestPlain('POST', `/beta/api/explore/export`, body, options),
   hostsExport: (body: HostsExportOpticParams, options?: ApiOptions) =>
     requestPlain('POST', `/beta/api/hosts/export`, body, options),
+  regions: (options?: ApiOptions) =>
+    requestJson<DisplayedRegion[]>('GET', `/beta/api/regions`, void 0, options),
   search: (body: ApiSearchQuery, options?: ApiOptions) =>
     requestJson<ApiSearchResult>('POST', `/beta/api/search`, body, options),
   searchSidebar: (body: SidebarQuery, options?: ApiOptions) =>
@@ -225,6 +229,7 @@ export type Calculation = {
   input: string;
   result: string;
 };
+export type ClusterKind = 'weaklyConnected' | 'stronglyConnected' | 'community';
 export type CodeOrText =
   | {
       _type: 'code';
@@ -259,6 +264,11 @@ export type DisplayedEntity = {
   smallAbstract: EntitySnippet;
   title: string;
 };
+export type DisplayedRegion = {
+  gl: string;
+  name: string;
+  region: Region;
+};
 export type DisplayedSidebar =
   | {
       _type: 'entity';
@@ -299,6 +309,17 @@ export type EntitySnippetFragment =
       text: string;
     };
 export type Example = string;
+export type ExploreCluster = {
+  host: string;
+  kind: ClusterKind;
+  members: string[];
+  size: number;
+};
+export type ExploreClustersParams = {
+  hosts: string[];
+  kind?: ClusterKind;
+  members?: number;
+};
 export type ExploreExportOpticParams = {
   chosenHosts: string[];
   similarHosts: string[];
@@ -353,8 +374,7 @@ export type PartOfSpeechMeaning = {
   pos: PartOfSpeech;
 };
 export type Property = string | StructuredData;
-export type Region = 'All' | 'Denmark' | 'France' | 'Germany' | 'Spain' | 'US';
-export const REGIONS = ['All', 'Denmark', 'France', 'Germany', 'Spain', 'US'] satisfies Region[];
+export type Region = string;
 export type ReturnBody =
   | {
       _type: 'all';
@@ -369,6 +389,7 @@ export type RichSnippet = {
   question: StackOverflowQuestion;
 };
 export type ScoredHost = {
+  cluster?: string;
   description?: string;
   host: string;
   score: number;
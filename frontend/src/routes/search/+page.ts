@@ -3,6 +3,9 @@ import type { PageLoad } from './$types';
 import { extractSearchParams, search, type SearchResults } from '$lib/search';
 import { globals } from '$lib/globals';
 import { browser } from '$app/environment';
+import { api, type DisplayedRegion } from '$lib/api';
+
+const ALL_REGIONS: DisplayedRegion[] = [{ region: 'All', name: 'All Languages', gl: 'all' }];
 
 export const load: PageLoad = async (req) => {
   const { url, fetch } = req;
@@ -37,9 +40,12 @@ export const load: PageLoad = async (req) => {
     redirect(301, results.redirectTo);
   }
 
+  const regions = await api.regions({ fetch }).data.catch(() => ALL_REGIONS);
+
   return {
     params,
     results,
+    regions,
     globals: await globals({
       title: `${params.query} – Stract`,
       header: { divider: true },